@@ -0,0 +1,151 @@
+// Benchmarks of the query builder: writing the SQL of deep query chains, collecting the
+// parameters of large inserts, and mapping rows with `FromRow`. Run them with `cargo bench`.
+//
+// Mapping rows needs a database, taken from `$TODO_BENCH_DATABASE` (a connection string); without
+// it those benchmarks are skipped.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use postgres::{NoTls, Row};
+use std::env;
+use std::marker::PhantomData;
+use todo_rust::client::Client;
+use todo_rust::typed_query::{
+    self, statement, Constant, Field, FromRow, Param, Table, ToSqlParams, WithDefault,
+};
+
+// The columns and fields are only written and mapped, not read.
+#[allow(dead_code)]
+struct Columns {
+    id: Field<i32>,
+    name: Field<String>,
+    note: Field<Option<String>>,
+}
+
+#[allow(dead_code)]
+struct Record {
+    id: i32,
+    name: String,
+    note: Option<String>,
+}
+
+impl FromRow for Record {
+    fn from_row(row: Row) -> Record {
+        Record {
+            id: row.get(0),
+            name: row.get(1),
+            note: row.get(2),
+        }
+    }
+}
+
+struct RecordInsert {
+    id: WithDefault<i32>,
+    name: String,
+    note: Option<String>,
+}
+
+impl ToSqlParams for RecordInsert {
+    fn to_sql_params(self) -> Vec<Param> {
+        vec![
+            Param(Box::new(self.id)),
+            Param(Box::new(self.name)),
+            Param(Box::new(self.note)),
+        ]
+    }
+}
+
+fn table(name: &'static str) -> Table<Columns, Record> {
+    Table {
+        name,
+        columns: Columns {
+            id: Field {
+                name: "id",
+                phantom: PhantomData,
+            },
+            name: Field {
+                name: "name",
+                phantom: PhantomData,
+            },
+            note: Field {
+                name: "note",
+                phantom: PhantomData,
+            },
+        },
+        phantom: PhantomData,
+    }
+}
+
+fn query_chain(depth: usize) -> typed_query::Query<Columns, Record> {
+    let mut query = typed_query::from(table("bench"));
+    for i in 0..depth {
+        query = query.where_(|t| t.id.clone().in_(vec![Constant { value: i as i32 }]));
+    }
+    query.order_by(|t| typed_query::asc(&t.name))
+}
+
+fn insert(rows: usize) -> typed_query::Insert<Columns, Record> {
+    let mut insert = typed_query::insert_into(table("bench"));
+    for i in 0..rows {
+        insert = insert.values(RecordInsert {
+            id: WithDefault::Default,
+            name: format!("task {}", i),
+            note: Some("note".to_string()),
+        });
+    }
+    insert
+}
+
+fn to_sql(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_sql");
+    for depth in [1, 10, 50] {
+        let query = query_chain(depth);
+        group.bench_with_input(BenchmarkId::new("chain", depth), &query, |b, query| {
+            b.iter(|| statement(query).0)
+        });
+    }
+    group.finish();
+}
+
+fn insert_params(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert params");
+    for rows in [10, 1_000] {
+        let insert = insert(rows);
+        group.bench_with_input(BenchmarkId::new("rows", rows), &insert, |b, insert| {
+            b.iter(|| statement(insert).1.as_slice().len())
+        });
+    }
+    group.finish();
+}
+
+fn from_row(c: &mut Criterion) {
+    let connection = match env::var("TODO_BENCH_DATABASE") {
+        Ok(connection) => connection,
+        Err(_) => {
+            println!("Set $TODO_BENCH_DATABASE to benchmark FromRow");
+            return;
+        }
+    };
+    let mut client = Client::connect(&connection, NoTls).unwrap();
+    let mut group = c.benchmark_group("FromRow");
+    group.sample_size(20);
+    for rows in [100, 10_000] {
+        let query = format!(
+            "select i, 'task ' || i, null::text from generate_series(1, {}) i",
+            rows
+        );
+        group.bench_with_input(BenchmarkId::new("rows", rows), &query, |b, query| {
+            b.iter(|| {
+                let rows = client.query(query.as_str(), &[]).unwrap();
+                rows.into_iter().map(Record::from_row).collect::<Vec<_>>()
+            })
+        });
+        // Without mapping, to tell the cost of the query apart.
+        group.bench_with_input(BenchmarkId::new("query only", rows), &query, |b, query| {
+            b.iter(|| client.query(query.as_str(), &[]).unwrap().len())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, to_sql, insert_params, from_row);
+criterion_main!(benches);
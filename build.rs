@@ -0,0 +1,9 @@
+// Generates the gRPC service of `todo serve --grpc` from proto/todo.proto. The vendored protoc is
+// used, so building doesn't need one installed.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/todo.proto"], &["proto"])?;
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use std::io::IsTerminal;
+use std::time::SystemTime;
+
+use crate::context;
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::output;
+use crate::status::{self, Status};
+use crate::time::{format_duration, format_iso, local_day};
+use crate::{alias, priority_key, priority_name, Context};
+use todo_rust::client::Client;
+
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+struct Item {
+    id: i32,
+    name: String,
+    due_time: Option<SystemTime>,
+    priority: Option<i16>,
+    estimate: Option<i32>,
+    waiting_for: Option<String>,
+}
+
+/// Shows the open tasks in the active context grouped by when they are due: overdue and today,
+/// tomorrow, the next `days` days, later, and without a due date. Tasks waiting for someone show
+/// up first once their follow-up date arrives.
+pub fn run(client: &mut Client, ctx: &Context, days: i64) -> Result<(), Error> {
+    let active = context::active(client, ctx)?;
+    let rows = client.query(
+        "select id, name, due_time, priority, estimate, status, waiting_until, waiting_for, uuid
+             from todo
+             where owner = $1 and status = any($2) and ($3::text is null or context = $3)
+             order by due_time nulls last, priority nulls last, id",
+        &[&ctx.user, &status::UNFINISHED, &active],
+    )?;
+    let zone = ctx.timezone.as_ref();
+    let today = local_day(SystemTime::now(), zone);
+    let now = SystemTime::now();
+
+    // With `--output tsv` or `null`, the first field of a record is the key of its group.
+    let mut groups: Vec<(&str, String, Vec<Item>)> = vec![
+        ("follow-up", tr!("Follow up"), Vec::new()),
+        ("today", tr!("Today"), Vec::new()),
+        ("tomorrow", tr!("Tomorrow"), Vec::new()),
+        ("soon", tr!("Next {} days", days), Vec::new()),
+        ("later", tr!("Later"), Vec::new()),
+        ("none", tr!("No due date"), Vec::new()),
+    ];
+    for row in rows {
+        let item = Item {
+            id: row.get(0),
+            name: ctx.decrypt(row.get(1), Location::task("name", row.get(8))),
+            due_time: row.get(2),
+            priority: row.get(3),
+            estimate: row.get(4),
+            waiting_for: row.get(7),
+        };
+        if row.get::<_, Status>(5) == Status::Waiting {
+            let until: Option<SystemTime> = row.get(6);
+            if until.is_none_or(|t| local_day(t, zone) <= today) {
+                groups[0].2.push(item);
+            }
+            continue;
+        }
+        let group = match item.due_time.map(|t| local_day(t, zone) - today) {
+            Some(d) if d <= 0 => 1,
+            Some(1) => 2,
+            Some(d) if d <= days => 3,
+            Some(_) => 4,
+            None => 5,
+        };
+        groups[group].2.push(item);
+    }
+    // Without due dates, the most important tasks come first.
+    groups[5]
+        .2
+        .sort_by_key(|item| (item.priority.unwrap_or(i16::MAX), item.id));
+
+    if !ctx.output.is_text() {
+        for (key, _, items) in &groups {
+            for item in items {
+                output::record(
+                    ctx.output,
+                    &[
+                        key,
+                        &item.id.to_string(),
+                        &alias::encode(item.id),
+                        &item.name,
+                        &item.due_time.map(format_iso).unwrap_or_default(),
+                        item.priority.map(priority_key).unwrap_or_default(),
+                        &item.estimate.map(|e| e.to_string()).unwrap_or_default(),
+                    ],
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    let color = std::io::stdout().is_terminal();
+    let mut first = true;
+    for (_, title, items) in groups {
+        if items.is_empty() {
+            continue;
+        }
+        if !first {
+            println!();
+        }
+        first = false;
+        // The estimated total shows whether the plan for a day is realistic.
+        let estimates: Vec<i32> = items.iter().filter_map(|item| item.estimate).collect();
+        if estimates.is_empty() {
+            println!("{}", title);
+        } else {
+            let total: i64 = estimates.iter().map(|&e| i64::from(e)).sum();
+            println!(
+                "{} ({})",
+                title,
+                tr!("{} estimated", format_duration(total as f64))
+            );
+        }
+        for item in items {
+            let mut details = Vec::new();
+            if let Some(person) = &item.waiting_for {
+                details.push(tr!("waiting for {}", person));
+            }
+            if let Some(priority) = item.priority {
+                details.push(tr!("{} priority", priority_name(priority)));
+            }
+            if let Some(estimate) = item.estimate {
+                details.push(tr!("estimate {}", format_duration(estimate as f64)));
+            }
+            let overdue = item.due_time.is_some_and(|t| t < now);
+            if let Some(due) = item.due_time {
+                if overdue {
+                    details.push(tr!("overdue, due {}", ctx.format_time(due)));
+                } else {
+                    details.push(tr!("due {}", ctx.format_time(due)));
+                }
+            }
+            let mut line = format!("  {} ({}): {}", item.id, alias::encode(item.id), item.name);
+            if !details.is_empty() {
+                line = format!("{} ({})", line, details.join(", "));
+            }
+            if overdue && color {
+                println!("{}{}{}", RED, line, RESET);
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+// Short aliases for task ids, such as `k3f9`. The alias is computed from the id by a bijection,
+// so aliases never collide and don't need to be stored. Aliases start with a letter, so they can't
+// be mistaken for ids.
+
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+const LETTERS: u64 = 26;
+const BASE: u64 = 36;
+const MIN_LENGTH: u32 = 4;
+// Long enough for any i32.
+const MAX_LENGTH: u32 = 7;
+// Coprime with every capacity, so multiplying by it permutes the aliases of a given length.
+const MULTIPLIER: u64 = 1_953_125;
+const OFFSET: u64 = 104_729;
+
+// The number of aliases of the given length.
+fn capacity(length: u32) -> u64 {
+    LETTERS * BASE.pow(length - 1)
+}
+
+// Ids below `capacity(MIN_LENGTH)` get aliases of the minimum length, the next ones are one
+// character longer, and so on. Returns the first id with an alias of the given length.
+fn first_id(length: u32) -> u64 {
+    if length == MIN_LENGTH {
+        0
+    } else {
+        capacity(length - 1)
+    }
+}
+
+/// Returns the alias of task `id`.
+pub fn encode(id: i32) -> String {
+    let id = id.max(0) as u64;
+    let mut length = MIN_LENGTH;
+    while id >= capacity(length) {
+        length += 1;
+    }
+    let n = capacity(length);
+    let mut value = (id % n * MULTIPLIER + OFFSET) % n;
+    let mut alias = Vec::new();
+    for _ in 1..length {
+        alias.push(DIGITS[(value % BASE) as usize]);
+        value /= BASE;
+    }
+    alias.push(DIGITS[(10 + value) as usize]);
+    alias.reverse();
+    String::from_utf8(alias).unwrap()
+}
+
+/// Returns the id of the task with `alias`, if it is a valid alias.
+pub fn decode(alias: &str) -> Option<i32> {
+    let alias = alias.to_ascii_lowercase();
+    let length = alias.len() as u32;
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) {
+        return None;
+    }
+    let mut chars = alias.bytes();
+    let first = chars.next()?;
+    if !first.is_ascii_lowercase() {
+        return None;
+    }
+    let mut value = (first - b'a') as u64;
+    for c in chars {
+        let digit = DIGITS.iter().position(|&d| d == c)? as u64;
+        value = value * BASE + digit;
+    }
+    let n = capacity(length);
+    let shifted = (value + n - OFFSET % n) % n;
+    let id = (shifted as u128 * inverse(MULTIPLIER % n, n) as u128 % n as u128) as u64;
+    if id < first_id(length) || id > i32::MAX as u64 {
+        return None;
+    }
+    Some(id as i32)
+}
+
+// The multiplicative inverse of `a` modulo `n`, by the extended Euclidean algorithm.
+fn inverse(a: u64, n: u64) -> u64 {
+    let (mut t, mut new_t) = (0i128, 1i128);
+    let (mut r, mut new_r) = (n as i128, a as i128);
+    while new_r != 0 {
+        let quotient = r / new_r;
+        (t, new_t) = (new_t, t - quotient * new_t);
+        (r, new_r) = (new_r, r - quotient * new_r);
+    }
+    t.rem_euclid(n as i128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn decode_inverts_encode() {
+        let ids = (0..100_000)
+            .chain((0..1000).map(|i| i * 2_147_483))
+            .chain(i32::MAX - 1000..=i32::MAX)
+            .chain([first_id(5) as i32, first_id(6) as i32, first_id(7) as i32]);
+        for id in ids {
+            assert_eq!(decode(&encode(id)), Some(id), "{} ({})", id, encode(id));
+        }
+    }
+
+    #[test]
+    fn aliases_are_distinct() {
+        let ids = (0..200_000).chain(i32::MAX - 200_000..=i32::MAX);
+        let mut aliases = HashSet::new();
+        for id in ids {
+            assert!(aliases.insert(encode(id)), "{} ({})", id, encode(id));
+        }
+    }
+
+    #[test]
+    fn aliases_start_with_a_letter() {
+        for id in [0, 1, 999, i32::MAX] {
+            let alias = encode(id);
+            assert!(alias.as_bytes()[0].is_ascii_lowercase(), "{}", alias);
+            assert!((MIN_LENGTH..=MAX_LENGTH).contains(&(alias.len() as u32)));
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Dated notes on a task, kept apart from its description. Like descriptions, they are encrypted
+// when encryption is enabled.
+
+use std::time::SystemTime;
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::{run_bulk, webhook, Context};
+use todo_rust::client::Client;
+
+/// Adds an annotation to the user's task `id`.
+pub fn add(client: &mut Client, ctx: &Context, id: i32, text: &str) -> Result<(), Error> {
+    if text.trim().is_empty() {
+        return Err(Error::Usage(tr!("The annotation can't be empty")));
+    }
+    run_bulk(client, ctx, &[id], &tr!("annotated"), |t, id| {
+        let uuid: String = match t.query_opt(
+            "select uuid from todo where id = $1 and owner = $2",
+            &[&id, &ctx.user],
+        )? {
+            Some(row) => row.get(0),
+            None => return Ok(0),
+        };
+        let text = ctx.encrypt(text, Location::new("annotation", "text", &uuid));
+        ctx.execute(
+            t,
+            "insert into annotation (todo_id, text) values ($1, $2)",
+            &[&id, &text],
+        )
+    })?;
+    let tasks = webhook::tasks(client, ctx, &[id])?;
+    webhook::send(ctx, webhook::Event::Modified, &tasks);
+    Ok(())
+}
+
+/// Prints the annotations of task `id`, oldest first.
+pub fn show(client: &mut Client, ctx: &Context, id: i32) -> Result<(), Error> {
+    let rows = client.query(
+        "select a.time, a.text, t.uuid from annotation a join todo t on t.id = a.todo_id
+         where a.todo_id = $1 order by a.time, a.id",
+        &[&id],
+    )?;
+    if rows.is_empty() {
+        return Ok(());
+    }
+    println!();
+    println!("{}", tr!("Annotations:"));
+    for row in rows {
+        let time: SystemTime = row.get(0);
+        let text = ctx.decrypt(row.get(1), Location::new("annotation", "text", row.get(2)));
+        let mut lines = text.lines();
+        println!(
+            "  {}  {}",
+            ctx.format_time(time),
+            lines.next().unwrap_or_default()
+        );
+        for line in lines {
+            println!("    {}", line);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+// Archives of all of a user's data, written by `export --format archive` and read by
+// `import --format archive`: a JSON document with the user's rows of every table in
+// `export::TABLES`, so besides the tasks also their history, time entries, annotations,
+// attachments and dependencies, the templates, contexts and the trash. Rows keep their ids, so
+// the references between them hold after importing into a fresh database, at the same schema
+// version. Like the SQL export, encrypted values stay encrypted.
+//
+// The `version` of the archive changes with its layout, not with the schema.
+
+use postgres::error::SqlState;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::SystemTime;
+use todo_rust::client::Client;
+use todo_rust::migrate;
+
+use crate::error::Error;
+use crate::export::{SEQUENCES, TABLES};
+use crate::json::Json;
+use crate::time::format_iso;
+use crate::Context;
+
+const FORMAT: &str = "todo-archive";
+const VERSION: i64 = 1;
+
+// The tables whose rows have no owner, and their columns referring to tasks. The rows are the
+// user's through those tasks, so they must be tasks of the archive.
+const TASK_COLUMNS: &[(&str, &[&str])] = &[
+    ("time_entry", &["todo_id"]),
+    ("annotation", &["todo_id"]),
+    ("attachment", &["todo_id"]),
+    ("dependency", &["todo_id", "depends_on"]),
+];
+
+/// Prints the user's data as an archive.
+pub fn export(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let mut tables = Vec::new();
+    for (table, condition) in TABLES {
+        let order = if SEQUENCES.contains(table) {
+            "t.id"
+        } else {
+            "null"
+        };
+        let rows: Json = client
+            .query_one(
+                format!(
+                    "select coalesce(jsonb_agg(to_jsonb(t) order by {}), '[]')
+                     from {} t where {}",
+                    order, table, condition
+                )
+                .as_str(),
+                &[&ctx.user],
+            )?
+            .get(0);
+        tables.push((table.to_string(), rows));
+    }
+    let archive = Json::object(vec![
+        ("format", Json::from(FORMAT)),
+        ("version", Json::from(VERSION)),
+        (
+            "schema_version",
+            Json::from(migrate::current_version(client)?),
+        ),
+        ("user", Json::from(ctx.user.as_str())),
+        ("exported", Json::from(format_iso(SystemTime::now()))),
+        ("tables", Json::Object(tables)),
+    ]);
+    println!("{}", archive);
+    Ok(())
+}
+
+/// Imports an archive as the user's data. The ids in it must not be in use yet.
+pub fn import(client: &mut Client, ctx: &Context, path: &Path) -> Result<(), Error> {
+    let text = if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| Error::Other(e.to_string()))?;
+        text
+    } else {
+        fs::read_to_string(path)
+            .map_err(|e| Error::Other(tr!("Failed to read {}: {}", path.display(), e)))?
+    };
+    let archive = Json::parse(&text).map_err(|e| Error::Other(tr!("Invalid archive: {}", e)))?;
+    if archive.get("format").and_then(Json::as_str) != Some(FORMAT) {
+        return Err(Error::Other(tr!(
+            "Not an archive written by 'todo export --format archive': {}",
+            path.display()
+        )));
+    }
+    let version = archive.get("version").and_then(Json::as_i64).unwrap_or(0);
+    if version > VERSION {
+        return Err(Error::Other(tr!(
+            "The archive is of version {}, which is newer than this version of todo can read",
+            version
+        )));
+    }
+    let schema_version = archive.get("schema_version").and_then(Json::as_i64);
+    let current = migrate::current_version(client)?;
+    if schema_version != Some(current as i64) {
+        return Err(Error::Other(tr!(
+            "The archive is of schema version {}, the database of version {}, migrate it to the same version first",
+            schema_version.map_or("unknown".to_string(), |v| v.to_string()),
+            current
+        )));
+    }
+    let tables = archive.get("tables");
+    let rows = |table: &str| {
+        tables
+            .and_then(|t| t.get(table))
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+    };
+    let task_ids: Vec<i32> = rows("todo")
+        .iter()
+        .filter_map(|task| task.get("id").and_then(Json::as_i64))
+        .map(|id| id as i32)
+        .collect();
+    for (table, columns) in TASK_COLUMNS {
+        for row in rows(table) {
+            for column in *columns {
+                let task = row.get(column);
+                let id = task
+                    .and_then(Json::as_i64)
+                    .and_then(|id| i32::try_from(id).ok());
+                if !id.is_some_and(|id| task_ids.contains(&id)) {
+                    return Err(Error::Other(tr!(
+                        "The archive has {} rows of task {}, which isn't in it",
+                        table,
+                        task.map_or("null".to_string(), |task| task.to_string())
+                    )));
+                }
+            }
+        }
+    }
+
+    // The rows become the user's, whoever exported them.
+    let owner = Json::object(vec![("owner", Json::from(ctx.user.as_str()))]);
+    let mut transaction = client.transaction()?;
+    for (table, _) in TABLES {
+        let rows = rows(table);
+        if rows.is_empty() {
+            continue;
+        }
+        // Inserting the tasks recorded their history again, which is replaced by the original.
+        if *table == "todo_history" {
+            ctx.execute(
+                &mut transaction,
+                "delete from todo_history where owner = $1 and todo_id = any($2)",
+                &[&ctx.user, &task_ids],
+            )?;
+        }
+        ctx.execute(
+            &mut transaction,
+            &format!(
+                "insert into {0}
+                     select (jsonb_populate_record(null::{0}, r || $2)).*
+                     from jsonb_array_elements($1) r",
+                table
+            ),
+            &[&Json::Array(rows.to_vec()), &owner],
+        )
+        .map_err(|e| match e.code() {
+            Some(&SqlState::UNIQUE_VIOLATION) => Error::Other(tr!(
+                "The ids in the archive are already in use, import it into an empty database"
+            )),
+            _ => Error::from(e),
+        })?;
+    }
+    // Tasks restored from the trash get the owner stored with them.
+    ctx.execute(
+        &mut transaction,
+        "update trash set task = task || jsonb_build_object('owner', owner) where owner = $1",
+        &[&ctx.user],
+    )?;
+    for table in SEQUENCES {
+        ctx.execute(
+            &mut transaction,
+            &format!(
+                "select setval(pg_get_serial_sequence('{0}', 'id'), coalesce(max(id), 0) + 1, false)
+                 from {0}",
+                table
+            ),
+            &[],
+        )?;
+    }
+    ctx.finish(transaction)?;
+    println!("{}", tr!("Imported {} tasks", task_ids.len()));
+    Ok(())
+}
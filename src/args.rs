@@ -0,0 +1,138 @@
+/// The arguments following a command name, from which flags and options are taken out by name
+/// until only positional arguments remain.
+pub struct Args {
+    command: String,
+    args: Vec<String>,
+}
+
+impl Args {
+    pub fn new(command: &str, args: impl Iterator<Item = String>) -> Args {
+        Args {
+            command: command.to_string(),
+            args: args.collect(),
+        }
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Removes `name` if present, returning whether it was given.
+    pub fn flag(&mut self, name: &str) -> bool {
+        match self.args.iter().position(|a| a == name) {
+            Some(i) => {
+                self.args.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes `name` together with its value, given either as `--name value` or `--name=value`.
+    pub fn value(&mut self, name: &str) -> Result<Option<String>, String> {
+        let prefix = format!("{}=", name);
+        for i in 0..self.args.len() {
+            if self.args[i] == name {
+                if i + 1 >= self.args.len() {
+                    return Err(tr!("Missing value for '{}'", name));
+                }
+                self.args.remove(i);
+                return Ok(Some(self.args.remove(i)));
+            }
+            if let Some(value) = self.args[i].strip_prefix(&prefix) {
+                let value = value.to_string();
+                self.args.remove(i);
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Like `value`, but parses the value using `FromStr`.
+    pub fn parsed<T>(&mut self, name: &str) -> Result<Option<T>, String>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        match self.value(name)? {
+            Some(v) => v
+                .parse::<T>()
+                .map(Some)
+                .map_err(|e| tr!("Invalid value for '{}': {}, {}", name, v, e)),
+            None => Ok(None),
+        }
+    }
+
+    /// Turns filter terms such as `status:open` into the options they stand for, given as
+    /// `(term, option)` pairs such as `("status", "--status")`. Other arguments are kept.
+    pub fn terms(&mut self, terms: &[(&str, &str)]) {
+        for arg in &mut self.args {
+            if arg.starts_with("--") {
+                continue;
+            }
+            if let Some((term, value)) = arg.split_once(':') {
+                if let Some((_, option)) = terms.iter().find(|(t, _)| *t == term) {
+                    *arg = format!("{}={}", option, value);
+                }
+            }
+        }
+    }
+
+    /// Removes the first argument if it is a positional one, such as the `add` in
+    /// `template add`.
+    pub fn subcommand(&mut self) -> Option<String> {
+        match self.args.first() {
+            Some(first) if !first.starts_with("--") => Some(self.args.remove(0)),
+            _ => None,
+        }
+    }
+
+    /// Returns the remaining positional arguments, failing if any unknown options are left.
+    pub fn rest(self) -> Result<Vec<String>, String> {
+        match self.args.iter().find(|a| a.starts_with("--")) {
+            Some(option) => Err(tr!(
+                "Unknown option to '{}' command: {}",
+                self.command,
+                option
+            )),
+            None => Ok(self.args),
+        }
+    }
+
+    /// Returns the remaining arguments as given, options included.
+    pub fn all(self) -> Vec<String> {
+        self.args
+    }
+
+    /// Returns the single remaining positional argument.
+    pub fn single(self, what: &str) -> Result<String, String> {
+        let command = self.command.clone();
+        let mut rest = self.rest()?;
+        match rest.len() {
+            1 => Ok(rest.remove(0)),
+            0 => Err(tr!("Missing {} argument to '{}' command", what, command)),
+            _ => Err(tr!("Too many arguments to '{}' command", command)),
+        }
+    }
+
+    /// Returns the remaining positional argument, if any.
+    pub fn optional(self) -> Result<Option<String>, String> {
+        let command = self.command.clone();
+        let mut rest = self.rest()?;
+        match rest.len() {
+            0 => Ok(None),
+            1 => Ok(Some(rest.remove(0))),
+            _ => Err(tr!("Too many arguments to '{}' command", command)),
+        }
+    }
+
+    /// Fails if there are any arguments left.
+    pub fn none(self) -> Result<(), String> {
+        let command = self.command.clone();
+        if self.rest()?.is_empty() {
+            Ok(())
+        } else {
+            Err(tr!("Too many arguments to '{}' command", command))
+        }
+    }
+}
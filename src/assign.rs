@@ -0,0 +1,72 @@
+// Assigning tasks to users, to hand work over: `assign <id> <user>` and `unassign <id>`, with
+// `me` for the current user. Like other changes, assignments are kept in the task's history, and
+// they are sent to webhooks and hooks as their own event.
+
+use crate::error::Error;
+use crate::{run_bulk, webhook, Context, TodoRecord};
+use todo_rust::client::Client;
+
+/// The tasks `list --assigned-to` shows: those assigned to a user, or those assigned to no one.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    User(String),
+    Unassigned,
+}
+
+impl Filter {
+    pub fn parse(s: &str) -> Filter {
+        match s {
+            "unassigned" => Filter::Unassigned,
+            user => Filter::User(user.to_string()),
+        }
+    }
+
+    /// Replaces `me` with the user.
+    pub fn resolve(self, ctx: &Context) -> Filter {
+        match self {
+            Filter::User(user) => Filter::User(resolve_user(ctx, user)),
+            Filter::Unassigned => Filter::Unassigned,
+        }
+    }
+
+    pub fn matches(&self, task: &TodoRecord) -> bool {
+        match self {
+            Filter::User(user) => task.assignee.as_ref() == Some(user),
+            Filter::Unassigned => task.assignee.is_none(),
+        }
+    }
+}
+
+fn resolve_user(ctx: &Context, user: String) -> String {
+    if user == "me" {
+        ctx.user.clone()
+    } else {
+        user
+    }
+}
+
+/// Assigns the user's tasks `ids` to `assignee`, or to no one.
+pub fn run(
+    client: &mut Client,
+    ctx: &Context,
+    ids: &[i32],
+    assignee: Option<String>,
+) -> Result<(), Error> {
+    let assignee = assignee.map(|user| resolve_user(ctx, user));
+    let done = match &assignee {
+        Some(user) => tr!("assigned to {}", user),
+        None => tr!("unassigned"),
+    };
+    run_bulk(client, ctx, ids, &done, |t, id| {
+        ctx.execute(
+            t,
+            "update todo set assignee = $1 where id = $2 and owner = $3",
+            &[&assignee, &id, &ctx.user],
+        )
+    })?;
+    if !ctx.dry_run {
+        let tasks = webhook::tasks(client, ctx, ids)?;
+        webhook::send(ctx, webhook::Event::Assigned, &tasks);
+    }
+    Ok(())
+}
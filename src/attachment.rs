@@ -0,0 +1,237 @@
+// Files kept with a task, such as receipts or screenshots. An attachment holds either the file's
+// contents, or with `--link` only its path, and always its SHA-256 checksum, so a linked file that
+// changed can be told apart. With encryption enabled, names, paths and contents are encrypted.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::crypto::{self, Location};
+use crate::error::Error;
+use crate::output;
+use crate::task_ref::TaskRef;
+use crate::time;
+use crate::{webhook, Context};
+use todo_rust::client::Client;
+
+#[derive(Debug)]
+pub enum Action {
+    Add {
+        id: TaskRef,
+        path: PathBuf,
+        link: bool,
+    },
+    List {
+        id: TaskRef,
+    },
+    Get {
+        attachment: i32,
+        to: Option<PathBuf>,
+    },
+}
+
+pub fn run(client: &mut Client, ctx: &Context, action: Action) -> Result<(), Error> {
+    match action {
+        Action::Add { id, path, link } => {
+            let id = id.resolve(client, ctx)?;
+            add(client, ctx, id, &path, link)
+        }
+        Action::List { id } => {
+            let id = id.resolve(client, ctx)?;
+            list(client, ctx, id)
+        }
+        Action::Get { attachment, to } => get(client, ctx, attachment, to),
+    }
+}
+
+fn add(client: &mut Client, ctx: &Context, id: i32, path: &Path, link: bool) -> Result<(), Error> {
+    let contents = read(path)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| Error::Usage(tr!("Not a file: {}", path.display())))?;
+    let checksum = crypto::sha256_bytes(&contents);
+    let size = contents.len() as i64;
+    let mut transaction = client.transaction()?;
+    let uuid: String = transaction
+        .query_opt(
+            "select uuid from todo where id = $1 and owner = $2",
+            &[&id, &ctx.user],
+        )?
+        .ok_or_else(|| Error::NotFound(tr!("No task with id {}", id)))?
+        .get(0);
+    let (content, linked_path) = if link {
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            env::current_dir()
+                .map_err(|e| Error::Other(e.to_string()))?
+                .join(path)
+        };
+        let location = Location::new("attachment", "path", &uuid);
+        (
+            None,
+            Some(ctx.encrypt(&absolute.to_string_lossy(), location)),
+        )
+    } else {
+        let content = match &ctx.cipher {
+            Some(cipher) => {
+                cipher.encrypt_bytes(&contents, Location::new("attachment", "content", &uuid))
+            }
+            None => contents,
+        };
+        (Some(content), None)
+    };
+    let encrypted = ctx.cipher.is_some();
+    let row = transaction.query_one(
+        "insert into attachment (todo_id, name, size, sha256, content, path, encrypted)
+         values ($1, $2, $3, $4, $5, $6, $7)
+         returning id",
+        &[
+            &id,
+            &ctx.encrypt(&name, Location::new("attachment", "name", &uuid)),
+            &size,
+            &checksum,
+            &content,
+            &linked_path,
+            &encrypted,
+        ],
+    )?;
+    let attachment: i32 = row.get(0);
+    ctx.finish(transaction)?;
+    if ctx.dry_run {
+        println!("{}", tr!("Would attach {} to task {}", name, id));
+        return Ok(());
+    }
+    println!(
+        "{}",
+        tr!("Attached {} to task {} as {}", name, id, attachment)
+    );
+    let tasks = webhook::tasks(client, ctx, &[id])?;
+    webhook::send(ctx, webhook::Event::Modified, &tasks);
+    Ok(())
+}
+
+fn list(client: &mut Client, ctx: &Context, id: i32) -> Result<(), Error> {
+    let rows = client.query(
+        "select a.id, a.time, a.name, a.size, a.sha256, a.path, t.uuid
+         from attachment a
+         join todo t on t.id = a.todo_id
+         where a.todo_id = $1
+         order by a.time, a.id",
+        &[&id],
+    )?;
+    if rows.is_empty() && ctx.output.is_text() {
+        println!("{}", tr!("No attachments"));
+    }
+    for row in rows {
+        let attachment: i32 = row.get(0);
+        let time: SystemTime = row.get(1);
+        let uuid: &str = row.get(6);
+        let name = ctx.decrypt(row.get(2), Location::new("attachment", "name", uuid));
+        let size: i64 = row.get(3);
+        let checksum: &str = row.get(4);
+        let path: Option<String> = row
+            .get::<_, Option<&str>>(5)
+            .map(|p| ctx.decrypt(p, Location::new("attachment", "path", uuid)));
+        if !ctx.output.is_text() {
+            output::record(
+                ctx.output,
+                &[
+                    &attachment.to_string(),
+                    &time::format_iso(time),
+                    &name,
+                    &size.to_string(),
+                    checksum,
+                    path.as_deref().unwrap_or_default(),
+                ],
+            );
+            continue;
+        }
+        let mut details = vec![ctx.format_time(time), format_size(size)];
+        if let Some(path) = &path {
+            details.push(tr!("linked to {}", path));
+        }
+        println!("{}: {} ({})", attachment, name, details.join(", "));
+    }
+    Ok(())
+}
+
+// Writes an attachment to `to`, or to a file with its name in the current directory. Existing
+// files aren't overwritten.
+fn get(
+    client: &mut Client,
+    ctx: &Context,
+    attachment: i32,
+    to: Option<PathBuf>,
+) -> Result<(), Error> {
+    let row = client
+        .query_opt(
+            "select a.name, a.sha256, a.content, a.path, a.encrypted, t.uuid
+             from attachment a
+             join todo t on t.id = a.todo_id
+             where a.id = $1 and t.owner = $2",
+            &[&attachment, &ctx.user],
+        )?
+        .ok_or_else(|| Error::NotFound(tr!("No attachment with id {}", attachment)))?;
+    let uuid: String = row.get(5);
+    let name = ctx.decrypt(row.get(0), Location::new("attachment", "name", &uuid));
+    let checksum: String = row.get(1);
+    let content: Option<Vec<u8>> = row.get(2);
+    let path: Option<String> = row.get(3);
+    let encrypted: bool = row.get(4);
+    let contents = match (content, path) {
+        (Some(content), _) if encrypted => match &ctx.cipher {
+            Some(cipher) => cipher
+                .decrypt_bytes(&content, Location::new("attachment", "content", &uuid))
+                .map_err(Error::Other)?,
+            None => {
+                return Err(Error::Other(tr!(
+                    "Attachment {} is encrypted, set the encryption key to get it",
+                    attachment
+                )))
+            }
+        },
+        (Some(content), _) => content,
+        (None, Some(path)) => {
+            let path = ctx.decrypt(&path, Location::new("attachment", "path", &uuid));
+            let contents = read(Path::new(&path))?;
+            if crypto::sha256_bytes(&contents) != checksum {
+                return Err(Error::Other(tr!("{} changed since it was attached", path)));
+            }
+            contents
+        }
+        (None, None) => return Err(Error::Other(tr!("Attachment {} is empty", attachment))),
+    };
+    // The name comes from the database, so only its last component is used.
+    let to = to.unwrap_or_else(|| PathBuf::from(Path::new(&name).file_name().unwrap_or_default()));
+    if to.exists() {
+        return Err(Error::Other(tr!(
+            "{} already exists, choose another file with '--to'",
+            to.display()
+        )));
+    }
+    fs::write(&to, contents)
+        .map_err(|e| Error::Other(tr!("Failed to write {}: {}", to.display(), e)))?;
+    println!("{}", tr!("Saved {} to {}", name, to.display()));
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|e| Error::Other(tr!("Failed to read {}: {}", path.display(), e)))
+}
+
+fn format_size(bytes: i64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return tr!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
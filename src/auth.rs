@@ -0,0 +1,81 @@
+// `todo auth set|unset <name>`: keeping credentials in the OS keyring instead of in the config file
+// or the environment. The environment and the config file still take precedence, except for the
+// database password, which is only taken from the keyring with `database.keyring = true`.
+
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use crate::error::Error;
+use crate::keyring;
+
+/// The credentials that can be stored.
+const NAMES: [&str; 5] = ["database", "encryption", "github", "caldav", "remote"];
+
+#[derive(Debug)]
+pub enum Action {
+    Set { name: String },
+    Unset { name: String },
+}
+
+pub fn parse_name(name: &str) -> Result<String, String> {
+    if NAMES.contains(&name) {
+        Ok(name.to_string())
+    } else {
+        Err(tr!(
+            "Unknown credential: {}, expected database, encryption, github, caldav or remote",
+            name
+        ))
+    }
+}
+
+pub fn run(action: Action) -> Result<(), Error> {
+    match action {
+        Action::Set { name } => {
+            let secret = read_secret(&name)?;
+            if secret.is_empty() {
+                return Err(Error::Usage(tr!("The {} credential is empty", name)));
+            }
+            keyring::set(&name, &secret)?;
+            println!("{}", tr!("Stored the {} credential in the keyring", name));
+        }
+        Action::Unset { name } => {
+            if !keyring::delete(&name)? {
+                return Err(Error::NotFound(tr!(
+                    "There is no {} credential in the keyring",
+                    name
+                )));
+            }
+            println!(
+                "{}",
+                tr!("Removed the {} credential from the keyring", name)
+            );
+        }
+    }
+    Ok(())
+}
+
+// Reads the secret from stdin, asking for it without echoing it on a terminal.
+fn read_secret(name: &str) -> Result<String, Error> {
+    let terminal = io::stdin().is_terminal();
+    if terminal {
+        eprint!("{}", tr!("The {} credential: ", name));
+        io::stderr().flush().ok();
+        stty("-echo");
+    }
+    let mut secret = String::new();
+    let read = io::stdin().read_line(&mut secret);
+    if terminal {
+        stty("echo");
+        eprintln!();
+    }
+    read.map_err(|e| Error::Other(e.to_string()))?;
+    Ok(secret.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn stty(setting: &str) {
+    Command::new("stty")
+        .arg(setting)
+        .stdin(Stdio::inherit())
+        .status()
+        .ok();
+}
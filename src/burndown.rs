@@ -0,0 +1,71 @@
+use crate::error::Error;
+use crate::output;
+use crate::status;
+use crate::Context;
+use todo_rust::client::Client;
+
+// The width of the longest bar, in characters.
+const WIDTH: i64 = 50;
+const OPEN_BAR: &str = "█";
+const COMPLETED_BAR: &str = "░";
+
+/// Shows a chart of the tasks open at the end of each of the last `weeks` weeks, and the tasks
+/// completed in it. Cancelled tasks aren't counted, since it isn't known when they were cancelled.
+pub fn run(client: &mut Client, ctx: &Context, weeks: i32) -> Result<(), Error> {
+    let rows = client.query(
+        "select to_char(w.start, 'IYYY-\"W\"IW'),
+                 count(t.id) filter (where t.created_time < w.start + interval '1 week'
+                     and (t.status = any($3)
+                         or (t.status = 'done' and t.completed_time >= w.start + interval '1 week'))),
+                 count(t.id) filter (where t.status = 'done' and t.completed_time >= w.start
+                     and t.completed_time < w.start + interval '1 week')
+             from generate_series(
+                 date_trunc('week', now()) - ($1 - 1) * interval '1 week',
+                 date_trunc('week', now()),
+                 interval '1 week'
+             ) as w(start)
+             left join todo t on t.owner = $2
+             group by w.start
+             order by w.start",
+        &[&weeks, &ctx.user, &status::OPEN],
+    )?;
+    let weeks: Vec<(String, i64, i64)> = rows
+        .iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect();
+
+    if !ctx.output.is_text() {
+        for (week, open, completed) in &weeks {
+            output::record(
+                ctx.output,
+                &[week, &open.to_string(), &completed.to_string()],
+            );
+        }
+        return Ok(());
+    }
+    let max = weeks
+        .iter()
+        .map(|(_, open, completed)| open + completed)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    // Rounds up, so a week with any tasks gets some bar.
+    let bar = |n: i64| (n * WIDTH + max - 1) / max;
+    println!("{}", tr!("{} open  {} completed", OPEN_BAR, COMPLETED_BAR));
+    for (week, open, completed) in &weeks {
+        let bars = format!(
+            "{}{}",
+            OPEN_BAR.repeat(bar(*open) as usize),
+            COMPLETED_BAR.repeat(bar(*completed) as usize)
+        );
+        // Both bars may be rounded up.
+        println!(
+            "{}  {:<width$}  {}",
+            week,
+            bars,
+            tr!("{} open, {} completed", open, completed),
+            width = WIDTH as usize + 1
+        );
+    }
+    Ok(())
+}
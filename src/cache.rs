@@ -0,0 +1,261 @@
+// A copy of the tasks `list` showed last, kept in the data directory so `list` still works, read
+// only, when the database can't be reached. Names and notes are kept as they are in the database,
+// so with encryption enabled they stay encrypted on disk. Every profile and user has its own copy.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::json::Json;
+use crate::outbox;
+use crate::status::Status;
+use crate::{data_dir, list_matches, print_list, stale_filter, time, Command, Context, TodoRecord};
+
+fn path(ctx: &Context) -> Option<PathBuf> {
+    let name = match &ctx.profile {
+        Some(profile) => format!("cache-{}-{}.json", profile, ctx.user),
+        None => format!("cache-{}.json", ctx.user),
+    };
+    data_dir().map(|dir| dir.join(name))
+}
+
+/// Keeps `tasks`, listed with `statuses` in the `active` context. Failing to do so only logs a
+/// warning, since the cache is a convenience.
+pub fn save(ctx: &Context, statuses: &[Status], active: &Option<String>, tasks: &[TodoRecord]) {
+    let path = match path(ctx) {
+        Some(path) => path,
+        None => return,
+    };
+    let cache = Json::object(vec![
+        ("saved", seconds(SystemTime::now())),
+        (
+            "statuses",
+            Json::Array(statuses.iter().map(|s| Json::from(s.as_str())).collect()),
+        ),
+        ("context", Json::from(active.clone())),
+        ("tasks", Json::Array(tasks.iter().map(task_json).collect())),
+    ]);
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| write_private(&path, &cache.to_string()));
+    if let Err(e) = written {
+        log::warn!(
+            "{}",
+            tr!("Failed to write the cache {}: {}", path.display(), e)
+        );
+    }
+}
+
+// Writes a file only the user can read, since it has the user's tasks.
+pub fn write_private(path: &PathBuf, contents: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    std::io::Write::write_all(&mut options.open(path)?, contents.as_bytes())
+}
+
+/// Runs `list` on the cached tasks, after connecting failed with `error`. The output starts with
+/// a note saying how old the tasks are.
+pub fn list(ctx: &Context, command: Command, error: postgres::Error) -> Result<(), Error> {
+    let (statuses, fields, sort, archived, pinned, stale, assigned, due_before, format) =
+        match command {
+            Command::List {
+                statuses,
+                fields,
+                sort,
+                archived,
+                pinned,
+                stale,
+                assigned,
+                due_before,
+                format,
+            } => (
+                statuses, fields, sort, archived, pinned, stale, assigned, due_before, format,
+            ),
+            _ => return Err(Error::Connection(error)),
+        };
+    let cache = match load(ctx) {
+        Some(cache) => cache,
+        None => return Err(Error::Connection(error)),
+    };
+    log::info!("Could not connect to the database: {}", error);
+
+    let note = tr!(
+        "The database can't be reached, showing the tasks as listed {}",
+        ctx.format_time(cache.saved)
+    );
+    if ctx.output.is_text() {
+        println!("[{}]", note);
+    } else {
+        log::warn!("{}", note);
+    }
+    if let Some(missing) = statuses.iter().find(|s| !cache.statuses.contains(s)) {
+        log::warn!(
+            "{}",
+            tr!(
+                "No {} tasks were listed before, so they are missing",
+                missing.as_str()
+            )
+        );
+    }
+    let filters = crate::fields::parse_all(&ctx.fields, &fields).map_err(Error::Usage)?;
+    let assigned = assigned.map(|a| a.resolve(ctx));
+    let stale = stale_filter(ctx, stale)?;
+    // Without the database, only the times it doesn't have to interpret can be used.
+    let due_before = due_before
+        .map(|t| time::parse_offline(&t).ok_or_else(|| Error::Usage(tr!("Invalid time: {}", t))))
+        .transpose()?;
+    let rows = cache
+        .tasks
+        .into_iter()
+        .filter(|row| statuses.contains(&row.status))
+        .filter(|row| {
+            let assigned = assigned.as_ref();
+            list_matches(row, archived, pinned, stale, &filters, assigned, due_before)
+        })
+        .collect();
+    print_list(ctx, rows, sort, format.as_ref(), &cache.context, None);
+    // Tasks added since can't be filtered like the others, so they're all shown.
+    if ctx.output.is_text() {
+        for task in outbox::load(ctx) {
+            println!(
+                "{}: {} ({})",
+                task.temporary_id(),
+                ctx.decrypt(&task.name, Location::task("name", &task.uuid)),
+                tr!("queued")
+            );
+        }
+    }
+    Ok(())
+}
+
+struct Cache {
+    saved: SystemTime,
+    // The statuses and context the tasks were listed with.
+    statuses: Vec<Status>,
+    context: Option<String>,
+    tasks: Vec<TodoRecord>,
+}
+
+fn load(ctx: &Context) -> Option<Cache> {
+    let cache = Json::parse(&fs::read_to_string(path(ctx)?).ok()?).ok()?;
+    let statuses = cache
+        .get("statuses")?
+        .as_array()?
+        .iter()
+        .map(|s| s.as_str().and_then(Status::parse))
+        .collect::<Option<_>>()?;
+    let tasks = cache
+        .get("tasks")?
+        .as_array()?
+        .iter()
+        .map(task)
+        .collect::<Option<_>>()?;
+    Some(Cache {
+        saved: cache.get("saved").and_then(time)?,
+        statuses,
+        context: cache
+            .get("context")
+            .and_then(Json::as_str)
+            .map(str::to_string),
+        tasks,
+    })
+}
+
+fn seconds(t: SystemTime) -> Json {
+    Json::from(
+        t.duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default(),
+    )
+}
+
+fn time(json: &Json) -> Option<SystemTime> {
+    json.as_f64()
+        .filter(|s| *s >= 0.0)
+        .map(|s| UNIX_EPOCH + Duration::from_secs_f64(s))
+}
+
+fn task_json(task: &TodoRecord) -> Json {
+    let time = |t: Option<SystemTime>| t.map(seconds).unwrap_or(Json::Null);
+    Json::object(vec![
+        ("id", Json::from(task.id)),
+        ("name", Json::from(task.name.as_str())),
+        ("created", seconds(task.created_time)),
+        ("completed", time(task.completed_time)),
+        ("due", time(task.due_time)),
+        ("snoozed_until", time(task.snoozed_until)),
+        ("reviewed", time(task.reviewed_time)),
+        ("archived", time(task.archived_time)),
+        ("description", Json::from(task.description.clone())),
+        ("project", Json::from(task.project.clone())),
+        ("priority", Json::from(task.priority.map(i32::from))),
+        ("status", Json::from(task.status.as_str())),
+        ("context", Json::from(task.context.clone())),
+        ("fields", task.fields.clone()),
+        ("uuid", Json::from(task.uuid.as_str())),
+        ("sort_order", Json::from(task.sort_order)),
+        ("pinned", Json::from(task.pinned)),
+        ("estimate", Json::from(task.estimate)),
+        ("assignee", Json::from(task.assignee.clone())),
+        ("waiting_until", time(task.waiting_until)),
+        ("waiting_for", Json::from(task.waiting_for.clone())),
+        ("inbox", Json::from(task.inbox)),
+        ("external_id", Json::from(task.external_id.clone())),
+    ])
+}
+
+// Reads a task written by `task_json`. Attributes that aren't cached are left empty.
+fn task(json: &Json) -> Option<TodoRecord> {
+    let text = |key: &str| json.get(key).and_then(Json::as_str).map(str::to_string);
+    let time = |key: &str| json.get(key).and_then(time);
+    Some(TodoRecord {
+        id: json.get("id")?.as_i64()? as i32,
+        name: text("name")?,
+        created_time: time("created")?,
+        completed_time: time("completed"),
+        due_time: time("due"),
+        remind_before: None,
+        reminded_time: None,
+        snoozed_until: time("snoozed_until"),
+        description: text("description"),
+        project: text("project"),
+        github_repo: None,
+        github_issue: None,
+        github_url: None,
+        github_close: false,
+        caldav_uid: None,
+        caldav_href: None,
+        caldav_etag: None,
+        caldav_state: None,
+        owner: None,
+        priority: json
+            .get("priority")
+            .and_then(Json::as_i64)
+            .map(|p| p as i16),
+        status: Status::parse(&text("status")?)?,
+        context: text("context"),
+        fields: json.get("fields").cloned().unwrap_or(Json::Null),
+        uuid: text("uuid").unwrap_or_default(),
+        reviewed_time: time("reviewed"),
+        archived_time: time("archived"),
+        sort_order: json.get("sort_order").and_then(Json::as_f64),
+        pinned: json.get("pinned") == Some(&Json::Bool(true)),
+        estimate: json
+            .get("estimate")
+            .and_then(Json::as_i64)
+            .map(|e| e as i32),
+        assignee: text("assignee"),
+        waiting_until: time("waiting_until"),
+        waiting_for: text("waiting_for"),
+        inbox: json.get("inbox") == Some(&Json::Bool(true)),
+        external_id: text("external_id"),
+    })
+}
@@ -0,0 +1,394 @@
+use std::env;
+use std::time::SystemTime;
+
+use crate::conflict::{self, Conflict, Resolution};
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::http;
+use crate::ical::{self, VTodo};
+use crate::keyring;
+use crate::task_ref::new_uuid;
+use crate::Context;
+use todo_rust::client::Client;
+
+// The name of the target in the conflict policies and `sync status`.
+const TARGET: &str = "caldav";
+
+/// A CalDAV calendar collection.
+pub struct Calendar {
+    url: String,
+    authorization: Option<String>,
+}
+
+impl Calendar {
+    /// The password for `user` is taken from `CALDAV_PASSWORD`, or else from the keyring.
+    pub fn new(url: &str, user: Option<String>) -> Calendar {
+        let url = if url.ends_with('/') {
+            url.to_string()
+        } else {
+            format!("{}/", url)
+        };
+        let authorization = user.map(|user| {
+            let password = env::var("CALDAV_PASSWORD")
+                .ok()
+                .or_else(|| keyring::get("caldav"))
+                .unwrap_or_default();
+            http::basic_auth(&user, &password)
+        });
+        Calendar { url, authorization }
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Option<&str>,
+    ) -> Result<http::Response, String> {
+        let mut all_headers = headers.to_vec();
+        if let Some(authorization) = &self.authorization {
+            all_headers.push(("Authorization", authorization));
+        }
+        http::request(method, url, &all_headers, body)
+    }
+
+    /// Fetches all VTODOs in the calendar, with their URL and ETag.
+    fn todos(&self) -> Result<Vec<(String, Option<String>, VTodo)>, String> {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#;
+        let response = self.request(
+            "REPORT",
+            &self.url,
+            &[
+                ("Depth", "1"),
+                ("Content-Type", "application/xml; charset=utf-8"),
+            ],
+            Some(body),
+        )?;
+        if response.status != 207 {
+            return Err(tr!(
+                "Unexpected status {} listing {}",
+                response.status,
+                self.url
+            ));
+        }
+        let mut todos = Vec::new();
+        for item in elements(&response.body, "response") {
+            let href = elements(item, "href").first().map(|h| unescape_xml(h));
+            let etag = elements(item, "getetag").first().map(|e| unescape_xml(e));
+            let data = elements(item, "calendar-data")
+                .first()
+                .map(|d| unescape_xml(d));
+            if let (Some(href), Some(data)) = (href, data) {
+                for todo in ical::parse(&data, false) {
+                    todos.push((self.resolve(&href), etag.clone(), todo));
+                }
+            }
+        }
+        Ok(todos)
+    }
+
+    /// Uploads a VTODO. When `etag` is given the upload only succeeds if the item on the server
+    /// is unchanged, otherwise it only succeeds if the item doesn't exist yet. Returns the new
+    /// ETag if the server sent one, or `Ok(None)` on a precondition failure (a conflict).
+    fn put(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        todo: &VTodo,
+    ) -> Result<Option<Option<String>>, String> {
+        let precondition = match etag {
+            Some(etag) => ("If-Match", etag),
+            None => ("If-None-Match", "*"),
+        };
+        let response = self.request(
+            "PUT",
+            url,
+            &[
+                ("Content-Type", "text/calendar; charset=utf-8"),
+                precondition,
+            ],
+            Some(&ical::to_ics(todo, Some(SystemTime::now()))),
+        )?;
+        match response.status {
+            412 => Ok(None),
+            s if (200..300).contains(&s) => {
+                Ok(Some(response.header("ETag").map(|e| e.to_string())))
+            }
+            s => Err(tr!("Unexpected status {} uploading {}", s, url)),
+        }
+    }
+
+    // Turns an href, which is usually an absolute path, into a URL.
+    fn resolve(&self, href: &str) -> String {
+        if href.contains("://") {
+            return href.to_string();
+        }
+        let origin_end = self
+            .url
+            .find("://")
+            .and_then(|i| self.url[i + 3..].find('/').map(|j| i + 3 + j))
+            .unwrap_or(self.url.len());
+        format!("{}{}", &self.url[..origin_end], href)
+    }
+}
+
+struct Local {
+    id: i32,
+    uuid: String,
+    todo: VTodo,
+    href: Option<String>,
+    etag: Option<String>,
+    state: Option<String>,
+}
+
+fn local_todos(client: &mut Client, ctx: &Context) -> Result<Vec<Local>, postgres::Error> {
+    Ok(client
+        .query(
+            "select id, coalesce(caldav_uid, ''), name, description, due_time, status,
+                 completed_time, caldav_href, caldav_etag, caldav_state, uuid
+             from todo
+             where owner = $1
+             order by id",
+            &[&ctx.user],
+        )?
+        .into_iter()
+        .map(|row| Local {
+            id: row.get(0),
+            uuid: row.get(10),
+            todo: VTodo {
+                uid: row.get(1),
+                summary: ctx.decrypt(row.get(2), Location::task("name", row.get(10))),
+                description: row
+                    .get::<_, Option<&str>>(3)
+                    .map(|d| ctx.decrypt(d, Location::task("description", row.get(10)))),
+                due: row.get(4),
+                status: row.get(5),
+                completed_time: row.get(6),
+                modified: None,
+            },
+            href: row.get(7),
+            etag: row.get(8),
+            state: row.get(9),
+        })
+        .collect())
+}
+
+// A fingerprint of the state of a task as last synchronized, used to detect local changes since
+// then.
+fn state(ctx: &Context, todo: &VTodo) -> String {
+    ctx.fingerprint(&ical::to_ics(todo, None))
+}
+
+// When the task `id` was last changed in the database.
+fn last_modified(client: &mut Client, id: i32) -> Result<Option<SystemTime>, postgres::Error> {
+    Ok(client
+        .query_one(
+            "select max(time) from todo_history where todo_id = $1",
+            &[&id],
+        )?
+        .get(0))
+}
+
+/// Synchronizes tasks with a CalDAV calendar. Items changed on the server since the last sync are
+/// pulled first, resolving tasks changed on both sides by the `caldav` conflict policy, then new
+/// and locally changed tasks are pushed.
+pub fn sync(client: &mut Client, ctx: &Context, calendar: &Calendar) -> Result<(), Error> {
+    let remote = calendar.todos()?;
+    let local = local_todos(client, ctx)?;
+    conflict::clear(client, ctx, TARGET)?;
+    // Tasks whose conflict is unresolved are left alone on both sides.
+    let mut unresolved = Vec::new();
+    for (href, etag, todo) in &remote {
+        let existing = local.iter().find(|l| l.todo.uid == todo.uid);
+        match existing {
+            Some(l) if l.etag.is_some() && l.etag == *etag => continue,
+            Some(l) => {
+                if l.state.as_deref() != Some(&state(ctx, &l.todo)) {
+                    let conflict = Conflict {
+                        item: l.id.to_string(),
+                        local: Some(l.todo.summary.clone()),
+                        remote: Some(todo.summary.clone()),
+                        local_modified: last_modified(client, l.id)?,
+                        remote_modified: todo.modified,
+                    };
+                    match conflict::resolve(ctx, ctx.conflicts.caldav, &conflict)? {
+                        Resolution::Remote => println!(
+                            "{}",
+                            tr!(
+                                "{}: changed on both sides, keeping the server's version",
+                                l.id
+                            )
+                        ),
+                        // Taking the server's ETag lets the local version replace it when pushing.
+                        Resolution::Local => {
+                            client.execute(
+                                "update todo set caldav_href = $1, caldav_etag = $2 where id = $3",
+                                &[href, etag, &l.id],
+                            )?;
+                            println!(
+                                "{}",
+                                tr!("{}: changed on both sides, keeping the local version", l.id)
+                            );
+                            continue;
+                        }
+                        Resolution::Unresolved => {
+                            conflict::record(client, ctx, TARGET, &conflict)?;
+                            unresolved.push(l.id);
+                            continue;
+                        }
+                    }
+                }
+                client.execute(
+                    "update todo
+                         set name = $1, description = $2, due_time = $3, status = $4,
+                             completed_time = $5, caldav_href = $6, caldav_etag = $7,
+                             caldav_state = $8
+                         where id = $9",
+                    &[
+                        &ctx.encrypt(&todo.summary, Location::task("name", &l.uuid)),
+                        &todo
+                            .description
+                            .as_deref()
+                            .map(|d| ctx.encrypt(d, Location::task("description", &l.uuid))),
+                        &todo.due,
+                        &todo.status,
+                        &todo.completed_time,
+                        href,
+                        etag,
+                        &state(ctx, todo),
+                        &l.id,
+                    ],
+                )?;
+                println!("{}", tr!("{}: updated from server", l.id));
+            }
+            None => {
+                // A task imported before keeps its uuid, which its values are encrypted with.
+                let uuid = match client.query_opt(
+                    "select uuid from todo where owner = $1 and external_id = 'ical:' || $2",
+                    &[&ctx.user, &todo.uid],
+                )? {
+                    Some(row) => row.get(0),
+                    None => new_uuid(),
+                };
+                let row = client.query_one(
+                    "insert into todo (name, description, due_time, status, completed_time,
+                             caldav_uid, caldav_href, caldav_etag, caldav_state, owner,
+                             external_id, uuid)
+                         values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 'ical:' || $6, $11)
+                         on conflict (owner, external_id) do update
+                             set name = excluded.name, description = excluded.description,
+                                 due_time = excluded.due_time, status = excluded.status,
+                                 completed_time = excluded.completed_time,
+                                 caldav_uid = excluded.caldav_uid,
+                                 caldav_href = excluded.caldav_href,
+                                 caldav_etag = excluded.caldav_etag,
+                                 caldav_state = excluded.caldav_state
+                         returning id",
+                    &[
+                        &ctx.encrypt(&todo.summary, Location::task("name", &uuid)),
+                        &todo
+                            .description
+                            .as_deref()
+                            .map(|d| ctx.encrypt(d, Location::task("description", &uuid))),
+                        &todo.due,
+                        &todo.status,
+                        &todo.completed_time,
+                        &todo.uid,
+                        href,
+                        etag,
+                        &state(ctx, todo),
+                        &ctx.user,
+                        &uuid,
+                    ],
+                )?;
+                let id: i32 = row.get(0);
+                println!("{}", tr!("{}: pulled {}", id, todo.summary));
+            }
+        }
+    }
+
+    for mut l in local_todos(client, ctx)? {
+        if l.state.as_deref() == Some(&state(ctx, &l.todo)) || unresolved.contains(&l.id) {
+            continue;
+        }
+        if l.todo.uid.is_empty() {
+            l.todo.uid = format!("{}@todo-rust", new_uid(client)?);
+        }
+        let href = l
+            .href
+            .clone()
+            .unwrap_or_else(|| format!("{}{}.ics", calendar.url, l.todo.uid));
+        match calendar.put(&href, l.etag.as_deref(), &l.todo)? {
+            Some(etag) => {
+                client.execute(
+                    "update todo
+                         set caldav_uid = $1, caldav_href = $2, caldav_etag = $3, caldav_state = $4,
+                             external_id = coalesce(external_id, 'ical:' || $1)
+                         where id = $5",
+                    &[&l.todo.uid, &href, &etag, &state(ctx, &l.todo), &l.id],
+                )?;
+                println!("{}", tr!("{}: pushed {}", l.id, l.todo.summary));
+            }
+            None => log::warn!(
+                "{}",
+                tr!("{}: changed on the server during the sync, try again", l.id)
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn new_uid(client: &mut Client) -> Result<String, postgres::Error> {
+    Ok(client
+        .query_one("select gen_random_uuid()::text", &[])?
+        .get(0))
+}
+
+// Returns the contents of all elements with the given local name, ignoring namespace prefixes.
+fn elements<'a>(xml: &'a str, local_name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = &rest[..end];
+        let name = tag.split_whitespace().next().unwrap_or("");
+        if name.starts_with('/') || tag.ends_with('/') {
+            continue;
+        }
+        if name.rsplit(':').next() == Some(local_name) {
+            let content = &rest[end + 1..];
+            let close = format!("</{}>", name);
+            if let Some(close_start) = content.find(&close) {
+                found.push(&content[..close_start]);
+                rest = &content[close_start + close.len()..];
+            }
+        }
+    }
+    found
+}
+
+fn unescape_xml(s: &str) -> String {
+    let s = s.trim();
+    if let Some(cdata) = s
+        .strip_prefix("<![CDATA[")
+        .and_then(|s| s.strip_suffix("]]>"))
+    {
+        return cdata.to_string();
+    }
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&amp;", "&")
+}
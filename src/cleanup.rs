@@ -0,0 +1,139 @@
+// Retention rules for finished tasks, from the `[cleanup]` section of the config: `archive_after`
+// and `purge_after` give the number of days after completion (or cancellation) after which tasks
+// are archived, hiding them from `list` unless `--archived` is given, or deleted. The rules are
+// applied by `todo gc`, and on startup unless `on_startup = false`.
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::status;
+use crate::Context;
+use todo_rust::client::Client;
+
+// When a finished task was finished. Cancelling doesn't record a time, so for cancelled tasks
+// it is their last change.
+const FINISHED_TIME: &str = "coalesce(completed_time,
+    (select max(h.time) from todo_history h where h.todo_id = todo.id), created_time)";
+
+#[derive(Debug, Default)]
+pub struct Policy {
+    archive_after: Option<i32>,
+    purge_after: Option<i32>,
+    on_startup: bool,
+}
+
+impl Policy {
+    pub fn load(config: &Config) -> Result<Policy, String> {
+        let days = |key: &str| {
+            config
+                .get(key)
+                .map(|v| {
+                    v.parse::<i32>()
+                        .ok()
+                        .filter(|&n| n >= 0)
+                        .ok_or_else(|| tr!("Invalid {} in the config file: {}", key, v))
+                })
+                .transpose()
+        };
+        Ok(Policy {
+            archive_after: days("cleanup.archive_after")?,
+            purge_after: days("cleanup.purge_after")?,
+            on_startup: config.get("cleanup.on_startup") != Some("false"),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.archive_after.is_none() && self.purge_after.is_none()
+    }
+
+    /// Whether to apply the rules on startup.
+    pub fn on_startup(&self) -> bool {
+        self.on_startup && !self.is_empty()
+    }
+}
+
+/// Applies the rules to the user's tasks, returning the number of tasks archived and purged.
+pub fn apply(client: &mut Client, ctx: &Context) -> Result<(u64, u64), Error> {
+    let policy = &ctx.cleanup;
+    let mut transaction = client.transaction()?;
+    // Purging first, so tasks about to be deleted aren't archived.
+    let purged = match policy.purge_after {
+        Some(days) => ctx.execute(
+            &mut transaction,
+            &format!(
+                "delete from todo
+                 where owner = $1 and not status = any($2)
+                     and {} < now() - $3::int * interval '1 day'",
+                FINISHED_TIME
+            ),
+            &[&ctx.user, &status::UNFINISHED, &days],
+        )?,
+        None => 0,
+    };
+    let archived = match policy.archive_after {
+        Some(days) => ctx.execute(
+            &mut transaction,
+            &format!(
+                "update todo set archived_time = now()
+                 where owner = $1 and not status = any($2) and archived_time is null
+                     and {} < now() - $3::int * interval '1 day'",
+                FINISHED_TIME
+            ),
+            &[&ctx.user, &status::UNFINISHED, &days],
+        )?,
+        None => 0,
+    };
+    ctx.finish(transaction)?;
+    Ok((archived, purged))
+}
+
+/// Applies the rules, reporting what was done.
+pub fn run(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    if ctx.cleanup.is_empty() {
+        return Err(Error::Usage(tr!(
+            "No cleanup rules, set archive_after or purge_after in the [cleanup] section of the config"
+        )));
+    }
+    let (archived, purged) = apply(client, ctx)?;
+    println!(
+        "{}",
+        tr!("Archived {} tasks, purged {} tasks", archived, purged)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{migrations, test_db};
+
+    #[test]
+    fn keeps_old_waiting_tasks() {
+        let mut client = match test_db::connect("cleanup_waiting") {
+            Some(client) => client,
+            None => return,
+        };
+        let mut ctx = test_db::context();
+        migrations::up(&mut client, &ctx, None).unwrap();
+        client
+            .batch_execute(
+                "insert into todo (name, owner, status, created_time) values
+                     ('waiting', 'alice', 'waiting', now() - interval '90 days'),
+                     ('done', 'alice', 'done', now() - interval '90 days');
+                 delete from todo_history",
+            )
+            .unwrap();
+        ctx.cleanup = Policy {
+            archive_after: Some(30),
+            purge_after: Some(60),
+            on_startup: false,
+        };
+        assert_eq!(apply(&mut client, &ctx).unwrap(), (0, 1));
+        ctx.cleanup.purge_after = None;
+        assert_eq!(apply(&mut client, &ctx).unwrap(), (0, 0));
+        let row = client
+            .query_one("select name, archived_time is null from todo", &[])
+            .unwrap();
+        assert_eq!(row.get::<_, String>(0), "waiting");
+        assert!(row.get::<_, bool>(1));
+    }
+}
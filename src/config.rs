@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Settings read from the config file, a small subset of TOML: `[section]` headers and
+/// `key = value` lines, where a value is a quoted string, a bare word (numbers, booleans) or an
+/// array of strings. Keys are stored with their section as prefix, e.g. `database.connection`.
+///
+/// A profile is a named set of settings that override the others when it is selected, such as
+/// `connection` in `[profiles.work.database]` for `database.connection` in the `work` profile.
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    values: BTreeMap<String, Value>,
+    profile: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    List(Vec<String>),
+}
+
+/// The config file location: `$TODO_CONFIG`, or `todo/config.toml` in the XDG config directory.
+pub fn path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("TODO_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let base = env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok()?;
+    Some(base.join("todo").join("config.toml"))
+}
+
+impl Config {
+    /// Loads the config file, returning an empty config if there is none.
+    pub fn load() -> Result<Config, String> {
+        match path() {
+            Some(path) if path.exists() => {
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| tr!("Failed to read {}: {}", path.display(), e))?;
+                Config::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut values = BTreeMap::new();
+        let mut section = String::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = format!("{}.", name.trim());
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected 'key = value'", n + 1))?;
+            let key = unquote(key.trim()).unwrap_or_else(|| key.trim().to_string());
+            let value = parse_value(value.trim())
+                .ok_or_else(|| format!("line {}: invalid value", n + 1))?;
+            values.insert(format!("{}{}", section, key), value);
+        }
+        Ok(Config {
+            values,
+            profile: None,
+        })
+    }
+
+    /// Applies the settings of profile `name` over the others.
+    pub fn with_profile(mut self, name: &str) -> Result<Config, String> {
+        let prefix = format!("profiles.{}.", name);
+        let overrides: Vec<(String, Value)> = self
+            .values
+            .iter()
+            .filter_map(|(key, value)| {
+                Some((key.strip_prefix(&prefix)?.to_string(), value.clone()))
+            })
+            .collect();
+        if overrides.is_empty() {
+            return Err(tr!(
+                "Unknown profile: {}, define it in the config file",
+                name
+            ));
+        }
+        self.values.extend(overrides);
+        self.profile = Some(name.to_string());
+        Ok(self)
+    }
+
+    /// The keys with values other than in `other`, including those only one of them has.
+    pub fn changes(&self, other: &Config) -> Vec<String> {
+        let mut keys: Vec<&String> = self.values.keys().chain(other.values.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        keys.into_iter()
+            .filter(|key| self.values.get(*key) != other.values.get(*key))
+            .cloned()
+            .collect()
+    }
+
+    /// The selected profile, if any.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match self.values.get(key) {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns a list value; a single string counts as a list of one.
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        match self.values.get(key) {
+            Some(Value::List(items)) => items.clone(),
+            Some(Value::String(s)) => vec![s.clone()],
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the names of the tables in `[section]`, such as `standup` for `[reports.standup]`
+    /// in `reports`.
+    pub fn tables(&self, section: &str) -> Vec<&str> {
+        let prefix = format!("{}.", section);
+        let mut names: Vec<&str> = self
+            .values
+            .keys()
+            .filter_map(|key| Some(key.strip_prefix(&prefix)?.split_once('.')?.0))
+            .collect();
+        names.dedup();
+        names
+    }
+
+    /// Returns the string values in `[section]`, by key without the section prefix.
+    pub fn section(&self, section: &str) -> Vec<(&str, &str)> {
+        let prefix = format!("{}.", section);
+        self.values
+            .iter()
+            .filter_map(|(key, value)| match value {
+                Value::String(s) => Some((key.strip_prefix(&prefix)?, s.as_str())),
+                Value::List(_) => None,
+            })
+            .collect()
+    }
+}
+
+// Removes a `#` comment, unless the `#` is inside a quoted string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '\\' if quoted => escaped = !escaped,
+            '"' if !escaped => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => escaped = false,
+        }
+        if c != '\\' {
+            escaped = false;
+        }
+    }
+    line
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+    if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        return split_list(items)
+            .iter()
+            .map(|item| unquote(item.trim()))
+            .collect::<Option<Vec<String>>>()
+            .map(Value::List);
+    }
+    if value.starts_with('"') {
+        return unquote(value).map(Value::String);
+    }
+    if value.is_empty() {
+        return None;
+    }
+    Some(Value::String(value.to_string()))
+}
+
+// Splits the items of an array on commas outside of quotes, dropping a trailing comma.
+fn split_list(items: &str) -> Vec<String> {
+    let mut list = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in items.chars() {
+        match c {
+            ',' if !quoted => list.push(std::mem::take(&mut current)),
+            _ => {
+                if c == '"' && !escaped {
+                    quoted = !quoted;
+                }
+                escaped = c == '\\' && !escaped;
+                current.push(c);
+            }
+        }
+    }
+    list.push(current);
+    list.retain(|item| !item.trim().is_empty());
+    list
+}
+
+fn unquote(s: &str) -> Option<String> {
+    let inner = s.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next()? {
+                'n' => unquoted.push('\n'),
+                't' => unquoted.push('\t'),
+                c => unquoted.push(c),
+            }
+        } else {
+            unquoted.push(c);
+        }
+    }
+    Some(unquoted)
+}
@@ -0,0 +1,252 @@
+// Conflicts found by a sync: tasks changed both in the database and in the calendar or repository
+// since the last sync. How they are resolved is set with `conflicts` in the `[sync]` section of
+// the config, or in `[caldav]` or `[git]` for one target:
+//
+// - `prefer-remote` (the default) keeps the calendar's or repository's version,
+// - `prefer-local` keeps the database's version,
+// - `newest-wins` keeps the version changed last, or the remote one if that isn't known,
+// - `interactive` asks for every conflict.
+//
+// Conflicts left unresolved, by skipping them or when there is no one to ask, are kept until the
+// next sync of the target, and listed by `todo sync status`.
+
+use std::io::{self, IsTerminal, Write};
+use std::time::SystemTime;
+
+use crate::config::Config;
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::output;
+use crate::time;
+use crate::Context;
+use todo_rust::client::Client;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    PreferLocal,
+    PreferRemote,
+    NewestWins,
+    Interactive,
+}
+
+impl Policy {
+    fn parse(s: &str) -> Option<Policy> {
+        match s {
+            "prefer-local" => Some(Policy::PreferLocal),
+            "prefer-remote" => Some(Policy::PreferRemote),
+            "newest-wins" => Some(Policy::NewestWins),
+            "interactive" => Some(Policy::Interactive),
+            _ => None,
+        }
+    }
+}
+
+/// The policy for each sync target.
+#[derive(Debug)]
+pub struct Policies {
+    pub caldav: Policy,
+    pub git: Policy,
+}
+
+impl Policies {
+    pub fn load(config: &Config) -> Result<Policies, String> {
+        let policy = |target: &str| {
+            let key = format!("{}.conflicts", target);
+            let (key, value) = match config.get(&key) {
+                Some(value) => (key, value),
+                None => match config.get("sync.conflicts") {
+                    Some(value) => ("sync.conflicts".to_string(), value),
+                    None => return Ok(Policy::PreferRemote),
+                },
+            };
+            Policy::parse(value).ok_or_else(|| {
+                tr!(
+                    "Invalid {} in the config file: {}, expected prefer-local, prefer-remote, \
+                     newest-wins or interactive",
+                    key,
+                    value
+                )
+            })
+        };
+        Ok(Policies {
+            caldav: policy("caldav")?,
+            git: policy("git")?,
+        })
+    }
+}
+
+/// The names of the two versions of a changed task, `None` for a side where it was deleted.
+/// `item` tells which task it is, such as its id.
+pub struct Conflict {
+    pub item: String,
+    pub local: Option<String>,
+    pub remote: Option<String>,
+    pub local_modified: Option<SystemTime>,
+    pub remote_modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    Local,
+    Remote,
+    Unresolved,
+}
+
+/// Decides which version of `conflict` to keep.
+pub fn resolve(ctx: &Context, policy: Policy, conflict: &Conflict) -> Result<Resolution, Error> {
+    Ok(match policy {
+        Policy::PreferLocal => Resolution::Local,
+        Policy::PreferRemote => Resolution::Remote,
+        Policy::NewestWins => match (conflict.local_modified, conflict.remote_modified) {
+            (Some(local), Some(remote)) if local > remote => Resolution::Local,
+            _ => Resolution::Remote,
+        },
+        Policy::Interactive if ctx.assume_yes || !io::stdin().is_terminal() => {
+            Resolution::Unresolved
+        }
+        Policy::Interactive => ask(ctx, conflict)?,
+    })
+}
+
+fn ask(ctx: &Context, conflict: &Conflict) -> Result<Resolution, Error> {
+    let version = |name: &Option<String>, modified: Option<SystemTime>| {
+        let name = name.clone().unwrap_or_else(|| tr!("(deleted)"));
+        match modified {
+            Some(t) => format!("{} ({})", name, ctx.format_time(t)),
+            None => name,
+        }
+    };
+    eprintln!("{}", tr!("{}: changed on both sides", conflict.item));
+    eprintln!(
+        "  {}",
+        tr!(
+            "local:  {}",
+            version(&conflict.local, conflict.local_modified)
+        )
+    );
+    eprintln!(
+        "  {}",
+        tr!(
+            "remote: {}",
+            version(&conflict.remote, conflict.remote_modified)
+        )
+    );
+    loop {
+        eprint!("{}", tr!("Keep (l)ocal, (r)emote or (s)kip? "));
+        io::stderr().flush().ok();
+        let mut answer = String::new();
+        let read = io::stdin()
+            .read_line(&mut answer)
+            .map_err(|e| Error::Other(e.to_string()))?;
+        if read == 0 {
+            return Ok(Resolution::Unresolved);
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "l" | "local" => return Ok(Resolution::Local),
+            "r" | "remote" => return Ok(Resolution::Remote),
+            "s" | "skip" => return Ok(Resolution::Unresolved),
+            _ => continue,
+        }
+    }
+}
+
+/// Forgets the conflicts of the last sync of `target`, at the start of a new one.
+pub fn clear(client: &mut Client, ctx: &Context, target: &str) -> Result<(), postgres::Error> {
+    client.execute(
+        "delete from sync_conflict where owner = $1 and target = $2",
+        &[&ctx.user, &target],
+    )?;
+    Ok(())
+}
+
+// The row of a conflict's encrypted values, the same as `todo encrypt` uses.
+fn location_row(target: &str, item: &str) -> String {
+    format!("{}/{}", target, item)
+}
+
+/// Keeps a conflict that wasn't resolved, for `todo sync status`.
+pub fn record(
+    client: &mut Client,
+    ctx: &Context,
+    target: &str,
+    conflict: &Conflict,
+) -> Result<(), postgres::Error> {
+    let row = location_row(target, &conflict.item);
+    client.execute(
+        "insert into sync_conflict (owner, target, item, local, remote)
+             values ($1, $2, $3, $4, $5)",
+        &[
+            &ctx.user,
+            &target,
+            &conflict.item,
+            &conflict
+                .local
+                .as_deref()
+                .map(|l| ctx.encrypt(l, Location::new("sync_conflict", "local", &row))),
+            &conflict
+                .remote
+                .as_deref()
+                .map(|r| ctx.encrypt(r, Location::new("sync_conflict", "remote", &row))),
+        ],
+    )?;
+    println!(
+        "{}",
+        tr!("{}: changed on both sides, left unresolved", conflict.item)
+    );
+    Ok(())
+}
+
+/// Lists the conflicts the last syncs left unresolved.
+pub fn status(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let rows = client.query(
+        "select target, item, local, remote, detected_time
+             from sync_conflict
+             where owner = $1
+             order by target, id",
+        &[&ctx.user],
+    )?;
+    if rows.is_empty() && ctx.output.is_text() {
+        println!("{}", tr!("No unresolved conflicts"));
+    }
+    for row in rows {
+        let target: String = row.get(0);
+        let item: String = row.get(1);
+        let key = location_row(&target, &item);
+        let local = row
+            .get::<_, Option<&str>>(2)
+            .map(|l| ctx.decrypt(l, Location::new("sync_conflict", "local", &key)));
+        let remote = row
+            .get::<_, Option<&str>>(3)
+            .map(|r| ctx.decrypt(r, Location::new("sync_conflict", "remote", &key)));
+        let detected: SystemTime = row.get(4);
+        if !ctx.output.is_text() {
+            output::record(
+                ctx.output,
+                &[
+                    &target,
+                    &item,
+                    local.as_deref().unwrap_or_default(),
+                    remote.as_deref().unwrap_or_default(),
+                    &time::format_iso(detected),
+                ],
+            );
+            continue;
+        }
+        let deleted = tr!("(deleted)");
+        println!(
+            "{} {}: {}",
+            target,
+            item,
+            tr!("found {}", ctx.format_time(detected))
+        );
+        println!(
+            "  {}",
+            tr!("local:  {}", local.as_deref().unwrap_or(&deleted))
+        );
+        println!(
+            "  {}",
+            tr!("remote: {}", remote.as_deref().unwrap_or(&deleted))
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use crate::error::Error;
+use crate::filter::Filter;
+use crate::output;
+use crate::status;
+use crate::Context;
+use todo_rust::client::{Client, GenericClient};
+
+#[derive(Debug)]
+pub enum Action {
+    Show,
+    Set {
+        name: String,
+    },
+    Clear,
+    List,
+    Add {
+        name: String,
+        filter: Option<String>,
+    },
+    Delete {
+        name: String,
+    },
+}
+
+/// Parses a context name, which may be written with a leading `@` as in `@home`.
+pub fn parse_name(s: &str) -> Result<String, String> {
+    let name = s.strip_prefix('@').unwrap_or(s);
+    if name.is_empty() || name.chars().any(|c| c.is_whitespace() || c == '@') {
+        return Err(tr!("Invalid context: {}", s));
+    }
+    Ok(name.to_string())
+}
+
+/// Returns the user's active context, which limits the tasks shown by `list`, `agenda` and
+/// `complete --name` until it is cleared.
+pub fn active(
+    client: &mut impl GenericClient,
+    ctx: &Context,
+) -> Result<Option<String>, postgres::Error> {
+    Ok(client
+        .query_opt(
+            "select context from active_context where owner = $1",
+            &[&ctx.user],
+        )?
+        .map(|row| row.get(0)))
+}
+
+/// Returns the filter defined for the context `name` with `contexts add --filter`, if any. With
+/// a filter, the context has the tasks matching it besides the tasks in it.
+pub fn filter(
+    client: &mut Client,
+    ctx: &Context,
+    name: &Option<String>,
+) -> Result<Option<Filter>, Error> {
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let filter: Option<String> = client
+        .query_opt(
+            "select filter from context where owner = $1 and name = $2",
+            &[&ctx.user, name],
+        )?
+        .and_then(|row| row.get(0));
+    filter
+        .map(|f| parse_filter(&f).map_err(Error::Other))
+        .transpose()
+}
+
+// Parses the filter of a context, which has no ids, since it is about kinds of tasks.
+fn parse_filter(filter: &str) -> Result<Filter, String> {
+    let terms: Vec<String> = filter.split_whitespace().map(str::to_string).collect();
+    let parsed = Filter::parse(&terms)?;
+    if parsed.has_ids() {
+        return Err(tr!(
+            "Expected only key:value terms in a context filter, got: {}",
+            filter
+        ));
+    }
+    Ok(parsed)
+}
+
+pub fn run(client: &mut Client, ctx: &Context, action: Action) -> Result<(), Error> {
+    match action {
+        Action::Show => match active(client, ctx)? {
+            Some(name) => println!("@{}", name),
+            None => println!("{}", tr!("No active context")),
+        },
+        Action::Set { name } => {
+            let mut transaction = client.transaction()?;
+            ctx.execute(
+                &mut transaction,
+                "insert into active_context (owner, context) values ($1, $2)
+                 on conflict (owner) do update set context = excluded.context",
+                &[&ctx.user, &name],
+            )?;
+            ctx.finish(transaction)?;
+            println!("{}", tr!("Active context: @{}", name));
+        }
+        Action::Clear => {
+            let mut transaction = client.transaction()?;
+            ctx.execute(
+                &mut transaction,
+                "delete from active_context where owner = $1",
+                &[&ctx.user],
+            )?;
+            ctx.finish(transaction)?;
+            println!("{}", tr!("Active context cleared"));
+        }
+        Action::List => list(client, ctx)?,
+        Action::Add { name, filter } => {
+            if let Some(filter) = &filter {
+                parse_filter(filter).map_err(Error::Usage)?;
+            }
+            let mut transaction = client.transaction()?;
+            ctx.execute(
+                &mut transaction,
+                "insert into context (owner, name, filter) values ($1, $2, $3)
+                 on conflict (owner, name) do update set filter = excluded.filter",
+                &[&ctx.user, &name, &filter],
+            )?;
+            ctx.finish(transaction)?;
+            println!("{}", tr!("Defined context @{}", name));
+        }
+        Action::Delete { name } => {
+            let mut transaction = client.transaction()?;
+            let deleted = ctx.execute(
+                &mut transaction,
+                "delete from context where owner = $1 and name = $2",
+                &[&ctx.user, &name],
+            )?;
+            ctx.finish(transaction)?;
+            if deleted == 0 && !ctx.dry_run {
+                return Err(Error::NotFound(tr!("No context named @{}", name)));
+            }
+            println!("{}", tr!("Deleted context @{}", name));
+        }
+    }
+    Ok(())
+}
+
+// Lists the defined contexts and the contexts open tasks are in, with how many open tasks each has.
+fn list(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let active = active(client, ctx)?;
+    let rows = client.query(
+        "select coalesce(d.name, t.context), d.filter, count(t.id)
+             from (select name, filter from context where owner = $1) d
+             full join (
+                 select id, context from todo
+                 where owner = $1 and status = any($2) and context is not null
+             ) t on t.context = d.name
+             group by 1, 2
+             order by 1",
+        &[&ctx.user, &status::OPEN],
+    )?;
+    if rows.is_empty() && ctx.output.is_text() {
+        println!("{}", tr!("No contexts, add one with 'contexts add <name>'"));
+    }
+    for row in rows {
+        let name: String = row.get(0);
+        let filter: Option<String> = row.get(1);
+        let open: i64 = row.get(2);
+        let is_active = active.as_ref() == Some(&name);
+        if !ctx.output.is_text() {
+            output::record(
+                ctx.output,
+                &[
+                    &name,
+                    &open.to_string(),
+                    filter.as_deref().unwrap_or_default(),
+                    if is_active { "active" } else { "" },
+                ],
+            );
+            continue;
+        }
+        let mut details = vec![tr!("{} open", open)];
+        if let Some(filter) = filter {
+            details.push(tr!("filter: {}", filter));
+        }
+        if is_active {
+            details.push(tr!("active"));
+        }
+        println!("@{} ({})", name, details.join(", "));
+    }
+    Ok(())
+}
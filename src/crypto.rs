@@ -0,0 +1,243 @@
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const SALT_LEN: usize = 16;
+const KDF_ITERATIONS: u32 = 600_000;
+
+/// Encrypts and decrypts field values on the client, so that the database only ever sees
+/// ciphertext. Values are encrypted with ChaCha20-Poly1305, with their `Location` as associated
+/// data, and stored as `enc:v1:<base64>`. Values that aren't encrypted aren't trusted, as anyone
+/// who can write to the database could have written them; `todo encrypt` encrypts them once they
+/// are known to be genuine.
+pub struct Cipher {
+    // Derived when first used, since deriving them is slow on purpose, and commands that don't
+    // encrypt or decrypt anything shouldn't wait for it.
+    keys: OnceLock<Keys>,
+    passphrase: String,
+    salt: Vec<u8>,
+}
+
+struct Keys {
+    cipher: ChaCha20Poly1305,
+    fingerprint_key: Vec<u8>,
+}
+
+impl Keys {
+    fn derive(passphrase: &str, salt: &[u8], iterations: u32) -> Keys {
+        let key = pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(passphrase.as_bytes(), salt, iterations);
+        Keys {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&hmac(&key, &[b"encrypt"]))),
+            fingerprint_key: hmac(&key, &[b"fingerprint"]),
+        }
+    }
+}
+
+/// Where an encrypted value is stored: its table and column, and the row it belongs to, by the
+/// uuid of its task or, for values that don't belong to a task, by another unique key. Values are
+/// authenticated together with it, so a value moved to another row or column fails to decrypt.
+#[derive(Debug, Clone, Copy)]
+pub struct Location<'a> {
+    pub table: &'a str,
+    pub column: &'a str,
+    pub row: &'a str,
+}
+
+impl<'a> Location<'a> {
+    pub fn new(table: &'a str, column: &'a str, row: &'a str) -> Location<'a> {
+        Location { table, column, row }
+    }
+
+    /// A column of the task with uuid `uuid`, such as its name.
+    pub fn task(column: &'a str, uuid: &'a str) -> Location<'a> {
+        Location::new("todo", column, uuid)
+    }
+
+    // The associated data of values stored here. Every part is prefixed with its length, so
+    // different locations never have the same associated data.
+    fn associated_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for part in [self.table, self.column, self.row] {
+            data.extend((part.len() as u64).to_be_bytes());
+            data.extend(part.as_bytes());
+        }
+        data
+    }
+}
+
+/// A new random salt for `Cipher::new`, encoded as base64.
+pub fn new_salt() -> String {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    base64::encode(salt)
+}
+
+impl Cipher {
+    /// A cipher with keys derived from a passphrase and a base64 salt from `new_salt` using
+    /// PBKDF2.
+    pub fn new(passphrase: &str, salt: &str) -> Result<Cipher, String> {
+        let salt = base64::decode(salt.trim())
+            .ok()
+            .filter(|salt| salt.len() >= SALT_LEN)
+            .ok_or_else(|| "Invalid encryption salt".to_string())?;
+        Ok(Cipher {
+            keys: OnceLock::new(),
+            passphrase: passphrase.to_string(),
+            salt,
+        })
+    }
+
+    fn keys(&self) -> &Keys {
+        self.keys
+            .get_or_init(|| Keys::derive(&self.passphrase, &self.salt, KDF_ITERATIONS))
+    }
+
+    pub fn encrypt(&self, plaintext: &str, location: Location) -> String {
+        format!(
+            "{}{}",
+            PREFIX,
+            base64::encode(self.encrypt_bytes(plaintext.as_bytes(), location))
+        )
+    }
+
+    /// Decrypts a value that `encrypt` stored at `location`. Values that aren't encrypted are
+    /// refused.
+    pub fn decrypt(&self, value: &str, location: Location) -> Result<String, String> {
+        let encoded = value
+            .strip_prefix(PREFIX)
+            .ok_or_else(|| "Value is not encrypted".to_string())?;
+        let data = base64::decode(encoded).map_err(|_| "Invalid encrypted value".to_string())?;
+        let plaintext = self.decrypt_bytes(&data, location)?;
+        String::from_utf8(plaintext).map_err(|_| "Invalid encrypted value".to_string())
+    }
+
+    /// Encrypts binary data stored at `location`, such as attachments, returning the nonce
+    /// followed by the ciphertext and its tag.
+    pub fn encrypt_bytes(&self, plaintext: &[u8], location: Location) -> Vec<u8> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg: plaintext,
+            aad: &location.associated_data(),
+        };
+        let ciphertext = self
+            .keys()
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .expect("encrypting a value");
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        data
+    }
+
+    /// Decrypts binary data that `encrypt_bytes` stored at `location`.
+    pub fn decrypt_bytes(&self, data: &[u8], location: Location) -> Result<Vec<u8>, String> {
+        if data.len() < NONCE_LEN + TAG_LEN {
+            return Err("Invalid encrypted value".to_string());
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &location.associated_data(),
+        };
+        self.keys()
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| "Failed to decrypt value, is the encryption key correct?".to_string())
+    }
+
+    /// A keyed digest of `value`, for detecting changes without storing the value itself.
+    pub fn fingerprint(&self, value: &str) -> String {
+        hex(&hmac(&self.keys().fingerprint_key, &[value.as_bytes()]))
+    }
+}
+
+/// Whether `value` was produced by `Cipher::encrypt`.
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// An unkeyed SHA-256 digest of `value`, used as fingerprint when encryption is disabled.
+pub fn sha256(value: &str) -> String {
+    hex(&Sha256::digest(value.as_bytes()))
+}
+
+/// The hex SHA-256 digest of binary data, such as a file.
+pub fn sha256_bytes(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+/// A hex HMAC-SHA256 of `message`, for signing webhook payloads.
+pub fn sign(key: &str, message: &str) -> String {
+    hex(&hmac(key.as_bytes(), &[message.as_bytes()]))
+}
+
+/// Whether `a` and `b` are equal, taking the same time wherever they differ, for comparing
+/// secrets. Only their lengths can be told from the time taken.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn hmac(key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).unwrap();
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A cipher with keys derived in a single iteration, as the usual number is slow unoptimized.
+    fn cipher() -> Cipher {
+        let cipher = Cipher::new("passphrase", &new_salt()).unwrap();
+        let _ = cipher.keys.set(Keys::derive("passphrase", &cipher.salt, 1));
+        cipher
+    }
+
+    #[test]
+    fn values_are_bound_to_their_location() {
+        let cipher = cipher();
+        let name = Location::task("name", "1f0c9a3e-5b7d-4c2e-9f61-0a8b3c4d5e6f");
+        let encrypted = cipher.encrypt("secret", name);
+        assert_eq!(cipher.decrypt(&encrypted, name), Ok("secret".to_string()));
+        for other in [
+            Location::task("description", "1f0c9a3e-5b7d-4c2e-9f61-0a8b3c4d5e6f"),
+            Location::task("name", "6d2b8c1a-0e4f-4a3b-8c5d-7e9f0a1b2c3d"),
+            Location::new("annotation", "text", "1f0c9a3e-5b7d-4c2e-9f61-0a8b3c4d5e6f"),
+        ] {
+            assert!(cipher.decrypt(&encrypted, other).is_err());
+        }
+        let data = cipher.encrypt_bytes(b"secret", name);
+        assert_eq!(cipher.decrypt_bytes(&data, name), Ok(b"secret".to_vec()));
+        assert!(cipher
+            .decrypt_bytes(&data, Location::task("name", "6d2b8c1a"))
+            .is_err());
+    }
+
+    #[test]
+    fn changed_values_are_refused() {
+        let cipher = cipher();
+        let name = Location::task("name", "1f0c9a3e-5b7d-4c2e-9f61-0a8b3c4d5e6f");
+        let mut data = cipher.encrypt_bytes(b"secret", name);
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(cipher.decrypt_bytes(&data, name).is_err());
+        assert!(cipher.decrypt("secret", name).is_err());
+        assert!(cipher.decrypt(PREFIX, name).is_err());
+    }
+}
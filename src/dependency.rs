@@ -0,0 +1,176 @@
+// Dependencies between tasks: a task can depend on others that have to be finished first, and is
+// blocked while any of them is open. `depend` and `undepend` change them, `blocked` lists the
+// blocked tasks with what blocks them, and `unblockable` the tasks that completing a task would
+// make actionable.
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::output;
+use crate::status::{self, Status};
+use crate::{alias, join_ids, Context};
+use todo_rust::client::Client;
+
+/// Makes the user's task `id` depend on the tasks `on`, refusing dependencies on itself or ones
+/// that would go round in a circle.
+pub fn add(client: &mut Client, ctx: &Context, id: i32, on: &[i32]) -> Result<(), Error> {
+    let mut transaction = client.transaction()?;
+    for &other in on {
+        if other == id {
+            return Err(Error::Usage(tr!("Task {} can't depend on itself", id)));
+        }
+        let circular: bool = transaction
+            .query_one(
+                "with recursive dependencies (id) as (
+                     select depends_on from dependency where todo_id = $1
+                     union
+                     select d.depends_on from dependency d join dependencies on d.todo_id = id
+                 )
+                 select exists (select from dependencies where id = $2)",
+                &[&other, &id],
+            )?
+            .get(0);
+        if circular {
+            return Err(Error::Usage(tr!(
+                "Task {} already depends on {}",
+                other,
+                id
+            )));
+        }
+        ctx.execute(
+            &mut transaction,
+            "insert into dependency (todo_id, depends_on) values ($1, $2) on conflict do nothing",
+            &[&id, &other],
+        )?;
+    }
+    ctx.finish(transaction)?;
+    println!("{}: {}", id, tr!("depends on {}", join_ids(on)));
+    Ok(())
+}
+
+/// Makes the user's task `id` no longer depend on the tasks `on`.
+pub fn remove(client: &mut Client, ctx: &Context, id: i32, on: &[i32]) -> Result<(), Error> {
+    let mut transaction = client.transaction()?;
+    let removed = ctx.execute(
+        &mut transaction,
+        "delete from dependency where todo_id = $1 and depends_on = any($2)",
+        &[&id, &on],
+    )?;
+    if removed == 0 {
+        return Err(Error::NotFound(tr!(
+            "Task {} doesn't depend on {}",
+            id,
+            join_ids(on)
+        )));
+    }
+    ctx.finish(transaction)?;
+    println!("{}: {}", id, tr!("no longer depends on {}", join_ids(on)));
+    Ok(())
+}
+
+/// Lists the open tasks with open dependencies, each with the tasks blocking it.
+pub fn blocked(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let rows = client.query(
+        "select t.id, t.name, b.id, b.name, t.uuid, b.uuid
+             from todo t
+             join dependency d on d.todo_id = t.id
+             join todo b on b.id = d.depends_on
+             where t.owner = $1 and t.status = any($2) and b.status = any($2)
+             order by t.id, b.id",
+        &[&ctx.user, &status::OPEN],
+    )?;
+    if rows.is_empty() && ctx.output.is_text() {
+        println!("{}", tr!("No blocked tasks"));
+    }
+    let mut last = None;
+    for row in rows {
+        let id: i32 = row.get(0);
+        let name = ctx.decrypt(row.get(1), Location::task("name", row.get(4)));
+        let blocker: i32 = row.get(2);
+        let blocker_name = ctx.decrypt(row.get(3), Location::task("name", row.get(5)));
+        if !ctx.output.is_text() {
+            output::record(
+                ctx.output,
+                &[
+                    &id.to_string(),
+                    &alias::encode(id),
+                    &name,
+                    &blocker.to_string(),
+                    &alias::encode(blocker),
+                    &blocker_name,
+                ],
+            );
+            continue;
+        }
+        if last != Some(id) {
+            println!("{} ({}): {}", id, alias::encode(id), name);
+            last = Some(id);
+        }
+        println!(
+            "  {}",
+            tr!(
+                "blocked by {} ({}): {}",
+                blocker,
+                alias::encode(blocker),
+                blocker_name
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Lists the open tasks that only wait for task `id`, which completing it would unblock.
+pub fn unblockable(client: &mut Client, ctx: &Context, id: i32) -> Result<(), Error> {
+    let rows = client.query(
+        "select t.id, t.name, t.uuid
+             from todo t
+             join dependency d on d.todo_id = t.id
+             where d.depends_on = $1 and t.owner = $2 and t.status = any($3)
+                 and not exists (
+                     select from dependency o join todo b on b.id = o.depends_on
+                     where o.todo_id = t.id and o.depends_on <> $1 and b.status = any($3)
+                 )
+             order by t.id",
+        &[&id, &ctx.user, &status::OPEN],
+    )?;
+    if rows.is_empty() && ctx.output.is_text() {
+        println!("{}", tr!("Completing {} doesn't unblock any tasks", id));
+    }
+    for row in rows {
+        let id: i32 = row.get(0);
+        let name = ctx.decrypt(row.get(1), Location::task("name", row.get(2)));
+        if ctx.output.is_text() {
+            println!("{} ({}): {}", id, alias::encode(id), name);
+        } else {
+            output::record(ctx.output, &[&id.to_string(), &alias::encode(id), &name]);
+        }
+    }
+    Ok(())
+}
+
+/// Prints the tasks task `id` depends on, for `show`.
+pub fn show(client: &mut Client, ctx: &Context, id: i32) -> Result<(), Error> {
+    let rows = client.query(
+        "select b.id, b.name, b.status, b.uuid
+             from dependency d join todo b on b.id = d.depends_on
+             where d.todo_id = $1
+             order by b.id",
+        &[&id],
+    )?;
+    if rows.is_empty() {
+        return Ok(());
+    }
+    println!();
+    println!("{}", tr!("Depends on:"));
+    for row in rows {
+        let id: i32 = row.get(0);
+        let status: Status = row.get(2);
+        println!(
+            "  {} ({}): {} [{}]",
+            id,
+            alias::encode(id),
+            ctx.decrypt(row.get(1), Location::task("name", row.get(3))),
+            status.as_str()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use postgres::NoTls;
+use todo_rust::client::Client;
+use todo_rust::migrate;
+
+use crate::error::Error;
+use crate::migrations::{INDEXES, SCHEMA_VERSION};
+use crate::pgpass;
+use crate::Context;
+
+// `gen_random_uuid()`, used for CalDAV sync, is built in from PostgreSQL 13.
+const MIN_SERVER_VERSION: i32 = 130000;
+
+const TABLES: &[&str] = &[
+    "todo",
+    "time_entry",
+    "active_context",
+    "context",
+    "template",
+    "todo_history",
+    "annotation",
+    "attachment",
+    "trash",
+    "sync_conflict",
+    "dependency",
+    "schema_migrations",
+];
+
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+struct Checks {
+    problems: usize,
+}
+
+impl Checks {
+    fn report(&mut self, status: Status, message: &str) {
+        let label = match status {
+            Status::Ok => tr!("ok"),
+            Status::Warning => tr!("warning"),
+            Status::Error => tr!("error"),
+        };
+        if !matches!(status, Status::Ok) {
+            self.problems += 1;
+        }
+        println!("[{:^7}] {}", label, message);
+    }
+}
+
+/// Checks the database connection and schema, printing what is wrong and how to fix it.
+pub fn run(ctx: &Context) -> Result<(), Error> {
+    let mut checks = Checks { problems: 0 };
+    let connection = hide_password(&ctx.connection);
+    let resolved = match pgpass::resolve(&ctx.connection) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            checks.report(Status::Error, &e);
+            return Err(tr!("1 problem found").into());
+        }
+    };
+    let mut client = match Client::connect(&resolved, NoTls) {
+        Ok(client) => {
+            checks.report(Status::Ok, &tr!("Connected to '{}'", connection));
+            client
+        }
+        Err(e) => {
+            checks.report(
+                Status::Error,
+                &tr!(
+                    "Could not connect to '{}': {}\n          Check that the server is running, \
+                     or set 'database.connection' in the config file",
+                    connection,
+                    e
+                ),
+            );
+            return Err(tr!("1 problem found").into());
+        }
+    };
+    if let Err(e) = check_database(&mut client, &mut checks) {
+        checks.report(Status::Error, &tr!("Query failed: {}", e));
+    }
+    if checks.problems > 0 {
+        return Err(tr!("{} problem(s) found", checks.problems).into());
+    }
+    println!("{}", tr!("No problems found"));
+    Ok(())
+}
+
+fn check_database(client: &mut Client, checks: &mut Checks) -> Result<(), postgres::Error> {
+    let row = client.query_one(
+        "select current_setting('server_version'),
+                current_setting('server_version_num')::int",
+        &[],
+    )?;
+    let version: String = row.get(0);
+    let version_num: i32 = row.get(1);
+    if version_num < MIN_SERVER_VERSION {
+        checks.report(
+            Status::Warning,
+            &tr!(
+                "PostgreSQL {} is older than 13, CalDAV sync won't work",
+                version
+            ),
+        );
+    } else {
+        checks.report(Status::Ok, &tr!("PostgreSQL {}", version));
+    }
+
+    let latest = SCHEMA_VERSION;
+    let current = migrate::current_version(client)?;
+    if current < latest {
+        checks.report(
+            Status::Error,
+            &tr!(
+                "Schema is at version {}, expected {}\n          Run 'todo migrate', or set \
+                 'database.auto_migrate = true' in the config file",
+                current,
+                latest
+            ),
+        );
+    } else if current > latest {
+        checks.report(
+            Status::Warning,
+            &tr!(
+                "Schema is at version {}, newer than this version of todo ({})\n          \
+                 Upgrade todo, or run 'todo migrate down --to {}'",
+                current,
+                latest,
+                latest
+            ),
+        );
+    } else {
+        checks.report(Status::Ok, &tr!("Schema is at version {}", current));
+    }
+
+    let missing: Vec<&str> = INDEXES
+        .iter()
+        .filter(|(table, index)| {
+            let found = client
+                .query_opt(
+                    "select 1 from pg_indexes
+                     where schemaname = current_schema() and tablename = $1 and indexname = $2",
+                    &[table, index],
+                )
+                .map(|row| row.is_some());
+            !matches!(found, Ok(true))
+        })
+        .map(|(_, index)| *index)
+        .collect();
+    if missing.is_empty() {
+        checks.report(Status::Ok, &tr!("All indexes are present"));
+    } else {
+        checks.report(
+            Status::Warning,
+            &tr!(
+                "Missing indexes: {}\n          Run 'todo migrate' to create them",
+                missing.join(", ")
+            ),
+        );
+    }
+
+    let mut writable = true;
+    for table in TABLES {
+        let row = client.query_one(
+            "select to_regclass($1::text) is not null
+                    and has_table_privilege($1::text, 'select, insert, update, delete')",
+            &[table],
+        )?;
+        let allowed: bool = row.get(0);
+        if !allowed {
+            writable = false;
+            checks.report(
+                Status::Error,
+                &tr!(
+                    "Table '{}' is missing or not writable\n          Run 'todo migrate', or \
+                     grant select, insert, update and delete on it to the database user",
+                    table
+                ),
+            );
+        }
+    }
+    let can_create: bool = client
+        .query_one(
+            "select has_schema_privilege(current_schema(), 'create')",
+            &[],
+        )?
+        .get(0);
+    if !can_create {
+        checks.report(
+            Status::Warning,
+            &tr!("The database user can't create tables, so migrations must be applied by another user"),
+        );
+    } else if writable {
+        checks.report(Status::Ok, &tr!("Permissions are sufficient"));
+    }
+    Ok(())
+}
+
+// Replaces the value of `password=` in a connection string, so it can be printed.
+pub fn hide_password(connection: &str) -> String {
+    connection
+        .split_whitespace()
+        .map(|part| match part.split_once('=') {
+            Some(("password", _)) => "password=***".to_string(),
+            _ => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,364 @@
+use std::env;
+use std::fs;
+use std::process::{self, Command};
+use std::time::SystemTime;
+
+use crate::context;
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::fields;
+use crate::json::Json;
+use crate::time;
+use crate::webhook;
+use crate::{
+    parse_estimate, parse_priority, priority_key, run_bulk, typed_query, Context, TaskOptions,
+    TODO_TABLE,
+};
+use todo_rust::client::Client;
+
+/// The editable attributes of a task.
+#[derive(Clone)]
+struct Values {
+    name: String,
+    due_time: Option<SystemTime>,
+    description: Option<String>,
+    project: Option<String>,
+    priority: Option<i16>,
+    context: Option<String>,
+    estimate: Option<i32>,
+    fields: Vec<(String, Json)>,
+    // Not edited, but the name and description are encrypted with it.
+    uuid: String,
+}
+
+/// Changes a task: the attributes given as options, or, without any, whatever is changed in a
+/// document opened in `$VISUAL` or `$EDITOR`. Empty option values remove an attribute.
+pub fn run(
+    client: &mut Client,
+    ctx: &Context,
+    id: i32,
+    name: Option<String>,
+    options: TaskOptions,
+) -> Result<(), Error> {
+    let original = load(client, ctx, id)?;
+    let edit_in_editor = name.is_none()
+        && options.due.is_none()
+        && options.note.is_none()
+        && options.project.is_none()
+        && options.priority.is_none()
+        && options.context.is_none()
+        && options.estimate.is_none()
+        && options.fields.is_empty();
+    let values = if edit_in_editor {
+        match edit_document(client, ctx, id, &original)? {
+            Some(values) => values,
+            None => {
+                println!("{}", tr!("No changes"));
+                return Ok(());
+            }
+        }
+    } else {
+        apply_options(client, ctx, original.clone(), name, options)?
+    };
+    if values.name.is_empty() {
+        return Err(Error::Usage(tr!("The task name can't be empty")));
+    }
+    save(client, ctx, id, &original, values)
+}
+
+fn load(client: &mut Client, ctx: &Context, id: i32) -> Result<Values, Error> {
+    let task = typed_query::from(TODO_TABLE)
+        .where_(|t| {
+            t.owner.clone().eq(typed_query::Constant {
+                value: ctx.user.clone(),
+            })
+        })
+        .where_(|t| t.id.clone().eq(typed_query::Constant { value: id }))
+        .query(client)?
+        .pop()
+        .ok_or_else(|| Error::NotFound(tr!("No task with id {}", id)))?;
+    let fields = match task.fields {
+        Json::Object(members) => members,
+        _ => Vec::new(),
+    };
+    let uuid = task.uuid;
+    Ok(Values {
+        name: ctx.decrypt(&task.name, Location::task("name", &uuid)),
+        due_time: task.due_time,
+        description: task
+            .description
+            .map(|d| ctx.decrypt(&d, Location::task("description", &uuid))),
+        project: task.project,
+        priority: task.priority,
+        context: task.context,
+        estimate: task.estimate,
+        fields,
+        uuid,
+    })
+}
+
+fn apply_options(
+    client: &mut Client,
+    ctx: &Context,
+    mut values: Values,
+    name: Option<String>,
+    options: TaskOptions,
+) -> Result<Values, Error> {
+    let non_empty = |s: String| Some(s).filter(|s| !s.is_empty());
+    if let Some(name) = name {
+        values.name = name;
+    }
+    if let Some(due) = options.due {
+        values.due_time = non_empty(due)
+            .map(|d| time::parse_time(client, &d))
+            .transpose()
+            .map_err(Error::Usage)?;
+    }
+    if let Some(note) = options.note {
+        values.description = non_empty(note);
+    }
+    if let Some(project) = options.project {
+        values.project = non_empty(project);
+    }
+    if options.priority.is_some() {
+        values.priority = options.priority;
+    }
+    if options.context.is_some() {
+        values.context = options.context;
+    }
+    if let Some(estimate) = options.estimate {
+        values.estimate = non_empty(estimate)
+            .map(|e| parse_estimate(&e))
+            .transpose()
+            .map_err(Error::Usage)?;
+    }
+    for (name, value) in fields::parse_all(&ctx.fields, &options.fields).map_err(Error::Usage)? {
+        values.fields.retain(|(n, _)| *n != name);
+        values.fields.push((name, value));
+    }
+    Ok(values)
+}
+
+// Opens the task in the editor, returning the edited values, or `None` if nothing changed.
+fn edit_document(
+    client: &mut Client,
+    ctx: &Context,
+    id: i32,
+    original: &Values,
+) -> Result<Option<Values>, Error> {
+    let document = render(ctx, id, original);
+    let path = env::temp_dir().join(format!("todo-edit-{}-{}.txt", id, process::id()));
+    fs::write(&path, &document)
+        .map_err(|e| Error::Other(tr!("Failed to write {}: {}", path.display(), e)))?;
+    let edited = open_editor(&path).and_then(|()| {
+        fs::read_to_string(&path)
+            .map_err(|e| Error::Other(tr!("Failed to read {}: {}", path.display(), e)))
+    });
+    // The document holds the decrypted name and note, so it isn't left behind.
+    fs::remove_file(&path).ok();
+    let edited = edited?;
+    if edited == document {
+        return Ok(None);
+    }
+    parse_document(client, ctx, &edited, original)
+        .map(Some)
+        .map_err(Error::Usage)
+}
+
+// Runs the editor like git does: through the shell, so `$EDITOR` can contain arguments.
+fn open_editor(path: &std::path::Path) -> Result<(), Error> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg(&editor)
+        .arg(path)
+        .status()
+        .map_err(|e| Error::Other(tr!("Failed to start editor '{}': {}", editor, e)))?;
+    if !status.success() {
+        return Err(Error::Other(tr!(
+            "Editor '{}' failed ({}), the task is unchanged",
+            editor,
+            status
+        )));
+    }
+    Ok(())
+}
+
+// The document has a `key: value` line per attribute, then an empty line and the note.
+fn render(ctx: &Context, id: i32, values: &Values) -> String {
+    let mut document = String::new();
+    document.push_str(&format!("name: {}\n", values.name));
+    document.push_str(&format!("due: {}\n", format_due(ctx, values.due_time)));
+    document.push_str(&format!(
+        "priority: {}\n",
+        values.priority.map(priority_key).unwrap_or_default()
+    ));
+    document.push_str(&format!(
+        "project: {}\n",
+        values.project.as_deref().unwrap_or_default()
+    ));
+    document.push_str(&format!(
+        "context: {}\n",
+        values.context.as_deref().unwrap_or_default()
+    ));
+    document.push_str(&format!("estimate: {}\n", format_estimate(values.estimate)));
+    for definition in &ctx.fields {
+        let value = match values.fields.iter().find(|(n, _)| *n == definition.name) {
+            Some((_, Json::String(s))) => s.clone(),
+            Some((_, Json::Null)) | None => String::new(),
+            Some((_, value)) => value.to_string(),
+        };
+        document.push_str(&format!("{}: {}\n", definition.name, value));
+    }
+    document.push('\n');
+    if let Some(description) = &values.description {
+        document.push_str(description);
+        document.push('\n');
+    }
+    document.push('\n');
+    for line in tr!(
+        "Edit task {} and save to apply the changes. The note goes below the attributes,\nafter an empty line. Leave a value empty to remove it. Lines starting with '#'\nare ignored.",
+        id
+    )
+    .lines()
+    {
+        document.push_str(&format!("# {}\n", line));
+    }
+    document
+}
+
+fn format_due(ctx: &Context, due: Option<SystemTime>) -> String {
+    due.map(|t| time::format_absolute(t, ctx.timezone.as_ref()))
+        .unwrap_or_default()
+}
+
+fn format_estimate(estimate: Option<i32>) -> String {
+    estimate
+        .map(|e| time::format_duration(e as f64).replace(' ', ""))
+        .unwrap_or_default()
+}
+
+fn parse_document(
+    client: &mut Client,
+    ctx: &Context,
+    document: &str,
+    original: &Values,
+) -> Result<Values, String> {
+    let mut lines = document.lines().filter(|l| !l.starts_with('#'));
+    let mut values = Values {
+        name: String::new(),
+        due_time: None,
+        description: None,
+        project: None,
+        priority: None,
+        context: None,
+        estimate: None,
+        fields: original.fields.clone(),
+        uuid: original.uuid.clone(),
+    };
+    for line in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| tr!("Expected 'key: value', got: {}", line))?;
+        let value = value.trim();
+        let non_empty = Some(value.to_string()).filter(|v| !v.is_empty());
+        match key.trim() {
+            "name" => values.name = value.to_string(),
+            // Due times are shown in minutes, so an unchanged one keeps its seconds.
+            "due" if value == format_due(ctx, original.due_time) => {
+                values.due_time = original.due_time
+            }
+            "due" => {
+                values.due_time = non_empty
+                    .map(|d| time::parse_time(client, &d))
+                    .transpose()?
+            }
+            "priority" => values.priority = non_empty.map(|p| parse_priority(&p)).transpose()?,
+            "project" => values.project = non_empty,
+            "context" => values.context = non_empty.map(|c| context::parse_name(&c)).transpose()?,
+            // Estimates are shown in at most two units, so an unchanged one keeps the rest.
+            "estimate" if value == format_estimate(original.estimate) => {
+                values.estimate = original.estimate
+            }
+            "estimate" => values.estimate = non_empty.map(|e| parse_estimate(&e)).transpose()?,
+            key => {
+                if !ctx.fields.iter().any(|d| d.name == key) {
+                    return Err(tr!("Unknown attribute: {}", key));
+                }
+                values.fields.retain(|(n, _)| n != key);
+                if let Some(value) = non_empty {
+                    values
+                        .fields
+                        .push(fields::parse(&ctx.fields, &format!("{}={}", key, value))?);
+                }
+            }
+        }
+    }
+    let description = lines.collect::<Vec<_>>().join("\n");
+    let description = description.trim();
+    if !description.is_empty() {
+        values.description = Some(description.to_string());
+    }
+    Ok(values)
+}
+
+// Stores the values. The name and note are only written when changed, since encrypting them again
+// would give a different value.
+fn save(
+    client: &mut Client,
+    ctx: &Context,
+    id: i32,
+    original: &Values,
+    values: Values,
+) -> Result<(), Error> {
+    let name = Some(values.name)
+        .filter(|n| *n != original.name)
+        .map(|n| ctx.encrypt(&n, Location::task("name", &original.uuid)));
+    let description_changed = values.description != original.description;
+    let description = values
+        .description
+        .map(|d| ctx.encrypt(&d, Location::task("description", &original.uuid)));
+    let Values {
+        due_time,
+        project,
+        priority,
+        context,
+        estimate,
+        fields,
+        ..
+    } = values;
+    let fields = Json::Object(fields);
+    run_bulk(client, ctx, &[id], &tr!("updated"), |t, id| {
+        ctx.execute(
+            t,
+            "update todo set name = coalesce($1, name), due_time = $2,
+                 description = case when $3 then $4 else description end,
+                 project = $5, priority = $6, context = $7, estimate = $8, fields = $9
+             where id = $10 and owner = $11",
+            &[
+                &name,
+                &due_time,
+                &description_changed,
+                &description,
+                &project,
+                &priority,
+                &context,
+                &estimate,
+                &fields,
+                &id,
+                &ctx.user,
+            ],
+        )
+    })?;
+    let tasks = webhook::tasks(client, ctx, &[id])?;
+    webhook::send(ctx, webhook::Event::Modified, &tasks);
+    Ok(())
+}
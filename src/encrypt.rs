@@ -0,0 +1,196 @@
+// `todo encrypt`: the one-time migration after enabling encryption. Values written before are
+// plaintext, which other commands mark as `[unencrypted]` since anyone with access to the database
+// could have written them. They are encrypted with the current key, bound to their location. Tasks
+// in the trash are left as they are; running this again after restoring them encrypts them too.
+
+use crate::crypto::{self, Cipher, Location};
+use crate::error::Error;
+use crate::Context;
+use todo_rust::client::{Client, Transaction};
+
+// An encrypted column: its table, the column, an expression identifying the row, a condition
+// selecting the user's rows, using the user as `$1`, and expressions for the table, column and row
+// of the `Location` its values are encrypted with.
+struct Column {
+    table: &'static str,
+    column: &'static str,
+    key: &'static str,
+    owner: &'static str,
+    location: &'static str,
+}
+
+const TASK_OWNER: &str = "todo_id in (select id from todo where owner = $1)";
+const HISTORY_OWNER: &str = "owner = $1 and field in ('name', 'description')";
+// Changes hold values copied from their task, so they have the location of the task's column. A
+// deleted task's uuid is kept in the record of its creation.
+const HISTORY_LOCATION: &str = "'todo', field, coalesce(
+    (select uuid from todo where todo.id = todo_history.todo_id),
+    (select i.new_value::jsonb ->> 'uuid' from todo_history i
+     where i.todo_id = todo_history.todo_id and i.action = 'insert' limit 1),
+    '')";
+
+const COLUMNS: &[Column] = &[
+    Column {
+        table: "todo",
+        column: "name",
+        key: "id::text",
+        owner: "owner = $1",
+        location: "'todo', 'name', uuid",
+    },
+    Column {
+        table: "todo",
+        column: "description",
+        key: "id::text",
+        owner: "owner = $1",
+        location: "'todo', 'description', uuid",
+    },
+    Column {
+        table: "annotation",
+        column: "text",
+        key: "id::text",
+        owner: TASK_OWNER,
+        location:
+            "'annotation', 'text', (select uuid from todo where todo.id = annotation.todo_id)",
+    },
+    Column {
+        table: "attachment",
+        column: "name",
+        key: "id::text",
+        owner: TASK_OWNER,
+        location:
+            "'attachment', 'name', (select uuid from todo where todo.id = attachment.todo_id)",
+    },
+    Column {
+        table: "attachment",
+        column: "path",
+        key: "id::text",
+        owner: TASK_OWNER,
+        location:
+            "'attachment', 'path', (select uuid from todo where todo.id = attachment.todo_id)",
+    },
+    Column {
+        table: "template",
+        column: "description",
+        key: "name",
+        owner: "owner = $1",
+        location: "'template', 'description', name",
+    },
+    Column {
+        table: "sync_conflict",
+        column: "local",
+        key: "id::text",
+        owner: "owner = $1",
+        location: "'sync_conflict', 'local', target || '/' || item",
+    },
+    Column {
+        table: "sync_conflict",
+        column: "remote",
+        key: "id::text",
+        owner: "owner = $1",
+        location: "'sync_conflict', 'remote', target || '/' || item",
+    },
+    // Last, so the changes made to the tasks above are encrypted too.
+    Column {
+        table: "todo_history",
+        column: "old_value",
+        key: "id::text",
+        owner: HISTORY_OWNER,
+        location: HISTORY_LOCATION,
+    },
+    Column {
+        table: "todo_history",
+        column: "new_value",
+        key: "id::text",
+        owner: HISTORY_OWNER,
+        location: HISTORY_LOCATION,
+    },
+];
+
+/// Encrypts the user's values that aren't encrypted yet, reporting how many.
+pub fn run(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let cipher = ctx.cipher.as_ref().ok_or_else(|| {
+        Error::Usage(tr!(
+            "Encryption is not enabled, set encryption.key or encryption.keyring in the config file"
+        ))
+    })?;
+    let mut transaction = client.transaction()?;
+    let mut count = 0;
+    for column in COLUMNS {
+        count += encrypt_column(&mut transaction, ctx, cipher, column)?;
+    }
+    count += encrypt_attachments(&mut transaction, ctx, cipher)?;
+    ctx.finish(transaction)?;
+    println!("{}", tr!("Encrypted {} values", count));
+    Ok(())
+}
+
+fn encrypt_column(
+    transaction: &mut Transaction,
+    ctx: &Context,
+    cipher: &Cipher,
+    column: &Column,
+) -> Result<u64, Error> {
+    let rows = transaction.query(
+        format!(
+            "select {key}, {column}, {location} from {table}
+             where {column} is not null and {owner}",
+            key = column.key,
+            column = column.column,
+            location = column.location,
+            table = column.table,
+            owner = column.owner,
+        )
+        .as_str(),
+        &[&ctx.user],
+    )?;
+    let update = format!(
+        "update {table} set {column} = $2 where {key} = $3 and {owner}",
+        table = column.table,
+        column = column.column,
+        key = column.key,
+        owner = column.owner,
+    );
+    let mut count = 0;
+    for row in rows {
+        let key: String = row.get(0);
+        let value: String = row.get(1);
+        if crypto::is_encrypted(&value) {
+            continue;
+        }
+        let location = Location::new(row.get(2), row.get(3), row.get(4));
+        let encrypted = cipher.encrypt(&value, location);
+        count += ctx.execute(transaction, &update, &[&ctx.user, &encrypted, &key])?;
+    }
+    Ok(count)
+}
+
+// Attachment contents are binary, with a flag saying whether they are encrypted.
+fn encrypt_attachments(
+    transaction: &mut Transaction,
+    ctx: &Context,
+    cipher: &Cipher,
+) -> Result<u64, Error> {
+    let rows = transaction.query(
+        format!(
+            "select id, content, (select uuid from todo where todo.id = attachment.todo_id)
+             from attachment
+             where content is not null and not encrypted and {}",
+            TASK_OWNER
+        )
+        .as_str(),
+        &[&ctx.user],
+    )?;
+    let mut count = 0;
+    for row in rows {
+        let id: i32 = row.get(0);
+        let content: Vec<u8> = row.get(1);
+        let location = Location::new("attachment", "content", row.get(2));
+        let encrypted = cipher.encrypt_bytes(&content, location);
+        count += ctx.execute(
+            transaction,
+            "update attachment set content = $1, encrypted = true where id = $2",
+            &[&encrypted, &id],
+        )?;
+    }
+    Ok(count)
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+
+/// An error that ends a command. Each kind has its own exit code, so scripts can tell them apart.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line is invalid.
+    Usage(String),
+    /// A task the command refers to doesn't exist.
+    NotFound(String),
+    /// The database can't be reached.
+    Connection(postgres::Error),
+    /// The `todo serve` daemon can't be reached, in remote mode.
+    Unreachable(String),
+    Database(postgres::Error),
+    Other(String),
+}
+
+pub const EXIT_OTHER: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_CONNECTION: i32 = 4;
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => EXIT_USAGE,
+            Error::NotFound(_) => EXIT_NOT_FOUND,
+            Error::Connection(_) | Error::Unreachable(_) => EXIT_CONNECTION,
+            Error::Database(e) if is_connection_lost(e) => EXIT_CONNECTION,
+            Error::Database(_) | Error::Other(_) => EXIT_OTHER,
+        }
+    }
+}
+
+/// Whether `e` means the connection to the database was lost during a command: it was closed or
+/// broke, or the server shut down.
+pub fn is_connection_lost(e: &postgres::Error) -> bool {
+    e.is_closed()
+        || std::error::Error::source(e).is_some_and(|source| source.is::<std::io::Error>())
+        // Connection exceptions, and operator interventions other than cancelled queries.
+        || e.code().is_some_and(|code| {
+            code.code().starts_with("08") || code.code().starts_with("57P")
+        })
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usage(message)
+            | Error::NotFound(message)
+            | Error::Unreachable(message)
+            | Error::Other(message) => write!(f, "{}", message),
+            Error::Connection(e) => {
+                write!(f, "{}", tr!("Could not connect to the database: {}", e))
+            }
+            Error::Database(e) if is_connection_lost(e) => {
+                write!(f, "{}", tr!("Lost the connection to the database: {}", e))
+            }
+            Error::Database(e) => match e.as_db_error() {
+                Some(db) => write!(f, "{}", tr!("Database error: {}", db.message())),
+                None => write!(f, "{}", tr!("Database error: {}", e)),
+            },
+        }
+    }
+}
+
+impl From<postgres::Error> for Error {
+    fn from(e: postgres::Error) -> Error {
+        Error::Database(e)
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Error {
+        Error::Other(message.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Error {
+        Error::Other(tr!("Database error: {}", e))
+    }
+}
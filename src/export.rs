@@ -0,0 +1,137 @@
+use std::time::SystemTime;
+use todo_rust::client::Client;
+use todo_rust::migrate;
+
+use crate::archive;
+use crate::error::Error;
+use crate::time::format_iso;
+use crate::Context;
+
+#[derive(Debug)]
+pub enum Format {
+    Sql,
+    Archive,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Format> {
+        match s {
+            "sql" => Some(Format::Sql),
+            "archive" => Some(Format::Archive),
+            _ => None,
+        }
+    }
+}
+
+/// The user's rows in each table. Tables are listed in an order that satisfies the foreign keys.
+pub const TABLES: &[(&str, &str)] = &[
+    ("todo", "owner = $1"),
+    ("todo_history", "owner = $1"),
+    (
+        "time_entry",
+        "todo_id in (select id from todo where owner = $1)",
+    ),
+    (
+        "annotation",
+        "todo_id in (select id from todo where owner = $1)",
+    ),
+    (
+        "attachment",
+        "todo_id in (select id from todo where owner = $1)",
+    ),
+    (
+        "dependency",
+        "todo_id in (select id from todo where owner = $1)",
+    ),
+    ("template", "owner = $1"),
+    ("active_context", "owner = $1"),
+    ("context", "owner = $1"),
+    ("trash", "owner = $1"),
+];
+
+/// Tables with a serial id, whose sequence has to be moved past the imported ids.
+pub const SEQUENCES: &[&str] = &[
+    "todo",
+    "todo_history",
+    "time_entry",
+    "annotation",
+    "attachment",
+];
+
+pub fn run(client: &mut Client, ctx: &Context, format: Format) -> Result<(), Error> {
+    match format {
+        Format::Sql => sql(client, ctx),
+        Format::Archive => archive::export(client, ctx),
+    }
+}
+
+/// Prints the user's data as a script of INSERT statements, which can be run with `psql` against
+/// a database at the same schema version. Encrypted names and notes are exported as they are
+/// stored, so they need the same key after importing.
+fn sql(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let version = migrate::current_version(client)?;
+    println!(
+        "-- todo export of {} at {}, schema version {}",
+        ctx.user,
+        format_iso(SystemTime::now()),
+        version
+    );
+    println!("begin;");
+    for (table, condition) in TABLES {
+        // Inserting the tasks records their history again, which is replaced by the original.
+        if *table == "todo_history" {
+            println!(
+                "delete from todo_history where owner = '{}';",
+                ctx.user.replace('\'', "''")
+            );
+        }
+        let query = insert_query(client, table, condition)?;
+        for row in client.query(query.as_str(), &[&ctx.user])? {
+            println!("{}", row.get::<_, String>(0));
+        }
+    }
+    for table in SEQUENCES {
+        println!(
+            "select setval(pg_get_serial_sequence('{0}', 'id'), coalesce(max(id), 0) + 1, false) \
+             from {0};",
+            table
+        );
+    }
+    println!("commit;");
+    Ok(())
+}
+
+// Builds a query that returns an INSERT statement for every row of `table` matching
+// `condition`. Values are quoted by the database, so they read back exactly as stored.
+fn insert_query(client: &mut Client, table: &str, condition: &str) -> Result<String, Error> {
+    let columns: Vec<String> = client
+        .query(
+            "select quote_ident(column_name::text)
+             from information_schema.columns
+             where table_schema = current_schema() and table_name = $1
+             order by ordinal_position",
+            &[&table],
+        )?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+    let values: Vec<String> = columns
+        .iter()
+        .map(|c| format!("quote_nullable({})", c))
+        .collect();
+    let order = if SEQUENCES.contains(&table) {
+        "id"
+    } else {
+        "1"
+    };
+    Ok(format!(
+        "select format('insert into {} ({}) values (%s);', concat_ws(', ', {}))
+         from {} where {} order by {}",
+        table,
+        columns.join(", "),
+        values.join(", "),
+        table,
+        condition,
+        order
+    ))
+}
@@ -0,0 +1,99 @@
+// Custom task attributes, defined in the `[fields]` section of the config with their type, e.g.
+// `effort = "number"`. Values are kept in the `fields` column as a JSON object, so adding a field
+// doesn't need a migration.
+
+use crate::config::Config;
+use crate::json::Json;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    String,
+    Number,
+    Bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub kind: Kind,
+}
+
+/// Reads the field definitions from the config.
+pub fn definitions(config: &Config) -> Result<Vec<Definition>, String> {
+    config
+        .section("fields")
+        .into_iter()
+        .map(|(name, kind)| {
+            let kind = match kind {
+                "string" => Kind::String,
+                "number" => Kind::Number,
+                "bool" => Kind::Bool,
+                _ => {
+                    return Err(tr!(
+                        "Invalid type for field '{}': {}, expected string, number or bool",
+                        name,
+                        kind
+                    ))
+                }
+            };
+            Ok(Definition {
+                name: name.to_string(),
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// Parses `name=value`, checking that the field is defined and the value has its type.
+pub fn parse(definitions: &[Definition], assignment: &str) -> Result<(String, Json), String> {
+    let (name, value) = assignment
+        .split_once('=')
+        .ok_or_else(|| tr!("Expected 'name=value' for a field, got: {}", assignment))?;
+    let definition = definitions.iter().find(|d| d.name == name).ok_or_else(|| {
+        tr!(
+            "Unknown field: {}, define it in the [fields] section of the config",
+            name
+        )
+    })?;
+    let value = match definition.kind {
+        Kind::String => Json::from(value),
+        Kind::Number => value
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .map(Json::from)
+            .ok_or_else(|| tr!("Invalid number for field '{}': {}", name, value))?,
+        Kind::Bool => match value {
+            "true" | "yes" => Json::from(true),
+            "false" | "no" => Json::from(false),
+            _ => {
+                return Err(tr!(
+                    "Invalid value for field '{}': {}, expected true or false",
+                    name,
+                    value
+                ))
+            }
+        },
+    };
+    Ok((name.to_string(), value))
+}
+
+/// Parses every `name=value` in `assignments`.
+pub fn parse_all(
+    definitions: &[Definition],
+    assignments: &[String],
+) -> Result<Vec<(String, Json)>, String> {
+    assignments.iter().map(|a| parse(definitions, a)).collect()
+}
+
+/// Returns `name: value` for every defined field set in `fields`, by field name.
+pub fn describe(definitions: &[Definition], fields: &Json) -> Vec<String> {
+    definitions
+        .iter()
+        .filter_map(|d| match fields.get(&d.name)? {
+            Json::Null => None,
+            Json::String(s) => Some(format!("{}: {}", d.name, s)),
+            value => Some(format!("{}: {}", d.name, value)),
+        })
+        .collect()
+}
@@ -0,0 +1,171 @@
+// Filters selecting tasks, as given to `modify` and in report definitions: ids, and `key:value`
+// terms where the key is `status`, `project`, `context`, `priority` or a custom field, such as
+// `project:home priority:high`. All terms have to match. Without a status, only open tasks match,
+// unless tasks are given by id. Archived tasks only match when given by id.
+
+use crate::context;
+use crate::error::Error;
+use crate::fields;
+use crate::json::Json;
+use crate::status::{self, Status};
+use crate::task_ref::{self, TaskRef};
+use crate::{parse_id, parse_priority, parse_status_filter, typed_query};
+use crate::{Context, TodoColumns, TodoRecord, TODO_TABLE};
+use todo_rust::client::GenericClient;
+
+// Custom field values to match, by field name.
+type Fields = Vec<(String, Json)>;
+
+#[derive(Debug, Default, Clone)]
+pub struct Filter {
+    ids: Vec<TaskRef>,
+    statuses: Option<Vec<Status>>,
+    project: Option<String>,
+    context: Option<String>,
+    priority: Option<i16>,
+    // `name=value`, checked against the field definitions when the filter is used.
+    fields: Vec<String>,
+}
+
+impl Filter {
+    pub fn parse(terms: &[String]) -> Result<Filter, String> {
+        let mut filter = Filter::default();
+        for term in terms {
+            let (key, value) = match term.split_once(':') {
+                Some(pair) => pair,
+                None => {
+                    filter.ids.push(parse_id(term)?);
+                    continue;
+                }
+            };
+            match key {
+                "status" => filter.statuses = Some(parse_status_filter(value)?),
+                "project" => filter.project = Some(value.to_string()),
+                "context" => filter.context = Some(context::parse_name(value)?),
+                "priority" => filter.priority = Some(parse_priority(value)?),
+                _ => filter.fields.push(format!("{}={}", key, value)),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// The user's tasks matching the filter, by creation.
+    pub fn tasks(
+        &self,
+        client: &mut impl GenericClient,
+        ctx: &Context,
+    ) -> Result<Vec<TodoRecord>, Error> {
+        let (ids, fields) = self.resolve(client, ctx)?;
+        Ok(self
+            .query(ctx, &ids)
+            .order_by(|t| typed_query::asc(&t.created_time))
+            .query(client)?
+            .into_iter()
+            .filter(|t| self.keeps(t, &ids, &fields))
+            .collect())
+    }
+
+    /// The first `limit` of the user's tasks matching the filter with an id after `after`, by
+    /// id, for pages of tasks. Tasks are read in batches until there are enough that match.
+    pub fn page(
+        &self,
+        client: &mut impl GenericClient,
+        ctx: &Context,
+        mut after: Option<i32>,
+        limit: usize,
+    ) -> Result<Vec<TodoRecord>, Error> {
+        let (ids, fields) = self.resolve(client, ctx)?;
+        let mut tasks = Vec::new();
+        loop {
+            let mut query = self.query(ctx, &ids);
+            if let Some(after) = after {
+                query = query.where_(|t| t.id.clone().gt(typed_query::Constant { value: after }));
+            }
+            let batch = query
+                .order_by(|t| typed_query::asc(&t.id))
+                .limit(limit as i64)
+                .query(client)?;
+            let complete = batch.len() < limit;
+            after = batch.last().map(|t| t.id);
+            tasks.extend(batch.into_iter().filter(|t| self.keeps(t, &ids, &fields)));
+            if complete || tasks.len() >= limit {
+                tasks.truncate(limit);
+                return Ok(tasks);
+            }
+        }
+    }
+
+    // The ids of the filter's tasks, if it has any, and its custom field values.
+    fn resolve(
+        &self,
+        client: &mut impl GenericClient,
+        ctx: &Context,
+    ) -> Result<(Option<Vec<i32>>, Fields), Error> {
+        let ids = if self.ids.is_empty() {
+            None
+        } else {
+            Some(task_ref::resolve_all(client, ctx, &self.ids)?)
+        };
+        let fields = fields::parse_all(&ctx.fields, &self.fields).map_err(Error::Usage)?;
+        Ok((ids, fields))
+    }
+
+    // The user's tasks with the filter's statuses. The rest of the filter is checked by `keeps`.
+    fn query(
+        &self,
+        ctx: &Context,
+        ids: &Option<Vec<i32>>,
+    ) -> typed_query::Query<TodoColumns, TodoRecord> {
+        let statuses = match (&self.statuses, ids) {
+            (Some(statuses), _) => statuses.clone(),
+            (None, Some(_)) => Status::ALL.to_vec(),
+            (None, None) => status::OPEN.to_vec(),
+        };
+        typed_query::from(TODO_TABLE)
+            .where_(|t| {
+                t.owner.clone().eq(typed_query::Constant {
+                    value: ctx.user.clone(),
+                })
+            })
+            .where_(|t| {
+                t.status.clone().in_(
+                    statuses
+                        .iter()
+                        .map(|&value| typed_query::Constant { value })
+                        .collect(),
+                )
+            })
+    }
+
+    fn keeps(&self, task: &TodoRecord, ids: &Option<Vec<i32>>, fields: &[(String, Json)]) -> bool {
+        let selected = match ids {
+            Some(ids) => ids.contains(&task.id),
+            None => task.archived_time.is_none(),
+        };
+        selected && self.matches_attributes(task, fields)
+    }
+
+    /// Whether the filter has ids, which only `tasks` can check.
+    pub fn has_ids(&self) -> bool {
+        !self.ids.is_empty()
+    }
+
+    /// Whether `task` matches the filter's statuses and attributes. Ids aren't checked.
+    pub fn matches(&self, ctx: &Context, task: &TodoRecord) -> Result<bool, Error> {
+        let fields = fields::parse_all(&ctx.fields, &self.fields).map_err(Error::Usage)?;
+        Ok(self
+            .statuses
+            .as_ref()
+            .is_none_or(|statuses| statuses.contains(&task.status))
+            && self.matches_attributes(task, &fields))
+    }
+
+    fn matches_attributes(&self, task: &TodoRecord, fields: &[(String, Json)]) -> bool {
+        (self.project.is_none() || task.project == self.project)
+            && (self.context.is_none() || task.context == self.context)
+            && (self.priority.is_none() || task.priority == self.priority)
+            && fields
+                .iter()
+                .all(|(name, value)| task.fields.get(name) == Some(value))
+    }
+}
@@ -0,0 +1,192 @@
+use std::env;
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::http;
+use crate::json::Json;
+use crate::keyring;
+use crate::task_ref::new_uuid;
+use crate::Context;
+use todo_rust::client::{Client, GenericClient};
+
+const PAGE_SIZE: usize = 100;
+const DEFAULT_API_URL: &str = "https://api.github.com";
+
+// `GITHUB_API_URL` allows pointing at a GitHub Enterprise instance.
+fn api_url() -> String {
+    env::var("GITHUB_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string())
+}
+
+fn token() -> Result<String, String> {
+    env::var("GITHUB_TOKEN")
+        .or_else(|_| env::var("GH_TOKEN"))
+        .ok()
+        .or_else(|| keyring::get("github"))
+        .ok_or_else(|| {
+            tr!("Set GITHUB_TOKEN to a GitHub access token, or store one with 'todo auth set github'")
+        })
+}
+
+fn call(method: &str, path: &str, body: Option<&Json>) -> Result<Json, String> {
+    let authorization = format!("Bearer {}", token()?);
+    let body = body.map(|b| b.to_string());
+    let response = http::request(
+        method,
+        &format!("{}{}", api_url(), path),
+        &[
+            ("Accept", "application/vnd.github+json"),
+            ("Authorization", &authorization),
+            ("User-Agent", "todo-rust"),
+        ],
+        body.as_deref(),
+    )?;
+    let json = Json::parse(&response.body)?;
+    if !response.is_success() {
+        let message = json.get("message").and_then(|m| m.as_str()).unwrap_or("");
+        return Err(tr!(
+            "GitHub request failed with status {}: {}",
+            response.status,
+            message
+        ));
+    }
+    Ok(json)
+}
+
+/// Imports the open issues of `repo` assigned to `assignee` (by default the owner of the token)
+/// as tasks, skipping issues that were imported before.
+pub fn import(
+    client: &mut Client,
+    ctx: &Context,
+    repo: &str,
+    assignee: Option<String>,
+    close_on_complete: bool,
+) -> Result<(), Error> {
+    let assignee = match assignee {
+        Some(a) => a,
+        None => call("GET", "/user", None)?
+            .get("login")
+            .and_then(|l| l.as_str())
+            .ok_or_else(|| tr!("Could not determine the GitHub user"))?
+            .to_string(),
+    };
+    let mut page = 1;
+    loop {
+        let issues = call(
+            "GET",
+            &format!(
+                "/repos/{}/issues?state=open&assignee={}&per_page={}&page={}",
+                repo, assignee, PAGE_SIZE, page
+            ),
+            None,
+        )?;
+        let issues = issues
+            .as_array()
+            .ok_or_else(|| tr!("Unexpected response from GitHub"))?;
+        for issue in issues {
+            // The issues endpoint also returns pull requests.
+            if issue.get("pull_request").is_some() {
+                continue;
+            }
+            let (number, title, url) = match (
+                issue.get("number").and_then(|n| n.as_i64()),
+                issue.get("title").and_then(|t| t.as_str()),
+                issue.get("html_url").and_then(|u| u.as_str()),
+            ) {
+                (Some(number), Some(title), Some(url)) => (number as i32, title, url),
+                _ => return Err(tr!("Unexpected issue in response from GitHub").into()),
+            };
+            // Issues imported before are updated instead, if they were renamed. With encryption
+            // enabled names differ every time they're encrypted, so they're always updated.
+            let external_id = external_id(repo, number);
+            // An issue imported before keeps its uuid, which its name is encrypted with.
+            let uuid = match client.query_opt(
+                "select uuid from todo where owner = $1 and external_id = $2",
+                &[&ctx.user, &external_id],
+            )? {
+                Some(row) => row.get(0),
+                None => new_uuid(),
+            };
+            let row = client.query_opt(
+                "insert into todo
+                         (name, github_repo, github_issue, github_url, github_close, owner,
+                          external_id, uuid)
+                     values ($1, $2, $3, $4, $5, $6, $7, $8)
+                     on conflict (owner, external_id) do update
+                         set name = excluded.name, github_url = excluded.github_url
+                         where todo.name <> excluded.name
+                     returning id, xmax = 0",
+                &[
+                    &ctx.encrypt(title, Location::task("name", &uuid)),
+                    &repo,
+                    &number,
+                    &url,
+                    &close_on_complete,
+                    &ctx.user,
+                    &external_id,
+                    &uuid,
+                ],
+            )?;
+            match row {
+                Some(row) if row.get(1) => {
+                    let id: i32 = row.get(0);
+                    println!("{}", tr!("{}: imported {}#{} {}", id, repo, number, title));
+                }
+                Some(row) => {
+                    let id: i32 = row.get(0);
+                    println!("{}", tr!("{}: updated {}#{} {}", id, repo, number, title));
+                }
+                None => {
+                    let id: i32 = client
+                        .query_one(
+                            "select id from todo where owner = $1 and external_id = $2",
+                            &[&ctx.user, &external_id],
+                        )?
+                        .get(0);
+                    println!("{}", tr!("{}: already imported {}#{}", id, repo, number));
+                }
+            }
+        }
+        if issues.len() < PAGE_SIZE {
+            return Ok(());
+        }
+        page += 1;
+    }
+}
+
+/// The external id of an issue, such as `github:owner/repo#12`.
+fn external_id(repo: &str, number: i32) -> String {
+    format!("github:{}#{}", repo, number)
+}
+
+/// Closes the GitHub issues of the given completed tasks that were imported with
+/// `--close-on-complete`.
+pub fn close_completed(
+    client: &mut impl GenericClient,
+    ctx: &Context,
+    ids: &[i32],
+) -> Result<(), Error> {
+    let rows = client.query(
+        "select id, github_repo, github_issue from todo
+             where id = any($1) and owner = $2 and status = 'done' and github_close
+                 and github_issue is not null",
+        &[&ids, &ctx.user],
+    )?;
+    for row in rows {
+        let id: i32 = row.get(0);
+        let repo: String = row.get(1);
+        let number: i32 = row.get(2);
+        let state = Json::object(vec![("state", Json::from("closed"))]);
+        match call(
+            "PATCH",
+            &format!("/repos/{}/issues/{}", repo, number),
+            Some(&state),
+        ) {
+            Ok(_) => println!("{}", tr!("{}: closed {}#{}", id, repo, number)),
+            Err(e) => log::warn!(
+                "{}",
+                tr!("{}: failed to close {}#{}: {}", id, repo, number, e)
+            ),
+        }
+    }
+    Ok(())
+}
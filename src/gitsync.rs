@@ -0,0 +1,386 @@
+// Synchronization through a git repository. Every task is a JSON file, `tasks/<uuid>.json`, with
+// its attributes on separate lines so changes diff and merge well. Names and notes are written as
+// stored, so with encryption enabled the repository only holds encrypted values.
+//
+// A sync first takes in changes made in the repository since the last sync (by hand, or conflicts
+// resolved after a failed pull), then writes the database's tasks and commits them. If the
+// repository has a remote, it then fetches and merges, takes in the merged changes and pushes. The last synced
+// commit is kept in `refs/todo/synced`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::conflict::{self, Conflict, Resolution};
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::json::Json;
+use crate::status::Status;
+use crate::{parse_priority, priority_key, time, typed_query, Context, TODO_TABLE};
+use todo_rust::client::{Client, Transaction};
+
+const TASKS_DIR: &str = "tasks";
+const SYNCED_REF: &str = "refs/todo/synced";
+// The name of the target in the conflict policies and `sync status`.
+const TARGET: &str = "git";
+
+pub fn sync(client: &mut Client, ctx: &Context, repo: &Path) -> Result<(), Error> {
+    if !repo.join(".git").exists() {
+        fs::create_dir_all(repo)
+            .map_err(|e| Error::Other(tr!("Failed to create {}: {}", repo.display(), e)))?;
+        git(repo, &["init", "--quiet"])?;
+    }
+    conflict::clear(client, ctx, TARGET)?;
+    let head = revision(repo, "HEAD")?;
+    let synced = revision(repo, SYNCED_REF)?;
+    if let Some(head) = &head {
+        if synced.as_ref() != Some(head) {
+            import_changes(client, ctx, repo, synced.as_deref(), head)?;
+        }
+    }
+
+    export(client, ctx, repo)?;
+    git(repo, &["add", "--all", "--", TASKS_DIR])?;
+    if git(repo, &["diff", "--cached", "--quiet"]).is_err() {
+        let message = format!("Sync tasks of {}", ctx.user);
+        git(repo, &["commit", "--quiet", "--message", &message])?;
+        println!("{}", tr!("Committed local changes"));
+    }
+
+    let remotes = git(repo, &["remote"])?;
+    if let Some(remote) = remotes.lines().next() {
+        git(repo, &["fetch", "--quiet", remote])?;
+        let before = revision(repo, "HEAD")?;
+        // Until the first push there is nothing to merge.
+        if revision(repo, "@{upstream}")?.is_some() {
+            if let Err(e) = git(repo, &["merge", "--quiet", "--no-edit", "@{upstream}"]) {
+                resolve_merge(client, ctx, repo, e)?;
+            }
+        }
+        if let Some(after) = revision(repo, "HEAD")? {
+            if before.as_ref() != Some(&after) {
+                import_changes(client, ctx, repo, before.as_deref(), &after)?;
+            }
+            if revision(repo, "@{upstream}")?.as_ref() != Some(&after) {
+                git(repo, &["push", "--quiet", "--set-upstream", remote, "HEAD"])?;
+                println!("{}", tr!("Pushed to {}", remote));
+            }
+        }
+    }
+    if let Some(head) = revision(repo, "HEAD")? {
+        git(repo, &["update-ref", SYNCED_REF, &head])?;
+    }
+    Ok(())
+}
+
+// Resolves the task files a merge, which failed with `error`, left conflicted by the `git`
+// conflict policy, and commits the merge. Conflicts in other files, or left unresolved, have to
+// be resolved by hand.
+fn resolve_merge(
+    client: &mut Client,
+    ctx: &Context,
+    repo: &Path,
+    error: Error,
+) -> Result<(), Error> {
+    let paths: Vec<String> = git(repo, &["diff", "--name-only", "--diff-filter=U"])?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    if paths.is_empty() || paths.iter().any(|p| !p.starts_with(TASKS_DIR)) {
+        return Err(Error::Other(tr!(
+            "Merging failed: {}\nResolve any conflicts in {}, commit them and sync again.",
+            error,
+            repo.display()
+        )));
+    }
+    let mut unresolved = 0;
+    for path in &paths {
+        // Stage 2 is the local version, stage 3 the merged one; a side without it deleted it.
+        let version = |stage: u8| git(repo, &["show", &format!(":{}:{}", stage, path)]).ok();
+        // Files are named after the uuid of their task.
+        let uuid = Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = |contents: &Option<String>| {
+            contents.as_ref().map(|c| {
+                Json::parse(c)
+                    .ok()
+                    .and_then(|task| {
+                        task.get("name")
+                            .and_then(|n| n.as_str())
+                            .map(|n| ctx.decrypt(n, Location::task("name", &uuid)))
+                    })
+                    .unwrap_or_else(|| path.clone())
+            })
+        };
+        let (local, remote) = (version(2), version(3));
+        let conflict = Conflict {
+            item: Path::new(path)
+                .file_stem()
+                .map_or(path.clone(), |s| s.to_string_lossy().into_owned()),
+            local: name(&local),
+            remote: name(&remote),
+            local_modified: commit_time(repo, "HEAD", path)?,
+            remote_modified: commit_time(repo, "MERGE_HEAD", path)?,
+        };
+        let (side, kept) = match conflict::resolve(ctx, ctx.conflicts.git, &conflict)? {
+            Resolution::Local => ("--ours", local),
+            Resolution::Remote => ("--theirs", remote),
+            Resolution::Unresolved => {
+                conflict::record(client, ctx, TARGET, &conflict)?;
+                unresolved += 1;
+                continue;
+            }
+        };
+        if kept.is_some() {
+            git(repo, &["checkout", side, "--", path])?;
+            git(repo, &["add", "--", path])?;
+        } else {
+            git(repo, &["rm", "--quiet", "--", path])?;
+        }
+    }
+    if unresolved > 0 {
+        return Err(Error::Other(tr!(
+            "{} conflicts are unresolved, see 'todo sync status'\nResolve them in {}, commit them and sync again.",
+            unresolved,
+            repo.display()
+        )));
+    }
+    git(repo, &["commit", "--quiet", "--no-edit"])?;
+    println!("{}", tr!("Resolved {} conflicts", paths.len()));
+    Ok(())
+}
+
+// When `path` was last changed in the history of `commit`.
+fn commit_time(repo: &Path, commit: &str, path: &str) -> Result<Option<SystemTime>, Error> {
+    let output = git(repo, &["log", "-1", "--format=%ct", commit, "--", path])?;
+    Ok(output
+        .trim()
+        .parse()
+        .ok()
+        .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds)))
+}
+
+// Runs git in `repo`, returning its output, or its error message when it fails.
+fn git(repo: &Path, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| Error::Other(tr!("Failed to run git: {}", e)))?;
+    if !output.status.success() {
+        // Some failures, such as merge conflicts, are only described on stdout.
+        let mut message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if message.is_empty() {
+            message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        }
+        return Err(Error::Other(if message.is_empty() {
+            tr!("git {} failed", args.join(" "))
+        } else {
+            message
+        }));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Returns the commit `name` refers to, if it exists.
+fn revision(repo: &Path, name: &str) -> Result<Option<String>, Error> {
+    let commit = format!("{}^{{commit}}", name);
+    match git(repo, &["rev-parse", "--quiet", "--verify", &commit]) {
+        Ok(output) => Ok(Some(output.trim().to_string())),
+        Err(_) => Ok(None),
+    }
+}
+
+// Applies the changes to task files between the commits `from` (or, without it, all task files)
+// and `to`, which is checked out.
+fn import_changes(
+    client: &mut Client,
+    ctx: &Context,
+    repo: &Path,
+    from: Option<&str>,
+    to: &str,
+) -> Result<(), Error> {
+    let changes: Vec<(String, String)> = match from {
+        Some(from) => git(
+            repo,
+            &[
+                "diff",
+                "--name-status",
+                "--no-renames",
+                from,
+                to,
+                "--",
+                TASKS_DIR,
+            ],
+        )?
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(status, path)| (status.to_string(), path.to_string()))
+        .collect(),
+        None => git(repo, &["ls-tree", "-r", "--name-only", to, "--", TASKS_DIR])?
+            .lines()
+            .map(|path| ("A".to_string(), path.to_string()))
+            .collect(),
+    };
+    let mut transaction = client.transaction()?;
+    let mut count = 0;
+    for (status, path) in changes {
+        let uuid = match Path::new(&path).file_stem().and_then(|s| s.to_str()) {
+            Some(uuid) if path.ends_with(".json") => uuid.to_string(),
+            _ => continue,
+        };
+        if status == "D" {
+            transaction.execute(
+                "delete from todo where uuid = $1 and owner = $2",
+                &[&uuid, &ctx.user],
+            )?;
+        } else {
+            let file = repo.join(&path);
+            let contents = fs::read_to_string(&file)
+                .map_err(|e| Error::Other(tr!("Failed to read {}: {}", file.display(), e)))?;
+            let task = Json::parse(&contents)
+                .map_err(|e| Error::Other(tr!("Invalid task file {}: {}", path, e)))?;
+            upsert(&mut transaction, ctx, &uuid, &task).map_err(|e| match e {
+                Error::Usage(e) => Error::Other(tr!("Invalid task file {}: {}", path, e)),
+                e => e,
+            })?;
+        }
+        count += 1;
+    }
+    transaction.commit()?;
+    if count > 0 {
+        println!("{}", tr!("Imported {} changes from the repository", count));
+    }
+    Ok(())
+}
+
+// Inserts or updates the task with `uuid` from the contents of its file. Invalid contents are
+// reported as a usage error.
+fn upsert(
+    transaction: &mut Transaction,
+    ctx: &Context,
+    uuid: &str,
+    task: &Json,
+) -> Result<(), Error> {
+    let text = |key: &str| {
+        task.get(key)
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+    let name = text("name").ok_or_else(|| Error::Usage(tr!("Missing task name")))?;
+    let status = match text("status") {
+        Some(s) => Status::parse(&s).ok_or_else(|| Error::Usage(tr!("Invalid status: {}", s)))?,
+        None => Status::Todo,
+    };
+    let priority = text("priority")
+        .map(|p| parse_priority(&p))
+        .transpose()
+        .map_err(Error::Usage)?;
+    let fields = task
+        .get("fields")
+        .cloned()
+        .unwrap_or_else(|| Json::Object(Vec::new()));
+    let row = transaction.query_opt(
+        "insert into todo (uuid, owner, name, description, status, created_time, due_time,
+                 completed_time, priority, project, context, fields)
+             values ($1, $2, $3, $4, $5, coalesce($6::text::timestamptz, now()),
+                 $7::text::timestamptz, $8::text::timestamptz, $9, $10, $11, $12)
+             on conflict (uuid) do update
+                 set name = excluded.name, description = excluded.description,
+                     status = excluded.status, created_time = excluded.created_time,
+                     due_time = excluded.due_time, completed_time = excluded.completed_time,
+                     priority = excluded.priority, project = excluded.project,
+                     context = excluded.context, fields = excluded.fields
+                 where todo.owner = excluded.owner
+             returning id",
+        &[
+            &uuid,
+            &ctx.user,
+            &name,
+            &text("note"),
+            &status,
+            &text("created"),
+            &text("due"),
+            &text("completed"),
+            &priority,
+            &text("project"),
+            &text("context"),
+            &fields,
+        ],
+    )?;
+    // The task exists, but isn't the user's.
+    if row.is_none() {
+        return Err(Error::Usage(tr!("Task {} belongs to another user", uuid)));
+    }
+    Ok(())
+}
+
+// Writes a file for every task of the user, and removes the files of other tasks.
+fn export(client: &mut Client, ctx: &Context, repo: &Path) -> Result<(), Error> {
+    let dir = repo.join(TASKS_DIR);
+    fs::create_dir_all(&dir)
+        .map_err(|e| Error::Other(tr!("Failed to create {}: {}", dir.display(), e)))?;
+    let tasks = typed_query::from(TODO_TABLE)
+        .where_(|t| {
+            t.owner.clone().eq(typed_query::Constant {
+                value: ctx.user.clone(),
+            })
+        })
+        .query(client)?;
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for task in tasks {
+        let file = Json::object(vec![
+            ("name", Json::from(task.name)),
+            ("note", Json::from(task.description)),
+            ("status", Json::from(task.status.as_str())),
+            ("created", Json::from(time::format_iso(task.created_time))),
+            ("due", Json::from(task.due_time.map(time::format_iso))),
+            (
+                "completed",
+                Json::from(task.completed_time.map(time::format_iso)),
+            ),
+            ("priority", Json::from(task.priority.map(priority_key))),
+            ("project", Json::from(task.project)),
+            ("context", Json::from(task.context)),
+            ("fields", task.fields),
+        ]);
+        let path = dir.join(format!("{}.json", task.uuid));
+        write_if_changed(&path, &to_file(&file))?;
+        paths.push(path);
+    }
+    let entries = fs::read_dir(&dir)
+        .map_err(|e| Error::Other(tr!("Failed to read {}: {}", dir.display(), e)))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "json") && !paths.contains(&path) {
+            fs::remove_file(&path)
+                .map_err(|e| Error::Other(tr!("Failed to remove {}: {}", path.display(), e)))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_if_changed(path: &Path, contents: &str) -> Result<(), Error> {
+    if fs::read_to_string(path).ok().as_deref() == Some(contents) {
+        return Ok(());
+    }
+    fs::write(path, contents)
+        .map_err(|e| Error::Other(tr!("Failed to write {}: {}", path.display(), e)))
+}
+
+// Formats an object with a member per line, so a change to one attribute changes one line.
+fn to_file(object: &Json) -> String {
+    let members = match object {
+        Json::Object(members) => members,
+        _ => return format!("{}\n", object),
+    };
+    let lines: Vec<String> = members
+        .iter()
+        .map(|(key, value)| format!("  {}: {}", Json::from(key.as_str()), value))
+        .collect();
+    format!("{{\n{}\n}}\n", lines.join(",\n"))
+}
@@ -0,0 +1,268 @@
+// gRPC mode of `todo serve`: with `--grpc`, the API is served as the `Tasks` service of
+// proto/todo.proto instead of as JSON over HTTP. Every call is answered like the equivalent HTTP
+// request, so tokens (sent as `authorization: Bearer <token>` metadata), rate limits, webhooks and
+// metrics work the same, and the statuses of the answers become gRPC codes.
+//
+// Calls are handled on a pool of up to 64 threads, waiting for a database connection from the
+// pool like the connections of HTTP mode. Changes to the config are applied from the next call on.
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+
+use tonic::transport::server::TcpIncoming;
+use tonic::{Code, Request, Response, Status};
+
+use crate::error::Error;
+use crate::json::Json;
+use crate::reload;
+use crate::serve::{self, Answer, Call, Server};
+use crate::task_ref::TaskRef;
+use crate::Context;
+
+mod proto {
+    tonic::include_proto!("todo.v1");
+}
+
+use proto::tasks_server::{Tasks, TasksServer};
+
+// Calls handled at once. More wait for one of them to finish.
+const MAX_CALLS: usize = 64;
+
+/// Serves the API over gRPC on `listen` (such as `127.0.0.1:8080`) until the process is stopped.
+pub fn run(ctx: &Context, listen: &str, pool_size: usize) -> Result<(), Error> {
+    let failed =
+        |e: &dyn std::fmt::Display| Error::Other(tr!("Failed to listen on {}: {}", listen, e));
+    let address = listen
+        .to_socket_addrs()
+        .and_then(|mut addresses| {
+            addresses
+                .next()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable))
+        })
+        .map_err(|e| failed(&e))?;
+    // The calls are handled on other threads, which need settings of their own.
+    let settings = reload::read(ctx).map_err(Error::Other)?;
+    let service = Service(Arc::new(Shared {
+        server: Server::new(ctx, pool_size)?,
+        settings: Mutex::new((reload::Watcher::new(), Arc::new(settings))),
+    }));
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .max_blocking_threads(MAX_CALLS)
+        .build()
+        .map_err(|e| failed(&e))?;
+    runtime.block_on(async {
+        let incoming = TcpIncoming::bind(address).map_err(|e| failed(&e))?;
+        println!("{}", tr!("Listening for gRPC on {}", listen));
+        tonic::transport::Server::builder()
+            .add_service(TasksServer::new(service))
+            .serve_with_incoming(incoming)
+            .await
+            .map_err(|e| failed(&e))
+    })
+}
+
+/// What the calls share.
+struct Shared {
+    server: Server,
+    /// The config watcher, and the settings it last read.
+    settings: Mutex<(reload::Watcher, Arc<Context>)>,
+}
+
+impl Shared {
+    // The current settings, read again if the config changed.
+    fn settings(&self) -> Arc<Context> {
+        let mut settings = self.settings.lock().unwrap();
+        let (watcher, current) = &mut *settings;
+        if let Some(reloaded) = watcher.check(current) {
+            *current = Arc::new(reloaded);
+        }
+        current.clone()
+    }
+}
+
+struct Service(Arc<Shared>);
+
+impl Service {
+    // Answers `call` on a thread of its own, failing with the code of its status unless it
+    // succeeded.
+    async fn answer(&self, call: Call) -> Result<Answer, Status> {
+        let shared = self.0.clone();
+        let answer = tokio::task::spawn_blocking(move || {
+            serve::answer(&shared.settings(), &shared.server, call)
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+        if (200..300).contains(&answer.status) {
+            return Ok(answer);
+        }
+        let message = answer
+            .body
+            .get("error")
+            .and_then(|e| e.as_str())
+            .unwrap_or_default();
+        Err(Status::new(code(answer.status), message))
+    }
+}
+
+#[tonic::async_trait]
+impl Tasks for Service {
+    async fn list_tasks(
+        &self,
+        request: Request<proto::ListTasksRequest>,
+    ) -> Result<Response<proto::ListTasksResponse>, Status> {
+        let mut call = call(&request, "GET", "/tasks".to_string());
+        let request = request.into_inner();
+        let mut params = Vec::new();
+        if !request.status.is_empty() {
+            params.push(format!("status={}", encode(&request.status)));
+        }
+        if request.limit != 0 {
+            params.push(format!("limit={}", request.limit));
+        }
+        if !request.cursor.is_empty() {
+            params.push(format!("cursor={}", encode(&request.cursor)));
+        }
+        call.query = params.join("&");
+        let answer = self.answer(call).await?;
+        Ok(Response::new(proto::ListTasksResponse {
+            tasks: answer
+                .body
+                .as_array()
+                .map(|tasks| tasks.iter().map(task).collect())
+                .unwrap_or_default(),
+            next_cursor: answer.next.unwrap_or_default(),
+        }))
+    }
+
+    async fn get_task(
+        &self,
+        request: Request<proto::TaskRef>,
+    ) -> Result<Response<proto::Task>, Status> {
+        let path = task_path(&request.get_ref().id)?;
+        let answer = self.answer(call(&request, "GET", path)).await?;
+        Ok(Response::new(task(&answer.body)))
+    }
+
+    async fn create_task(
+        &self,
+        request: Request<proto::CreateTaskRequest>,
+    ) -> Result<Response<proto::Task>, Status> {
+        let mut call = call(&request, "POST", "/tasks".to_string());
+        let request = request.into_inner();
+        // Empty strings are left out, as they are unset.
+        let text = |value: String| Json::from(Some(value).filter(|v| !v.is_empty()));
+        call.body = Json::object(vec![
+            ("name", text(request.name)),
+            ("due", text(request.due)),
+            ("note", text(request.note)),
+            ("project", text(request.project)),
+            ("priority", text(request.priority)),
+            ("context", text(request.context)),
+        ])
+        .to_string();
+        let answer = self.answer(call).await?;
+        Ok(Response::new(task(&answer.body)))
+    }
+
+    async fn complete_task(
+        &self,
+        request: Request<proto::TaskRef>,
+    ) -> Result<Response<proto::Task>, Status> {
+        let path = format!("{}/complete", task_path(&request.get_ref().id)?);
+        let answer = self.answer(call(&request, "POST", path)).await?;
+        Ok(Response::new(task(&answer.body)))
+    }
+
+    async fn delete_task(
+        &self,
+        request: Request<proto::TaskRef>,
+    ) -> Result<Response<proto::DeleteTaskResponse>, Status> {
+        let path = task_path(&request.get_ref().id)?;
+        self.answer(call(&request, "DELETE", path)).await?;
+        Ok(Response::new(proto::DeleteTaskResponse {}))
+    }
+}
+
+// The call made by `request`, without a query or body.
+fn call<T>(request: &Request<T>, method: &'static str, path: String) -> Call {
+    Call {
+        method,
+        path,
+        query: String::new(),
+        body: String::new(),
+        authorization: request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string()),
+        peer: request
+            .remote_addr()
+            .map(|address: SocketAddr| address.ip()),
+    }
+}
+
+// The path of a task in the HTTP API. Ids that aren't a task's can't be part of it, as they could
+// name another path.
+fn task_path(id: &str) -> Result<String, Status> {
+    match TaskRef::parse(id) {
+        Some(task) => Ok(format!("/tasks/{}", task)),
+        None => Err(Status::not_found(tr!("No task with id {}", id))),
+    }
+}
+
+// A task as served by the HTTP API.
+fn task(json: &Json) -> proto::Task {
+    let text = |key: &str| {
+        json.get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    proto::Task {
+        id: json.get("id").and_then(|v| v.as_i64()).unwrap_or_default() as i32,
+        alias: text("alias"),
+        name: text("name"),
+        status: text("status"),
+        created: text("created"),
+        due: text("due"),
+        completed: text("completed"),
+        priority: text("priority"),
+        project: text("project"),
+        context: text("context"),
+        note: text("note"),
+        fields_json: json
+            .get("fields")
+            .map(|f| f.to_string())
+            .unwrap_or_default(),
+        uuid: text("uuid"),
+    }
+}
+
+// The gRPC code for the HTTP status of an answer.
+fn code(status: u16) -> Code {
+    match status {
+        400 => Code::InvalidArgument,
+        401 => Code::Unauthenticated,
+        404 => Code::NotFound,
+        405 => Code::Unimplemented,
+        409 => Code::FailedPrecondition,
+        429 => Code::ResourceExhausted,
+        503 => Code::Unavailable,
+        _ => Code::Internal,
+    }
+}
+
+// Percent-encodes a query parameter.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
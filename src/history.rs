@@ -0,0 +1,93 @@
+use std::time::SystemTime;
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::output;
+use crate::time;
+use crate::Context;
+use todo_rust::client::{Client, GenericClient};
+
+// Columns holding values that may be encrypted.
+const ENCRYPTED: &[&str] = &["name", "description"];
+
+/// Sets the source recorded with changes made on this connection, such as the command name.
+pub fn set_source(client: &mut impl GenericClient, source: &str) -> Result<(), postgres::Error> {
+    client.execute("select set_config('todo.command', $1, false)", &[&source])?;
+    Ok(())
+}
+
+/// Shows the changes made to a task, oldest first. Changes are recorded by the `todo_history`
+/// trigger: creation and deletion as a whole, and updates per column.
+pub fn run(client: &mut Client, ctx: &Context, id: i32) -> Result<(), Error> {
+    let rows = client.query(
+        "select time, command, action, field, old_value, new_value from todo_history
+         where todo_id = $1 and owner = $2
+         order by id",
+        &[&id, &ctx.user],
+    )?;
+    if rows.is_empty() {
+        return Err(Error::NotFound(tr!("No history for task {}", id)));
+    }
+    // The values are copied from the task, so they are encrypted with its uuid. A deleted task's
+    // uuid is kept in the record of its creation.
+    let uuid: String = client
+        .query_one(
+            "select coalesce(
+                 (select uuid from todo where id = $1),
+                 (select new_value::jsonb ->> 'uuid' from todo_history
+                  where todo_id = $1 and action = 'insert' limit 1),
+                 '')",
+            &[&id],
+        )?
+        .get(0);
+    for row in rows {
+        let time: SystemTime = row.get(0);
+        let command: Option<String> = row.get(1);
+        let action: String = row.get(2);
+        let field: Option<String> = row.get(3);
+        let field = field.unwrap_or_default();
+        let value = |value: Option<String>| match value {
+            Some(v) if ENCRYPTED.contains(&field.as_str()) => {
+                ctx.decrypt(&v, Location::task(&field, &uuid))
+            }
+            Some(v) => v,
+            None => String::new(),
+        };
+        let (old, new) = match action.as_str() {
+            "update" => (value(row.get(4)), value(row.get(5))),
+            _ => (String::new(), String::new()),
+        };
+        let command = command.unwrap_or_default();
+        if !ctx.output.is_text() {
+            output::record(
+                ctx.output,
+                &[
+                    &time::format_iso(time),
+                    &command,
+                    &action,
+                    &field,
+                    &old,
+                    &new,
+                ],
+            );
+            continue;
+        }
+        let change = match action.as_str() {
+            "insert" => tr!("created"),
+            "delete" => tr!("deleted"),
+            _ => format!(
+                "{}: {} → {}",
+                field,
+                if old.is_empty() { "-" } else { &old },
+                if new.is_empty() { "-" } else { &new }
+            ),
+        };
+        println!(
+            "{}  {:<10}  {}",
+            time::format_absolute(time, ctx.timezone.as_ref()),
+            command,
+            change
+        );
+    }
+    Ok(())
+}
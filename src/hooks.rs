@@ -0,0 +1,62 @@
+// User scripts run after tasks change. A hook is an executable file named after the event, such
+// as `on-add`, in the hooks directory: `hooks.dir` in the config, or `hooks` next to the config
+// file. It gets the same JSON payload as webhooks on stdin, once per changed task.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::{self, Config};
+use crate::webhook::Event;
+
+#[derive(Debug, Default)]
+pub struct Hooks {
+    dir: Option<PathBuf>,
+}
+
+impl Hooks {
+    pub fn load(config: &Config) -> Hooks {
+        let dir = match config.get("hooks.dir") {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => config::path().and_then(|p| p.parent().map(|d| d.join("hooks"))),
+        };
+        Hooks {
+            dir: dir.filter(|d| d.is_dir()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dir.is_none()
+    }
+
+    /// Runs the hook for `event`, if there is one, with `payload` on stdin. Failures are reported
+    /// as warnings, since the change itself has been made.
+    pub fn run(&self, event: Event, payload: &str) {
+        let path = match &self.dir {
+            Some(dir) => dir.join(event.hook_name()),
+            None => return,
+        };
+        if !path.is_file() {
+            return;
+        }
+        if let Err(e) = run_hook(&path, payload) {
+            log::warn!("{}", tr!("Hook {} failed: {}", path.display(), e));
+        }
+    }
+}
+
+fn run_hook(path: &Path, payload: &str) -> Result<(), String> {
+    let mut child = Command::new(path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read its input isn't an error.
+        stdin.write_all(payload.as_bytes()).ok();
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(status.to_string());
+    }
+    Ok(())
+}
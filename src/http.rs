@@ -0,0 +1,183 @@
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+// Seconds to wait for a connection, and for the whole request, so a server that never answers
+// doesn't hang the command.
+const CONNECT_TIMEOUT: u32 = 5;
+const MAX_TIME: u32 = 30;
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Performs an HTTP request by running `curl`. Headers, credentials and the body are passed to
+/// curl on stdin, so they don't show up in the process list.
+pub fn request(
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: Option<&str>,
+) -> Result<Response, String> {
+    log::info!("{} {}", method, url);
+    let child = spawn(&config(method, url, headers, body), Stdio::piped())?;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| tr!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(tr!(
+            "Request to {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let response = parse_response(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| tr!("Invalid response from {}", url))?;
+    log::debug!("{} {}: {}", method, url, response.status);
+    Ok(response)
+}
+
+/// Posts each of `requests`, given as headers and a body, to `url` in turn, without waiting for
+/// them: curl runs on by itself, even after the command has finished. Requests that can't
+/// connect or get a server error are tried `retries` more times, with growing waits in between.
+/// When some failed, `failed` gets the errors, if the command still runs by then.
+pub fn post_in_background<F>(
+    url: &str,
+    requests: &[(Vec<(&str, &str)>, &str)],
+    retries: u32,
+    failed: F,
+) -> Result<(), String>
+where
+    F: FnOnce(String) + Send + 'static,
+{
+    let transfers: Vec<String> = requests
+        .iter()
+        .map(|(headers, body)| {
+            let mut config = config("POST", url, headers, Some(body));
+            config.push_str(&format!(
+                "retry = {}
+retry-connrefused
+fail
+",
+                retries
+            ));
+            config
+        })
+        .collect();
+    log::info!("POST {} ({} requests)", url, requests.len());
+    let child = spawn(&transfers.join("next\n"), Stdio::null())?;
+    thread::spawn(move || match child.wait_with_output() {
+        Ok(output) if output.status.success() => {}
+        // curl reports every failed try, which are mostly the same.
+        Ok(output) => {
+            let mut errors: Vec<&str> = Vec::new();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            for line in stderr.lines().filter(|l| !l.is_empty()) {
+                if !errors.contains(&line) {
+                    errors.push(line);
+                }
+            }
+            failed(errors.join("; "))
+        }
+        Err(e) => failed(e.to_string()),
+    });
+    Ok(())
+}
+
+// The curl config of a request.
+fn config(method: &str, url: &str, headers: &[(&str, &str)], body: Option<&str>) -> String {
+    let mut config = String::new();
+    config.push_str(&format!("url = {}\n", quote(url)));
+    config.push_str(&format!("request = {}\n", quote(method)));
+    config.push_str(&format!("connect-timeout = {}\n", CONNECT_TIMEOUT));
+    config.push_str(&format!("max-time = {}\n", MAX_TIME));
+    // Only HTTP, not the other protocols curl speaks, such as `file://`.
+    config.push_str("proto = \"=http,https\"\n");
+    for (name, value) in headers {
+        config.push_str(&format!(
+            "header = {}\n",
+            quote(&format!("{}: {}", name, value))
+        ));
+    }
+    if let Some(body) = body {
+        config.push_str(&format!("data-binary = {}\n", quote(body)));
+    }
+    config
+}
+
+// Starts curl on `config`, with its response written to `stdout`.
+fn spawn(config: &str, stdout: Stdio) -> Result<Child, String> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--include", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| tr!("Failed to run curl: {}", e))?;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(config.as_bytes())
+        .map_err(|e| tr!("Failed to run curl: {}", e))?;
+    Ok(child)
+}
+
+fn parse_response(mut raw: &str) -> Option<Response> {
+    loop {
+        let (head, body) = raw.split_once("\r\n\r\n")?;
+        let mut lines = head.lines();
+        let status: u16 = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+        // Skip interim responses such as `100 Continue`.
+        if (100..200).contains(&status) {
+            raw = body;
+            continue;
+        }
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        return Some(Response {
+            status,
+            headers,
+            body: body.to_string(),
+        });
+    }
+}
+
+/// Builds the value of an `Authorization` header for HTTP basic authentication.
+pub fn basic_auth(user: &str, password: &str) -> String {
+    format!("Basic {}", base64::encode(format!("{}:{}", user, password)))
+}
+
+// Quotes a value for a curl config file.
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
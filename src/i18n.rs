@@ -0,0 +1,776 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use crate::config::Config;
+
+/// Translates a message and fills in its arguments, e.g. `tr!("No task with id {}", id)`.
+/// Translations can refer to arguments by position (`{0}`) to change their order.
+macro_rules! tr {
+    ($message:literal $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::format($crate::i18n::translate($message), &[$(&$arg),*])
+    };
+}
+
+// Translations from English, by language code.
+const CATALOGS: &[(&str, &[(&str, &str)])] = &[("nl", NL)];
+
+static CATALOG: OnceLock<&'static [(&'static str, &'static str)]> = OnceLock::new();
+
+/// Selects the language from `language` in the config file, or else from the environment
+/// (`LC_ALL`, `LC_MESSAGES` or `LANG`, such as `nl_NL.UTF-8`). English is used for languages
+/// without translations.
+pub fn init(config: &Config) {
+    let language = config.get("language").map(|l| l.to_string()).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+    });
+    let code = language
+        .as_deref()
+        .and_then(|l| l.split(['_', '.', '-']).next())
+        .unwrap_or("en");
+    let catalog = CATALOGS
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or(&[][..], |(_, catalog)| *catalog);
+    let _ = CATALOG.set(catalog);
+}
+
+pub fn translate(message: &'static str) -> &'static str {
+    CATALOG
+        .get()
+        .and_then(|catalog| catalog.iter().find(|(english, _)| *english == message))
+        .map_or(message, |(_, translated)| translated)
+}
+
+/// Replaces `{}` in `template` by the next argument and `{N}` by argument N.
+pub fn format(template: &str, args: &[&dyn Display]) -> String {
+    let mut result = String::new();
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let index = match &rest[1..end] {
+            "" => {
+                next += 1;
+                next - 1
+            }
+            n => match n.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => {
+                    result.push_str(&rest[..=end]);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            },
+        };
+        if let Some(arg) = args.get(index) {
+            result.push_str(&arg.to_string());
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+const NL: &[(&str, &str)] = &[
+    (
+        "'--dry-run' is not supported by this command",
+        "'--dry-run' wordt niet ondersteund door deze opdracht",
+    ),
+    (
+        "'--stale' needs display.stale_after in the config file, which is set to never",
+        "'--stale' heeft display.stale_after in het configuratiebestand nodig, dat op never staat",
+    ),
+    ("'init' can't be used with '--dry-run'", "'init' kan niet met '--dry-run' gebruikt worden"),
+    ("'review' needs a terminal", "'review' heeft een terminal nodig"),
+    ("'triage' needs a terminal", "'triage' heeft een terminal nodig"),
+    ("'{}' failed to open {} ({})", "'{}' kon {} niet openen ({})"),
+    ("'{}' matches several tasks:", "'{}' past bij meerdere taken:"),
+    ("(deleted)", "(verwijderd)"),
+    ("(no project)", "(geen project)"),
+    ("(no {})", "(geen {})"),
+    ("1 problem found", "1 probleem gevonden"),
+    ("Aborted", "Afgebroken"),
+    ("Active context cleared", "Actieve context gewist"),
+    ("Active context: @{}", "Actieve context: @{}"),
+    ("Add an example task? [y/N]", "Een voorbeeldtaak toevoegen? [j/N]"),
+    ("Added an example task", "Voorbeeldtaak toegevoegd"),
+    ("All indexes are present", "Alle indexen zijn aanwezig"),
+    (
+        "All set, run 'todo add <name>' to add a task",
+        "Klaar, voer 'todo add <naam>' uit om een taak toe te voegen",
+    ),
+    ("Annotations:", "Aantekeningen:"),
+    (
+        "Another instance holds the lock for {}, try again when it has finished",
+        "Een andere instantie heeft de vergrendeling voor {}, probeer het opnieuw als die klaar is",
+    ),
+    ("Applied {}: {}", "Toegepast {}: {}"),
+    ("Archived {} tasks, purged {} tasks", "{} taken gearchiveerd, {} taken verwijderd"),
+    ("Archived: {}", "Gearchiveerd: {}"),
+    ("Assigned to: {}", "Toegewezen aan: {}"),
+    ("Attached {} to task {} as {}", "{} aan taak {} toegevoegd als {}"),
+    ("Attachment {} is empty", "Bijlage {} is leeg"),
+    (
+        "Attachment {} is encrypted, set the encryption key to get it",
+        "Bijlage {} is versleuteld, stel de sleutel in om hem op te halen",
+    ),
+    ("Average time to completion: {}", "Gemiddelde tijd tot afronding: {}"),
+    ("Can't move task {} before itself", "Kan taak {} niet voor zichzelf plaatsen"),
+    ("Cancel these tasks?", "Deze taken annuleren?"),
+    ("Cancelled: {}", "Geannuleerd: {}"),
+    (
+        "Cannot find the config directory for the encryption salt",
+        "Kan de configuratiemap voor het versleutelingszout niet vinden",
+    ),
+    ("Captured task {} ({}) in the inbox", "Taak {} ({}) in de inbox gezet"),
+    (
+        "Change notifications are not available, refreshing every {}s",
+        "Wijzigingsmeldingen zijn niet beschikbaar, elke {}s verversen",
+    ),
+    ("Cleaning up failed: {}", "Opruimen mislukt: {}"),
+    ("Committed local changes", "Lokale wijzigingen vastgelegd"),
+    ("Complete these tasks?", "Deze taken afronden?"),
+    (
+        "Complete this task with 'todo complete', or remove it with 'todo remove'",
+        "Voltooi deze taak met 'todo complete', of verwijder hem met 'todo remove'",
+    ),
+    ("Completed per day:", "Afgerond per dag:"),
+    ("Completed per week:", "Afgerond per week:"),
+    ("Completed: {}", "Afgerond: {}"),
+    ("Completing {} doesn't unblock any tasks", "Het voltooien van {} deblokkeert geen taken"),
+    ("Connected to '{}'", "Verbonden met '{}'"),
+    ("Connected to the database", "Verbonden met de database"),
+    ("Context: @{}", "Context: @{}"),
+    ("Continue? [y/N] ", "Doorgaan? [j/N] "),
+    (
+        "Could not connect to '{}': {}\n          Check that the server is running, or set 'database.connection' in the config file",
+        "Kan geen verbinding maken met '{}': {}\n          Controleer of de server draait, of stel 'database.connection' in het configuratiebestand in",
+    ),
+    (
+        "Could not connect to the database, trying again in {}: {}",
+        "Kon geen verbinding maken met de database, opnieuw proberen over {}: {}",
+    ),
+    ("Could not connect to the database: {}", "Kan geen verbinding maken met de database: {}"),
+    ("Could not determine the GitHub user", "Kan de GitHub-gebruiker niet bepalen"),
+    (
+        "Could not determine the user, use '--user' or set 'user' in the config file",
+        "Kan de gebruiker niet bepalen, gebruik '--user' of stel 'user' in het configuratiebestand in",
+    ),
+    (
+        "Could not determine where to write the config file, set TODO_CONFIG",
+        "Kan niet bepalen waar het configuratiebestand geschreven moet worden, stel TODO_CONFIG in",
+    ),
+    (
+        "Could not determine where to keep the SQLite database, set database.path in the config file",
+        "Kan niet bepalen waar de SQLite-database moet staan, stel database.path in het configuratiebestand in",
+    ),
+    ("Could not open the database {}: {}", "Kan de database {} niet openen: {}"),
+    ("Created queued task {} as {} ({})", "Taak {} uit de wachtrij aangemaakt als {} ({})"),
+    ("Created task {} ({})", "Taak {} ({}) aangemaakt"),
+    ("Created the database {}", "Database {} aangemaakt"),
+    ("Created: {}", "Aangemaakt: {}"),
+    ("Database connection", "Databaseverbinding"),
+    ("Database error: {}", "Databasefout: {}"),
+    ("Database is up to date", "De database is bijgewerkt"),
+    ("Defined context @{}", "Context @{} gedefinieerd"),
+    ("Delete {} tasks in the trash for good?", "{} taken in de prullenbak definitief verwijderen?"),
+    ("Deleted context @{}", "Context @{} verwijderd"),
+    ("Deleted {} tasks", "{} taken verwijderd"),
+    ("Depends on:", "Hangt af van:"),
+    ("Did you mean:", "Bedoelde je:"),
+    ("Due in {}", "Verloopt over {}"),
+    ("Due now", "Verloopt nu"),
+    ("Due time: ", "Vervaltijd: "),
+    ("Due: {}", "Deadline: {}"),
+    ("Duplicated task {} as {}", "Taak {} gedupliceerd als {}"),
+    (
+        "Edit task {} and save to apply the changes. The note goes below the attributes,\nafter an empty line. Leave a value empty to remove it. Lines starting with '#'\nare ignored.",
+        "Bewerk taak {} en sla op om de wijzigingen toe te passen. De notitie staat onder de\neigenschappen, na een lege regel. Laat een waarde leeg om die te verwijderen.\nRegels die met '#' beginnen worden genegeerd.",
+    ),
+    (
+        "Editor '{}' failed ({}), the task is unchanged",
+        "Editor '{}' is mislukt ({}), de taak is niet gewijzigd",
+    ),
+    ("Encrypted {} values", "{} waarden versleuteld"),
+    (
+        "Encryption is not enabled, set encryption.key or encryption.keyring in the config file",
+        "Versleuteling staat niet aan, zet encryption.key of encryption.keyring in het configuratiebestand",
+    ),
+    ("Estimate: {}", "Schatting: {}"),
+    ("Estimated open work per project:", "Geschat open werk per project:"),
+    (
+        "Expected 'attach <id> <path>' or 'attach get <id>'",
+        "Verwacht 'attach <id> <pad>' of 'attach get <id>'",
+    ),
+    ("Expected 'auth [set|unset] <name>'", "Verwacht 'auth [set|unset] <naam>'"),
+    ("Expected 'context [show|set <name>|clear]'", "Verwacht 'context [show|set <naam>|clear]'"),
+    (
+        "Expected 'contexts [list|add <name> [--filter <terms>]|delete <name>]'",
+        "Verwachtte 'contexts [list|add <naam> [--filter <termen>]|delete <naam>]'",
+    ),
+    ("Expected 'key: value', got: {}", "Verwacht 'sleutel: waarde', kreeg: {}"),
+    (
+        "Expected 'key=value' for '--set', got: {}",
+        "Verwacht 'sleutel=waarde' voor '--set', maar kreeg: {}",
+    ),
+    ("Expected 'migrate [up|down|status]'", "Verwacht 'migrate [up|down|status]'"),
+    (
+        "Expected 'name=value' for a field, got: {}",
+        "Verwacht 'naam=waarde' voor een veld, kreeg: {}",
+    ),
+    (
+        "Expected 'sync caldav <url>', 'sync git <path>' or 'sync status'",
+        "Verwacht 'sync caldav <url>', 'sync git <pad>' of 'sync status'",
+    ),
+    ("Expected 'template [add|list|remove]'", "Verwacht 'template [add|list|remove]'"),
+    ("Expected 'trash [list|empty]'", "Verwachtte 'trash [list|empty]'"),
+    (
+        "Expected a filter to 'modify' command, such as ids or project:name",
+        "De opdracht 'modify' verwacht een filter, zoals ids of project:naam",
+    ),
+    (
+        "Expected an id and optional duration to 'snooze' command",
+        "De opdracht 'snooze' verwacht een id en optioneel een duur",
+    ),
+    (
+        "Expected an id and text to 'annotate' command",
+        "De opdracht 'annotate' verwacht een id en tekst",
+    ),
+    ("Expected an id and text to 'note' command", "De opdracht 'note' verwacht een id en tekst"),
+    (
+        "Expected an id and user to 'assign' command",
+        "Verwacht een id en gebruiker bij het commando 'assign'",
+    ),
+    (
+        "Expected at least one '--set key=value' to 'modify' command",
+        "De opdracht 'modify' verwacht minstens één '--set sleutel=waarde'",
+    ),
+    (
+        "Expected one of '--before <id>', '--top' or '--bottom'",
+        "Verwacht een van '--before <id>', '--top' of '--bottom'",
+    ),
+    (
+        "Expected only key:value terms in a context filter, got: {}",
+        "Verwachtte alleen key:value-termen in een contextfilter, kreeg: {}",
+    ),
+    (
+        "Expected the id, alias or UUID of a task in the trash, got: {}",
+        "Verwachtte het id, de alias of de UUID van een taak in de prullenbak, kreeg: {}",
+    ),
+    ("Failed to create {}: {}", "Kan {} niet aanmaken: {}"),
+    ("Failed to listen on {}: {}", "Kan niet luisteren op {}: {}"),
+    ("Failed to read {}: {}", "Kan {} niet lezen: {}"),
+    (
+        "Failed to reload the config, keeping the settings: {}",
+        "Opnieuw laden van de config mislukt, de instellingen blijven: {}",
+    ),
+    ("Failed to remove {}: {}", "Kan {} niet verwijderen: {}"),
+    ("Failed to run curl: {}", "Kan curl niet uitvoeren: {}"),
+    ("Failed to run git: {}", "Kan git niet uitvoeren: {}"),
+    ("Failed to run {}: {}", "Uitvoeren van {} mislukt: {}"),
+    ("Failed to send webhook to {}: {}", "Kan webhook niet versturen naar {}: {}"),
+    ("Failed to start '{}': {}", "Kan '{}' niet starten: {}"),
+    ("Failed to start editor '{}': {}", "Kan editor '{}' niet starten: {}"),
+    ("Failed to write the cache {}: {}", "Kan de cache {} niet schrijven: {}"),
+    ("Failed to write the outbox {}: {}", "Kon de wachtrij {} niet schrijven: {}"),
+    ("Failed to write to the journal {}: {}", "Kan niet schrijven naar het logboek {}: {}"),
+    ("Failed to write {}: {}", "Kan {} niet schrijven: {}"),
+    ("Follow up", "Opvolgen"),
+    ("Follow up: {}", "Opvolgen: {}"),
+    ("GitHub request failed with status {}: {}", "GitHub-verzoek mislukt met status {}: {}"),
+    ("Hook {} failed: {}", "Hook {} mislukt: {}"),
+    ("Ignoring the invalid outbox: {}", "De ongeldige wachtrij wordt genegeerd: {}"),
+    (
+        "Ignoring {}, since others can read it; restrict it with 'chmod 0600'",
+        "{} wordt genegeerd, omdat anderen het kunnen lezen; beperk het met 'chmod 0600'",
+    ),
+    ("Imported {} changes from the repository", "{} wijzigingen uit de repository geïmporteerd"),
+    ("Imported {} tasks", "{} taken geïmporteerd"),
+    (
+        "Imported {} tasks, updated {} and left {} unchanged",
+        "{} taken geïmporteerd, {} bijgewerkt en {} ongewijzigd",
+    ),
+    ("Invalid archive: {}", "Ongeldig archief: {}"),
+    (
+        "Invalid confirm.threshold in the config file: {}",
+        "Ongeldige confirm.threshold in het configuratiebestand: {}",
+    ),
+    ("Invalid connection string: {}", "Ongeldige verbindingsreeks: {}"),
+    ("Invalid context: {}", "Ongeldige context: {}"),
+    ("Invalid cursor: {}", "Ongeldige cursor: {}"),
+    (
+        "Invalid database.connect_retries in the config file: {}",
+        "Ongeldige database.connect_retries in het configuratiebestand: {}",
+    ),
+    (
+        "Invalid display.stale_after in the config file: {}",
+        "Ongeldige display.stale_after in het configuratiebestand: {}",
+    ),
+    ("Invalid duration: {}", "Ongeldige duur: {}"),
+    ("Invalid estimate: {}", "Ongeldige schatting: {}"),
+    ("Invalid limit: {}, expected 1 to {}", "Ongeldige limiet: {}, verwacht 1 tot {}"),
+    (
+        "Invalid log.level in the config file: {}, expected error, warn, info, debug or trace",
+        "Ongeldig log.level in het configuratiebestand: {}, verwacht error, warn, info, debug of trace",
+    ),
+    ("Invalid number for field '{}': {}", "Ongeldig getal voor veld '{}': {}"),
+    (
+        "Invalid priority: {}, expected high, medium or low",
+        "Ongeldige prioriteit: {}, verwacht high, medium of low",
+    ),
+    ("Invalid query string: {}", "Ongeldige querystring: {}"),
+    ("Invalid range: {}", "Ongeldig bereik: {}"),
+    ("Range too large: {}, at most {} ids", "Bereik te groot: {}, hoogstens {} ids"),
+    (
+        "Invalid remind.interval in the config file: {}",
+        "Ongeldig remind.interval in het configuratiebestand: {}",
+    ),
+    ("Invalid report '{}': {}", "Ongeldig rapport '{}': {}"),
+    ("Invalid response from {}", "Ongeldig antwoord van {}"),
+    (
+        "Invalid sort key: {}, expected created, due, priority, urgency or manual",
+        "Ongeldige sorteersleutel: {}, verwacht created, due, priority, urgency of manual",
+    ),
+    ("Invalid status: {}", "Ongeldige status: {}"),
+    (
+        "Invalid status: {}, expected todo, in-progress, waiting, done, cancelled, open or all",
+        "Ongeldige status: {}, verwacht todo, in-progress, waiting, done, cancelled, open of all",
+    ),
+    ("Invalid task file {}: {}", "Ongeldig taakbestand {}: {}"),
+    ("Invalid task from the server: {}", "Ongeldige taak van de server: {}"),
+    ("Invalid task id: {}", "Ongeldig taak-id: {}"),
+    ("Invalid taskwarrior export: {}", "Ongeldige taskwarrior-export: {}"),
+    ("Invalid time: {}", "Ongeldige tijd: {}"),
+    (
+        "Invalid type for field '{}': {}, expected string, number or bool",
+        "Ongeldig type voor veld '{}': {}, verwacht string, number of bool",
+    ),
+    (
+        "Invalid urgency.{} in the config file: {}",
+        "Ongeldige urgency.{} in het configuratiebestand: {}",
+    ),
+    ("Invalid value for '--weeks': {}", "Ongeldige waarde voor '--weeks': {}"),
+    ("Invalid value for '{}': {}, {}", "Ongeldige waarde voor '{}': {}, {}"),
+    (
+        "Invalid value for field '{}': {}, expected true or false",
+        "Ongeldige waarde voor veld '{}': {}, verwacht true of false",
+    ),
+    ("Invalid {} in the config file: {}", "Ongeldige {} in het configuratiebestand: {}"),
+    (
+        "Invalid {} in the config file: {}, expected prefer-local, prefer-remote, newest-wins or interactive",
+        "Ongeldige {} in het configuratiebestand: {}, verwacht prefer-local, prefer-remote, newest-wins of interactive",
+    ),
+    ("Item without a UID: {}", "Item zonder UID: {}"),
+    ("Keep (l)ocal, (r)emote or (s)kip? ", "Lokaal (l) of extern (r) behouden, of overslaan (s)? "),
+    ("Later", "Later"),
+    ("Listening on http://{}", "Luistert op http://{}"),
+    ("Listening for gRPC on {}", "Luistert naar gRPC op {}"),
+    ("Logged task {} ({})", "Taak {} ({}) vastgelegd"),
+    (
+        "Lost the connection to the database, reconnecting: {}",
+        "Verbinding met de database verloren, opnieuw verbinden: {}",
+    ),
+    ("Lost the connection to the database: {}", "Verbinding met de database verbroken: {}"),
+    (
+        "Merging failed: {}\nResolve any conflicts in {}, commit them and sync again.",
+        "Samenvoegen mislukt: {}\nLos eventuele conflicten op in {}, leg ze vast en synchroniseer opnieuw.",
+    ),
+    ("Missing '--repo' option to 'import github'", "Optie '--repo' ontbreekt bij 'import github'"),
+    ("Missing '--until' option to 'wait'", "De optie '--until' ontbreekt bij 'wait'"),
+    ("Missing argument to '{}' command", "Argument ontbreekt bij opdracht '{}'"),
+    (
+        "Missing indexes: {}\n          Run 'todo migrate' to create them",
+        "Ontbrekende indexen: {}\n          Voer 'todo migrate' uit om ze aan te maken",
+    ),
+    ("Missing task name", "Taaknaam ontbreekt"),
+    ("Missing value for '--backend'", "Waarde ontbreekt voor '--backend'"),
+    ("Missing value for '--profile'", "Ontbrekende waarde voor '--profile'"),
+    ("Missing value for '--user'", "Waarde ontbreekt voor '--user'"),
+    ("Missing value for '{}'", "Waarde ontbreekt voor '{}'"),
+    ("Missing {} argument to '{}' command", "Argument {} ontbreekt bij opdracht '{}'"),
+    ("Modified {} tasks", "{} taken gewijzigd"),
+    ("Modify these tasks?", "Deze taken wijzigen?"),
+    ("Multiple open tasks match '{}':\n", "Meerdere open taken komen overeen met '{}':\n"),
+    ("New due time: ", "Nieuwe deadline: "),
+    ("Next {} days", "Komende {} dagen"),
+    ("No active context", "Geen actieve context"),
+    ("No attachment with id {}", "Geen bijlage met id {}"),
+    ("No attachments", "Geen bijlagen"),
+    ("No blocked tasks", "Geen geblokkeerde taken"),
+    ("No changes", "Geen wijzigingen"),
+    (
+        "No cleanup rules, set archive_after or purge_after in the [cleanup] section of the config",
+        "Geen opruimregels, stel archive_after of purge_after in in de sectie [cleanup] van de configuratie",
+    ),
+    ("No command found", "Geen opdracht gegeven"),
+    ("No context named @{}", "Geen context met de naam @{}"),
+    (
+        "No contexts, add one with 'contexts add <name>'",
+        "Geen contexten, voeg er een toe met 'contexts add <naam>'",
+    ),
+    ("No data directory for the outbox", "Geen datamap voor de wachtrij"),
+    (
+        "No database password found in the keyring for todo-rust, store it with 'todo auth set database'",
+        "Geen databasewachtwoord gevonden in de sleutelbos voor todo-rust, sla het op met 'todo auth set database'",
+    ),
+    ("No due date", "Geen deadline"),
+    (
+        "No encryption key found in the keyring for todo-rust",
+        "Geen versleutelingssleutel voor todo-rust gevonden in de sleutelhanger",
+    ),
+    ("No history for task {}", "Geen geschiedenis voor taak {}"),
+    ("No open task matches '{}'", "Geen open taak komt overeen met '{}'"),
+    ("No open task with id {}", "Geen open taak met id {}"),
+    ("No open tasks with ids {}", "Geen open taken met ids {}"),
+    ("No problems found", "Geen problemen gevonden"),
+    ("No projects", "Geen projecten"),
+    ("No reminders due", "Geen herinneringen"),
+    (
+        "No reports, define them in the [reports] section of the config",
+        "Geen rapporten, definieer ze in de sectie [reports] van de configuratie",
+    ),
+    ("No task in the trash with UUID {}", "Geen taak in de prullenbak met UUID {}"),
+    ("No task is being tracked", "Er wordt geen tijd bijgehouden"),
+    ("No task with id {}", "Geen taak met id {}"),
+    ("No tasks in the trash with ids {}", "Geen taken in de prullenbak met ids {}"),
+    ("No tasks match the filter", "Geen taken voldoen aan het filter"),
+    ("No tasks match the report", "Geen taken passen bij het rapport"),
+    ("No tasks with ids {}", "Geen taken met ids {}"),
+    ("No template named '{}'", "Geen sjabloon met de naam '{}'"),
+    ("No templates", "Geen sjablonen"),
+    ("No time tracked this {}", "Geen tijd bijgehouden in deze periode ({})"),
+    ("No unresolved conflicts", "Geen onopgeloste conflicten"),
+    (
+        "No {} tasks were listed before, so they are missing",
+        "Er zijn eerder geen {} taken getoond, dus die ontbreken",
+    ),
+    ("Not a file: {}", "Geen bestand: {}"),
+    ("Not a task id, alias or UUID: {}", "Geen taak-id, alias of UUID: {}"),
+    (
+        "Not an archive written by 'todo export --format archive': {}",
+        "Geen archief geschreven door 'todo export --format archive': {}",
+    ),
+    ("Not an attachment id", "Geen bijlage-id"),
+    ("Nothing to do", "Niets te doen"),
+    ("Nothing to revert", "Niets terug te draaien"),
+    ("Nothing to review", "Niets te bekijken"),
+    ("Oldest open tasks:", "Oudste open taken:"),
+    (
+        "Only --note, --project, --priority and --context can be given while the database can't be reached",
+        "Alleen --note, --project, --priority en --context kunnen worden opgegeven zolang de database niet bereikbaar is",
+    ),
+    ("Overdue by {}", "{} over de deadline"),
+    ("Permissions are sufficient", "Rechten zijn voldoende"),
+    (
+        "PostgreSQL {} is older than 13, CalDAV sync won't work",
+        "PostgreSQL {} is ouder dan 13, CalDAV-synchronisatie werkt niet",
+    ),
+    ("Priority (high, medium or low): ", "Prioriteit (high, medium of low): "),
+    ("Priority: {}", "Prioriteit: {}"),
+    ("Project: ", "Project: "),
+    ("Pushed to {}", "Naar {} gepusht"),
+    ("Query failed: {}", "Query mislukt: {}"),
+    (
+        "Queued task {}, to be created once the database can be reached",
+        "Taak {} in de wachtrij gezet, om aan te maken zodra de database bereikbaar is",
+    ),
+    ("Reloaded the config: {}", "Config opnieuw geladen: {}"),
+    ("Remove these tasks?", "Deze taken verwijderen?"),
+    ("Removed template '{}'", "Sjabloon '{}' verwijderd"),
+    (
+        "Removed the {} credential from the keyring",
+        "De inloggegevens {} zijn verwijderd uit de sleutelbos",
+    ),
+    ("Reopen these tasks?", "Deze taken heropenen?"),
+    ("Request to {} failed: {}", "Verzoek aan {} mislukt: {}"),
+    ("Resolved {} conflicts", "{} conflicten opgelost"),
+    ("Reverted {}: {}", "Teruggedraaid {}: {}"),
+    ("Saved template '{}'", "Sjabloon '{}' opgeslagen"),
+    ("Saved {} to {}", "{} opgeslagen in {}"),
+    (
+        "Schema is at version {}, expected {}\n          Run 'todo migrate', or set 'database.auto_migrate = true' in the config file",
+        "Schema heeft versie {}, verwacht {}\n          Voer 'todo migrate' uit, of stel 'database.auto_migrate = true' in het configuratiebestand in",
+    ),
+    (
+        "Schema is at version {}, newer than this version of todo ({})\n          Upgrade todo, or run 'todo migrate down --to {}'",
+        "Schema heeft versie {}, nieuwer dan deze versie van todo ({})\n          Werk todo bij, of voer 'todo migrate down --to {}' uit",
+    ),
+    ("Schema is at version {}", "Schema heeft versie {}"),
+    (
+        "Set GITHUB_TOKEN to a GitHub access token, or store one with 'todo auth set github'",
+        "Stel GITHUB_TOKEN in op een GitHub-toegangstoken, of sla er een op met 'todo auth set github'",
+    ),
+    ("Started {}: {}", "Gestart {}: {}"),
+    ("Stopped {}: {} ({})", "Gestopt {}: {} ({})"),
+    (
+        "Stored the {} credential in the keyring",
+        "De inloggegevens {} zijn opgeslagen in de sleutelbos",
+    ),
+    (
+        "Table '{}' is missing or not writable\n          Run 'todo migrate', or grant select, insert, update and delete on it to the database user",
+        "Tabel '{}' ontbreekt of is niet schrijfbaar\n          Voer 'todo migrate' uit, of geef de databasegebruiker select, insert, update en delete rechten erop",
+    ),
+    (
+        "Tags were not imported, define a '{}' string field in the config to keep them",
+        "Tags zijn niet geïmporteerd, definieer een tekstveld '{}' in de configuratie om ze te bewaren",
+    ),
+    ("Task without a UUID", "Taak zonder UUID"),
+    ("Task {} ({}) already exists", "Taak {} ({}) bestaat al"),
+    ("Task {} already depends on {}", "Taak {} hangt al af van {}"),
+    ("Task {} belongs to another user", "Taak {} is van een andere gebruiker"),
+    ("Task {} can't depend on itself", "Taak {} kan niet van zichzelf afhangen"),
+    ("Task {} doesn't depend on {}", "Taak {} hangt niet af van {}"),
+    ("Task {} has no web addresses", "Taak {} heeft geen webadressen"),
+    ("Task {} is already finished", "Taak {} is al afgerond"),
+    ("Task {} of {}:", "Taak {} van {}:"),
+    ("The UUID is in use by another task", "De UUID is in gebruik door een andere taak"),
+    ("The annotation can't be empty", "De aantekening mag niet leeg zijn"),
+    (
+        "The archive has {} rows of task {}, which isn't in it",
+        "Het archief bevat rijen van {} voor taak {}, die er niet in staat",
+    ),
+    (
+        "The archive is of schema version {}, the database of version {}, migrate it to the same version first",
+        "Het archief heeft schemaversie {}, de database versie {}, migreer die eerst naar dezelfde versie",
+    ),
+    (
+        "The archive is of version {}, which is newer than this version of todo can read",
+        "Het archief heeft versie {}, die nieuwer is dan deze versie van todo kan lezen",
+    ),
+    (
+        "The completion time can't be in the future",
+        "Het voltooiingstijdstip kan niet in de toekomst liggen",
+    ),
+    (
+        "The config file {} exists already, use '--force' to overwrite it",
+        "Het configuratiebestand {} bestaat al, gebruik '--force' om het te overschrijven",
+    ),
+    (
+        "The database can't be reached, showing the tasks as listed {}",
+        "De database is niet bereikbaar, de taken worden getoond zoals ze {} werden getoond",
+    ),
+    (
+        "The database is at schema version {}, newer than this version of todo supports ({}); upgrade todo, or run 'todo migrate down --to {}'",
+        "De database heeft schemaversie {}, nieuwer dan deze versie van todo ondersteunt ({}); werk todo bij, of voer 'todo migrate down --to {}' uit",
+    ),
+    (
+        "The database is at schema version {}, this version of todo needs {}; run 'todo migrate' first",
+        "De database heeft schemaversie {}, deze versie van todo heeft {} nodig; voer eerst 'todo migrate' uit",
+    ),
+    ("The database is up to date", "De database is bijgewerkt"),
+    (
+        "The database user can't create tables, so migrations must be applied by another user",
+        "De databasegebruiker kan geen tabellen aanmaken, dus migraties moeten door een andere gebruiker worden toegepast",
+    ),
+    (
+        "The ids in the archive are already in use, import it into an empty database",
+        "De ids in het archief zijn al in gebruik, importeer het in een lege database",
+    ),
+    ("The inbox is empty", "De inbox is leeg"),
+    ("The task name can't be empty", "De taaknaam mag niet leeg zijn"),
+    ("The task was not created", "De taak is niet aangemaakt"),
+    ("The trash is empty", "De prullenbak is leeg"),
+    ("The {} credential is empty", "De inloggegevens {} zijn leeg"),
+    ("The {} credential: ", "De inloggegevens {}: "),
+    ("There is no {} credential in the keyring", "Er zijn geen inloggegevens {} in de sleutelbos"),
+    (
+        "This command is not available with the SQLite backend, use '--backend postgres'",
+        "Deze opdracht is niet beschikbaar met de SQLite-backend, gebruik '--backend postgres'",
+    ),
+    (
+        "This command is not available in remote mode, remove 'remote_url' from the config to use it",
+        "Deze opdracht is niet beschikbaar op afstand, verwijder 'remote_url' uit de configuratie om hem te gebruiken",
+    ),
+    ("Today", "Vandaag"),
+    ("Tomorrow", "Morgen"),
+    ("Too many arguments to '{}' command", "Te veel argumenten voor opdracht '{}'"),
+    ("Total: {}", "Totaal: {}"),
+    ("Tracked since {}", "Bijgehouden sinds {}"),
+    ("Try todo", "Probeer todo"),
+    ("UUID: {}", "UUID: {}"),
+    ("Unclosed placeholder in format: {}", "Niet afgesloten plaatshouder in formaat: {}"),
+    ("Unexpected issue in response from GitHub", "Onverwacht issue in antwoord van GitHub"),
+    ("Unexpected response from GitHub", "Onverwacht antwoord van GitHub"),
+    ("Unexpected status {} listing {}", "Onverwachte status {} bij het ophalen van {}"),
+    ("Unexpected status {} uploading {}", "Onverwachte status {} bij het versturen van {}"),
+    ("Unknown attribute: {}", "Onbekende eigenschap: {}"),
+    (
+        "Unknown backend: {}, expected 'postgres' or 'sqlite'",
+        "Onbekende backend: {}, verwacht 'postgres' of 'sqlite'",
+    ),
+    ("Unknown command: {}", "Onbekende opdracht: {}"),
+    (
+        "Unknown credential: {}, expected database, encryption, github, caldav or remote",
+        "Onbekende inloggegevens: {}, verwacht database, encryption, github, caldav of remote",
+    ),
+    ("Unknown database service: {}", "Onbekende databaseservice: {}"),
+    ("Unknown export format: {} (expected sql or archive)", "Onbekend exportformaat: {} (verwacht sql of archive)"),
+    ("Unknown field in urgency.{}: {}", "Onbekend veld in urgency.{}: {}"),
+    (
+        "Unknown field: {}, define it in the [fields] section of the config",
+        "Onbekend veld: {}, definieer het in de sectie [fields] van de configuratie",
+    ),
+    ("Unknown import format: {}", "Onbekend importformaat: {}"),
+    ("Unknown import source: {}", "Onbekende importbron: {}"),
+    ("Unknown option to '{}' command: {}", "Onbekende optie voor opdracht '{}': {}"),
+    ("Unknown option: {}", "Onbekende optie: {}"),
+    (
+        "Unknown output format: {}, expected text, tsv, null or jsonl",
+        "Onbekend uitvoerformaat: {}, verwacht text, tsv, null of jsonl",
+    ),
+    (
+        "Unknown placeholder in format: {}, expected one of {}",
+        "Onbekende plaatshouder in formaat: {}, verwacht een van {}",
+    ),
+    (
+        "Unknown profile: {}, define it in the config file",
+        "Onbekend profiel: {}, definieer het in het configuratiebestand",
+    ),
+    (
+        "Unknown report: {}, define it in the [reports] section of the config",
+        "Onbekend rapport: {}, definieer het in de sectie [reports] van de configuratie",
+    ),
+    (
+        "Unknown setting in the config file: urgency.{}",
+        "Onbekende instelling in het configuratiebestand: urgency.{}",
+    ),
+    ("Unknown sync target: {}", "Onbekend synchronisatiedoel: {}"),
+    ("Unknown time zone: {}", "Onbekende tijdzone: {}"),
+    ("Unmatched closing brace in format: {}", "Losse sluitaccolade in formaat: {}"),
+    (
+        "Unterminated quote in connection string",
+        "Niet afgesloten aanhalingsteken in verbindingsreeks",
+    ),
+    ("Use 'complete <id>' to pick one.", "Gebruik 'complete <id>' om er een te kiezen."),
+    ("Using the config file {}", "Configuratiebestand {} wordt gebruikt"),
+    ("Waiting for: {}", "Wachten op: {}"),
+    ("Would attach {} to task {}", "Zou {} aan taak {} toevoegen"),
+    ("Would modify {} tasks", "Zou {} taken wijzigen"),
+    ("Wrote the config file {}", "Configuratiebestand {} geschreven"),
+    (
+        "[k]eep, [r]eschedule, [c]omplete, [d]elete, [s]kip or [q]uit? ",
+        "Houden [k], verzetten [r], afronden [c], verwijderen [d], overslaan [s] of stoppen [q]? ",
+    ),
+    (
+        "[t]riage, [d]elete, [s]kip or [q]uit? ",
+        "Triëren [t], verwijderen [d], overslaan [s] of stoppen [q]? ",
+    ),
+    ("active", "actief"),
+    ("annotated", "aantekening toegevoegd"),
+    ("assigned to {}", "toegewezen aan {}"),
+    ("blocked by {} ({}): {}", "geblokkeerd door {} ({}): {}"),
+    ("cancelled", "geannuleerd"),
+    ("cleaning up", "opruimen"),
+    ("completed", "afgerond"),
+    ("created", "aangemaakt"),
+    ("deleted", "verwijderd"),
+    ("depends on {}", "hangt af van {}"),
+    ("done", "klaar"),
+    ("due {}", "deadline {}"),
+    ("error", "fout"),
+    ("error: {}", "fout: {}"),
+    ("estimate {}", "schatting {}"),
+    ("expected an array of tasks", "verwachtte een lijst van taken"),
+    ("filter: {}", "filter: {}"),
+    ("found {}", "gevonden {}"),
+    ("git {} failed", "git {} is mislukt"),
+    ("high", "hoog"),
+    ("in progress", "bezig"),
+    ("in {}", "over {}"),
+    ("inbox", "inbox"),
+    ("just now", "zojuist"),
+    ("kept", "gehouden"),
+    ("linked to {}", "gekoppeld aan {}"),
+    ("local:  {}", "lokaal: {}"),
+    ("low", "laag"),
+    ("medium", "gemiddeld"),
+    ("migrating the database", "de database migreren"),
+    ("moved", "verplaatst"),
+    ("moved to the trash", "naar de prullenbak verplaatst"),
+    ("next due {}", "eerstvolgende deadline {}"),
+    ("no longer depends on {}", "hangt niet meer af van {}"),
+    ("note added", "notitie toegevoegd"),
+    ("overdue, due {}", "te laat, deadline {}"),
+    ("pinned", "vastgezet"),
+    ("project {}", "project {}"),
+    ("queued", "in de wachtrij"),
+    ("reminder set", "herinnering ingesteld"),
+    ("remote: {}", "extern: {}"),
+    ("removed", "verwijderd"),
+    ("removed {}", "{} verwijderd"),
+    ("reopened", "heropend"),
+    ("rescheduled", "verzet"),
+    ("restored", "teruggezet"),
+    ("sending reminders", "herinneringen versturen"),
+    ("snoozed", "gesnoozed"),
+    ("stale", "verouderd"),
+    ("syncing", "synchroniseren"),
+    ("to do", "te doen"),
+    ("tracking, {}", "loopt, {}"),
+    ("triaged", "getrieerd"),
+    ("unassigned", "niet toegewezen"),
+    ("unpinned", "losgemaakt"),
+    ("updated", "bijgewerkt"),
+    ("urgency {}", "urgentie {}"),
+    ("waiting", "wachtend"),
+    ("waiting for {}", "wacht op {}"),
+    ("warning", "let op"),
+    ("warning: {}", "waarschuwing: {}"),
+    ("y", "j"),
+    ("{} ({} of {} tasks estimated)", "{} ({} van {} taken geschat)"),
+    ("{} ago", "{} geleden"),
+    (
+        "{} already exists, choose another file with '--to'",
+        "{} bestaat al, kies een ander bestand met '--to'",
+    ),
+    ("{} bytes", "{} bytes"),
+    ("{} changed since it was attached", "{} is gewijzigd sinds het toevoegen"),
+    (
+        "{} conflicts are unresolved, see 'todo sync status'\nResolve them in {}, commit them and sync again.",
+        "{} conflicten zijn niet opgelost, zie 'todo sync status'\nLos ze op in {}, leg ze vast en synchroniseer opnieuw.",
+    ),
+    ("{} due today", "{} voor vandaag"),
+    ("{} estimated", "{} geschat"),
+    ("{} of {} open", "{} van {} open"),
+    ("{} open", "{} open"),
+    ("{} open  {} completed", "{} open  {} afgerond"),
+    ("{} open, {} completed", "{} open, {} afgerond"),
+    ("{} overdue", "{} te laat"),
+    ("{} priority", "prioriteit {}"),
+    ("{} problem(s) found", "{} problemen gevonden"),
+    (
+        "{} statements, {} rows, {} ms in the database, {} ms in total",
+        "{} statements, {} rijen, {} ms in de database, {} ms in totaal",
+    ),
+    ("{}: already imported {}#{}", "{}: {}#{} is al geïmporteerd"),
+    ("{}: changed on both sides", "{}: aan beide kanten gewijzigd"),
+    (
+        "{}: changed on both sides, keeping the local version",
+        "{}: aan beide kanten gewijzigd, de lokale versie blijft behouden",
+    ),
+    (
+        "{}: changed on both sides, keeping the server's version",
+        "{}: aan beide kanten gewijzigd, de versie van de server wordt behouden",
+    ),
+    ("{}: changed on both sides, left unresolved", "{}: aan beide kanten gewijzigd, niet opgelost"),
+    (
+        "{}: changed on the server during the sync, try again",
+        "{}: gewijzigd op de server tijdens het synchroniseren, probeer het opnieuw",
+    ),
+    ("{}: closed {}#{}", "{}: {}#{} gesloten"),
+    ("{}: failed to close {}#{}: {}", "{}: kan {}#{} niet sluiten: {}"),
+    ("{}: imported {}#{} {}", "{}: {}#{} {} geïmporteerd"),
+    ("{}: pulled {}", "{}: {} opgehaald"),
+    ("{}: pushed {}", "{}: {} verstuurd"),
+    ("{}: updated from server", "{}: bijgewerkt vanaf de server"),
+    ("{}: updated {}#{} {}", "{}: {}#{} {} bijgewerkt"),
+];
@@ -0,0 +1,215 @@
+use std::time::SystemTime;
+
+use crate::status::Status;
+use crate::time::{from_utc, to_utc};
+
+/// A task as represented by an iCalendar VTODO component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VTodo {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub due: Option<SystemTime>,
+    pub status: Status,
+    pub completed_time: Option<SystemTime>,
+    /// When the item was last changed, if known. It is only read, not written.
+    pub modified: Option<SystemTime>,
+}
+
+/// Parses the VTODO components of an iCalendar document. VEVENTs are included as well when
+/// `events` is set, using their start time as due date.
+pub fn parse(text: &str, events: bool) -> Vec<VTodo> {
+    let mut todos = Vec::new();
+    // The component being parsed, and whether it is a VEVENT.
+    let mut current: Option<(VTodo, bool)> = None;
+    for line in unfold(text) {
+        let (name_and_params, value) = match line.split_once(':') {
+            Some(split) => split,
+            None => continue,
+        };
+        let name = name_and_params
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .to_ascii_uppercase();
+        match (name.as_str(), value.trim()) {
+            ("BEGIN", "VTODO") => current = Some((empty(), false)),
+            ("BEGIN", "VEVENT") if events => current = Some((empty(), true)),
+            ("END", "VTODO") | ("END", "VEVENT") => {
+                if let Some((todo, _)) = current.take() {
+                    todos.push(todo);
+                }
+            }
+            _ => {
+                if let Some((todo, event)) = current.as_mut() {
+                    match name.as_str() {
+                        "UID" => todo.uid = unescape(value),
+                        "SUMMARY" => todo.summary = unescape(value),
+                        "DESCRIPTION" => todo.description = Some(unescape(value)),
+                        "DUE" if !*event => todo.due = parse_time(value),
+                        "DTSTART" if *event => todo.due = parse_time(value),
+                        "STATUS" => todo.status = parse_status(value),
+                        "COMPLETED" => todo.completed_time = parse_time(value),
+                        "LAST-MODIFIED" => todo.modified = parse_time(value),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    todos
+}
+
+fn empty() -> VTodo {
+    VTodo {
+        uid: String::new(),
+        summary: String::new(),
+        description: None,
+        due: None,
+        status: Status::Todo,
+        completed_time: None,
+        modified: None,
+    }
+}
+
+/// Serializes a task as an iCalendar document containing a single VTODO. `stamp` is the DTSTAMP
+/// (the time the object was created), which is left out when not given.
+pub fn to_ics(todo: &VTodo, stamp: Option<SystemTime>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//todo-rust//EN".to_string(),
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", escape(&todo.uid)),
+    ];
+    if let Some(stamp) = stamp {
+        lines.push(format!("DTSTAMP:{}", format_time(stamp)));
+    }
+    lines.push(format!("SUMMARY:{}", escape(&todo.summary)));
+    if let Some(description) = &todo.description {
+        lines.push(format!("DESCRIPTION:{}", escape(description)));
+    }
+    if let Some(due) = todo.due {
+        lines.push(format!("DUE:{}", format_time(due)));
+    }
+    lines.push(format!("STATUS:{}", format_status(todo.status)));
+    if todo.status == Status::Done {
+        if let Some(completed_time) = todo.completed_time {
+            lines.push(format!("COMPLETED:{}", format_time(completed_time)));
+        }
+    }
+    lines.push("END:VTODO".to_string());
+    lines.push("END:VCALENDAR".to_string());
+    let mut ics = String::new();
+    for line in lines {
+        ics.push_str(&fold(&line));
+        ics.push_str("\r\n");
+    }
+    ics
+}
+
+// Maps the STATUS property of a VTODO to a task status, see RFC 5545 section 3.8.1.11.
+fn parse_status(value: &str) -> Status {
+    match value.trim() {
+        "IN-PROCESS" => Status::InProgress,
+        "COMPLETED" => Status::Done,
+        "CANCELLED" => Status::Cancelled,
+        _ => Status::Todo,
+    }
+}
+
+fn format_status(status: Status) -> &'static str {
+    match status {
+        Status::Todo | Status::Waiting => "NEEDS-ACTION",
+        Status::InProgress => "IN-PROCESS",
+        Status::Done => "COMPLETED",
+        Status::Cancelled => "CANCELLED",
+    }
+}
+
+/// Formats a time as a UTC date-time, e.g. `20210801T140000Z`.
+pub fn format_time(t: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = to_utc(t);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Parses a date (`20210801`) or date-time (`20210801T140000`, optionally followed by `Z`).
+/// Times without a zone are taken as UTC.
+pub fn parse_time(value: &str) -> Option<SystemTime> {
+    let value = value.trim().trim_end_matches('Z');
+    let (date, time) = value.split_once('T').unwrap_or((value, "000000"));
+    if date.len() != 8
+        || time.len() != 6
+        || !(date.to_string() + time)
+            .chars()
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let n = |s: &str| s.parse::<u32>().ok();
+    Some(from_utc(
+        n(&date[0..4])? as i64,
+        n(&date[4..6])?,
+        n(&date[6..8])?,
+        n(&time[0..2])?,
+        n(&time[2..4])?,
+        n(&time[4..6])?,
+    ))
+}
+
+// Joins lines that were folded by starting the continuation with a space or tab.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+// Folds a content line so that no line is longer than 75 octets.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => {}
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
@@ -0,0 +1,94 @@
+// Import of iCalendar files, such as those exported by calendar and task apps. Tasks keep the UID
+// of their VTODO (or VEVENT) as their CalDAV UID, so a later CalDAV sync knows them as the same
+// items, and as their external id, so importing a file again updates the tasks imported before
+// instead of adding them twice.
+
+use postgres::types::ToSql;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::ical;
+use crate::status::Status;
+use crate::task_ref::new_uuid;
+use crate::Context;
+use todo_rust::client::Client;
+
+/// Imports the VTODOs in `path`, or on stdin if it is `-`, and with `events` the VEVENTs too.
+pub fn import(client: &mut Client, ctx: &Context, path: &Path, events: bool) -> Result<(), Error> {
+    let text = if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| Error::Other(e.to_string()))?;
+        text
+    } else {
+        fs::read_to_string(path)
+            .map_err(|e| Error::Other(tr!("Failed to read {}: {}", path.display(), e)))?
+    };
+    let todos = ical::parse(&text, events);
+
+    let mut transaction = client.transaction()?;
+    let (mut imported, mut updated, mut unchanged) = (0, 0, 0);
+    for todo in &todos {
+        if todo.uid.is_empty() {
+            return Err(Error::Other(tr!("Item without a UID: {}", todo.summary)));
+        }
+        let completed_time = todo.completed_time.filter(|_| todo.status == Status::Done);
+        let sql =
+            "insert into todo (name, description, due_time, status, completed_time, caldav_uid,
+                       owner, external_id, uuid)
+                   values ($1, $2, $3, $4, $5, $6, $7, 'ical:' || $6, $8)
+                   on conflict (owner, external_id) do update
+                       set name = excluded.name, description = excluded.description,
+                           due_time = excluded.due_time, status = excluded.status,
+                           completed_time = excluded.completed_time
+                       where (todo.name, todo.description, todo.due_time, todo.status,
+                               todo.completed_time)
+                           is distinct from (excluded.name, excluded.description,
+                               excluded.due_time, excluded.status, excluded.completed_time)
+                   returning xmax = 0";
+        // A task imported before keeps its uuid, which its values are encrypted with.
+        let uuid = match transaction.query_opt(
+            "select uuid from todo where owner = $1 and external_id = 'ical:' || $2",
+            &[&ctx.user, &todo.uid],
+        )? {
+            Some(row) => row.get(0),
+            None => new_uuid(),
+        };
+        let name = ctx.encrypt(&todo.summary, Location::task("name", &uuid));
+        let description = todo
+            .description
+            .as_deref()
+            .map(|d| ctx.encrypt(d, Location::task("description", &uuid)));
+        let params: [&(dyn ToSql + Sync); 8] = [
+            &name,
+            &description,
+            &todo.due,
+            &todo.status,
+            &completed_time,
+            &todo.uid,
+            &ctx.user,
+            &uuid,
+        ];
+        ctx.show_statement(sql, &params);
+        match transaction.query_opt(sql, &params)? {
+            Some(row) if row.get(0) => imported += 1,
+            Some(_) => updated += 1,
+            None => unchanged += 1,
+        }
+    }
+    ctx.finish(transaction)?;
+    println!(
+        "{}",
+        tr!(
+            "Imported {} tasks, updated {} and left {} unchanged",
+            imported,
+            updated,
+            unchanged
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,122 @@
+// The inbox, for getting tasks out of one's head quickly and deciding about them later:
+// `todo capture "<text>"` adds a task that is marked as in the inbox, without asking for anything
+// else, and `todo triage` goes through the inbox asking for the project, due time and priority of
+// each task. Tasks in the inbox are ordinary open tasks otherwise, shown as such in the list.
+
+use postgres::types::ToSql;
+use std::io::{self, IsTerminal};
+
+use crate::context;
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::review::{self, prompt};
+use crate::task_ref::new_uuid;
+use crate::time;
+use crate::webhook;
+use crate::{alias, parse_priority, run_bulk, typed_query, Context, TODO_TABLE};
+use todo_rust::client::Client;
+
+pub fn capture(client: &mut Client, ctx: &Context, name: &str) -> Result<(), Error> {
+    let context = context::active(client, ctx)?;
+    let sql = "insert into todo (name, context, owner, inbox, uuid) values ($1, $2, $3, true, $4)
+                   returning id";
+    let uuid = new_uuid();
+    let name = ctx.encrypt(name, Location::task("name", &uuid));
+    let params: [&(dyn ToSql + Sync); 4] = [&name, &context, &ctx.user, &uuid];
+    let mut transaction = client.transaction()?;
+    ctx.show_statement(sql, &params);
+    let id: i32 = transaction.query_one(sql, &params)?.get(0);
+    ctx.finish(transaction)?;
+    if ctx.dry_run {
+        return Ok(());
+    }
+    if ctx.verbosity >= 0 {
+        println!(
+            "{}",
+            tr!("Captured task {} ({}) in the inbox", id, alias::encode(id))
+        );
+    }
+    let tasks = webhook::tasks(client, ctx, &[id])?;
+    webhook::send(ctx, webhook::Event::Created, &tasks);
+    Ok(())
+}
+
+/// Goes through the tasks in the inbox, oldest first, asking whether to triage, delete or skip
+/// each. Triaged tasks get a project, due time and priority, and leave the inbox.
+pub fn triage(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    if !io::stdin().is_terminal() {
+        return Err(Error::Usage(tr!("'triage' needs a terminal")));
+    }
+    let tasks = typed_query::from(TODO_TABLE)
+        .where_(|t| {
+            t.owner.clone().eq(typed_query::Constant {
+                value: ctx.user.clone(),
+            })
+        })
+        .where_(|t| t.inbox.clone().eq(typed_query::Constant { value: true }))
+        .order_by(|t| typed_query::asc(&t.created_time))
+        .query(client)?;
+    if tasks.is_empty() {
+        println!("{}", tr!("The inbox is empty"));
+        return Ok(());
+    }
+    let total = tasks.len();
+    for (n, task) in tasks.iter().enumerate() {
+        println!();
+        println!("{}", tr!("Task {} of {}:", n + 1, total));
+        println!(
+            "{}: {}",
+            task.id,
+            ctx.decrypt(&task.name, Location::task("name", &task.uuid))
+        );
+        loop {
+            let answer = prompt(&tr!("[t]riage, [d]elete, [s]kip or [q]uit? "))?;
+            match answer.to_lowercase().as_str() {
+                "t" | "triage" => clarify(client, ctx, task.id)?,
+                "d" | "delete" => review::delete(client, ctx, task.id)?,
+                "s" | "skip" | "" => {}
+                "q" | "quit" => return Ok(()),
+                _ => continue,
+            }
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Asks for the project, due time and priority of a task, which may be left empty, and takes it
+// out of the inbox.
+fn clarify(client: &mut Client, ctx: &Context, id: i32) -> Result<(), Error> {
+    let project = Some(prompt(&tr!("Project: "))?).filter(|p| !p.is_empty());
+    let due = loop {
+        let due = prompt(&tr!("Due time: "))?;
+        if due.is_empty() {
+            break None;
+        }
+        match time::parse_time(client, &due) {
+            Ok(due) => break Some(due),
+            Err(e) => eprintln!("{}", e),
+        }
+    };
+    let priority = loop {
+        let priority = prompt(&tr!("Priority (high, medium or low): "))?;
+        if priority.is_empty() {
+            break None;
+        }
+        match parse_priority(&priority.to_lowercase()) {
+            Ok(priority) => break Some(priority),
+            Err(e) => eprintln!("{}", e),
+        }
+    };
+    run_bulk(client, ctx, &[id], &tr!("triaged"), |t, id| {
+        ctx.execute(
+            t,
+            "update todo set project = $1, due_time = $2, priority = $3, inbox = false
+             where id = $4 and owner = $5",
+            &[&project, &due, &priority, &id, &ctx.user],
+        )
+    })?;
+    let tasks = webhook::tasks(client, ctx, &[id])?;
+    webhook::send(ctx, webhook::Event::Modified, &tasks);
+    Ok(())
+}
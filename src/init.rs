@@ -0,0 +1,155 @@
+// First-time setup with `todo init`: writes the config file, checks that the database can be
+// reached, creates it if it doesn't exist yet, applies the migrations and can add an example task.
+// Without a terminal, or with `--yes`, it doesn't ask anything and takes the settings from its
+// options.
+
+use postgres::error::SqlState;
+use postgres::NoTls;
+use std::fs;
+use std::io::{self, Write};
+
+use crate::config;
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::lock::{self, Lock};
+use crate::migrations;
+use crate::pgpass;
+use crate::task_ref::new_uuid;
+use crate::Context;
+use todo_rust::client::Client;
+
+const MAINTENANCE_DATABASE: &str = "postgres";
+
+#[derive(Debug)]
+pub struct Options {
+    /// The connection string to write to the config file.
+    pub connection: Option<String>,
+    /// Whether to add an example task without asking.
+    pub example: bool,
+    /// Whether to overwrite an existing config file.
+    pub force: bool,
+}
+
+pub fn run(ctx: &Context, options: Options) -> Result<(), Error> {
+    if ctx.dry_run {
+        return Err(Error::Usage(tr!("'init' can't be used with '--dry-run'")));
+    }
+    let path = config::path().ok_or_else(|| {
+        Error::Other(tr!(
+            "Could not determine where to write the config file, set TODO_CONFIG"
+        ))
+    })?;
+    let connection = if path.exists() && !options.force {
+        if options.connection.is_some() {
+            return Err(Error::Usage(tr!(
+                "The config file {} exists already, use '--force' to overwrite it",
+                path.display()
+            )));
+        }
+        println!("{}", tr!("Using the config file {}", path.display()));
+        ctx.connection.clone()
+    } else {
+        let connection = match options.connection {
+            Some(connection) => connection,
+            None if ctx.assume_yes => ctx.connection.clone(),
+            None => ask(&tr!("Database connection"), &ctx.connection)?,
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| Error::Other(tr!("Failed to create {}: {}", dir.display(), e)))?;
+        }
+        fs::write(&path, config_file(&connection))
+            .map_err(|e| Error::Other(tr!("Failed to write {}: {}", path.display(), e)))?;
+        println!("{}", tr!("Wrote the config file {}", path.display()));
+        connection
+    };
+
+    let mut client = connect_or_create(&connection)?;
+    let applied = lock::with(&mut client, ctx, Lock::Migrations, |client| {
+        Ok(migrations::up(client, ctx, None)?)
+    })?;
+    if applied == 0 {
+        println!("{}", tr!("The database is up to date"));
+    }
+
+    let example = options.example
+        || !ctx.assume_yes
+            && matches!(
+                ask(&tr!("Add an example task? [y/N]"), "")?.to_lowercase(),
+                a if a == "y" || a == "yes" || a == tr!("y")
+            );
+    if example {
+        let uuid = new_uuid();
+        client.execute(
+            "insert into todo (name, description, owner, uuid) values ($1, $2, $3, $4)",
+            &[
+                &ctx.encrypt(&tr!("Try todo"), Location::task("name", &uuid)),
+                &ctx.encrypt(
+                    &tr!(
+                        "Complete this task with 'todo complete', or remove it with 'todo remove'"
+                    ),
+                    Location::task("description", &uuid),
+                ),
+                &ctx.user,
+                &uuid,
+            ],
+        )?;
+        println!("{}", tr!("Added an example task"));
+    }
+    println!("{}", tr!("All set, run 'todo add <name>' to add a task"));
+    Ok(())
+}
+
+fn config_file(connection: &str) -> String {
+    format!(
+        "[database]\nconnection = \"{}\"\n",
+        connection.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+// Connects to the database, creating it first if the server doesn't have it yet.
+fn connect_or_create(connection: &str) -> Result<Client, Error> {
+    let resolved = pgpass::resolve(connection)?;
+    let error = match Client::connect(&resolved, NoTls) {
+        Ok(client) => {
+            println!("{}", tr!("Connected to the database"));
+            return Ok(client);
+        }
+        Err(e) if e.code() == Some(&SqlState::INVALID_CATALOG_NAME) => e,
+        Err(e) => return Err(Error::Connection(e)),
+    };
+    // Only connection strings, not URLs, can be pointed at another database.
+    let database = match pgpass::param(&resolved, "dbname") {
+        Some(database) => database,
+        None => return Err(Error::Connection(error)),
+    };
+    let maintenance = pgpass::with_param(&resolved, "dbname", MAINTENANCE_DATABASE)?;
+    let mut server = Client::connect(&maintenance, NoTls).map_err(Error::Connection)?;
+    server.batch_execute(&format!(
+        "create database \"{}\"",
+        database.replace('"', "\"\"")
+    ))?;
+    println!("{}", tr!("Created the database {}", database));
+    Client::connect(&resolved, NoTls).map_err(Error::Connection)
+}
+
+// Asks for a setting on stderr, returning `default` for an empty answer.
+fn ask(question: &str, default: &str) -> Result<String, Error> {
+    if default.is_empty() {
+        eprint!("{} ", question);
+    } else {
+        eprint!("{} [{}]: ", question, default);
+    }
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    let read = io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| Error::Other(e.to_string()))?;
+    if read == 0 {
+        return Err(Error::Other(tr!("Aborted")));
+    }
+    match answer.trim() {
+        "" => Ok(default.to_string()),
+        answer => Ok(answer.to_string()),
+    }
+}
@@ -0,0 +1,64 @@
+// A journal of completed tasks: with `journal.path` in the config, every completion is appended to
+// that file as a line with the time and the task's name, such as
+// `2021-08-01 14:00 CEST  Water the plants`. The file is only ever appended to, so it is a log
+// kept apart from the database, which survives removing or purging the tasks. Names are written
+// decrypted.
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::config::Config;
+use crate::crypto::Location;
+use crate::time::format_absolute;
+use crate::{Context, TodoRecord};
+
+#[derive(Debug, Default)]
+pub struct Journal {
+    path: Option<PathBuf>,
+}
+
+impl Journal {
+    pub fn load(config: &Config) -> Journal {
+        let path = config.get("journal.path").map(|path| {
+            match (path.strip_prefix("~/"), env::var_os("HOME")) {
+                (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+                _ => PathBuf::from(path),
+            }
+        });
+        Journal { path }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.path.is_none()
+    }
+
+    /// Appends the completion of `task` to the journal. Failures are reported as warnings, since
+    /// the task has been completed.
+    pub fn record(&self, ctx: &Context, task: &TodoRecord) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let completed = task.completed_time.unwrap_or_else(SystemTime::now);
+        let line = format!(
+            "{}  {}\n",
+            format_absolute(completed, ctx.timezone.as_ref()),
+            ctx.decrypt(&task.name, Location::task("name", &task.uuid))
+                .replace('\n', " ")
+        );
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(e) = written {
+            log::warn!(
+                "{}",
+                tr!("Failed to write to the journal {}: {}", path.display(), e)
+            );
+        }
+    }
+}
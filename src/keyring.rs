@@ -0,0 +1,99 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const SERVICE: &str = "todo-rust";
+
+/// Looks up a secret in the OS keyring, using `secret-tool` (libsecret) on Linux and `security`
+/// on macOS.
+pub fn get(name: &str) -> Option<String> {
+    let output = if cfg!(target_os = "macos") {
+        Command::new("security")
+            .args(["find-generic-password", "-s", SERVICE, "-a", name, "-w"])
+            .output()
+    } else {
+        Command::new("secret-tool")
+            .args(["lookup", "service", SERVICE, "key", name])
+            .output()
+    };
+    match output {
+        Ok(output) if output.status.success() => {
+            let secret = String::from_utf8_lossy(&output.stdout)
+                .trim_end_matches('\n')
+                .to_string();
+            Some(secret).filter(|s| !s.is_empty())
+        }
+        _ => None,
+    }
+}
+
+/// Stores a secret in the OS keyring, replacing the one stored before. The secret is passed on
+/// stdin, so it doesn't show up in the process list.
+pub fn set(name: &str, secret: &str) -> Result<(), String> {
+    let (mut command, input) = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.arg("-i");
+        let input = format!(
+            "add-generic-password -U -s {} -a {} -w {}\n",
+            SERVICE,
+            name,
+            quote(secret)
+        );
+        (command, input)
+    } else {
+        let mut command = Command::new("secret-tool");
+        command.args([
+            "store",
+            &format!("--label={} {}", SERVICE, name),
+            "service",
+            SERVICE,
+            "key",
+            name,
+        ]);
+        (command, secret.to_string())
+    };
+    run(&mut command, &input)
+}
+
+/// Removes a secret from the OS keyring, returning whether there was one.
+pub fn delete(name: &str) -> Result<bool, String> {
+    if get(name).is_none() {
+        return Ok(false);
+    }
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args(["delete-generic-password", "-s", SERVICE, "-a", name]);
+        command
+    } else {
+        let mut command = Command::new("secret-tool");
+        command.args(["clear", "service", SERVICE, "key", name]);
+        command
+    };
+    run(&mut command, "").map(|()| true)
+}
+
+fn run(command: &mut Command, input: &str) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let failed = |e: &dyn std::fmt::Display| tr!("Failed to run {}: {}", program, e);
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| failed(&e))?;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .map_err(|e| failed(&e))?;
+    let output = child.wait_with_output().map_err(|e| failed(&e))?;
+    if !output.status.success() {
+        return Err(failed(&String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+// Quotes a value for the command line of `security -i`.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
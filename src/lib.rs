@@ -1,9 +1,233 @@
+pub mod client {
+    use postgres::fallible_iterator::FallibleIterator;
+    use postgres::tls::{MakeTlsConnect, TlsConnect};
+    use postgres::types::ToSql;
+    use postgres::{Error, Notifications, Row, Socket};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// The statements run so far, the rows they returned or changed and the time they took, for
+    /// `--timing`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Stats {
+        pub statements: u64,
+        pub rows: u64,
+        pub time: Duration,
+    }
+
+    const NO_STATS: Stats = Stats {
+        statements: 0,
+        rows: 0,
+        time: Duration::ZERO,
+    };
+
+    static STATS: Mutex<Stats> = Mutex::new(NO_STATS);
+
+    pub fn stats() -> Stats {
+        *STATS.lock().unwrap()
+    }
+
+    /// Forgets the statements run so far.
+    pub fn reset_stats() {
+        *STATS.lock().unwrap() = NO_STATS;
+    }
+
+    /// A connection, or a transaction on one, that counts every statement run on it in `stats`.
+    pub struct Timed<C>(C);
+
+    pub type Client = Timed<postgres::Client>;
+
+    pub type Transaction<'a> = Timed<postgres::Transaction<'a>>;
+
+    /// What connections and transactions can both do, like `postgres::GenericClient`.
+    pub trait GenericClient {
+        fn query(&mut self, query: &str, params: &[&(dyn ToSql + Sync)])
+            -> Result<Vec<Row>, Error>;
+        fn query_one(&mut self, query: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Row, Error>;
+        fn query_opt(
+            &mut self,
+            query: &str,
+            params: &[&(dyn ToSql + Sync)],
+        ) -> Result<Option<Row>, Error>;
+        fn execute(&mut self, query: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, Error>;
+        fn batch_execute(&mut self, query: &str) -> Result<(), Error>;
+        fn transaction(&mut self) -> Result<Transaction<'_>, Error>;
+    }
+
+    impl Client {
+        pub fn connect<T>(params: &str, tls_mode: T) -> Result<Client, Error>
+        where
+            T: MakeTlsConnect<Socket> + 'static + Send,
+            T::TlsConnect: Send,
+            T::Stream: Send,
+            <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+        {
+            postgres::Client::connect(params, tls_mode).map(Timed)
+        }
+
+        pub fn is_closed(&self) -> bool {
+            self.0.is_closed()
+        }
+
+        pub fn notifications(&mut self) -> Notifications<'_> {
+            self.0.notifications()
+        }
+    }
+
+    impl Transaction<'_> {
+        pub fn commit(self) -> Result<(), Error> {
+            self.0.commit()
+        }
+
+        pub fn rollback(self) -> Result<(), Error> {
+            self.0.rollback()
+        }
+    }
+
+    impl<C: postgres::GenericClient> Timed<C> {
+        pub fn query(
+            &mut self,
+            query: &str,
+            params: &[&(dyn ToSql + Sync)],
+        ) -> Result<Vec<Row>, Error> {
+            timed(
+                query,
+                params,
+                || self.0.query(query, params),
+                |rows| rows.len(),
+            )
+        }
+
+        pub fn query_one(
+            &mut self,
+            query: &str,
+            params: &[&(dyn ToSql + Sync)],
+        ) -> Result<Row, Error> {
+            timed(query, params, || self.0.query_one(query, params), |_| 1)
+        }
+
+        pub fn query_opt(
+            &mut self,
+            query: &str,
+            params: &[&(dyn ToSql + Sync)],
+        ) -> Result<Option<Row>, Error> {
+            timed(
+                query,
+                params,
+                || self.0.query_opt(query, params),
+                |row| row.is_some() as usize,
+            )
+        }
+
+        /// Like `query`, but passes the rows to `f` as they arrive instead of collecting them.
+        pub fn query_each<F>(
+            &mut self,
+            query: &str,
+            params: &[&(dyn ToSql + Sync)],
+            mut f: F,
+        ) -> Result<(), Error>
+        where
+            F: FnMut(Row),
+        {
+            let each = || {
+                let raw = params.iter().map(|&p| p as &dyn ToSql);
+                let mut rows = self.0.query_raw(query, raw)?;
+                let mut count = 0;
+                while let Some(row) = rows.next()? {
+                    f(row);
+                    count += 1;
+                }
+                Ok(count)
+            };
+            timed(query, params, each, |&count| count).map(|_| ())
+        }
+
+        pub fn execute(
+            &mut self,
+            query: &str,
+            params: &[&(dyn ToSql + Sync)],
+        ) -> Result<u64, Error> {
+            timed(
+                query,
+                params,
+                || self.0.execute(query, params),
+                |&affected| affected as usize,
+            )
+        }
+
+        pub fn batch_execute(&mut self, query: &str) -> Result<(), Error> {
+            timed(query, &[], || self.0.batch_execute(query), |_| 0)
+        }
+
+        pub fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+            self.0.transaction().map(Timed)
+        }
+    }
+
+    impl<C: postgres::GenericClient> GenericClient for Timed<C> {
+        fn query(
+            &mut self,
+            query: &str,
+            params: &[&(dyn ToSql + Sync)],
+        ) -> Result<Vec<Row>, Error> {
+            Timed::query(self, query, params)
+        }
+
+        fn query_one(&mut self, query: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Row, Error> {
+            Timed::query_one(self, query, params)
+        }
+
+        fn query_opt(
+            &mut self,
+            query: &str,
+            params: &[&(dyn ToSql + Sync)],
+        ) -> Result<Option<Row>, Error> {
+            Timed::query_opt(self, query, params)
+        }
+
+        fn execute(&mut self, query: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, Error> {
+            Timed::execute(self, query, params)
+        }
+
+        fn batch_execute(&mut self, query: &str) -> Result<(), Error> {
+            Timed::batch_execute(self, query)
+        }
+
+        fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+            Timed::transaction(self)
+        }
+    }
+
+    // Runs a statement, counting it with the rows it returned or changed, failed or not.
+    fn timed<T>(
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+        run: impl FnOnce() -> Result<T, Error>,
+        rows: impl FnOnce(&T) -> usize,
+    ) -> Result<T, Error> {
+        let start = Instant::now();
+        let result = run();
+        let elapsed = start.elapsed();
+        let rows = result.as_ref().map_or(0, rows) as u64;
+        let mut stats = STATS.lock().unwrap();
+        stats.statements += 1;
+        stats.rows += rows;
+        stats.time += elapsed;
+        drop(stats);
+        log::debug!("{} {:?} ({} rows, {:?})", query, params, rows, elapsed);
+        result
+    }
+}
+
 pub mod typed_query {
     use postgres::types::private::BytesMut;
     use postgres::types::{IsNull, Type};
-    use postgres::{Client, Row};
+    use postgres::Row;
+    use std::fmt;
     use std::marker::PhantomData;
 
+    use crate::client::{Client, GenericClient};
+
     pub trait FromRow {
         fn from_row(row: Row) -> Self;
     }
@@ -35,6 +259,10 @@ pub mod typed_query {
             query: Box<Query<C, R>>,
             order: Order,
         },
+        Limit {
+            query: Box<Query<C, R>>,
+            limit: i64,
+        },
     }
 
     pub fn from<C, R: FromRow>(table: Table<C, R>) -> Query<C, R> {
@@ -46,6 +274,41 @@ pub mod typed_query {
         values: InsertParams,
     }
 
+    /// An update of the rows of a table that match all of its predicates, or of all rows if it
+    /// has none.
+    pub struct Update<C, R: FromRow> {
+        table: Table<C, R>,
+        assignments: Vec<Assignment>,
+        predicates: Vec<Predicate>,
+    }
+
+    /// A deletion of the rows of a table that match all of its predicates, or of all rows if it
+    /// has none.
+    pub struct Delete<C, R: FromRow> {
+        table: Table<C, R>,
+        predicates: Vec<Predicate>,
+    }
+
+    /// A new value for a column, made with `Field::set`.
+    pub struct Assignment {
+        column: &'static str,
+        value: Box<dyn postgres::types::ToSql + Sync>,
+    }
+
+    /// An update setting the column of `assignment`, since an update needs at least one. `set`
+    /// adds more.
+    pub fn update<C, R: FromRow, F>(table: Table<C, R>, assignment: F) -> Update<C, R>
+    where
+        F: FnOnce(&C) -> Assignment,
+    {
+        let assignment = assignment(&table.columns);
+        Update {
+            table,
+            assignments: vec![assignment],
+            predicates: Vec::new(),
+        }
+    }
+
     #[derive(Debug)]
     pub enum WithDefault<T> {
         Value(T),
@@ -60,7 +323,7 @@ pub mod typed_query {
             &self,
             ty: &Type,
             out: &mut BytesMut,
-        ) -> Result<IsNull, Box<(dyn std::error::Error + Send + Sync + 'static)>> {
+        ) -> Result<IsNull, Box<dyn std::error::Error + Send + Sync + 'static>> {
             match self {
                 WithDefault::Value(v) => v.to_sql(ty, out),
                 // Should be filtered out of the list of params instead, since we can't send the
@@ -106,17 +369,40 @@ pub mod typed_query {
                     predicate: _,
                 } => query.columns(),
                 Query::Order { query, order: _ } => query.columns(),
+                Query::Limit { query, limit: _ } => query.columns(),
             }
         }
 
-        pub fn query(&self, client: &mut Client) -> Vec<R> {
-            let mut vec: Vec<R> = Vec::new();
+        pub fn query(&self, client: &mut impl GenericClient) -> Result<Vec<R>, postgres::Error> {
+            let (q, ps) = statement(self);
+            let rows = client.query(q.as_str(), ps.as_slice())?;
+            Ok(rows.into_iter().map(FromRow::from_row).collect())
+        }
 
-            let q = &self.to_sql();
-            for row in client.query(q.as_str(), &[]).unwrap() {
-                vec.push(FromRow::from_row(row));
-            }
-            vec
+        /// Like `query`, but returns each row as a JSON object keyed by column name, for callers
+        /// without a `FromRow` type for it.
+        pub fn query_json(
+            &self,
+            client: &mut impl GenericClient,
+        ) -> Result<Vec<serde_json::Value>, postgres::Error> {
+            let (q, ps) = self.json_statement();
+            let rows = client.query(q.as_str(), ps.as_slice())?;
+            rows.iter().map(|row| row.try_get(0)).collect()
+        }
+
+        // The query wrapped to return each row as a single `jsonb` value.
+        fn json_statement(&self) -> (String, Params<'_>) {
+            let (q, ps) = statement(self);
+            (format!("select to_jsonb(t) from ({}) t", q), ps)
+        }
+
+        /// Like `query`, but passes the rows to `f` as they arrive instead of collecting them.
+        pub fn for_each<F>(&self, client: &mut Client, mut f: F) -> Result<(), postgres::Error>
+        where
+            F: FnMut(R),
+        {
+            let (q, ps) = statement(self);
+            client.query_each(q.as_str(), ps.as_slice(), |row| f(FromRow::from_row(row)))
         }
 
         pub fn where_<F>(self, condition: F) -> Query<C, R>
@@ -126,7 +412,7 @@ pub mod typed_query {
             let predicate = condition(self.columns());
             Query::Where {
                 query: Box::new(self),
-                predicate: predicate,
+                predicate,
             }
         }
 
@@ -137,7 +423,15 @@ pub mod typed_query {
             let order = make_order(self.columns());
             Query::Order {
                 query: Box::new(self),
-                order: order,
+                order,
+            }
+        }
+
+        /// Returns only the first `limit` rows, in the order of the query.
+        pub fn limit(self, limit: i64) -> Query<C, R> {
+            Query::Limit {
+                query: Box::new(self),
+                limit,
             }
         }
     }
@@ -152,71 +446,216 @@ pub mod typed_query {
                 values: InsertParams(values),
             }
         }
+    }
 
-        pub fn execute(&self, client: &mut Client) {
-            let q = &self.to_sql();
-            let InsertParams(vss) = &self.values;
-            let mut ps: Vec<&(dyn postgres::types::ToSql + Sync)> = Vec::new();
-            for vs in vss {
-                for Param(v) in vs {
-                    if !(*v).is_default() {
-                        let v_: &(dyn postgres::types::ToSql + Sync) = (&**v).as_dyn_to_sql();
-                        ps.push(v_);
-                    }
-                }
-            }
-            client.execute(q.as_str(), &*ps).unwrap();
+    impl<C, R: FromRow> Update<C, R> {
+        /// Adds a column to set.
+        pub fn set<F>(mut self, assignment: F) -> Self
+        where
+            F: FnOnce(&C) -> Assignment,
+        {
+            let assignment = assignment(&self.table.columns);
+            self.assignments.push(assignment);
+            self
+        }
+
+        /// Restricts the update to rows matching `condition`, besides the earlier conditions.
+        pub fn where_<F>(mut self, condition: F) -> Self
+        where
+            F: FnOnce(&C) -> Predicate,
+        {
+            let predicate = condition(&self.table.columns);
+            self.predicates.push(predicate);
+            self
+        }
+    }
+
+    pub fn delete_from<C, R: FromRow>(table: Table<C, R>) -> Delete<C, R> {
+        Delete {
+            table,
+            predicates: Vec::new(),
+        }
+    }
+
+    impl<C, R: FromRow> Delete<C, R> {
+        /// Restricts the deletion to rows matching `condition`, besides the earlier conditions.
+        pub fn where_<F>(mut self, condition: F) -> Self
+        where
+            F: FnOnce(&C) -> Predicate,
+        {
+            let predicate = condition(&self.table.columns);
+            self.predicates.push(predicate);
+            self
+        }
+    }
+
+    /// What inserts, updates and deletions share: running them, or showing what they would run.
+    pub trait Statement: ToSql {
+        /// The rows of the table the statement changes.
+        type Row: FromRow;
+
+        /// Returns the statement and parameters `execute` would run, without running it.
+        fn dry_run(&self) -> String {
+            let (q, ps) = statement(self);
+            format!("{}\n  -- {:?}", q, ps)
+        }
+
+        /// Runs the statement, in a transaction or not, and returns the number of changed rows.
+        fn execute(&self, client: &mut impl GenericClient) -> Result<u64, postgres::Error> {
+            let (q, ps) = statement(self);
+            client.execute(q.as_str(), ps.as_slice())
+        }
+
+        /// Runs the statement and returns the changed rows, as they are after it, with default
+        /// values filled in.
+        fn returning(
+            &self,
+            client: &mut impl GenericClient,
+        ) -> Result<Vec<Self::Row>, postgres::Error> {
+            let (q, ps) = statement(self);
+            let q = q + " returning *";
+            let rows = client.query(q.as_str(), ps.as_slice())?;
+            Ok(rows.into_iter().map(FromRow::from_row).collect())
+        }
+    }
+
+    impl<C, R: FromRow> Statement for Insert<C, R> {
+        type Row = R;
+    }
+
+    impl<C, R: FromRow> Statement for Update<C, R> {
+        type Row = R;
+    }
+
+    impl<C, R: FromRow> Statement for Delete<C, R> {
+        type Row = R;
+    }
+
+    /// The values of the `$n` placeholders of a statement, collected while writing its SQL.
+    #[derive(Default)]
+    pub struct Params<'a>(Vec<&'a (dyn postgres::types::ToSql + Sync)>);
+
+    impl<'a> Params<'a> {
+        /// Adds a value, returning the placeholder to write in its place.
+        pub fn push(&mut self, value: &'a (dyn postgres::types::ToSql + Sync)) -> String {
+            self.0.push(value);
+            format!("${}", self.0.len())
+        }
+
+        pub fn as_slice(&self) -> &[&'a (dyn postgres::types::ToSql + Sync)] {
+            &self.0
+        }
+    }
+
+    impl fmt::Debug for Params<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            self.0.fmt(f)
         }
     }
 
     pub trait ToSql {
-        fn to_sql(&self) -> String;
+        /// Writes the SQL, adding the values it uses to `params` and referring to them by their
+        /// placeholders, so values are never written into the SQL itself.
+        fn to_sql<'a>(&'a self, params: &mut Params<'a>) -> String;
+    }
+
+    /// Writes the SQL of a statement, with the values of its placeholders.
+    pub fn statement<S: ToSql + ?Sized>(s: &S) -> (String, Params<'_>) {
+        let mut params = Params::default();
+        let sql = s.to_sql(&mut params);
+        (sql, params)
     }
 
     impl<C, R: FromRow> ToSql for Table<C, R> {
-        fn to_sql(&self) -> String {
+        fn to_sql<'a>(&'a self, _: &mut Params<'a>) -> String {
             self.name.to_string()
         }
     }
 
     impl<C, R: FromRow> ToSql for Query<C, R> {
-        fn to_sql(&self) -> String {
+        fn to_sql<'a>(&'a self, params: &mut Params<'a>) -> String {
             match self {
                 Query::Table { table } => format!(
                     // TODO column names
                     "select * from {}",
-                    table.to_sql()
+                    table.to_sql(params)
                 ),
                 Query::Where { query, predicate } => format!(
                     "select * from ({}) t where {}", // TODO unique number on alias
-                    query.to_sql(),
-                    predicate.to_sql()
+                    query.to_sql(params),
+                    predicate.to_sql(params)
                 ),
                 Query::Order { query, order } => format!(
                     "select * from ({}) t order by {}", // TODO unique number on alias
-                    query.to_sql(),
-                    order.to_sql()
+                    query.to_sql(params),
+                    order.to_sql(params)
+                ),
+                // Appended rather than wrapping the query, so it keeps the order of the query.
+                Query::Limit { query, limit } if !matches!(**query, Query::Limit { .. }) => {
+                    let query = query.to_sql(params);
+                    format!("{} limit {}", query, params.push(limit))
+                }
+                Query::Limit { query, limit } => format!(
+                    "select * from ({}) t limit {}",
+                    query.to_sql(params),
+                    params.push(limit)
                 ),
             }
         }
     }
 
     impl<C, R: FromRow> ToSql for Insert<C, R> {
-        fn to_sql(&self) -> String {
-            "insert into ".to_string() + &self.table.to_sql() + " values " + &self.values.to_sql()
+        fn to_sql<'a>(&'a self, params: &mut Params<'a>) -> String {
+            "insert into ".to_string()
+                + &self.table.to_sql(params)
+                + " values "
+                + &self.values.to_sql(params)
+        }
+    }
+
+    impl<C, R: FromRow> ToSql for Update<C, R> {
+        fn to_sql<'a>(&'a self, params: &mut Params<'a>) -> String {
+            let assignments: Vec<String> = self
+                .assignments
+                .iter()
+                .map(|a| format!("{} = {}", a.column, params.push(&*a.value)))
+                .collect();
+            let mut sql = format!(
+                "update {} set {}",
+                self.table.to_sql(params),
+                assignments.join(", ")
+            );
+            if !self.predicates.is_empty() {
+                sql = sql + " where " + &conjunction(&self.predicates, params);
+            }
+            sql
         }
     }
 
+    impl<C, R: FromRow> ToSql for Delete<C, R> {
+        fn to_sql<'a>(&'a self, params: &mut Params<'a>) -> String {
+            let mut sql = format!("delete from {}", self.table.to_sql(params));
+            if !self.predicates.is_empty() {
+                sql = sql + " where " + &conjunction(&self.predicates, params);
+            }
+            sql
+        }
+    }
+
+    fn conjunction<'a>(predicates: &'a [Predicate], params: &mut Params<'a>) -> String {
+        let predicates: Vec<String> = predicates.iter().map(|p| p.to_sql(params)).collect();
+        predicates.join(" and ")
+    }
+
     impl ToSql for InsertParams {
-        fn to_sql(&self) -> String {
+        fn to_sql<'a>(&'a self, params: &mut Params<'a>) -> String {
             let InsertParams(vss) = self;
-            let mut ix = 1;
             let mut sql_str = String::new();
             for (i, vs) in vss.iter().enumerate() {
                 if i > 0 {
                     sql_str.push_str(", ")
                 }
-                sql_str.push_str("(");
+                sql_str.push('(');
                 for (j, Param(v)) in vs.iter().enumerate() {
                     if j > 0 {
                         sql_str.push_str(", ");
@@ -224,12 +663,10 @@ pub mod typed_query {
                     if (**v).is_default() {
                         sql_str.push_str("default");
                     } else {
-                        sql_str.push_str("$");
-                        sql_str.push_str(&ix.to_string());
-                        ix += 1;
+                        sql_str.push_str(&params.push((**v).as_dyn_to_sql()));
                     }
                 }
-                sql_str.push_str(")");
+                sql_str.push(')');
             }
             sql_str
         }
@@ -256,13 +693,13 @@ pub mod typed_query {
     pub trait SomeField: ToSql {}
 
     impl<T> ToSql for Field<T> {
-        fn to_sql(&self) -> String {
+        fn to_sql<'a>(&'a self, _: &mut Params<'a>) -> String {
             self.name.to_string()
         }
     }
 
     impl<T> ToSql for &Field<T> {
-        fn to_sql(&self) -> String {
+        fn to_sql<'a>(&'a self, _: &mut Params<'a>) -> String {
             self.name.to_string()
         }
     }
@@ -275,29 +712,78 @@ pub mod typed_query {
 
     impl<T> SomeField for &Field<T> {}
 
+    /// A value in a query, sent as a parameter.
     pub struct Constant<T> {
         pub value: T,
     }
 
-    impl<T: ToString> ToSql for Constant<T> {
-        fn to_sql(&self) -> String {
-            self.value.to_string() // TODO escaping/query params
+    impl<T: postgres::types::ToSql + Sync> ToSql for Constant<T> {
+        fn to_sql<'a>(&'a self, params: &mut Params<'a>) -> String {
+            params.push(&self.value)
         }
     }
 
-    impl<T: ToString> SomeField for Constant<T> {}
+    impl<T: postgres::types::ToSql + Sync> SomeField for Constant<T> {}
 
     pub enum Predicate {
         Eq {
             field1: Box<dyn SomeField>,
             field2: Box<dyn SomeField>,
         },
+        Neq {
+            field1: Box<dyn SomeField>,
+            field2: Box<dyn SomeField>,
+        },
+        Lt {
+            field1: Box<dyn SomeField>,
+            field2: Box<dyn SomeField>,
+        },
+        Lte {
+            field1: Box<dyn SomeField>,
+            field2: Box<dyn SomeField>,
+        },
+        Gt {
+            field1: Box<dyn SomeField>,
+            field2: Box<dyn SomeField>,
+        },
+        Gte {
+            field1: Box<dyn SomeField>,
+            field2: Box<dyn SomeField>,
+        },
+        In {
+            field: Box<dyn SomeField>,
+            values: Vec<Box<dyn SomeField>>,
+        },
     }
 
     impl ToSql for Predicate {
-        fn to_sql(&self) -> String {
+        fn to_sql<'a>(&'a self, params: &mut Params<'a>) -> String {
             match self {
-                Predicate::Eq { field1, field2 } => field1.to_sql() + " = " + &field2.to_sql(),
+                Predicate::Eq { field1, field2 } => {
+                    field1.to_sql(params) + " = " + &field2.to_sql(params)
+                }
+                Predicate::Neq { field1, field2 } => {
+                    field1.to_sql(params) + " <> " + &field2.to_sql(params)
+                }
+                Predicate::Lt { field1, field2 } => {
+                    field1.to_sql(params) + " < " + &field2.to_sql(params)
+                }
+                Predicate::Lte { field1, field2 } => {
+                    field1.to_sql(params) + " <= " + &field2.to_sql(params)
+                }
+                Predicate::Gt { field1, field2 } => {
+                    field1.to_sql(params) + " > " + &field2.to_sql(params)
+                }
+                Predicate::Gte { field1, field2 } => {
+                    field1.to_sql(params) + " >= " + &field2.to_sql(params)
+                }
+                // `in ()` is a syntax error, and nothing is in an empty list.
+                Predicate::In { values, .. } if values.is_empty() => "false".to_string(),
+                Predicate::In { field, values } => {
+                    let field = field.to_sql(params);
+                    let values: Vec<String> = values.iter().map(|v| v.to_sql(params)).collect();
+                    field + " in (" + &values.join(", ") + ")"
+                }
             }
         }
     }
@@ -309,12 +795,75 @@ pub mod typed_query {
                 field2: Box::new(other),
             }
         }
+
+        // As in SQL, a comparison with null matches no rows, so `neq` doesn't match null either.
+        pub fn neq(self, other: impl SomeField + 'static) -> Predicate {
+            Predicate::Neq {
+                field1: Box::new(self),
+                field2: Box::new(other),
+            }
+        }
+
+        pub fn lt(self, other: impl SomeField + 'static) -> Predicate {
+            Predicate::Lt {
+                field1: Box::new(self),
+                field2: Box::new(other),
+            }
+        }
+
+        pub fn lte(self, other: impl SomeField + 'static) -> Predicate {
+            Predicate::Lte {
+                field1: Box::new(self),
+                field2: Box::new(other),
+            }
+        }
+
+        pub fn gt(self, other: impl SomeField + 'static) -> Predicate {
+            Predicate::Gt {
+                field1: Box::new(self),
+                field2: Box::new(other),
+            }
+        }
+
+        pub fn gte(self, other: impl SomeField + 'static) -> Predicate {
+            Predicate::Gte {
+                field1: Box::new(self),
+                field2: Box::new(other),
+            }
+        }
+
+        pub fn in_<V: SomeField + 'static>(self, values: Vec<V>) -> Predicate {
+            Predicate::In {
+                field: Box::new(self),
+                values: values
+                    .into_iter()
+                    .map(|v| Box::new(v) as Box<dyn SomeField>)
+                    .collect(),
+            }
+        }
+    }
+
+    impl<T: postgres::types::ToSql + Sync + 'static> Field<T> {
+        pub fn set(&self, value: T) -> Assignment {
+            Assignment {
+                column: self.name,
+                value: Box::new(value),
+            }
+        }
+
+        /// Sets a nullable column to null.
+        pub fn set_null(&self) -> Assignment {
+            Assignment {
+                column: self.name,
+                value: Box::new(None::<T>),
+            }
+        }
     }
 
     impl<T: Clone> Clone for Field<T> {
         fn clone(&self) -> Self {
             Field {
-                name: &self.name,
+                name: self.name,
                 phantom: PhantomData,
             }
         }
@@ -330,9 +879,9 @@ pub mod typed_query {
         pub direction: Direction,
     }
 
-    pub fn asc<F: 'static>(field: &F) -> Order
+    pub fn asc<F>(field: &F) -> Order
     where
-        F: SomeField + Clone,
+        F: SomeField + Clone + 'static,
     {
         Order {
             by: Box::new((*field).clone()),
@@ -340,9 +889,9 @@ pub mod typed_query {
         }
     }
 
-    pub fn desc<F: 'static>(field: &F) -> Order
+    pub fn desc<F>(field: &F) -> Order
     where
-        F: SomeField + Clone,
+        F: SomeField + Clone + 'static,
     {
         Order {
             by: Box::new((*field).clone()),
@@ -351,17 +900,374 @@ pub mod typed_query {
     }
 
     impl ToSql for Order {
-        fn to_sql(&self) -> String {
-            self.by.to_sql() + " " + &self.direction.to_sql()
+        fn to_sql<'a>(&'a self, params: &mut Params<'a>) -> String {
+            self.by.to_sql(params) + " " + &self.direction.to_sql(params)
         }
     }
 
     impl ToSql for Direction {
-        fn to_sql(&self) -> String {
+        fn to_sql<'a>(&'a self, _: &mut Params<'a>) -> String {
             match self {
                 Direction::Ascending => "asc".to_string(),
                 Direction::Descending => "desc".to_string(),
             }
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use postgres::types::FromSql;
+
+        struct Columns {
+            id: Field<i32>,
+            name: Field<String>,
+        }
+
+        struct Record;
+
+        impl FromRow for Record {
+            fn from_row(_: Row) -> Record {
+                Record
+            }
+        }
+
+        fn field<T>(name: &'static str) -> Field<T> {
+            Field {
+                name,
+                phantom: PhantomData,
+            }
+        }
+
+        fn table() -> Table<Columns, Record> {
+            Table {
+                name: "todo",
+                columns: Columns {
+                    id: field("id"),
+                    name: field("name"),
+                },
+                phantom: PhantomData,
+            }
+        }
+
+        struct RecordInsert {
+            id: WithDefault<i32>,
+            name: String,
+        }
+
+        impl ToSqlParams for RecordInsert {
+            fn to_sql_params(self) -> Vec<Param> {
+                vec![Param(Box::new(self.id)), Param(Box::new(self.name))]
+            }
+        }
+
+        #[test]
+        fn insert_numbers_placeholders_across_rows() {
+            let insert = insert_into(table())
+                .values(RecordInsert {
+                    id: WithDefault::Default,
+                    name: "a".to_string(),
+                })
+                .values(RecordInsert {
+                    id: WithDefault::Value(2),
+                    name: "b".to_string(),
+                });
+            let (sql, params) = statement(&insert);
+            assert_eq!(sql, "insert into todo values (default, $1), ($2, $3)");
+            assert_eq!(params.as_slice().len(), 3);
+        }
+
+        #[test]
+        fn update_numbers_placeholders_across_set_and_where() {
+            let update = update(table(), |t| t.name.set("a".to_string()))
+                .set(|t| t.id.set(2))
+                .where_(|t| t.id.clone().eq(Constant { value: 1 }))
+                .where_(|t| t.name.clone().neq(Constant { value: "b" }));
+            let (sql, params) = statement(&update);
+            assert_eq!(
+                sql,
+                "update todo set name = $1, id = $2 where id = $3 and name <> $4"
+            );
+            assert_eq!(params.as_slice().len(), 4);
+        }
+
+        #[test]
+        fn delete_without_predicates_deletes_everything() {
+            let delete = delete_from(table());
+            let (sql, params) = statement(&delete);
+            assert_eq!(sql, "delete from todo");
+            assert!(params.as_slice().is_empty());
+        }
+
+        #[test]
+        fn in_numbers_placeholders_and_matches_nothing_when_empty() {
+            let delete = delete_from(table())
+                .where_(|t| {
+                    t.id.clone()
+                        .in_(vec![Constant { value: 1 }, Constant { value: 2 }])
+                })
+                .where_(|t| t.name.clone().eq(Constant { value: "a" }));
+            let (sql, params) = statement(&delete);
+            assert_eq!(sql, "delete from todo where id in ($1, $2) and name = $3");
+            assert_eq!(params.as_slice().len(), 3);
+
+            let empty: Vec<Constant<i32>> = Vec::new();
+            let update = update(table(), |t| t.name.set("a".to_string()))
+                .where_(|t| t.id.clone().in_(empty))
+                .where_(|t| t.id.clone().gt(Constant { value: 0 }));
+            let (sql, params) = statement(&update);
+            assert_eq!(sql, "update todo set name = $1 where false and id > $2");
+            assert_eq!(params.as_slice().len(), 2);
+        }
+
+        #[test]
+        fn limit_keeps_the_order() {
+            let query = from(table())
+                .where_(|t| t.id.clone().gt(Constant { value: 10 }))
+                .order_by(|t| asc(&t.id))
+                .limit(5)
+                .limit(3);
+            let (sql, params) = statement(&query);
+            assert_eq!(
+                sql,
+                "select * from (select * from (select * from (select * from todo) t \
+                 where id > $1) t order by id asc limit $2) t limit $3"
+            );
+            assert_eq!(params.as_slice().len(), 3);
+        }
+
+        #[test]
+        fn query_json_returns_rows_as_jsonb() {
+            let query = from(table())
+                .where_(|t| t.id.clone().eq(Constant { value: 1 }))
+                .order_by(|t| asc(&t.name));
+            let (sql, params) = query.json_statement();
+            assert_eq!(
+                sql,
+                "select to_jsonb(t) from (select * from (select * from (select * from todo) t \
+                 where id = $1) t order by name asc) t"
+            );
+            assert_eq!(params.as_slice().len(), 1);
+            let row =
+                serde_json::Value::from_sql(&Type::JSONB, b"\x01{\"id\": 1, \"name\": \"a\"}")
+                    .unwrap();
+            assert_eq!(row, serde_json::json!({"id": 1, "name": "a"}));
+        }
+    }
+}
+
+pub mod migrate {
+    use postgres::Error;
+    use std::time::SystemTime;
+
+    use crate::client::Client;
+
+    /// A schema change, with the SQL to apply it and to revert it.
+    pub struct Migration {
+        pub version: i32,
+        pub name: &'static str,
+        pub up: &'static str,
+        pub down: &'static str,
+    }
+
+    fn ensure_table(client: &mut Client) -> Result<(), Error> {
+        // `create table if not exists` needs the right to create tables even if the table exists,
+        // which roles restricted to the data don't have.
+        let exists: bool = client
+            .query_one("select to_regclass('schema_migrations') is not null", &[])?
+            .get(0);
+        if exists {
+            return Ok(());
+        }
+        client.batch_execute(
+            "create table if not exists schema_migrations (
+                version integer primary key,
+                name text not null,
+                applied_time timestamp with time zone not null default now()
+            )",
+        )
+    }
+
+    /// Returns the migrations with the time each was applied, if it was.
+    pub fn status<'a>(
+        client: &mut Client,
+        migrations: &'a [Migration],
+    ) -> Result<Vec<(&'a Migration, Option<SystemTime>)>, Error> {
+        ensure_table(client)?;
+        let mut status = Vec::new();
+        for migration in migrations {
+            let applied = client
+                .query_opt(
+                    "select applied_time from schema_migrations where version = $1",
+                    &[&migration.version],
+                )?
+                .map(|row| row.get(0));
+            status.push((migration, applied));
+        }
+        Ok(status)
+    }
+
+    /// Returns the version of the latest applied migration, or 0 if none were applied.
+    pub fn current_version(client: &mut Client) -> Result<i32, Error> {
+        ensure_table(client)?;
+        let row = client.query_one(
+            "select coalesce(max(version), 0) from schema_migrations",
+            &[],
+        )?;
+        Ok(row.get(0))
+    }
+
+    /// Returns the migrations that `up` would apply.
+    pub fn pending<'a>(
+        client: &mut Client,
+        migrations: &'a [Migration],
+        target: Option<i32>,
+    ) -> Result<Vec<&'a Migration>, Error> {
+        Ok(status(client, migrations)?
+            .into_iter()
+            .filter(|(migration, applied_time)| {
+                applied_time.is_none() && target.is_none_or(|t| migration.version <= t)
+            })
+            .map(|(migration, _)| migration)
+            .collect())
+    }
+
+    /// Returns the migrations that `down` would revert, newest first.
+    pub fn revertible<'a>(
+        client: &mut Client,
+        migrations: &'a [Migration],
+        target: i32,
+    ) -> Result<Vec<&'a Migration>, Error> {
+        Ok(status(client, migrations)?
+            .into_iter()
+            .rev()
+            .filter(|(migration, applied_time)| {
+                applied_time.is_some() && migration.version > target
+            })
+            .map(|(migration, _)| migration)
+            .collect())
+    }
+
+    /// Applies the pending migrations up to and including version `target` (or all of them),
+    /// each in its own transaction. Returns the migrations that were applied.
+    pub fn up<'a>(
+        client: &mut Client,
+        migrations: &'a [Migration],
+        target: Option<i32>,
+    ) -> Result<Vec<&'a Migration>, Error> {
+        let mut applied = Vec::new();
+        for migration in pending(client, migrations, target)? {
+            let mut transaction = client.transaction()?;
+            log::debug!("Applying migration {}: {}", migration.version, migration.up);
+            transaction.batch_execute(migration.up)?;
+            transaction.execute(
+                "insert into schema_migrations (version, name) values ($1, $2)",
+                &[&migration.version, &migration.name],
+            )?;
+            transaction.commit()?;
+            applied.push(migration);
+        }
+        Ok(applied)
+    }
+
+    /// Reverts the applied migrations newer than version `target`, newest first. Returns the
+    /// migrations that were reverted.
+    pub fn down<'a>(
+        client: &mut Client,
+        migrations: &'a [Migration],
+        target: i32,
+    ) -> Result<Vec<&'a Migration>, Error> {
+        let mut reverted = Vec::new();
+        for migration in revertible(client, migrations, target)? {
+            let mut transaction = client.transaction()?;
+            log::debug!(
+                "Reverting migration {}: {}",
+                migration.version,
+                migration.down
+            );
+            transaction.batch_execute(migration.down)?;
+            transaction.execute(
+                "delete from schema_migrations where version = $1",
+                &[&migration.version],
+            )?;
+            transaction.commit()?;
+            reverted.push(migration);
+        }
+        Ok(reverted)
+    }
+}
+
+pub mod session {
+    use postgres::Error;
+
+    use crate::client::{Client, GenericClient, Transaction};
+
+    /// Makes the statements on `client` run as database role `role`, if given, and as the
+    /// application user `user`, available as `current_setting('todo.user')`, so row-level
+    /// security policies can restrict them to that user's rows. With `local`, this only lasts
+    /// for the current transaction, for connections shared by several users; otherwise it lasts
+    /// for the session.
+    pub fn set_identity(
+        client: &mut impl GenericClient,
+        role: Option<&str>,
+        user: &str,
+        local: bool,
+    ) -> Result<(), Error> {
+        // `set_config('role', ...)` is `set role`, which can't take the name as a parameter.
+        if let Some(role) = role {
+            client.execute("select set_config('role', $1, $2)", &[&role, &local])?;
+        }
+        client.execute("select set_config('todo.user', $1, $2)", &[&user, &local])?;
+        Ok(())
+    }
+
+    /// Undoes `set_identity` for the session.
+    pub fn reset_identity(client: &mut impl GenericClient) -> Result<(), Error> {
+        client.batch_execute("reset role; select set_config('todo.user', '', false)")
+    }
+
+    /// Starts a transaction on `client` whose statements run with the identity of `set_identity`,
+    /// which ends with it. Connections shared by several users run every statement in one, so no
+    /// statement runs with the identity of another user.
+    pub fn transaction<'a>(
+        client: &'a mut Client,
+        role: Option<&str>,
+        user: &str,
+    ) -> Result<Transaction<'a>, Error> {
+        let mut transaction = client.transaction()?;
+        set_identity(&mut transaction, role, user, true)?;
+        Ok(transaction)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use postgres::NoTls;
+
+        fn user(client: &mut impl GenericClient) -> Option<String> {
+            client
+                .query_one("select nullif(current_setting('todo.user', true), '')", &[])
+                .unwrap()
+                .get(0)
+        }
+
+        // Needs a database, from the connection string in `TODO_TEST_DATABASE`.
+        #[test]
+        fn transaction_identity_ends_with_the_transaction() {
+            let connection = match std::env::var("TODO_TEST_DATABASE") {
+                Ok(connection) => connection,
+                Err(_) => return,
+            };
+            let mut client = Client::connect(&connection, NoTls).unwrap();
+            let mut t = transaction(&mut client, None, "alice").unwrap();
+            assert_eq!(user(&mut t).as_deref(), Some("alice"));
+            t.commit().unwrap();
+            assert_eq!(user(&mut client), None);
+
+            let mut t = transaction(&mut client, None, "bob").unwrap();
+            assert_eq!(user(&mut t).as_deref(), Some("bob"));
+            t.rollback().unwrap();
+            assert_eq!(user(&mut client), None);
+        }
+    }
 }
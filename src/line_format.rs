@@ -0,0 +1,154 @@
+// User-defined lines for `list --format`, such as `{id}\t{name} ({age})`. Placeholders are the
+// names below in braces, and `{field:<name>}` for a custom field; `{{` and `}}` are literal
+// braces, and `\t`, `\n` and `\\` are a tab, a newline and a backslash. Placeholders for unset
+// attributes are empty.
+
+use std::time::SystemTime;
+
+use crate::crypto::Location;
+use crate::json::Json;
+use crate::{alias, priority_key, time, urgency, Context, TodoRecord};
+
+const PLACEHOLDERS: &[&str] = &[
+    "id",
+    "alias",
+    "uuid",
+    "name",
+    "status",
+    "age",
+    "created",
+    "due",
+    "completed",
+    "priority",
+    "project",
+    "context",
+    "urgency",
+    "note",
+    "pinned",
+    "estimate",
+    "assignee",
+];
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Placeholder(String),
+    Field(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct LineFormat {
+    parts: Vec<Part>,
+}
+
+impl LineFormat {
+    pub fn parse(format: &str) -> Result<LineFormat, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(tr!("Unclosed placeholder in format: {}", format)),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(match name.strip_prefix("field:") {
+                        Some(field) => Part::Field(field.to_string()),
+                        None if PLACEHOLDERS.contains(&name.as_str()) => Part::Placeholder(name),
+                        None => {
+                            return Err(tr!(
+                                "Unknown placeholder in format: {}, expected one of {}",
+                                format!("{{{}}}", name),
+                                PLACEHOLDERS.join(", ")
+                            ))
+                        }
+                    });
+                }
+                '}' => return Err(tr!("Unmatched closing brace in format: {}", format)),
+                '\\' => match chars.next() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some(c) => text.push(c),
+                    None => text.push('\\'),
+                },
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(LineFormat { parts })
+    }
+
+    /// Formats `task`, whose urgency is `score`.
+    pub fn render(&self, ctx: &Context, task: &TodoRecord, score: f64) -> String {
+        let mut line = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => line.push_str(text),
+                Part::Placeholder(name) => line.push_str(&placeholder(ctx, task, score, name)),
+                Part::Field(name) => match task.fields.get(name) {
+                    Some(Json::String(s)) => line.push_str(s),
+                    Some(Json::Null) | None => {}
+                    Some(value) => line.push_str(&value.to_string()),
+                },
+            }
+        }
+        line
+    }
+}
+
+fn placeholder(ctx: &Context, task: &TodoRecord, score: f64, name: &str) -> String {
+    let when = |t: Option<SystemTime>| t.map(|t| ctx.format_time(t)).unwrap_or_default();
+    match name {
+        "id" => task.id.to_string(),
+        "alias" => alias::encode(task.id),
+        "uuid" => task.uuid.clone(),
+        "name" => ctx.decrypt(&task.name, Location::task("name", &task.uuid)),
+        "status" => task.status.as_str().to_string(),
+        "age" => time::format_relative(task.created_time, SystemTime::now()),
+        "created" => ctx.format_time(task.created_time),
+        "due" => when(task.due_time),
+        "completed" => when(task.completed_time),
+        "priority" => task
+            .priority
+            .map(priority_key)
+            .unwrap_or_default()
+            .to_string(),
+        "project" => task.project.clone().unwrap_or_default(),
+        "context" => task.context.clone().unwrap_or_default(),
+        "assignee" => task.assignee.clone().unwrap_or_default(),
+        "urgency" => urgency::format(score),
+        // Only the first line, so every task stays on one line.
+        "note" => task
+            .description
+            .as_ref()
+            .and_then(|d| {
+                let description = ctx.decrypt(d, Location::task("description", &task.uuid));
+                description.lines().next().map(str::to_string)
+            })
+            .unwrap_or_default(),
+        "pinned" => if task.pinned { "pinned" } else { "" }.to_string(),
+        "estimate" => task
+            .estimate
+            .map(|e| time::format_duration(e as f64))
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,63 @@
+// Advisory locks keeping operations that would conflict from running at the same time, from two
+// shells or a shell and a daemon: migrations, cleaning up, sending reminders and syncing. The
+// locks are held by the database session, so they are released when a process dies. An operation
+// that finds its lock taken fails instead of waiting, since the other one may take long (such as
+// the reminder daemon) or make this one unnecessary (such as migrations).
+
+use crate::error::Error;
+use crate::Context;
+use todo_rust::client::Client;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Lock {
+    Migrations,
+    Cleanup,
+    Reminders,
+    Sync,
+}
+
+impl Lock {
+    // Migrations change the schema everyone shares, the others only the user's tasks.
+    fn key(self, ctx: &Context) -> String {
+        match self {
+            Lock::Migrations => "todo-rust migrations".to_string(),
+            Lock::Cleanup => format!("todo-rust cleanup {}", ctx.user),
+            Lock::Reminders => format!("todo-rust reminders {}", ctx.user),
+            Lock::Sync => format!("todo-rust sync {}", ctx.user),
+        }
+    }
+
+    fn description(self) -> String {
+        match self {
+            Lock::Migrations => tr!("migrating the database"),
+            Lock::Cleanup => tr!("cleaning up"),
+            Lock::Reminders => tr!("sending reminders"),
+            Lock::Sync => tr!("syncing"),
+        }
+    }
+}
+
+/// Runs `f` holding `lock`, or fails if another session holds it.
+pub fn with<T>(
+    client: &mut Client,
+    ctx: &Context,
+    lock: Lock,
+    f: impl FnOnce(&mut Client) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let key = lock.key(ctx);
+    let locked: bool = client
+        .query_one("select pg_try_advisory_lock(hashtext($1))", &[&key])?
+        .get(0);
+    if !locked {
+        return Err(Error::Other(tr!(
+            "Another instance holds the lock for {}, try again when it has finished",
+            lock.description()
+        )));
+    }
+    let result = f(client);
+    // Unlocking only fails when the connection was lost, which releases the lock as well.
+    client
+        .execute("select pg_advisory_unlock(hashtext($1))", &[&key])
+        .ok();
+    result
+}
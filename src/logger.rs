@@ -0,0 +1,65 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// Log messages of other crates (such as the postgres driver) are only shown from this verbosity.
+const DEPENDENCY_VERBOSITY: i32 = 3;
+
+struct Logger {
+    verbosity: i32,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+            && (metadata.target().starts_with("todo_rust")
+                || self.verbosity >= DEPENDENCY_VERBOSITY)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error => eprintln!("{}", tr!("error: {}", record.args())),
+            Level::Warn => eprintln!("{}", tr!("warning: {}", record.args())),
+            level => eprintln!(
+                "[{} {}] {}",
+                level.as_str().to_lowercase(),
+                record.target(),
+                record.args()
+            ),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Sends log messages to stderr. With verbosity -1 (`--quiet`) only errors are shown, with 0
+/// warnings too, and every `-v` adds a level.
+pub fn init(verbosity: i32) {
+    if log::set_boxed_logger(Box::new(Logger { verbosity })).is_ok() {
+        set_verbosity(verbosity);
+    }
+}
+
+/// Changes the level of the messages shown, such as after reloading the config.
+pub fn set_verbosity(verbosity: i32) {
+    log::set_max_level(match verbosity {
+        i32::MIN..=-1 => LevelFilter::Error,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    });
+}
+
+/// The verbosity of `log.level` in the config, such as 1 for `info`.
+pub fn verbosity(level: &str) -> Option<i32> {
+    match level {
+        "error" => Some(-1),
+        "warn" => Some(0),
+        "info" => Some(1),
+        "debug" => Some(2),
+        "trace" => Some(3),
+        _ => None,
+    }
+}
@@ -1,26 +1,752 @@
-use postgres::{Client, NoTls, Row};
+use postgres::types::ToSql;
+use postgres::{NoTls, Row};
+use std::collections::HashSet;
 use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
 use std::marker::PhantomData;
-use std::time::SystemTime;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::Instant;
+use std::time::{Duration, SystemTime};
 
-use todo_rust::typed_query;
+use json::Json;
+use status::Status;
+use task_ref::TaskRef;
+use todo_rust::client::{Client, GenericClient, Transaction};
+use todo_rust::typed_query::Statement;
+use todo_rust::{session, typed_query};
 
-fn main() {
-    let mut client =
-        Client::connect("host=localhost user=postgres password=postgres", NoTls).unwrap();
-    create_tables(&mut client);
+#[macro_use]
+mod i18n;
 
-    let args = env::args();
-    let command = parse_args(args);
-    match command {
-        Err(e) => {
-            println!("{}", e);
+mod agenda;
+mod alias;
+mod annotation;
+mod archive;
+mod args;
+mod assign;
+mod attachment;
+mod auth;
+mod burndown;
+mod cache;
+mod caldav;
+mod cleanup;
+mod config;
+mod conflict;
+mod context;
+mod crypto;
+mod dependency;
+mod doctor;
+mod edit;
+mod encrypt;
+mod error;
+mod export;
+mod fields;
+mod filter;
+mod github;
+mod gitsync;
+mod grpc;
+mod history;
+mod hooks;
+mod http;
+mod ical;
+mod ical_import;
+mod inbox;
+mod init;
+mod journal;
+mod json;
+mod keyring;
+mod line_format;
+mod lock;
+mod logger;
+mod man;
+mod metrics;
+mod migrations;
+mod modify;
+mod notify;
+mod open;
+mod ordering;
+mod outbox;
+mod output;
+mod pgpass;
+mod plugin;
+mod projects;
+mod prompt;
+mod reload;
+mod remind;
+mod remote;
+mod report;
+mod review;
+mod serve;
+mod sqlite;
+mod stats;
+mod status;
+mod store;
+mod task_ref;
+mod taskwarrior;
+mod template;
+#[cfg(test)]
+mod test_db;
+mod time;
+mod timetrack;
+mod trash;
+mod tz;
+mod urgency;
+mod watch;
+mod webhook;
+
+use args::Args;
+use config::Config;
+use crypto::Location;
+use error::Error;
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        if let Error::Usage(_) = e {
             print_usage();
         }
-        Ok(cmd) => {
-            run_command(&mut client, cmd);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let config = Config::load()?;
+    // The profile decides the settings the other options are read with.
+    let profile = take_profile(&mut args)
+        .map_err(Error::Usage)?
+        .or_else(|| env::var("TODO_PROFILE").ok().filter(|p| !p.is_empty()))
+        .or_else(|| config.get("profile").map(|p| p.to_string()));
+    let config = match profile {
+        Some(profile) => config.with_profile(&profile).map_err(Error::Usage)?,
+        None => config,
+    };
+    i18n::init(&config);
+    let given = args.clone();
+    let mut ctx = parse_options(&mut args, &config).map_err(Error::Usage)?;
+    ctx.options = given[..given.len() - args.len()].to_vec();
+    logger::init(ctx.verbosity);
+    if args.is_empty() {
+        args = default_command(&config);
+    }
+    expand_alias(&mut args, &config);
+    let source = args.first().cloned().unwrap_or_default();
+    let command = parse_args(args.clone()).map_err(Error::Usage)?;
+    // The manual page doesn't need a database.
+    if let Command::Man = command {
+        man::run();
+        return Ok(());
+    }
+    // Nor do credentials, such as the database password.
+    if let Command::Auth { action } = command {
+        return auth::run(action);
+    }
+    // The doctor connects by itself, so it can report connection problems.
+    if let Command::Doctor = command {
+        return doctor::run(&ctx);
+    }
+    // Setting up creates the database, if it has to.
+    if let Command::Init { options } = command {
+        return init::run(&ctx, options);
+    }
+    // Plugins connect by themselves, if they need to.
+    if let Command::Plugin { path, args } = &command {
+        return plugin::run(&ctx, path, args);
+    }
+    if let Some(url) = &ctx.remote_url {
+        return remote::run(&ctx, url, command);
+    }
+    if let Some(path) = &ctx.sqlite {
+        return sqlite::run(&ctx, path, command);
+    }
+    // The prompt is shown with every shell prompt, so it doesn't wait for the database, nor does
+    // it do the work of other commands on startup.
+    if let Command::Prompt = command {
+        ctx.connect_retries = 0;
+        let mut client = connect(&ctx)?;
+        return prompt::run(&mut client, &ctx);
+    }
+
+    // `--timing` counts the statements run before the command too, such as flushing the outbox.
+    let connecting = Instant::now();
+    let mut client = match connect(&ctx) {
+        Ok(client) => client,
+        // `list` can still show the tasks it listed last, and tasks can still be added.
+        Err(Error::Connection(e)) if is_unreachable(&e) => match command {
+            Command::List { .. } => return cache::list(&ctx, command, e),
+            Command::Add {
+                name: Some(name),
+                template: None,
+                options,
+                unique: false,
+                ..
+            } => {
+                log::info!("Could not connect to the database: {}", e);
+                return outbox::queue(&ctx, &name, options, false);
+            }
+            Command::Capture { name } => {
+                log::info!("Could not connect to the database: {}", e);
+                return outbox::queue(&ctx, &name, TaskOptions::default(), true);
+            }
+            _ => return Err(Error::Connection(e)),
+        },
+        Err(e) => return Err(e),
+    };
+    history::set_source(&mut client, &source)?;
+    if ctx.auto_migrate && !ctx.dry_run {
+        lock::with(&mut client, &ctx, lock::Lock::Migrations, |client| {
+            Ok(migrations::up(client, &ctx, None)?)
+        })?;
+    }
+    // Commands other than `migrate` only run against the schema they were written for.
+    if !matches!(command, Command::Migrate { .. }) {
+        migrations::check(&mut client)?;
+    }
+    outbox::flush(&mut client, &ctx)?;
+    // Cleaning up on startup is opportunistic, so a failure doesn't stop the command.
+    if ctx.cleanup.on_startup() && !ctx.dry_run && !matches!(command, Command::Gc) {
+        history::set_source(&mut client, "gc")?;
+        let cleaned = lock::with(&mut client, &ctx, lock::Lock::Cleanup, |client| {
+            cleanup::apply(client, &ctx)
+        });
+        match cleaned {
+            Ok((archived, purged)) => {
+                log::info!("Archived {} tasks, purged {} tasks", archived, purged)
+            }
+            Err(e) => log::warn!("{}", tr!("Cleaning up failed: {}", e)),
+        }
+        history::set_source(&mut client, &source)?;
+    }
+    let start = Instant::now();
+    let repeatable = is_read_only(&command) && ctx.output != output::Format::Jsonl;
+    let mut result = run_command(&mut client, &ctx, command);
+    // Reads can be run again, on a new connection, if they lost theirs, as long as they hadn't
+    // printed anything yet. Lists streamed with `--output jsonl` print as they go, so they aren't.
+    if let Err(Error::Database(e)) = &result {
+        if repeatable && error::is_connection_lost(e) {
+            log::warn!(
+                "{}",
+                tr!("Lost the connection to the database, reconnecting: {}", e)
+            );
+            // The statements of the failed run aren't part of `--timing`.
+            todo_rust::client::reset_stats();
+            client = connect(&ctx)?;
+            history::set_source(&mut client, &source)?;
+            let command = parse_args(args).map_err(Error::Usage)?;
+            result = run_command(&mut client, &ctx, command);
+        }
+    }
+    log::info!("Command took {:?}", start.elapsed());
+    if ctx.timing {
+        let stats = todo_rust::client::stats();
+        eprintln!(
+            "{}",
+            tr!(
+                "{} statements, {} rows, {} ms in the database, {} ms in total",
+                stats.statements,
+                stats.rows,
+                stats.time.as_millis(),
+                connecting.elapsed().as_millis()
+            )
+        );
+    }
+    result
+}
+
+// The command to run when none is given: `default_command` from the config file (such as
+// `agenda --days 3`), or else the list of open tasks.
+fn default_command(config: &Config) -> Vec<String> {
+    config
+        .get("default_command")
+        .unwrap_or(DEFAULT_COMMAND)
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+// Replaces a command that is an alias in the config file, such as `wip` with
+// `alias.wip = "list --status in-progress"`, by the words of the alias. The arguments after it
+// are kept. Aliases are expanded once, so an alias can add options to the command of its name.
+fn expand_alias(args: &mut Vec<String>, config: &Config) {
+    let alias = match args.first() {
+        Some(command) => config.get(&format!("alias.{}", command)),
+        None => None,
+    };
+    if let Some(alias) = alias {
+        let words = alias.split_whitespace().map(|s| s.to_string());
+        args.splice(..1, words);
+    }
+}
+
+// Connects to the database, interpreting times in the user's zone. When the database can't be
+// reached, such as while it is starting, connecting is retried a few times with increasing waits.
+// Other errors, such as a wrong password, aren't retried.
+fn connect(ctx: &Context) -> Result<Client, Error> {
+    let start = Instant::now();
+    let mut connection = pgpass::resolve(&ctx.connection)?;
+    if ctx.keyring_password && pgpass::param(&connection, "password").is_none() {
+        let password = keyring::get("database").ok_or_else(|| {
+            tr!("No database password found in the keyring for todo-rust, store it with 'todo auth set database'")
+        })?;
+        connection = pgpass::with_password(&connection, &password);
+    }
+    let mut delay = CONNECT_RETRY_DELAY;
+    let mut retries = 0;
+    let mut client = loop {
+        match Client::connect(&connection, NoTls) {
+            Ok(client) => break client,
+            Err(e) if retries < ctx.connect_retries && is_unreachable(&e) => {
+                log::warn!(
+                    "{}",
+                    tr!(
+                        "Could not connect to the database, trying again in {}: {}",
+                        format!("{:?}", delay),
+                        e
+                    )
+                );
+                thread::sleep(delay);
+                delay *= 2;
+                retries += 1;
+            }
+            Err(e) => return Err(Error::Connection(e)),
+        }
+    };
+    log::info!(
+        "Connected to '{}' as {} in {:?}",
+        doctor::hide_password(&ctx.connection),
+        ctx.user,
+        start.elapsed()
+    );
+    session::set_identity(&mut client, ctx.role.as_deref(), &ctx.user, false)?;
+    time::set_session_zone(&mut client, ctx.timezone.as_ref())?;
+    Ok(client)
+}
+
+// Whether a command only reads from the database, and only prints after its last query, so it
+// can be run again. `show` and `stats` print between queries, so they aren't.
+fn is_read_only(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::List { .. }
+            | Command::Agenda { .. }
+            | Command::Burndown { .. }
+            | Command::Report { .. }
+            | Command::Projects
+            | Command::Timesheet { .. }
+            | Command::SyncStatus
+            | Command::Next { .. }
+            | Command::History { .. }
+            | Command::Blocked
+            | Command::Unblockable { .. }
+    )
+}
+
+// Whether connecting failed because the server couldn't be reached, rather than being refused by it.
+fn is_unreachable(e: &postgres::Error) -> bool {
+    std::error::Error::source(e).is_some_and(|source| source.is::<io::Error>())
+}
+
+const DEFAULT_COMMAND: &str = "list";
+const DEFAULT_CONFIRM_THRESHOLD: usize = 5;
+const DEFAULT_CONNECT_RETRIES: u32 = 3;
+// The wait before the first retry, which doubles for every next one.
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(250);
+const DEFAULT_CONNECTION: &str = "host=localhost user=postgres password=postgres";
+
+/// Settings that apply to every command.
+struct Context {
+    /// The user whose tasks are operated on.
+    user: String,
+    /// Encrypts task names and descriptions, when encryption is enabled.
+    cipher: Option<crypto::Cipher>,
+    /// The connection string for the database.
+    connection: String,
+    /// Whether to take the database password from the OS keyring.
+    keyring_password: bool,
+    /// Whether to apply pending migrations on startup.
+    auto_migrate: bool,
+    /// The database role to switch to after connecting, for row-level security.
+    role: Option<String>,
+    /// How often to try connecting again when the database can't be reached.
+    connect_retries: u32,
+    /// How much to log: -1 for `--quiet`, 0 by default, and one more for every `-v`.
+    verbosity: i32,
+    /// Whether to show modifying statements instead of applying them.
+    dry_run: bool,
+    /// Whether to print how many statements the command ran and how long it took, for `--timing`.
+    timing: bool,
+    /// Whether to show times as dates rather than relative to now.
+    absolute_times: bool,
+    /// The zone to show times in, or `None` for UTC.
+    timezone: Option<tz::Zone>,
+    /// Whether to show times as ISO 8601 UTC timestamps, for scripts.
+    utc: bool,
+    /// The custom fields defined in the config.
+    fields: Vec<fields::Definition>,
+    /// How to print tasks: for people, or for scripts with `--output tsv` or `--output null`.
+    output: output::Format,
+    /// Where to send task events.
+    webhooks: webhook::Webhooks,
+    /// Scripts to run on task events.
+    hooks: hooks::Hooks,
+    /// Where to log completed tasks.
+    journal: journal::Journal,
+    /// Whether to skip confirmation prompts: with `--yes`, or when stdin is not a terminal.
+    assume_yes: bool,
+    /// Bulk changes to more tasks than this ask for confirmation. Removals always do.
+    confirm_threshold: usize,
+    /// How much each part of a task's urgency counts.
+    urgency: urgency::Coefficients,
+    /// When to archive and delete finished tasks.
+    cleanup: cleanup::Policy,
+    /// How long open tasks go unreviewed before `list` flags them as stale, or `None` for never.
+    stale_after: Option<Duration>,
+    /// How `list` shows tasks by default, from `display.list_format`.
+    list_format: Option<line_format::LineFormat>,
+    /// The named reports from the `[reports]` section of the config.
+    reports: Vec<report::Report>,
+    /// How syncs resolve tasks changed on both sides.
+    conflicts: conflict::Policies,
+    /// The SQLite file to keep tasks in instead of the database, with `--backend sqlite`.
+    sqlite: Option<PathBuf>,
+    /// The `todo serve` daemon to send commands to instead of connecting to the database.
+    remote_url: Option<String>,
+    /// The token to send to the daemon.
+    remote_token: Option<String>,
+    /// Who may use `todo serve`.
+    serve: serve::Settings,
+    /// The profile from the config file the settings were taken from.
+    profile: Option<String>,
+    /// How often `remind --daemon` checks for reminders.
+    remind_interval: Duration,
+    /// The options given before the command, to read the settings with again when reloading.
+    options: Vec<String>,
+}
+
+impl Context {
+    /// Encrypts a value to be stored at `location`, when encryption is enabled.
+    fn encrypt(&self, value: &str, location: Location) -> String {
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(value, location),
+            None => value.to_string(),
+        }
+    }
+
+    /// Decrypts a value read from `location` in the database, showing a placeholder for values
+    /// that can't be decrypted.
+    fn decrypt(&self, value: &str, location: Location) -> String {
+        match &self.cipher {
+            // Unencrypted values could have been written by anyone with access to the
+            // database, so they are marked until `todo encrypt` has encrypted them.
+            Some(_) if !crypto::is_encrypted(value) => format!("[unencrypted] {}", value),
+            Some(cipher) => cipher
+                .decrypt(value, location)
+                .unwrap_or_else(|_| "[undecryptable]".to_string()),
+            None if crypto::is_encrypted(value) => "[encrypted]".to_string(),
+            None => value.to_string(),
+        }
+    }
+
+    /// Runs a modifying statement, printing it first with `--dry-run`.
+    fn execute(
+        &self,
+        transaction: &mut Transaction,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, postgres::Error> {
+        self.show_statement(sql, params);
+        transaction.execute(sql, params)
+    }
+
+    /// Prints a modifying statement with `--dry-run`.
+    fn show_statement(&self, sql: &str, params: &[&(dyn ToSql + Sync)]) {
+        if self.dry_run {
+            let sql: Vec<&str> = sql.split_whitespace().collect();
+            println!("{}\n  -- {:?}", sql.join(" "), params);
+        }
+    }
+
+    /// Commits a transaction, or rolls it back with `--dry-run`, so statements still run and
+    /// show what they would affect without changing anything.
+    fn finish(&self, transaction: Transaction) -> Result<(), postgres::Error> {
+        if self.dry_run {
+            transaction.rollback()
+        } else {
+            transaction.commit()
+        }
+    }
+
+    fn format_time(&self, t: SystemTime) -> String {
+        if self.utc {
+            time::format_iso(t)
+        } else if self.absolute_times {
+            time::format_absolute(t, self.timezone.as_ref())
+        } else {
+            time::format_relative(t, SystemTime::now())
+        }
+    }
+
+    fn fingerprint(&self, value: &str) -> String {
+        match &self.cipher {
+            Some(cipher) => cipher.fingerprint(value),
+            None => crypto::sha256(value),
+        }
+    }
+}
+
+// Encryption is enabled by setting `encryption.key` in the config file, or by setting
+// `encryption.keyring = true` to read the key from the OS keyring.
+fn load_cipher(config: &Config) -> Result<Option<crypto::Cipher>, String> {
+    let (key, in_keyring) = match (
+        config.get("encryption.key"),
+        config.get("encryption.keyring"),
+    ) {
+        (Some(key), _) => (key.to_string(), false),
+        (None, Some("true")) => (
+            keyring::get("encryption")
+                .ok_or_else(|| tr!("No encryption key found in the keyring for todo-rust"))?,
+            true,
+        ),
+        _ => return Ok(None),
+    };
+    let salt = load_salt(config, in_keyring)?;
+    crypto::Cipher::new(&key, &salt).map(Some)
+}
+
+// The salt is kept next to the key: in the keyring, or in `encryption-salt` beside the config
+// file. The first run with encryption generates it; `encryption.salt` overrides it, for sharing
+// the database between machines.
+fn load_salt(config: &Config, in_keyring: bool) -> Result<String, String> {
+    if let Some(salt) = config.get("encryption.salt") {
+        return Ok(salt.to_string());
+    }
+    if in_keyring {
+        if let Some(salt) = keyring::get("encryption-salt") {
+            return Ok(salt);
+        }
+        let salt = crypto::new_salt();
+        keyring::set("encryption-salt", &salt)?;
+        return Ok(salt);
+    }
+    let path = config::path()
+        .and_then(|path| Some(path.parent()?.join("encryption-salt")))
+        .ok_or_else(|| tr!("Cannot find the config directory for the encryption salt"))?;
+    match fs::read_to_string(&path) {
+        Ok(salt) => Ok(salt.trim().to_string()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let salt = crypto::new_salt();
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)
+                    .map_err(|e| tr!("Failed to write {}: {}", path.display(), e))?;
+            }
+            cache::write_private(&path, &format!("{}\n", salt))
+                .map_err(|e| tr!("Failed to write {}: {}", path.display(), e))?;
+            Ok(salt)
+        }
+        Err(e) => Err(tr!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+// Takes `--profile <name>` off the global options at the front of `args`.
+fn take_profile(args: &mut Vec<String>) -> Result<Option<String>, String> {
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with('-') {
+        match args[i].as_str() {
+            "--profile" => {
+                if i + 1 == args.len() {
+                    return Err(tr!("Missing value for '--profile'"));
+                }
+                args.remove(i);
+                return Ok(Some(args.remove(i)));
+            }
+            // Skip the values of the other options.
+            "--user" | "--backend" | "--output" => i += 2,
+            _ => i += 1,
+        }
+    }
+    Ok(None)
+}
+
+// Takes the global options, which come before the command name, off the front of `args`.
+fn parse_options(args: &mut Vec<String>, config: &Config) -> Result<Context, String> {
+    let mut user = None;
+    let mut backend = None;
+    let mut verbosity = match config.get("log.level") {
+        Some(level) => logger::verbosity(level).ok_or_else(|| {
+            tr!(
+                "Invalid log.level in the config file: {}, expected error, warn, info, debug or trace",
+                level
+            )
+        })?,
+        None => 0,
+    };
+    let mut dry_run = false;
+    let mut timing = false;
+    let mut absolute_times = config.get("display.times") == Some("absolute");
+    let mut utc = false;
+    let mut output = output::Format::Text;
+    let mut assume_yes = !io::stdin().is_terminal();
+    while let Some(arg) = args.first() {
+        if !arg.starts_with('-') {
+            break;
+        }
+        let option = args.remove(0);
+        match option.as_str() {
+            "--user" => {
+                if args.is_empty() {
+                    return Err(tr!("Missing value for '--user'"));
+                }
+                user = Some(args.remove(0));
+            }
+            "--backend" => {
+                if args.is_empty() {
+                    return Err(tr!("Missing value for '--backend'"));
+                }
+                backend = Some(args.remove(0));
+            }
+            "--output" => {
+                if args.is_empty() {
+                    return Err(tr!("Missing value for '--output'"));
+                }
+                let format = args.remove(0);
+                output = output::Format::parse(&format).ok_or_else(|| {
+                    tr!(
+                        "Unknown output format: {}, expected text, tsv, null or jsonl",
+                        format
+                    )
+                })?;
+            }
+            "-q" | "--quiet" => verbosity = -1,
+            "-n" | "--dry-run" => dry_run = true,
+            "--timing" => timing = true,
+            "-y" | "--yes" => assume_yes = true,
+            "--absolute" => absolute_times = true,
+            "--relative" => absolute_times = false,
+            "--utc" => utc = true,
+            "--verbose" => verbosity += 1,
+            "-v" | "-vv" | "-vvv" => verbosity += option.len() as i32 - 1,
+            _ => return Err(tr!("Unknown option: {}", option)),
         }
     }
+    let user = user
+        .or_else(|| config.get("user").map(|u| u.to_string()))
+        .or_else(|| env::var("USER").ok())
+        .or_else(|| env::var("USERNAME").ok())
+        .ok_or_else(|| {
+            tr!("Could not determine the user, use '--user' or set 'user' in the config file")
+        })?;
+    let backend = backend.or_else(|| config.get("database.backend").map(|b| b.to_string()));
+    let sqlite = check_backend(backend.as_deref().unwrap_or("postgres"), config)?;
+    let cipher = load_cipher(config)?;
+    let confirm_threshold = match config.get("confirm.threshold") {
+        Some(n) => n
+            .parse()
+            .map_err(|_| tr!("Invalid confirm.threshold in the config file: {}", n))?,
+        None => DEFAULT_CONFIRM_THRESHOLD,
+    };
+    let stale_after = match config.get("display.stale_after") {
+        Some("never") => None,
+        Some(d) => Some(
+            time::parse_duration(d)
+                .filter(|&s| s > 0)
+                .ok_or_else(|| tr!("Invalid display.stale_after in the config file: {}", d))?,
+        ),
+        None => Some(DEFAULT_STALE_AFTER),
+    };
+    let stale_after = stale_after.map(Duration::from_secs);
+    let remind_interval = match config.get("remind.interval") {
+        Some(n) => n
+            .parse()
+            .map_err(|_| tr!("Invalid remind.interval in the config file: {}", n))?,
+        None => DEFAULT_REMIND_INTERVAL,
+    };
+    let connect_retries = match config.get("database.connect_retries") {
+        Some(n) => n
+            .parse()
+            .map_err(|_| tr!("Invalid database.connect_retries in the config file: {}", n))?,
+        None => DEFAULT_CONNECT_RETRIES,
+    };
+    let timezone = match config.get("display.timezone") {
+        _ if utc => None,
+        Some(name) => Some(tz::Zone::load(name).ok_or_else(|| tr!("Unknown time zone: {}", name))?),
+        None => tz::Zone::local(),
+    };
+    let fields = fields::definitions(config)?;
+    let urgency = urgency::Coefficients::load(config, &fields)?;
+    Ok(Context {
+        user,
+        cipher,
+        connection: config
+            .get("database.connection")
+            .unwrap_or(DEFAULT_CONNECTION)
+            .to_string(),
+        keyring_password: config.get("database.keyring") == Some("true"),
+        auto_migrate: config.get("database.auto_migrate") == Some("true"),
+        role: config.get("database.role").map(|r| r.to_string()),
+        connect_retries,
+        verbosity,
+        dry_run,
+        timing,
+        absolute_times,
+        timezone,
+        utc,
+        fields,
+        output,
+        webhooks: webhook::Webhooks::load(config),
+        hooks: hooks::Hooks::load(config),
+        journal: journal::Journal::load(config),
+        assume_yes,
+        confirm_threshold,
+        urgency,
+        cleanup: cleanup::Policy::load(config)?,
+        stale_after,
+        list_format: config
+            .get("display.list_format")
+            .map(line_format::LineFormat::parse)
+            .transpose()?,
+        reports: report::load(config)?,
+        conflicts: conflict::Policies::load(config)?,
+        sqlite,
+        remote_url: config.get("remote_url").map(|u| u.to_string()),
+        remote_token: config
+            .get("remote_token")
+            .map(|t| t.to_string())
+            .or_else(|| {
+                config
+                    .get("remote_url")
+                    .and_then(|_| keyring::get("remote"))
+            }),
+        serve: serve::Settings::load(config)?,
+        profile: config.profile().map(|p| p.to_string()),
+        remind_interval: Duration::from_secs(remind_interval),
+        options: Vec::new(),
+    })
+}
+
+// The directory for the data kept outside the database, `todo` in the XDG data directory.
+fn data_dir() -> Option<PathBuf> {
+    env::var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .ok()
+        .map(|base| base.join("todo"))
+}
+
+// Returns the SQLite file to use instead of PostgreSQL, for the SQLite backend.
+fn check_backend(backend: &str, config: &Config) -> Result<Option<PathBuf>, String> {
+    match backend {
+        "postgres" => Ok(None),
+        "sqlite" => sqlite::path(config).map(Some).ok_or_else(|| {
+            tr!("Could not determine where to keep the SQLite database, set database.path in the config file")
+        }),
+        _ => Err(tr!(
+            "Unknown backend: {}, expected 'postgres' or 'sqlite'",
+            backend
+        )),
+    }
 }
 
 #[allow(dead_code)]
@@ -28,8 +754,37 @@ struct TodoRecord {
     id: i32,
     name: String,
     created_time: SystemTime,
-    completed: bool,
     completed_time: Option<SystemTime>,
+    due_time: Option<SystemTime>,
+    remind_before: Option<i32>,
+    reminded_time: Option<SystemTime>,
+    snoozed_until: Option<SystemTime>,
+    description: Option<String>,
+    project: Option<String>,
+    github_repo: Option<String>,
+    github_issue: Option<i32>,
+    github_url: Option<String>,
+    github_close: bool,
+    caldav_uid: Option<String>,
+    caldav_href: Option<String>,
+    caldav_etag: Option<String>,
+    caldav_state: Option<String>,
+    owner: Option<String>,
+    priority: Option<i16>,
+    status: Status,
+    context: Option<String>,
+    fields: Json,
+    uuid: String,
+    reviewed_time: Option<SystemTime>,
+    archived_time: Option<SystemTime>,
+    sort_order: Option<f64>,
+    pinned: bool,
+    estimate: Option<i32>,
+    assignee: Option<String>,
+    waiting_until: Option<SystemTime>,
+    waiting_for: Option<String>,
+    inbox: bool,
+    external_id: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -37,8 +792,37 @@ struct TodoRecordInsert {
     id: typed_query::WithDefault<i32>,
     name: String,
     created_time: typed_query::WithDefault<SystemTime>,
-    completed: typed_query::WithDefault<bool>,
     completed_time: Option<SystemTime>,
+    due_time: Option<SystemTime>,
+    remind_before: Option<i32>,
+    reminded_time: Option<SystemTime>,
+    snoozed_until: Option<SystemTime>,
+    description: Option<String>,
+    project: Option<String>,
+    github_repo: Option<String>,
+    github_issue: Option<i32>,
+    github_url: Option<String>,
+    github_close: typed_query::WithDefault<bool>,
+    caldav_uid: Option<String>,
+    caldav_href: Option<String>,
+    caldav_etag: Option<String>,
+    caldav_state: Option<String>,
+    owner: Option<String>,
+    priority: Option<i16>,
+    status: typed_query::WithDefault<Status>,
+    context: Option<String>,
+    fields: typed_query::WithDefault<Json>,
+    uuid: typed_query::WithDefault<String>,
+    reviewed_time: Option<SystemTime>,
+    archived_time: Option<SystemTime>,
+    sort_order: Option<f64>,
+    pinned: typed_query::WithDefault<bool>,
+    estimate: Option<i32>,
+    assignee: Option<String>,
+    waiting_until: Option<SystemTime>,
+    waiting_for: Option<String>,
+    inbox: typed_query::WithDefault<bool>,
+    external_id: Option<String>,
 }
 
 impl typed_query::FromRow for TodoRecord {
@@ -47,21 +831,79 @@ impl typed_query::FromRow for TodoRecord {
             id: row.get(0),
             name: row.get(1),
             created_time: row.get(2),
-            completed: row.get(3),
-            completed_time: row.get(4),
+            completed_time: row.get(3),
+            due_time: row.get(4),
+            remind_before: row.get(5),
+            reminded_time: row.get(6),
+            snoozed_until: row.get(7),
+            description: row.get(8),
+            project: row.get(9),
+            github_repo: row.get(10),
+            github_issue: row.get(11),
+            github_url: row.get(12),
+            github_close: row.get(13),
+            caldav_uid: row.get(14),
+            caldav_href: row.get(15),
+            caldav_etag: row.get(16),
+            caldav_state: row.get(17),
+            owner: row.get(18),
+            priority: row.get(19),
+            status: row.get(20),
+            context: row.get(21),
+            fields: row.get(22),
+            uuid: row.get(23),
+            reviewed_time: row.get(24),
+            archived_time: row.get(25),
+            sort_order: row.get(26),
+            pinned: row.get(27),
+            estimate: row.get(28),
+            assignee: row.get(29),
+            waiting_until: row.get(30),
+            waiting_for: row.get(31),
+            inbox: row.get(32),
+            external_id: row.get(33),
         }
     }
 }
 
 impl typed_query::ToSqlParams for TodoRecordInsert {
     fn to_sql_params(self) -> Vec<typed_query::Param> {
-        let mut v: Vec<typed_query::Param> = Vec::new();
-        v.push(typed_query::Param(Box::new(self.id)));
-        v.push(typed_query::Param(Box::new(self.name)));
-        v.push(typed_query::Param(Box::new(self.created_time)));
-        v.push(typed_query::Param(Box::new(self.completed)));
-        v.push(typed_query::Param(Box::new(self.completed_time)));
-        v
+        vec![
+            typed_query::Param(Box::new(self.id)),
+            typed_query::Param(Box::new(self.name)),
+            typed_query::Param(Box::new(self.created_time)),
+            typed_query::Param(Box::new(self.completed_time)),
+            typed_query::Param(Box::new(self.due_time)),
+            typed_query::Param(Box::new(self.remind_before)),
+            typed_query::Param(Box::new(self.reminded_time)),
+            typed_query::Param(Box::new(self.snoozed_until)),
+            typed_query::Param(Box::new(self.description)),
+            typed_query::Param(Box::new(self.project)),
+            typed_query::Param(Box::new(self.github_repo)),
+            typed_query::Param(Box::new(self.github_issue)),
+            typed_query::Param(Box::new(self.github_url)),
+            typed_query::Param(Box::new(self.github_close)),
+            typed_query::Param(Box::new(self.caldav_uid)),
+            typed_query::Param(Box::new(self.caldav_href)),
+            typed_query::Param(Box::new(self.caldav_etag)),
+            typed_query::Param(Box::new(self.caldav_state)),
+            typed_query::Param(Box::new(self.owner)),
+            typed_query::Param(Box::new(self.priority)),
+            typed_query::Param(Box::new(self.status)),
+            typed_query::Param(Box::new(self.context)),
+            typed_query::Param(Box::new(self.fields)),
+            typed_query::Param(Box::new(self.uuid)),
+            typed_query::Param(Box::new(self.reviewed_time)),
+            typed_query::Param(Box::new(self.archived_time)),
+            typed_query::Param(Box::new(self.sort_order)),
+            typed_query::Param(Box::new(self.pinned)),
+            typed_query::Param(Box::new(self.estimate)),
+            typed_query::Param(Box::new(self.assignee)),
+            typed_query::Param(Box::new(self.waiting_until)),
+            typed_query::Param(Box::new(self.waiting_for)),
+            typed_query::Param(Box::new(self.inbox)),
+            typed_query::Param(Box::new(self.external_id)),
+        ]
     }
 }
 
@@ -80,14 +922,130 @@ const TODO_TABLE: typed_query::Table<TodoColumns, TodoRecord> = typed_query::Tab
             name: "created_time",
             phantom: PhantomData,
         },
-        completed: typed_query::Field {
-            name: "completed",
-            phantom: PhantomData,
-        },
         completed_time: typed_query::Field {
             name: "completed_time",
             phantom: PhantomData,
         },
+        due_time: typed_query::Field {
+            name: "due_time",
+            phantom: PhantomData,
+        },
+        remind_before: typed_query::Field {
+            name: "remind_before",
+            phantom: PhantomData,
+        },
+        reminded_time: typed_query::Field {
+            name: "reminded_time",
+            phantom: PhantomData,
+        },
+        snoozed_until: typed_query::Field {
+            name: "snoozed_until",
+            phantom: PhantomData,
+        },
+        description: typed_query::Field {
+            name: "description",
+            phantom: PhantomData,
+        },
+        project: typed_query::Field {
+            name: "project",
+            phantom: PhantomData,
+        },
+        github_repo: typed_query::Field {
+            name: "github_repo",
+            phantom: PhantomData,
+        },
+        github_issue: typed_query::Field {
+            name: "github_issue",
+            phantom: PhantomData,
+        },
+        github_url: typed_query::Field {
+            name: "github_url",
+            phantom: PhantomData,
+        },
+        github_close: typed_query::Field {
+            name: "github_close",
+            phantom: PhantomData,
+        },
+        caldav_uid: typed_query::Field {
+            name: "caldav_uid",
+            phantom: PhantomData,
+        },
+        caldav_href: typed_query::Field {
+            name: "caldav_href",
+            phantom: PhantomData,
+        },
+        caldav_etag: typed_query::Field {
+            name: "caldav_etag",
+            phantom: PhantomData,
+        },
+        caldav_state: typed_query::Field {
+            name: "caldav_state",
+            phantom: PhantomData,
+        },
+        owner: typed_query::Field {
+            name: "owner",
+            phantom: PhantomData,
+        },
+        priority: typed_query::Field {
+            name: "priority",
+            phantom: PhantomData,
+        },
+        status: typed_query::Field {
+            name: "status",
+            phantom: PhantomData,
+        },
+        context: typed_query::Field {
+            name: "context",
+            phantom: PhantomData,
+        },
+        fields: typed_query::Field {
+            name: "fields",
+            phantom: PhantomData,
+        },
+        uuid: typed_query::Field {
+            name: "uuid",
+            phantom: PhantomData,
+        },
+        reviewed_time: typed_query::Field {
+            name: "reviewed_time",
+            phantom: PhantomData,
+        },
+        archived_time: typed_query::Field {
+            name: "archived_time",
+            phantom: PhantomData,
+        },
+        sort_order: typed_query::Field {
+            name: "sort_order",
+            phantom: PhantomData,
+        },
+        pinned: typed_query::Field {
+            name: "pinned",
+            phantom: PhantomData,
+        },
+        estimate: typed_query::Field {
+            name: "estimate",
+            phantom: PhantomData,
+        },
+        assignee: typed_query::Field {
+            name: "assignee",
+            phantom: PhantomData,
+        },
+        waiting_until: typed_query::Field {
+            name: "waiting_until",
+            phantom: PhantomData,
+        },
+        waiting_for: typed_query::Field {
+            name: "waiting_for",
+            phantom: PhantomData,
+        },
+        inbox: typed_query::Field {
+            name: "inbox",
+            phantom: PhantomData,
+        },
+        external_id: typed_query::Field {
+            name: "external_id",
+            phantom: PhantomData,
+        },
     },
     phantom: PhantomData,
 };
@@ -97,93 +1055,1930 @@ struct TodoColumns {
     id: typed_query::Field<i32>,
     name: typed_query::Field<String>,
     created_time: typed_query::Field<SystemTime>,
-    completed: typed_query::Field<bool>,
     completed_time: typed_query::Field<SystemTime>,
+    due_time: typed_query::Field<SystemTime>,
+    remind_before: typed_query::Field<i32>,
+    reminded_time: typed_query::Field<SystemTime>,
+    snoozed_until: typed_query::Field<SystemTime>,
+    description: typed_query::Field<String>,
+    project: typed_query::Field<String>,
+    github_repo: typed_query::Field<String>,
+    github_issue: typed_query::Field<i32>,
+    github_url: typed_query::Field<String>,
+    github_close: typed_query::Field<bool>,
+    caldav_uid: typed_query::Field<String>,
+    caldav_href: typed_query::Field<String>,
+    caldav_etag: typed_query::Field<String>,
+    caldav_state: typed_query::Field<String>,
+    owner: typed_query::Field<String>,
+    priority: typed_query::Field<Option<i16>>,
+    status: typed_query::Field<Status>,
+    context: typed_query::Field<Option<String>>,
+    fields: typed_query::Field<Json>,
+    uuid: typed_query::Field<String>,
+    reviewed_time: typed_query::Field<Option<SystemTime>>,
+    archived_time: typed_query::Field<Option<SystemTime>>,
+    sort_order: typed_query::Field<Option<f64>>,
+    pinned: typed_query::Field<bool>,
+    estimate: typed_query::Field<i32>,
+    assignee: typed_query::Field<String>,
+    waiting_until: typed_query::Field<SystemTime>,
+    waiting_for: typed_query::Field<String>,
+    inbox: typed_query::Field<bool>,
+    external_id: typed_query::Field<String>,
 }
 
-fn create_tables(client: &mut Client) {
-    client
-        .execute(
-            "
-        create table if not exists todo (
-            id serial primary key,
-            name text not null,
-            created_time timestamp with time zone not null default now(),
-            completed boolean not null default false,
-            completed_time timestamp with time zone null
-        )",
-            &[],
-        )
-        .unwrap();
+/// Attributes of a new task, given as options to `add` or stored in a template.
+#[derive(Debug, Default)]
+struct TaskOptions {
+    due: Option<String>,
+    note: Option<String>,
+    project: Option<String>,
+    priority: Option<i16>,
+    context: Option<String>,
+    /// How long the task is expected to take, such as `90m`.
+    estimate: Option<String>,
+    /// Custom fields as `name=value`.
+    fields: Vec<String>,
+}
+
+/// The order of `list`: by creation (the default), due time, priority, urgency or the order set
+/// with `move`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortKey {
+    Created,
+    Due,
+    Priority,
+    Urgency,
+    Manual,
 }
 
 #[derive(Debug)]
 enum Command {
-    Add { name: String },
-    List,
-    Complete { id: i32 },
-}
-
-fn parse_args(mut args: env::Args) -> Result<Command, String> {
-    match args.nth(1) {
-        Some(s) => match s.as_str() {
-            "add" => args
-                .nth(0)
-                .map(|arg| Command::Add { name: arg })
-                .ok_or("Missing argument to 'add' command".to_string()),
-            "list" => Ok(Command::List),
-            "complete" => {
-                let id_str = args
-                    .nth(0)
-                    .ok_or("Missing argument to 'complete' command".to_string())?;
-                let id = id_str.parse::<i32>().map_err(|e| {
-                    format!("Failed to parse argument as number: {}, {}", id_str, e)
-                })?;
-                Ok(Command::Complete { id })
+    Add {
+        name: Option<String>,
+        template: Option<String>,
+        options: TaskOptions,
+        /// Print nothing on success.
+        quiet: bool,
+        /// Print the created task as JSON.
+        json: bool,
+        /// Don't add the task if an open task with the same name exists.
+        unique: bool,
+    },
+    /// Records a task that is done already, completed `at` (now by default).
+    Log {
+        name: String,
+        at: Option<String>,
+        options: TaskOptions,
+    },
+    List {
+        statuses: Vec<Status>,
+        fields: Vec<String>,
+        sort: SortKey,
+        archived: bool,
+        pinned: bool,
+        stale: bool,
+        assigned: Option<assign::Filter>,
+        /// Only tasks due before this time, as given.
+        due_before: Option<String>,
+        format: Option<line_format::LineFormat>,
+    },
+    Watch {
+        statuses: Vec<Status>,
+        fields: Vec<String>,
+        sort: SortKey,
+        archived: bool,
+        pinned: bool,
+        stale: bool,
+        assigned: Option<assign::Filter>,
+        due_before: Option<String>,
+        format: Option<line_format::LineFormat>,
+        interval: u64,
+    },
+    Agenda {
+        days: i64,
+    },
+    Burndown {
+        weeks: i32,
+    },
+    Review {
+        days: i32,
+    },
+    Capture {
+        name: String,
+    },
+    Triage,
+    Gc,
+    Encrypt,
+    Show {
+        id: TaskRef,
+    },
+    Edit {
+        id: TaskRef,
+        name: Option<String>,
+        options: TaskOptions,
+    },
+    Note {
+        id: TaskRef,
+        text: String,
+    },
+    Annotate {
+        id: TaskRef,
+        text: String,
+    },
+    Duplicate {
+        id: TaskRef,
+        name: Option<String>,
+    },
+    Modify {
+        filter: filter::Filter,
+        changes: Vec<String>,
+    },
+    Report {
+        name: Option<String>,
+    },
+    Move {
+        id: TaskRef,
+        position: ordering::Position,
+    },
+    Pin {
+        ids: Vec<TaskRef>,
+        pinned: bool,
+    },
+    Attachment {
+        action: attachment::Action,
+    },
+    Complete {
+        ids: Vec<TaskRef>,
+    },
+    CompleteByName {
+        query: String,
+    },
+    Reopen {
+        ids: Vec<TaskRef>,
+    },
+    Cancel {
+        ids: Vec<TaskRef>,
+    },
+    Remove {
+        ids: Vec<TaskRef>,
+        force: bool,
+    },
+    Restore {
+        ids: Vec<TaskRef>,
+    },
+    Trash {
+        action: trash::Action,
+    },
+    Stats,
+    Projects,
+    Remind {
+        daemon: bool,
+        interval: Option<u64>,
+    },
+    RemindBefore {
+        id: TaskRef,
+        before: u64,
+    },
+    Snooze {
+        id: TaskRef,
+        duration: u64,
+    },
+    Wait {
+        id: TaskRef,
+        until: String,
+        person: Option<String>,
+    },
+    Start {
+        id: TaskRef,
+    },
+    Stop,
+    Timesheet {
+        period: &'static str,
+    },
+    ImportGithub {
+        repo: String,
+        assignee: Option<String>,
+        close_on_complete: bool,
+    },
+    ImportTaskwarrior {
+        path: PathBuf,
+    },
+    ImportIcal {
+        path: PathBuf,
+        events: bool,
+    },
+    ImportArchive {
+        path: PathBuf,
+    },
+    SyncCaldav {
+        url: String,
+        user: Option<String>,
+    },
+    SyncStatus,
+    SyncGit {
+        repo: PathBuf,
+    },
+    Migrate {
+        action: migrations::Action,
+    },
+    Context {
+        action: context::Action,
+    },
+    Template {
+        action: template::Action,
+    },
+    Next {
+        n: usize,
+    },
+    History {
+        id: TaskRef,
+    },
+    Doctor,
+    Man,
+    Auth {
+        action: auth::Action,
+    },
+    Depend {
+        id: TaskRef,
+        on: Vec<TaskRef>,
+        remove: bool,
+    },
+    Blocked,
+    Prompt,
+    Assign {
+        ids: Vec<TaskRef>,
+        assignee: Option<String>,
+    },
+    Unblockable {
+        id: TaskRef,
+    },
+    Open {
+        id: TaskRef,
+        print: bool,
+    },
+    Init {
+        options: init::Options,
+    },
+    Export {
+        format: export::Format,
+    },
+    Serve {
+        listen: String,
+        pool_size: usize,
+        /// Whether to serve the API over gRPC instead of HTTP.
+        grpc: bool,
+    },
+    /// An external `todo-<name>` command.
+    Plugin {
+        path: PathBuf,
+        args: Vec<String>,
+    },
+}
+
+const DEFAULT_REMIND_INTERVAL: u64 = 60;
+const DEFAULT_AGENDA_DAYS: i64 = 7;
+const DEFAULT_BURNDOWN_WEEKS: i32 = 8;
+const DEFAULT_REVIEW_DAYS: i32 = 14;
+const DEFAULT_WATCH_INTERVAL: u64 = 60;
+const DEFAULT_STALE_AFTER: u64 = 30 * 24 * 60 * 60;
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+const DEFAULT_POOL_SIZE: usize = 4;
+// The filter terms of `list` and `watch`, such as `status:open`, and the options they stand for.
+const LIST_TERMS: &[(&str, &str)] = &[
+    ("status", "--status"),
+    ("due.before", "--due-before"),
+    ("assigned", "--assigned-to"),
+    ("sort", "--sort"),
+];
+// The most ids a range such as 3-7 can have.
+const MAX_RANGE: i64 = 1000;
+const DEFAULT_SNOOZE: u64 = 10 * 60;
+
+fn parse_args(args: Vec<String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let command = args.next().ok_or_else(|| tr!("No command found"))?;
+    let mut args = Args::new(&command, args);
+    match command.as_str() {
+        "add" => {
+            let template = args.value("--from-template")?;
+            let options = task_options(&mut args)?;
+            let quiet = args.flag("--quiet");
+            let json = args.flag("--json");
+            let unique = args.flag("--unique");
+            // The template's name is used when no name is given.
+            let name = match template {
+                Some(_) => args.optional()?,
+                None => Some(args.single("name")?),
+            };
+            Ok(Command::Add {
+                name,
+                template,
+                options,
+                quiet,
+                json,
+                unique,
+            })
+        }
+        "log" => {
+            let at = args.value("--at")?;
+            let options = task_options(&mut args)?;
+            Ok(Command::Log {
+                name: args.single("name")?,
+                at,
+                options,
+            })
+        }
+        "list" => {
+            args.terms(LIST_TERMS);
+            let statuses = match args.value("--status")? {
+                Some(s) => parse_status_filter(&s)?,
+                None => status::OPEN.to_vec(),
+            };
+            let fields = field_options(&mut args)?;
+            let sort = sort_option(&mut args)?;
+            let archived = args.flag("--archived");
+            let pinned = args.flag("--pinned");
+            let stale = args.flag("--stale");
+            let assigned = args
+                .value("--assigned-to")?
+                .map(|a| assign::Filter::parse(&a));
+            let due_before = args.value("--due-before")?;
+            let format = args
+                .value("--format")?
+                .map(|f| line_format::LineFormat::parse(&f))
+                .transpose()?;
+            args.none()?;
+            Ok(Command::List {
+                statuses,
+                fields,
+                sort,
+                archived,
+                pinned,
+                stale,
+                assigned,
+                due_before,
+                format,
+            })
+        }
+        "watch" => {
+            args.terms(LIST_TERMS);
+            let statuses = match args.value("--status")? {
+                Some(s) => parse_status_filter(&s)?,
+                None => status::OPEN.to_vec(),
+            };
+            let fields = field_options(&mut args)?;
+            let sort = sort_option(&mut args)?;
+            let archived = args.flag("--archived");
+            let pinned = args.flag("--pinned");
+            let stale = args.flag("--stale");
+            let assigned = args
+                .value("--assigned-to")?
+                .map(|a| assign::Filter::parse(&a));
+            let due_before = args.value("--due-before")?;
+            let format = args
+                .value("--format")?
+                .map(|f| line_format::LineFormat::parse(&f))
+                .transpose()?;
+            let interval = args.parsed("--interval")?.unwrap_or(DEFAULT_WATCH_INTERVAL);
+            args.none()?;
+            Ok(Command::Watch {
+                statuses,
+                fields,
+                sort,
+                archived,
+                pinned,
+                stale,
+                assigned,
+                due_before,
+                format,
+                interval,
+            })
+        }
+        "agenda" => {
+            let days = args.parsed("--days")?.unwrap_or(DEFAULT_AGENDA_DAYS);
+            args.none()?;
+            Ok(Command::Agenda { days })
+        }
+        "burndown" => {
+            let weeks = args.parsed("--weeks")?.unwrap_or(DEFAULT_BURNDOWN_WEEKS);
+            if weeks < 1 {
+                return Err(tr!("Invalid value for '--weeks': {}", weeks));
+            }
+            args.none()?;
+            Ok(Command::Burndown { weeks })
+        }
+        "review" => {
+            let days = args.parsed("--days")?.unwrap_or(DEFAULT_REVIEW_DAYS);
+            args.none()?;
+            Ok(Command::Review { days })
+        }
+        "capture" => Ok(Command::Capture {
+            name: args.single("text")?,
+        }),
+        "triage" => {
+            args.none()?;
+            Ok(Command::Triage)
+        }
+        "gc" => {
+            args.none()?;
+            Ok(Command::Gc)
+        }
+        "encrypt" => {
+            args.none()?;
+            Ok(Command::Encrypt)
+        }
+        "next" => {
+            let n = args.parsed("--n")?.unwrap_or(1);
+            args.none()?;
+            Ok(Command::Next { n })
+        }
+        "show" => Ok(Command::Show {
+            id: parse_id(&args.single("id")?)?,
+        }),
+        "history" => Ok(Command::History {
+            id: parse_id(&args.single("id")?)?,
+        }),
+        "edit" => {
+            let name = args.value("--name")?;
+            let options = task_options(&mut args)?;
+            Ok(Command::Edit {
+                id: parse_id(&args.single("id")?)?,
+                name,
+                options,
+            })
+        }
+        "note" => match args.rest()?.as_slice() {
+            [id, text] => Ok(Command::Note {
+                id: parse_id(id)?,
+                text: text.clone(),
+            }),
+            _ => Err(tr!("Expected an id and text to 'note' command")),
+        },
+        "annotate" => match args.rest()?.as_slice() {
+            [id, text] => Ok(Command::Annotate {
+                id: parse_id(id)?,
+                text: text.clone(),
+            }),
+            _ => Err(tr!("Expected an id and text to 'annotate' command")),
+        },
+        "duplicate" => {
+            let name = args.value("--name")?;
+            Ok(Command::Duplicate {
+                id: parse_id(&args.single("id")?)?,
+                name,
+            })
+        }
+        "modify" => {
+            let mut changes = Vec::new();
+            while let Some(change) = args.value("--set")? {
+                changes.push(change);
+            }
+            let terms = args.rest()?;
+            if terms.is_empty() {
+                return Err(tr!(
+                    "Expected a filter to 'modify' command, such as ids or project:name"
+                ));
+            }
+            Ok(Command::Modify {
+                filter: filter::Filter::parse(&terms)?,
+                changes,
+            })
+        }
+        "depend" | "undepend" => {
+            let id = args
+                .subcommand()
+                .ok_or_else(|| tr!("Missing argument to '{}' command", command))?;
+            Ok(Command::Depend {
+                id: parse_id(&id)?,
+                on: parse_ids(args)?,
+                remove: command == "undepend",
+            })
+        }
+        "prompt" => {
+            args.none()?;
+            Ok(Command::Prompt)
+        }
+        "blocked" => {
+            args.none()?;
+            Ok(Command::Blocked)
+        }
+        "assign" => match args.rest()?.as_slice() {
+            [id, user] => Ok(Command::Assign {
+                ids: vec![parse_id(id)?],
+                assignee: Some(user.clone()),
+            }),
+            _ => Err(tr!("Expected an id and user to 'assign' command")),
+        },
+        "unassign" => Ok(Command::Assign {
+            ids: parse_ids(args)?,
+            assignee: None,
+        }),
+        "unblockable" => Ok(Command::Unblockable {
+            id: parse_id(&args.single("id")?)?,
+        }),
+        "report" => Ok(Command::Report {
+            name: args.optional()?,
+        }),
+        "pin" | "unpin" => Ok(Command::Pin {
+            pinned: command == "pin",
+            ids: parse_ids(args)?,
+        }),
+        "move" => {
+            let before = args.value("--before")?;
+            let position = match (before, args.flag("--top"), args.flag("--bottom")) {
+                (Some(other), false, false) => ordering::Position::Before(parse_id(&other)?),
+                (None, true, false) => ordering::Position::Top,
+                (None, false, true) => ordering::Position::Bottom,
+                _ => {
+                    return Err(tr!(
+                        "Expected one of '--before <id>', '--top' or '--bottom'"
+                    ))
+                }
+            };
+            Ok(Command::Move {
+                id: parse_id(&args.single("id")?)?,
+                position,
+            })
+        }
+        "attach" => {
+            let link = args.flag("--link");
+            let to = args.value("--to")?.map(PathBuf::from);
+            let action = match args.subcommand().as_deref() {
+                Some("get") => attachment::Action::Get {
+                    attachment: args
+                        .single("attachment id")?
+                        .parse()
+                        .map_err(|_| tr!("Not an attachment id"))?,
+                    to,
+                },
+                Some(id) => attachment::Action::Add {
+                    id: parse_id(id)?,
+                    path: PathBuf::from(args.single("path")?),
+                    link,
+                },
+                None => return Err(tr!("Expected 'attach <id> <path>' or 'attach get <id>'")),
+            };
+            Ok(Command::Attachment { action })
+        }
+        "attachments" => Ok(Command::Attachment {
+            action: attachment::Action::List {
+                id: parse_id(&args.single("id")?)?,
+            },
+        }),
+        "complete" => match args.value("--name")? {
+            Some(query) => {
+                args.none()?;
+                Ok(Command::CompleteByName { query })
+            }
+            None => Ok(Command::Complete {
+                ids: parse_ids(args)?,
+            }),
+        },
+        "reopen" => Ok(Command::Reopen {
+            ids: parse_ids(args)?,
+        }),
+        "cancel" => Ok(Command::Cancel {
+            ids: parse_ids(args)?,
+        }),
+        "remove" => {
+            let force = args.flag("--force");
+            Ok(Command::Remove {
+                ids: parse_ids(args)?,
+                force,
+            })
+        }
+        "restore" => Ok(Command::Restore {
+            ids: parse_ids(args)?,
+        }),
+        "trash" => {
+            let action = match args.rest()?.as_slice() {
+                [] => trash::Action::List,
+                [a] if a == "list" => trash::Action::List,
+                [a] if a == "empty" => trash::Action::Empty,
+                _ => return Err(tr!("Expected 'trash [list|empty]'")),
+            };
+            Ok(Command::Trash { action })
+        }
+        "stats" => {
+            args.none()?;
+            Ok(Command::Stats)
+        }
+        "projects" => {
+            args.none()?;
+            Ok(Command::Projects)
+        }
+        "remind" => match args.value("--before")? {
+            Some(before) => Ok(Command::RemindBefore {
+                before: parse_duration_arg(&before)?,
+                id: parse_id(&args.single("id")?)?,
+            }),
+            None => {
+                let daemon = args.flag("--daemon");
+                let interval = args.parsed("--interval")?;
+                args.none()?;
+                Ok(Command::Remind { daemon, interval })
+            }
+        },
+        "snooze" => {
+            let rest = args.rest()?;
+            match rest.as_slice() {
+                [id] => Ok(Command::Snooze {
+                    id: parse_id(id)?,
+                    duration: DEFAULT_SNOOZE,
+                }),
+                [id, duration] => Ok(Command::Snooze {
+                    id: parse_id(id)?,
+                    duration: parse_duration_arg(duration)?,
+                }),
+                _ => Err(tr!(
+                    "Expected an id and optional duration to 'snooze' command"
+                )),
+            }
+        }
+        "wait" => {
+            let until = args
+                .value("--until")?
+                .ok_or_else(|| tr!("Missing '--until' option to 'wait'"))?;
+            let person = args.value("--for")?;
+            Ok(Command::Wait {
+                id: parse_id(&args.single("id")?)?,
+                until,
+                person,
+            })
+        }
+        "start" => Ok(Command::Start {
+            id: parse_id(&args.single("id")?)?,
+        }),
+        "stop" => {
+            args.none()?;
+            Ok(Command::Stop)
+        }
+        "timesheet" => {
+            let period = if args.flag("--day") {
+                "day"
+            } else if args.flag("--month") {
+                "month"
+            } else {
+                args.flag("--week");
+                "week"
+            };
+            args.none()?;
+            Ok(Command::Timesheet { period })
+        }
+        "import" => {
+            match args.value("--format")?.as_deref() {
+                Some("taskwarrior") => {
+                    return Ok(Command::ImportTaskwarrior {
+                        path: PathBuf::from(args.single("file")?),
+                    })
+                }
+                Some("ics") => {
+                    let events = args.flag("--events");
+                    return Ok(Command::ImportIcal {
+                        path: PathBuf::from(args.single("file")?),
+                        events,
+                    });
+                }
+                Some("archive") => {
+                    return Ok(Command::ImportArchive {
+                        path: PathBuf::from(args.single("file")?),
+                    })
+                }
+                Some(format) => return Err(tr!("Unknown import format: {}", format)),
+                None => {}
+            }
+            let repo = args.value("--repo")?;
+            let assignee = args.value("--assignee")?;
+            let close_on_complete = args.flag("--close-on-complete");
+            match (args.single("source")?.as_str(), repo) {
+                ("github", Some(repo)) => Ok(Command::ImportGithub {
+                    repo,
+                    assignee,
+                    close_on_complete,
+                }),
+                ("github", None) => Err(tr!("Missing '--repo' option to 'import github'")),
+                (source, _) => Err(tr!("Unknown import source: {}", source)),
             }
-            cmd => Err(format!("Unknown command: {}", cmd)),
+        }
+        "sync" => {
+            let user = args.value("--user")?;
+            match args.rest()?.as_slice() {
+                [kind, url] if kind == "caldav" => Ok(Command::SyncCaldav {
+                    url: url.clone(),
+                    user,
+                }),
+                [kind, repo] if kind == "git" => Ok(Command::SyncGit {
+                    repo: PathBuf::from(repo),
+                }),
+                [kind] if kind == "status" => Ok(Command::SyncStatus),
+                [kind, ..] if kind != "caldav" && kind != "git" && kind != "status" => {
+                    Err(tr!("Unknown sync target: {}", kind))
+                }
+                _ => Err(tr!(
+                    "Expected 'sync caldav <url>', 'sync git <path>' or 'sync status'"
+                )),
+            }
+        }
+        "migrate" => {
+            let to = args.parsed("--to")?;
+            let action = match args.rest()?.as_slice() {
+                [] => migrations::Action::Up { to },
+                [a] if a == "up" => migrations::Action::Up { to },
+                [a] if a == "down" => migrations::Action::Down { to },
+                [a] if a == "status" => migrations::Action::Status,
+                _ => return Err(tr!("Expected 'migrate [up|down|status]'")),
+            };
+            Ok(Command::Migrate { action })
+        }
+        "context" => {
+            let action = match args.rest()?.as_slice() {
+                [] => context::Action::Show,
+                [a] if a == "show" => context::Action::Show,
+                [a, name] if a == "set" => context::Action::Set {
+                    name: context::parse_name(name)?,
+                },
+                [a] if a == "clear" => context::Action::Clear,
+                _ => return Err(tr!("Expected 'context [show|set <name>|clear]'")),
+            };
+            Ok(Command::Context { action })
+        }
+        "contexts" => {
+            let filter = args.value("--filter")?;
+            let action = match args.rest()?.as_slice() {
+                [] => context::Action::List,
+                [a] if a == "list" => context::Action::List,
+                [a, name] if a == "add" => context::Action::Add {
+                    name: context::parse_name(name)?,
+                    filter,
+                },
+                [a, name] if a == "delete" => context::Action::Delete {
+                    name: context::parse_name(name)?,
+                },
+                _ => {
+                    return Err(tr!(
+                        "Expected 'contexts [list|add <name> [--filter <terms>]|delete <name>]'"
+                    ))
+                }
+            };
+            Ok(Command::Context { action })
+        }
+        "template" => match args.subcommand().as_deref() {
+            Some("add") => {
+                let options = task_options(&mut args)?;
+                Ok(Command::Template {
+                    action: template::Action::Add {
+                        name: args.single("name")?,
+                        options,
+                    },
+                })
+            }
+            Some("list") => {
+                args.none()?;
+                Ok(Command::Template {
+                    action: template::Action::List,
+                })
+            }
+            Some("remove") => Ok(Command::Template {
+                action: template::Action::Remove {
+                    name: args.single("name")?,
+                },
+            }),
+            _ => Err(tr!("Expected 'template [add|list|remove]'")),
+        },
+        "serve" => {
+            let listen = args
+                .value("--listen")?
+                .unwrap_or_else(|| DEFAULT_LISTEN.to_string());
+            let pool_size = args.parsed("--pool-size")?.unwrap_or(DEFAULT_POOL_SIZE);
+            let grpc = args.flag("--grpc");
+            args.none()?;
+            Ok(Command::Serve {
+                listen,
+                pool_size,
+                grpc,
+            })
+        }
+        "doctor" => {
+            args.none()?;
+            Ok(Command::Doctor)
+        }
+        "man" => {
+            args.none()?;
+            Ok(Command::Man)
+        }
+        "auth" => match args.subcommand().as_deref() {
+            Some("set") => Ok(Command::Auth {
+                action: auth::Action::Set {
+                    name: auth::parse_name(&args.single("name")?)?,
+                },
+            }),
+            Some("unset") => Ok(Command::Auth {
+                action: auth::Action::Unset {
+                    name: auth::parse_name(&args.single("name")?)?,
+                },
+            }),
+            _ => Err(tr!("Expected 'auth [set|unset] <name>'")),
+        },
+        "open" => {
+            let print = args.flag("--print");
+            Ok(Command::Open {
+                id: parse_id(&args.single("id")?)?,
+                print,
+            })
+        }
+        "init" => {
+            let options = init::Options {
+                connection: args.value("--connection")?,
+                example: args.flag("--example"),
+                force: args.flag("--force"),
+            };
+            args.none()?;
+            Ok(Command::Init { options })
+        }
+        "export" => {
+            let format = match args.value("--format")? {
+                Some(f) => export::Format::parse(&f)
+                    .ok_or_else(|| tr!("Unknown export format: {} (expected sql or archive)", f))?,
+                None => export::Format::Sql,
+            };
+            args.none()?;
+            Ok(Command::Export { format })
+        }
+        cmd => match plugin::find(cmd) {
+            Some(path) => Ok(Command::Plugin {
+                path,
+                args: args.all(),
+            }),
+            None => Err(tr!("Unknown command: {}", cmd)),
         },
-        None => Err("No command found".to_string()),
     }
 }
 
+// Parses a task id or alias.
+fn parse_id(s: &str) -> Result<TaskRef, String> {
+    TaskRef::parse(s).ok_or_else(|| tr!("Not a task id, alias or UUID: {}", s))
+}
+
+// Priorities are stored as 1 (high) to 3 (low), so sorting puts the most important first.
+fn parse_priority(s: &str) -> Result<i16, String> {
+    match s {
+        "high" | "h" | "1" => Ok(1),
+        "medium" | "m" | "2" => Ok(2),
+        "low" | "l" | "3" => Ok(3),
+        _ => Err(tr!("Invalid priority: {}, expected high, medium or low", s)),
+    }
+}
+
+fn task_options(args: &mut Args) -> Result<TaskOptions, String> {
+    Ok(TaskOptions {
+        due: args.value("--due")?,
+        note: args.value("--note")?,
+        project: args.value("--project")?,
+        priority: args
+            .value("--priority")?
+            .map(|p| parse_priority(&p))
+            .transpose()?,
+        context: args
+            .value("--context")?
+            .map(|c| context::parse_name(&c))
+            .transpose()?,
+        estimate: args.value("--estimate")?,
+        fields: field_options(args)?,
+    })
+}
+
+// Takes every `--field name=value` option, which are checked against the definitions later.
+fn field_options(args: &mut Args) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    while let Some(field) = args.value("--field")? {
+        fields.push(field);
+    }
+    Ok(fields)
+}
+
+// Parses the statuses to list: a single status, `open` (the default) or `all`.
+fn parse_status_filter(s: &str) -> Result<Vec<Status>, String> {
+    match s {
+        "open" => Ok(status::OPEN.to_vec()),
+        "all" => Ok(Status::ALL.to_vec()),
+        _ => Status::parse(s).map(|status| vec![status]).ok_or_else(|| {
+            tr!(
+                "Invalid status: {}, expected todo, in-progress, waiting, done, cancelled, open or all",
+                s
+            )
+        }),
+    }
+}
+
+fn sort_option(args: &mut Args) -> Result<SortKey, String> {
+    parse_sort(args.value("--sort")?.as_deref())
+}
+
+fn parse_sort(key: Option<&str>) -> Result<SortKey, String> {
+    match key {
+        None | Some("created") => Ok(SortKey::Created),
+        Some("due") => Ok(SortKey::Due),
+        Some("priority") => Ok(SortKey::Priority),
+        Some("urgency") => Ok(SortKey::Urgency),
+        Some("manual") => Ok(SortKey::Manual),
+        Some(s) => Err(tr!(
+            "Invalid sort key: {}, expected created, due, priority, urgency or manual",
+            s
+        )),
+    }
+}
+
+// Whether `list` shows `row`, of the statuses and context listed.
+fn list_matches(
+    row: &TodoRecord,
+    archived: bool,
+    pinned: bool,
+    stale: Option<Duration>,
+    fields: &[(String, Json)],
+    assigned: Option<&assign::Filter>,
+    due_before: Option<SystemTime>,
+) -> bool {
+    (archived || row.archived_time.is_none())
+        && (!pinned || row.pinned)
+        && stale.is_none_or(|after| is_stale(row, after, SystemTime::now()))
+        && assigned.is_none_or(|a| a.matches(row))
+        && due_before.is_none_or(|before| row.due_time.is_some_and(|due| due < before))
+        && fields
+            .iter()
+            .all(|(name, value)| row.fields.get(name) == Some(value))
+}
+
+// Whether `row` has been open for longer than `after` without being reviewed.
+fn is_stale(row: &TodoRecord, after: Duration, now: SystemTime) -> bool {
+    let since = row
+        .reviewed_time
+        .map_or(row.created_time, |r| r.max(row.created_time));
+    status::OPEN.contains(&row.status) && since + after < now
+}
+
+// The threshold of `list --stale`.
+fn stale_filter(ctx: &Context, stale: bool) -> Result<Option<Duration>, Error> {
+    if !stale {
+        return Ok(None);
+    }
+    ctx.stale_after.map(Some).ok_or_else(|| {
+        Error::Usage(tr!(
+            "'--stale' needs display.stale_after in the config file, which is set to never"
+        ))
+    })
+}
+
+// Prints the tasks of `list`. With an active context, all tasks are in it; `tracking` is the
+// task whose time is being tracked, with the time so far.
+fn print_list(
+    ctx: &Context,
+    rows: Vec<TodoRecord>,
+    sort: SortKey,
+    format: Option<&line_format::LineFormat>,
+    active: &Option<String>,
+    tracking: Option<(i32, f64)>,
+) {
+    let now = SystemTime::now();
+    let mut rows: Vec<(f64, TodoRecord)> = rows
+        .into_iter()
+        .map(|row| (urgency::score(&row, now, &ctx.urgency), row))
+        .collect();
+    sort_tasks(&mut rows, sort);
+    // The configured format is for people, so scripts still get records.
+    let format = format.or(ctx.list_format.as_ref().filter(|_| ctx.output.is_text()));
+    let color = io::stdout().is_terminal();
+    for (score, row) in rows {
+        if let Some(format) = format {
+            println!("{}", format.render(ctx, &row, score));
+            continue;
+        }
+        if ctx.output == output::Format::Jsonl {
+            println!("{}", task_json(ctx, &row));
+            continue;
+        }
+        if !ctx.output.is_text() {
+            output::record(
+                ctx.output,
+                &[
+                    &row.id.to_string(),
+                    &alias::encode(row.id),
+                    row.status.as_str(),
+                    &ctx.decrypt(&row.name, Location::task("name", &row.uuid)),
+                    &time::format_iso(row.created_time),
+                    &row.due_time.map(time::format_iso).unwrap_or_default(),
+                    row.priority.map(priority_key).unwrap_or_default(),
+                    row.project.as_deref().unwrap_or_default(),
+                    row.context.as_deref().unwrap_or_default(),
+                    &urgency::format(score),
+                    if row.pinned { "pinned" } else { "" },
+                    &row.estimate.map(|e| e.to_string()).unwrap_or_default(),
+                ],
+            );
+            continue;
+        }
+        let mut details = vec![ctx.format_time(row.created_time)];
+        if ctx
+            .stale_after
+            .is_some_and(|after| is_stale(&row, after, now))
+        {
+            if color {
+                details[0] = format!("{}{}{}", YELLOW, details[0], RESET);
+            }
+            details.push(tr!("stale"));
+        }
+        if row.inbox {
+            details.push(tr!("inbox"));
+        }
+        if row.pinned {
+            details.push(tr!("pinned"));
+        }
+        if sort == SortKey::Urgency {
+            details.push(tr!("urgency {}", urgency::format(score)));
+        }
+        if row.status != Status::Todo {
+            details.push(row.status.label());
+        }
+        // With an active context, all tasks shown are in it.
+        if let (None, Some(name)) = (&active, &row.context) {
+            details.push(format!("@{}", name));
+        }
+        if let Some(priority) = row.priority {
+            details.push(tr!("{} priority", priority_name(priority)));
+        }
+        if let Some(due) = row.due_time {
+            details.push(tr!("due {}", ctx.format_time(due)));
+        }
+        if let Some(estimate) = row.estimate {
+            details.push(tr!("estimate {}", time::format_duration(estimate as f64)));
+        }
+        details.extend(fields::describe(&ctx.fields, &row.fields));
+        if let Some((_, seconds)) = tracking.filter(|(id, _)| *id == row.id) {
+            details.push(tr!("tracking, {}", time::format_duration(seconds)));
+        }
+        println!(
+            "{} ({}): {} ({})",
+            row.id,
+            alias::encode(row.id),
+            ctx.decrypt(&row.name, Location::task("name", &row.uuid)),
+            details.join(", ")
+        );
+    }
+}
+
+// Sorts tasks, listed by creation with their urgency, by `sort`. Pinned tasks come first,
+// whatever the order.
+fn sort_tasks(rows: &mut [(f64, TodoRecord)], sort: SortKey) {
+    // Tasks without a due time or priority go last; the sort keeps them by creation.
+    match sort {
+        SortKey::Created => {}
+        SortKey::Due => rows.sort_by_key(|(_, row)| (row.due_time.is_none(), row.due_time)),
+        SortKey::Priority => rows.sort_by_key(|(_, row)| (row.priority.is_none(), row.priority)),
+        SortKey::Urgency => rows.sort_by(|(a, _), (b, _)| b.total_cmp(a)),
+        SortKey::Manual => rows.sort_by(|(_, a), (_, b)| {
+            let key = |row: &TodoRecord| row.sort_order.unwrap_or(f64::INFINITY);
+            key(a).total_cmp(&key(b))
+        }),
+    }
+    rows.sort_by_key(|(_, row)| !row.pinned);
+}
+
+// The untranslated name of a priority, as accepted by `--priority`.
+fn priority_key(priority: i16) -> &'static str {
+    match priority {
+        1 => "high",
+        2 => "medium",
+        _ => "low",
+    }
+}
+
+fn priority_name(priority: i16) -> String {
+    match priority {
+        1 => tr!("high"),
+        2 => tr!("medium"),
+        _ => tr!("low"),
+    }
+}
+
+// Parses an estimate such as `90m` or `2h`, which is stored in seconds.
+fn parse_estimate(s: &str) -> Result<i32, String> {
+    time::parse_duration(s)
+        .filter(|&seconds| seconds <= i32::MAX as u64)
+        .map(|seconds| seconds as i32)
+        .ok_or_else(|| tr!("Invalid estimate: {}", s))
+}
+
+fn parse_duration_arg(s: &str) -> Result<u64, String> {
+    time::parse_duration(s).ok_or_else(|| tr!("Invalid duration: {}", s))
+}
+
+// Parses a list of ids, aliases, UUIDs (or prefixes of them) and inclusive ranges of ids (e.g. `3 7 10-12`), dropping
+// duplicates while keeping the order in which they were given.
+fn parse_ids(args: Args) -> Result<Vec<TaskRef>, String> {
+    let command = args.command().to_string();
+    let mut ids: Vec<TaskRef> = Vec::new();
+    let mut seen = HashSet::new();
+    for arg in args.rest()? {
+        // UUIDs and their prefixes contain dashes too, but only ranges have numbers on both sides.
+        let range = arg
+            .split_once('-')
+            .and_then(|(from, to)| Some((from.parse().ok()?, to.parse().ok()?)));
+        let (from, to): (i32, i32) = match range {
+            Some(range) => range,
+            None => match parse_id(&arg)? {
+                TaskRef::Id(id) => (id, id),
+                task => {
+                    if seen.insert(task.clone()) {
+                        ids.push(task);
+                    }
+                    continue;
+                }
+            },
+        };
+        if from > to {
+            return Err(tr!("Invalid range: {}", arg));
+        }
+        if i64::from(to) - i64::from(from) >= MAX_RANGE {
+            return Err(tr!("Range too large: {}, at most {} ids", arg, MAX_RANGE));
+        }
+        for id in from..=to {
+            if seen.insert(TaskRef::Id(id)) {
+                ids.push(TaskRef::Id(id));
+            }
+        }
+    }
+    if ids.is_empty() {
+        return Err(tr!("Missing argument to '{}' command", command));
+    }
+    Ok(ids)
+}
+
 fn print_usage() {
     // TODO
 }
 
-fn run_command(client: &mut Client, command: Command) {
+fn run_command(client: &mut Client, ctx: &Context, command: Command) -> Result<(), Error> {
+    let user = ctx.user.as_str();
+    // These commands have effects outside the database, which can't be rolled back.
+    if ctx.dry_run
+        && matches!(
+            command,
+            Command::ImportGithub { .. }
+                | Command::SyncCaldav { .. }
+                | Command::SyncGit { .. }
+                | Command::Remind { .. }
+                | Command::Serve { .. }
+        )
+    {
+        return Err(Error::Usage(tr!(
+            "'--dry-run' is not supported by this command"
+        )));
+    }
     match command {
-        Command::Add { name } => {
-            typed_query::insert_into(TODO_TABLE)
-                .values(TodoRecordInsert { // TODO make a default value for this?
-                    id: typed_query::WithDefault::Default,
-                    name,
-                    created_time: typed_query::WithDefault::Default,
-                    completed: typed_query::WithDefault::Default,
-                    completed_time: Option::None,
-                })
-                .execute(client);
+        // The basic commands, which SQLite mode has too.
+        command @ (Command::Add { .. }
+        | Command::List { .. }
+        | Command::Show { .. }
+        | Command::Note { .. }
+        | Command::Complete { .. }
+        | Command::Reopen { .. }
+        | Command::Cancel { .. }
+        | Command::Remove { .. }) => {
+            store::run(&mut store::Postgres(client), ctx, command)?;
+        }
+        Command::Log { name, at, options } => {
+            let completed_time = match at {
+                Some(at) => time::parse_time(client, &at).map_err(Error::Usage)?,
+                None => SystemTime::now(),
+            };
+            if completed_time > SystemTime::now() {
+                return Err(Error::Usage(tr!(
+                    "The completion time can't be in the future"
+                )));
+            }
+            let fields = fields::parse_all(&ctx.fields, &options.fields).map_err(Error::Usage)?;
+            let due_time = options
+                .due
+                .map(|d| time::parse_time(client, &d))
+                .transpose()
+                .map_err(Error::Usage)?;
+            let estimate = options
+                .estimate
+                .map(|e| parse_estimate(&e))
+                .transpose()
+                .map_err(Error::Usage)?;
+            // The task counts as created when it was done, so it isn't older than its completion.
+            let uuid = task_ref::new_uuid();
+            let insert = typed_query::insert_into(TODO_TABLE).values(TodoRecordInsert {
+                id: typed_query::WithDefault::Default,
+                name: ctx.encrypt(&name, Location::task("name", &uuid)),
+                created_time: typed_query::WithDefault::Value(completed_time),
+                completed_time: Some(completed_time),
+                due_time,
+                remind_before: None,
+                reminded_time: None,
+                snoozed_until: None,
+                description: options
+                    .note
+                    .map(|n| ctx.encrypt(&n, Location::task("description", &uuid))),
+                project: options.project,
+                github_repo: None,
+                github_issue: None,
+                github_url: None,
+                github_close: typed_query::WithDefault::Default,
+                caldav_uid: None,
+                caldav_href: None,
+                caldav_etag: None,
+                caldav_state: None,
+                owner: Some(ctx.user.clone()),
+                priority: options.priority,
+                status: typed_query::WithDefault::Value(Status::Done),
+                context: options.context,
+                fields: typed_query::WithDefault::Value(Json::Object(fields)),
+                uuid: typed_query::WithDefault::Value(uuid.clone()),
+                reviewed_time: None,
+                archived_time: None,
+                sort_order: None,
+                pinned: typed_query::WithDefault::Default,
+                estimate,
+                assignee: None,
+                waiting_until: None,
+                waiting_for: None,
+                inbox: typed_query::WithDefault::Default,
+                external_id: None,
+            });
+            if ctx.dry_run {
+                println!("{}", insert.dry_run());
+                return Ok(());
+            }
+            let task = insert
+                .returning(client)?
+                .pop()
+                .ok_or_else(|| Error::Other(tr!("The task was not created")))?;
+            if !ctx.output.is_text() {
+                output::record(ctx.output, &[&task.id.to_string(), &alias::encode(task.id)]);
+            } else if ctx.verbosity >= 0 {
+                println!(
+                    "{}",
+                    tr!("Logged task {} ({})", task.id, alias::encode(task.id))
+                );
+            }
+            let tasks = webhook::tasks(client, ctx, &[task.id])?;
+            webhook::send(ctx, webhook::Event::Completed, &tasks);
+            for task in &tasks {
+                ctx.journal.record(ctx, task);
+            }
+        }
+        Command::Watch {
+            statuses,
+            fields,
+            sort,
+            archived,
+            pinned,
+            stale,
+            assigned,
+            due_before,
+            format,
+            interval,
+        } => watch::run(client, ctx, Duration::from_secs(interval), |client, ctx| {
+            run_command(
+                client,
+                ctx,
+                Command::List {
+                    statuses: statuses.clone(),
+                    fields: fields.clone(),
+                    sort,
+                    archived,
+                    pinned,
+                    stale,
+                    assigned: assigned.clone(),
+                    due_before: due_before.clone(),
+                    format: format.clone(),
+                },
+            )
+        })?,
+        Command::Edit { id, name, options } => {
+            let id = id.resolve(client, ctx)?;
+            edit::run(client, ctx, id, name, options)?
+        }
+        Command::Annotate { id, text } => {
+            let id = id.resolve(client, ctx)?;
+            annotation::add(client, ctx, id, &text)?
+        }
+        Command::Modify { filter, changes } => modify::run(client, ctx, filter, &changes)?,
+        Command::Report { name } => report::run(client, ctx, name.as_deref())?,
+        Command::Duplicate { id, name } => {
+            let id = id.resolve(client, ctx)?;
+            if name.as_deref() == Some("") {
+                return Err(Error::Usage(tr!("The task name can't be empty")));
+            }
+            // The copy is a new open task with the original's attributes; its status, times and
+            // links to GitHub and CalDAV start afresh.
+            let mut transaction = client.transaction()?;
+            let original = transaction
+                .query_opt(
+                    "select name, description, uuid from todo where id = $1 and owner = $2",
+                    &[&id, &user],
+                )?
+                .ok_or_else(|| Error::NotFound(tr!("No task with id {}", id)))?;
+            let (from, uuid): (&str, String) = (original.get(2), task_ref::new_uuid());
+            let name = match name {
+                Some(name) => ctx.encrypt(&name, Location::task("name", &uuid)),
+                None => copy_value(ctx, original.get(0), "name", from, &uuid)?,
+            };
+            let description = original
+                .get::<_, Option<&str>>(1)
+                .map(|d| copy_value(ctx, d, "description", from, &uuid))
+                .transpose()?;
+            let sql = "insert into todo (name, due_time, remind_before, description, project,
+                     owner, priority, context, estimate, fields, uuid)
+                 select $1, due_time, remind_before, $2, project,
+                     owner, priority, context, estimate, fields, $3
+                 from todo
+                 where id = $4 and owner = $5
+                 returning id";
+            let params: &[&(dyn ToSql + Sync)] = &[&name, &description, &uuid, &id, &user];
+            ctx.show_statement(sql, params);
+            let copy: i32 = transaction.query_one(sql, params)?.get(0);
+            ctx.finish(transaction)?;
+            if !ctx.dry_run {
+                println!("{}", tr!("Duplicated task {} as {}", id, copy));
+                let tasks = webhook::tasks(client, ctx, &[copy])?;
+                webhook::send(ctx, webhook::Event::Created, &tasks);
+            }
+        }
+        Command::Pin { ids, pinned } => {
+            let ids = task_ref::resolve_all(client, ctx, &ids)?;
+            let done = if pinned {
+                tr!("pinned")
+            } else {
+                tr!("unpinned")
+            };
+            run_bulk(client, ctx, &ids, &done, |t, id| {
+                ctx.execute(
+                    t,
+                    "update todo set pinned = $1 where id = $2 and owner = $3",
+                    &[&pinned, &id, &user],
+                )
+            })?;
+        }
+        Command::Move { id, position } => {
+            let id = id.resolve(client, ctx)?;
+            ordering::move_task(client, ctx, id, position)?
         }
-        Command::List => {
-            for row in typed_query::from(TODO_TABLE)
+        Command::Attachment { action } => attachment::run(client, ctx, action)?,
+        Command::CompleteByName { query } => {
+            let active = context::active(client, ctx)?;
+            let open = typed_query::from(TODO_TABLE)
                 .where_(|t| {
-                    t.completed
-                        .clone()
-                        .eq(typed_query::Constant { value: false })
+                    t.owner.clone().eq(typed_query::Constant {
+                        value: ctx.user.clone(),
+                    })
                 })
+                .where_(|t| {
+                    t.status.clone().in_(
+                        status::OPEN
+                            .iter()
+                            .map(|&value| typed_query::Constant { value })
+                            .collect(),
+                    )
+                });
+            let open: Vec<TodoRecord> = in_context(open, &active)
                 .order_by(|t| typed_query::asc(&t.created_time))
-                .query(client)
-            {
-                println!("{}: {}", row.id, row.name);
+                .query(client)?
+                .into_iter()
+                .map(|t| TodoRecord {
+                    name: ctx.decrypt(&t.name, Location::task("name", &t.uuid)),
+                    ..t
+                })
+                .collect();
+            let matches = match_by_name(&open, &query);
+            match matches.as_slice() {
+                [] => return Err(Error::NotFound(tr!("No open task matches '{}'", query))),
+                [todo] => store::complete(&mut store::Postgres(client), ctx, &[todo.id])?,
+                _ => {
+                    let mut message = tr!("Multiple open tasks match '{}':\n", query);
+                    for todo in matches {
+                        message.push_str(&format!("  {}: {}\n", todo.id, todo.name));
+                    }
+                    message.push_str(&tr!("Use 'complete <id>' to pick one."));
+                    return Err(Error::Usage(message));
+                }
             }
         }
-        Command::Complete { id } => {
-            client
-                .execute(
-                    "update todo set completed = true, completed_time = $1 where id = $2",
-                    &[&SystemTime::now(), &id],
+        Command::Restore { ids } => trash::restore(client, ctx, &ids)?,
+        Command::Trash { action } => trash::run(client, ctx, action)?,
+        Command::Stats => stats::run(client, ctx)?,
+        Command::Projects => projects::run(client, ctx)?,
+        Command::Agenda { days } => agenda::run(client, ctx, days)?,
+        Command::Burndown { weeks } => burndown::run(client, ctx, weeks)?,
+        Command::Review { days } => review::run(client, ctx, days)?,
+        Command::Capture { name } => inbox::capture(client, ctx, &name)?,
+        Command::Triage => inbox::triage(client, ctx)?,
+        Command::Gc => lock::with(client, ctx, lock::Lock::Cleanup, |client| {
+            cleanup::run(client, ctx)
+        })?,
+        Command::Encrypt => encrypt::run(client, ctx)?,
+        Command::ImportGithub {
+            repo,
+            assignee,
+            close_on_complete,
+        } => github::import(client, ctx, &repo, assignee, close_on_complete)?,
+        Command::SyncCaldav { url, user } => lock::with(client, ctx, lock::Lock::Sync, |client| {
+            caldav::sync(client, ctx, &caldav::Calendar::new(&url, user))
+        })?,
+        Command::ImportTaskwarrior { path } => taskwarrior::import(client, ctx, &path)?,
+        Command::ImportIcal { path, events } => ical_import::import(client, ctx, &path, events)?,
+        Command::ImportArchive { path } => archive::import(client, ctx, &path)?,
+        Command::SyncGit { repo } => lock::with(client, ctx, lock::Lock::Sync, |client| {
+            gitsync::sync(client, ctx, &repo)
+        })?,
+        Command::SyncStatus => conflict::status(client, ctx)?,
+        Command::Migrate { action } => lock::with(client, ctx, lock::Lock::Migrations, |client| {
+            Ok(migrations::run(client, ctx, action)?)
+        })?,
+        Command::Context { action } => context::run(client, ctx, action)?,
+        Command::Template { action } => template::run(client, ctx, action)?,
+        Command::Next { n } => urgency::next(client, ctx, n)?,
+        Command::History { id } => {
+            let id = id.resolve(client, ctx)?;
+            history::run(client, ctx, id)?
+        }
+        Command::Doctor => doctor::run(ctx)?,
+        Command::Man => man::run(),
+        Command::Auth { action } => auth::run(action)?,
+        Command::Depend { id, on, remove } => {
+            let id = id.resolve(client, ctx)?;
+            let on = task_ref::resolve_existing(client, ctx, &on)?;
+            if remove {
+                dependency::remove(client, ctx, id, &on)?
+            } else {
+                dependency::add(client, ctx, id, &on)?
+            }
+        }
+        Command::Blocked => dependency::blocked(client, ctx)?,
+        Command::Prompt => prompt::run(client, ctx)?,
+        Command::Assign { ids, assignee } => {
+            let ids = task_ref::resolve_all(client, ctx, &ids)?;
+            assign::run(client, ctx, &ids, assignee)?
+        }
+        Command::Unblockable { id } => {
+            let id = id.resolve(client, ctx)?;
+            dependency::unblockable(client, ctx, id)?
+        }
+        Command::Open { id, print } => {
+            let id = id.resolve(client, ctx)?;
+            open::run(client, ctx, id, print)?
+        }
+        Command::Init { options } => init::run(ctx, options)?,
+        Command::Plugin { path, args } => plugin::run(ctx, &path, &args)?,
+        Command::Export { format } => export::run(client, ctx, format)?,
+        Command::Serve {
+            listen,
+            pool_size,
+            grpc: false,
+        } => serve::run(ctx, &listen, pool_size)?,
+        Command::Serve {
+            listen,
+            pool_size,
+            grpc: true,
+        } => grpc::run(ctx, &listen, pool_size)?,
+        Command::Start { id } => {
+            let id = id.resolve(client, ctx)?;
+            timetrack::start(client, ctx, id)?
+        }
+        Command::Stop => timetrack::stop(client, ctx)?,
+        Command::Timesheet { period } => timetrack::timesheet(client, ctx, period)?,
+        Command::Remind { daemon, interval } => {
+            lock::with(client, ctx, lock::Lock::Reminders, |client| {
+                if daemon {
+                    remind::daemon(client, ctx, interval.map(Duration::from_secs))?;
+                } else if remind::check(client, ctx)? == 0 {
+                    println!("{}", tr!("No reminders due"));
+                }
+                Ok(())
+            })?
+        }
+        Command::RemindBefore { id, before } => {
+            let id = id.resolve(client, ctx)?;
+            let before = before as i32;
+            run_bulk(client, ctx, &[id], &tr!("reminder set"), |t, id| {
+                ctx.execute(
+                    t,
+                    "update todo set remind_before = $1, reminded_time = null
+                     where id = $2 and owner = $3",
+                    &[&before, &id, &user],
+                )
+            })?;
+        }
+        Command::Snooze { id, duration } => {
+            let id = id.resolve(client, ctx)?;
+            let until = SystemTime::now() + Duration::from_secs(duration);
+            run_bulk(client, ctx, &[id], &tr!("snoozed"), |t, id| {
+                ctx.execute(
+                    t,
+                    "update todo set snoozed_until = $1, reminded_time = null
+                     where id = $2 and owner = $3",
+                    &[&until, &id, &user],
+                )
+            })?;
+        }
+        Command::Wait { id, until, person } => {
+            let id = id.resolve(client, ctx)?;
+            let until = time::parse_time(client, &until).map_err(Error::Usage)?;
+            // Until the follow-up date, the task is left out of lists and reminders.
+            let missing = apply_bulk(client, ctx, &[id], &tr!("waiting"), |t, id| {
+                ctx.execute(
+                    t,
+                    "update todo set status = $1, waiting_until = $2, waiting_for = $3
+                     where id = $4 and owner = $5",
+                    &[&Status::Waiting, &until, &person, &id, &user],
                 )
-                .unwrap();
+            })?;
+            let tasks = webhook::tasks(client, ctx, &changed_ids(&[id], &missing))?;
+            webhook::send(ctx, webhook::Event::Modified, &tasks);
+            report_missing(&missing)?;
+        }
+    }
+    Ok(())
+}
+
+// Limits `query` to the tasks in `context`, if there is one.
+fn in_context(
+    query: typed_query::Query<TodoColumns, TodoRecord>,
+    context: &Option<String>,
+) -> typed_query::Query<TodoColumns, TodoRecord> {
+    match context {
+        Some(name) => query.where_(|t| {
+            t.context.clone().eq(typed_query::Constant {
+                value: name.clone(),
+            })
+        }),
+        None => query,
+    }
+}
+
+fn show(ctx: &Context, todo: &TodoRecord) {
+    println!(
+        "{}: {}",
+        todo.id,
+        ctx.decrypt(&todo.name, Location::task("name", &todo.uuid))
+    );
+    println!("{}", tr!("Alias: {}", alias::encode(todo.id)));
+    println!("{}", tr!("UUID: {}", todo.uuid));
+    println!("{}", tr!("Status: {}", todo.status.label()));
+    println!("{}", tr!("Created: {}", ctx.format_time(todo.created_time)));
+    if let Some(due) = todo.due_time {
+        println!("{}", tr!("Due: {}", ctx.format_time(due)));
+    }
+    if let Some(completed) = todo.completed_time {
+        println!("{}", tr!("Completed: {}", ctx.format_time(completed)));
+    }
+    if let Some(archived) = todo.archived_time {
+        println!("{}", tr!("Archived: {}", ctx.format_time(archived)));
+    }
+    if let Some(priority) = todo.priority {
+        println!("{}", tr!("Priority: {}", priority_name(priority)));
+    }
+    if let Some(project) = &todo.project {
+        println!("{}", tr!("Project: {}", project));
+    }
+    if let Some(context) = &todo.context {
+        println!("{}", tr!("Context: @{}", context));
+    }
+    if let Some(assignee) = &todo.assignee {
+        println!("{}", tr!("Assigned to: {}", assignee));
+    }
+    if let Some(external_id) = &todo.external_id {
+        println!("{}", tr!("Imported as: {}", external_id));
+    }
+    if todo.status == Status::Waiting {
+        if let Some(person) = &todo.waiting_for {
+            println!("{}", tr!("Waiting for: {}", person));
+        }
+        if let Some(until) = todo.waiting_until {
+            println!("{}", tr!("Follow up: {}", ctx.format_time(until)));
+        }
+    }
+    if let Some(estimate) = todo.estimate {
+        println!(
+            "{}",
+            tr!("Estimate: {}", time::format_duration(estimate as f64))
+        );
+    }
+    for field in fields::describe(&ctx.fields, &todo.fields) {
+        println!("{}", field);
+    }
+    if let Some(url) = &todo.github_url {
+        println!("{}", tr!("GitHub: {}", url));
+    }
+    if let Some(description) = &todo.description {
+        println!();
+        for line in ctx
+            .decrypt(description, Location::task("description", &todo.uuid))
+            .lines()
+        {
+            println!("    {}", line);
+        }
+    }
+}
+
+// A value of the task with uuid `from` to store with the task with uuid `to`. Encrypted values are
+// bound to their task, so they are encrypted again; others are copied as they are.
+fn copy_value(
+    ctx: &Context,
+    value: &str,
+    column: &str,
+    from: &str,
+    to: &str,
+) -> Result<String, Error> {
+    match &ctx.cipher {
+        Some(cipher) if crypto::is_encrypted(value) => {
+            let value = cipher
+                .decrypt(value, Location::task(column, from))
+                .map_err(Error::Other)?;
+            Ok(cipher.encrypt(&value, Location::task(column, to)))
+        }
+        _ => Ok(value.to_string()),
+    }
+}
+
+// The user's unfinished task named `name`, for `add --unique`. Encrypted names differ every time they're
+// written, so with encryption they are compared after decrypting.
+fn find_open(
+    client: &mut impl GenericClient,
+    ctx: &Context,
+    name: &str,
+) -> Result<Option<TodoRecord>, Error> {
+    let query = typed_query::from(TODO_TABLE)
+        .where_(|t| {
+            t.owner.clone().eq(typed_query::Constant {
+                value: ctx.user.clone(),
+            })
+        })
+        .where_(|t| {
+            t.status.clone().in_(
+                status::UNFINISHED
+                    .iter()
+                    .map(|&value| typed_query::Constant { value })
+                    .collect(),
+            )
+        });
+    let query = match ctx.cipher {
+        Some(_) => query,
+        None => query.where_(|t| {
+            t.name.clone().eq(typed_query::Constant {
+                value: name.to_string(),
+            })
+        }),
+    };
+    Ok(query
+        .order_by(|t| typed_query::asc(&t.created_time))
+        .query(client)?
+        .into_iter()
+        .find(|task| ctx.decrypt(&task.name, Location::task("name", &task.uuid)) == name))
+}
+
+/// A task as JSON, as served by `todo serve` and sent to webhooks.
+fn task_json(ctx: &Context, task: &TodoRecord) -> Json {
+    Json::object(vec![
+        ("id", Json::from(task.id)),
+        ("alias", Json::from(alias::encode(task.id))),
+        ("uuid", Json::from(task.uuid.as_str())),
+        (
+            "name",
+            Json::from(ctx.decrypt(&task.name, Location::task("name", &task.uuid))),
+        ),
+        ("status", Json::from(task.status.as_str())),
+        ("created", Json::from(time::format_iso(task.created_time))),
+        ("due", Json::from(task.due_time.map(time::format_iso))),
+        (
+            "completed",
+            Json::from(task.completed_time.map(time::format_iso)),
+        ),
+        ("priority", Json::from(task.priority.map(priority_key))),
+        ("project", Json::from(task.project.clone())),
+        ("context", Json::from(task.context.clone())),
+        (
+            "note",
+            Json::from(
+                task.description
+                    .as_deref()
+                    .map(|d| ctx.decrypt(d, Location::task("description", &task.uuid))),
+            ),
+        ),
+        ("estimate", Json::from(task.estimate)),
+        ("assignee", Json::from(task.assignee.clone())),
+        (
+            "waiting_until",
+            Json::from(task.waiting_until.map(time::format_iso)),
+        ),
+        ("waiting_for", Json::from(task.waiting_for.clone())),
+        ("inbox", Json::from(task.inbox)),
+        ("external_id", Json::from(task.external_id.clone())),
+        ("fields", task.fields.clone()),
+    ])
+}
+
+// Completes the user's task `id` at `now`, if it is unfinished.
+fn complete_update(
+    ctx: &Context,
+    id: i32,
+    now: SystemTime,
+) -> typed_query::Update<TodoColumns, TodoRecord> {
+    typed_query::update(TODO_TABLE, |t| t.status.set(Status::Done))
+        .set(|t| t.completed_time.set(now))
+        .where_(|t| t.id.clone().eq(typed_query::Constant { value: id }))
+        .where_(|t| {
+            t.owner.clone().eq(typed_query::Constant {
+                value: ctx.user.clone(),
+            })
+        })
+        .where_(|t| {
+            t.status.clone().in_(
+                status::UNFINISHED
+                    .iter()
+                    .map(|&value| typed_query::Constant { value })
+                    .collect(),
+            )
+        })
+}
+
+// Applies `op` to every id in a single transaction, reporting each affected task. The others
+// are still applied when some ids don't exist, which is then reported as an error.
+fn run_bulk<F>(
+    client: &mut Client,
+    ctx: &Context,
+    ids: &[i32],
+    done: &str,
+    op: F,
+) -> Result<(), Error>
+where
+    F: FnMut(&mut Transaction, i32) -> Result<u64, postgres::Error>,
+{
+    let missing = apply_bulk(client, ctx, ids, done, op)?;
+    report_missing(&missing)
+}
+
+// Fails with the ids `apply_bulk` changed nothing for, if there are any.
+fn report_missing(missing: &[i32]) -> Result<(), Error> {
+    match missing {
+        [] => Ok(()),
+        [id] => Err(Error::NotFound(tr!("No task with id {}", id))),
+        _ => Err(Error::NotFound(tr!(
+            "No tasks with ids {}",
+            join_ids(missing)
+        ))),
+    }
+}
+
+// The ids `apply_bulk` changed, given those it changed nothing for.
+fn changed_ids(ids: &[i32], missing: &[i32]) -> Vec<i32> {
+    ids.iter()
+        .copied()
+        .filter(|id| !missing.contains(id))
+        .collect()
+}
+
+// Like `run_bulk`, but returns the ids for which `op` changed nothing.
+fn apply_bulk<F>(
+    client: &mut Client,
+    ctx: &Context,
+    ids: &[i32],
+    done: &str,
+    mut op: F,
+) -> Result<Vec<i32>, Error>
+where
+    F: FnMut(&mut Transaction, i32) -> Result<u64, postgres::Error>,
+{
+    let mut transaction = client.transaction()?;
+    let missing = store::each(ids, done, |id| Ok(op(&mut transaction, id)?))?;
+    ctx.finish(transaction)?;
+    Ok(missing)
+}
+
+fn join_ids(ids: &[i32]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Shows the user's tasks with the given ids and asks whether to go on, failing if the answer
+// isn't yes. Doesn't ask with `--yes`, when stdin isn't a terminal, or for dry runs.
+fn confirm(client: &mut Client, ctx: &Context, question: &str, ids: &[i32]) -> Result<(), Error> {
+    if ctx.assume_yes || ctx.dry_run {
+        return Ok(());
+    }
+    let rows = client.query(
+        "select id, name, uuid from todo where id = any($1) and owner = $2 order by id",
+        &[&ids, &ctx.user],
+    )?;
+    if rows.is_empty() {
+        // Nothing would be affected; the command reports the missing ids.
+        return Ok(());
+    }
+    let tasks: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let name = ctx.decrypt(row.get(1), Location::task("name", row.get(2)));
+            format!("{}: {}", row.get::<_, i32>(0), name)
+        })
+        .collect();
+    ask(question, &tasks)
+}
+
+// Asks `question` about `tasks` on stderr, failing if the answer isn't yes.
+fn ask(question: &str, tasks: &[String]) -> Result<(), Error> {
+    eprintln!("{}", question);
+    for task in tasks {
+        eprintln!("  {}", task);
+    }
+    eprint!("{}", tr!("Continue? [y/N] "));
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| Error::Other(e.to_string()))?;
+    let yes = tr!("y");
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        a if a == yes => Ok(()),
+        _ => Err(Error::Other(tr!("Aborted"))),
+    }
+}
+
+// Finds the tasks whose name contains `query` (ignoring case). If there are none, falls back to
+// fuzzy matching, where the characters of `query` only have to appear in order.
+fn match_by_name<'a>(todos: &'a [TodoRecord], query: &str) -> Vec<&'a TodoRecord> {
+    let query = query.to_lowercase();
+    let substring: Vec<&TodoRecord> = todos
+        .iter()
+        .filter(|t| t.name.to_lowercase().contains(&query))
+        .collect();
+    if !substring.is_empty() {
+        return substring;
+    }
+    todos
+        .iter()
+        .filter(|t| {
+            let name = t.name.to_lowercase();
+            let mut chars = name.chars();
+            query.chars().all(|c| chars.any(|n| n == c))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(args: &[&str]) -> Result<Vec<TaskRef>, String> {
+        parse_ids(Args::new("done", args.iter().map(|a| a.to_string())))
+    }
+
+    #[test]
+    fn parse_ids_deduplicates_in_order() {
+        assert_eq!(
+            ids(&["3", "2-4", "3", "1"]),
+            Ok(vec![
+                TaskRef::Id(3),
+                TaskRef::Id(2),
+                TaskRef::Id(4),
+                TaskRef::Id(1)
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_ids_caps_ranges() {
+        assert_eq!(ids(&["1-1000"]).map(|ids| ids.len()), Ok(1000));
+        assert!(ids(&["1-1001"]).is_err());
+        assert!(ids(&["0-2147483647"]).is_err());
+    }
+
+    #[test]
+    fn aliases_can_give_filter_terms() {
+        let config =
+            Config::parse("alias.today = \"list due.before:tomorrow status:open\"").unwrap();
+        let mut args = vec!["today".to_string(), "--pinned".to_string()];
+        expand_alias(&mut args, &config);
+        match parse_args(args) {
+            Ok(Command::List {
+                statuses,
+                pinned,
+                due_before,
+                ..
+            }) => {
+                assert_eq!(statuses, status::OPEN);
+                assert!(pinned);
+                assert_eq!(due_before.as_deref(), Some("tomorrow"));
+            }
+            _ => panic!("expected a list command"),
         }
+        assert!(parse_args(vec!["list".to_string(), "due.after:tomorrow".to_string()]).is_err());
     }
 }
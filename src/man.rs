@@ -0,0 +1,9 @@
+// The manual page, printed by `todo man` as roff so packagers can install it, e.g. with
+// `todo man > /usr/share/man/man1/todo.1`. The page is kept in `doc/todo.1`; when adding commands,
+// options or config keys, document them there too.
+
+const PAGE: &str = include_str!("../doc/todo.1");
+
+pub fn run() {
+    print!("{}", PAGE.replace("@VERSION@", env!("CARGO_PKG_VERSION")));
+}
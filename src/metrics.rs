@@ -0,0 +1,134 @@
+// Metrics of `todo serve` for Prometheus, served at `/metrics` in its text format: the requests by
+// method and status, how long they took, the database connections in use, and the user's open and
+// overdue tasks. The counts start at zero when the server starts.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::status;
+use crate::Context;
+use todo_rust::client::GenericClient;
+
+// The upper bounds of the buckets of request durations, in seconds.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+// The methods counted by name, others count as `other`.
+const METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+
+pub struct Metrics {
+    requests: Mutex<Requests>,
+}
+
+#[derive(Default)]
+struct Requests {
+    counts: BTreeMap<(&'static str, u16), u64>,
+    // For every bucket, the requests that took at most its bound.
+    buckets: [u64; BUCKETS.len()],
+    seconds: f64,
+    count: u64,
+}
+
+/// The database connections of the server.
+pub struct Connections {
+    pub size: usize,
+    pub idle: usize,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            requests: Mutex::new(Requests::default()),
+        }
+    }
+
+    /// Counts a request that was answered with `status` after `duration`.
+    pub fn record(&self, method: &str, status: u16, duration: Duration) {
+        let method = METHODS
+            .iter()
+            .find(|m| **m == method)
+            .copied()
+            .unwrap_or("other");
+        let seconds = duration.as_secs_f64();
+        let mut requests = self.requests.lock().unwrap();
+        *requests.counts.entry((method, status)).or_insert(0) += 1;
+        for (bucket, bound) in requests.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        requests.seconds += seconds;
+        requests.count += 1;
+    }
+
+    /// The metrics in the Prometheus text format.
+    pub fn render(
+        &self,
+        client: &mut impl GenericClient,
+        ctx: &Context,
+        connections: Connections,
+    ) -> Result<String, Error> {
+        let row = client.query_one(
+            "select count(*), count(*) filter (where due_time < now())
+                 from todo
+                 where owner = $1 and status = any($2) and archived_time is null",
+            &[&ctx.user, &status::OPEN],
+        )?;
+        let (open, overdue): (i64, i64) = (row.get(0), row.get(1));
+
+        let mut text = String::new();
+        let requests = self.requests.lock().unwrap();
+        header(
+            &mut text,
+            "todo_http_requests_total",
+            "counter",
+            "HTTP requests by method and status.",
+        );
+        for ((method, status), count) in &requests.counts {
+            text += &format!(
+                "todo_http_requests_total{{method=\"{}\",status=\"{}\"}} {}\n",
+                method, status, count
+            );
+        }
+        let name = "todo_http_request_duration_seconds";
+        header(&mut text, name, "histogram", "How long requests took.");
+        for (bound, count) in BUCKETS.iter().zip(requests.buckets) {
+            text += &format!("{}_bucket{{le=\"{}\"}} {}\n", name, bound, count);
+        }
+        text += &format!("{}_bucket{{le=\"+Inf\"}} {}\n", name, requests.count);
+        text += &format!("{}_sum {}\n", name, requests.seconds);
+        text += &format!("{}_count {}\n", name, requests.count);
+        drop(requests);
+
+        let gauges = [
+            (
+                "todo_db_connections",
+                "Database connections of the server.",
+                connections.size as i64,
+            ),
+            (
+                "todo_db_connections_idle",
+                "Database connections not in use.",
+                connections.idle as i64,
+            ),
+            ("todo_tasks_open", "Open tasks.", open),
+            (
+                "todo_tasks_overdue",
+                "Open tasks past their due time.",
+                overdue,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            header(&mut text, name, "gauge", help);
+            text += &format!("{} {}\n", name, value);
+        }
+        Ok(text)
+    }
+}
+
+fn header(text: &mut String, name: &str, kind: &str, help: &str) {
+    *text += &format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind);
+}
@@ -0,0 +1,541 @@
+use todo_rust::client::Client;
+use todo_rust::migrate::{self, Migration};
+
+use crate::error::Error;
+use crate::Context;
+
+// The `if not exists` clauses allow adopting databases created before there were migrations.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create todo table",
+        up: "
+            create table if not exists todo (
+                id serial primary key,
+                name text not null,
+                created_time timestamp with time zone not null default now(),
+                completed boolean not null default false,
+                completed_time timestamp with time zone null
+            )",
+        down: "drop table todo",
+    },
+    Migration {
+        version: 2,
+        name: "add due dates and reminders",
+        up: "
+            alter table todo
+                add column if not exists due_time timestamp with time zone null,
+                add column if not exists remind_before integer null,
+                add column if not exists reminded_time timestamp with time zone null,
+                add column if not exists snoozed_until timestamp with time zone null",
+        down: "
+            alter table todo
+                drop column due_time,
+                drop column remind_before,
+                drop column reminded_time,
+                drop column snoozed_until",
+    },
+    Migration {
+        version: 3,
+        name: "add descriptions",
+        up: "alter table todo add column if not exists description text null",
+        down: "alter table todo drop column description",
+    },
+    Migration {
+        version: 4,
+        name: "add projects and time tracking",
+        up: "
+            alter table todo add column if not exists project text null;
+            create table if not exists time_entry (
+                id serial primary key,
+                todo_id integer not null references todo (id) on delete cascade,
+                start_time timestamp with time zone not null default now(),
+                end_time timestamp with time zone null
+            )",
+        down: "
+            drop table time_entry;
+            alter table todo drop column project",
+    },
+    Migration {
+        version: 5,
+        name: "add github issues",
+        up: "
+            alter table todo
+                add column if not exists github_repo text null,
+                add column if not exists github_issue integer null,
+                add column if not exists github_url text null,
+                add column if not exists github_close boolean not null default false",
+        down: "
+            alter table todo
+                drop column github_repo,
+                drop column github_issue,
+                drop column github_url,
+                drop column github_close",
+    },
+    Migration {
+        version: 6,
+        name: "add caldav sync state",
+        up: "
+            alter table todo
+                add column if not exists caldav_uid text null,
+                add column if not exists caldav_href text null,
+                add column if not exists caldav_etag text null,
+                add column if not exists caldav_state text null",
+        down: "
+            alter table todo
+                drop column caldav_uid,
+                drop column caldav_href,
+                drop column caldav_etag,
+                drop column caldav_state",
+    },
+    Migration {
+        version: 7,
+        name: "add task owners",
+        // Existing tasks go to the user running the migration, see `migrate`.
+        up: "
+            alter table todo add column if not exists owner text null;
+            update todo set owner = current_setting('todo.user') where owner is null",
+        down: "alter table todo drop column owner",
+    },
+    Migration {
+        version: 8,
+        name: "add indexes",
+        up: "
+            create index if not exists todo_owner_completed_idx on todo (owner, completed);
+            create index if not exists time_entry_todo_id_idx on time_entry (todo_id)",
+        down: "
+            drop index todo_owner_completed_idx;
+            drop index time_entry_todo_id_idx",
+    },
+    Migration {
+        version: 9,
+        name: "add priorities",
+        up: "alter table todo add column if not exists priority smallint null",
+        down: "alter table todo drop column priority",
+    },
+    Migration {
+        version: 10,
+        name: "add statuses",
+        up: "
+            alter table todo add column if not exists status text not null default 'todo'
+                check (status in ('todo', 'in-progress', 'done', 'cancelled'));
+            update todo set status = 'done' where completed;
+            drop index if exists todo_owner_completed_idx;
+            alter table todo drop column completed;
+            create index if not exists todo_owner_status_idx on todo (owner, status)",
+        down: "
+            alter table todo add column completed boolean not null default false;
+            update todo set completed = status in ('done', 'cancelled');
+            drop index todo_owner_status_idx;
+            alter table todo drop column status;
+            create index if not exists todo_owner_completed_idx on todo (owner, completed)",
+    },
+    Migration {
+        version: 11,
+        name: "add contexts",
+        up: "
+            alter table todo add column if not exists context text null;
+            create table if not exists active_context (
+                owner text primary key,
+                context text not null
+            )",
+        down: "
+            drop table active_context;
+            alter table todo drop column context",
+    },
+    Migration {
+        version: 12,
+        name: "add custom fields",
+        up: "alter table todo add column if not exists fields jsonb not null default '{}'",
+        down: "alter table todo drop column fields",
+    },
+    Migration {
+        version: 13,
+        name: "add templates",
+        up: "
+            create table if not exists template (
+                owner text not null,
+                name text not null,
+                due text null,
+                description text null,
+                project text null,
+                priority smallint null,
+                context text null,
+                fields jsonb not null default '{}',
+                primary key (owner, name)
+            )",
+        down: "drop table template",
+    },
+    Migration {
+        version: 14,
+        name: "add change notifications",
+        up: "
+            create or replace function todo_notify() returns trigger language plpgsql as $$
+            begin
+                perform pg_notify('todo_changed', coalesce(new.owner, old.owner));
+                return null;
+            end
+            $$;
+            create trigger todo_notify after insert or update or delete on todo
+                for each row execute function todo_notify()",
+        down: "
+            drop trigger todo_notify on todo;
+            drop function todo_notify()",
+    },
+    Migration {
+        version: 15,
+        name: "add task history",
+        up: "
+            create table todo_history (
+                id serial primary key,
+                todo_id integer not null,
+                owner text not null,
+                time timestamptz not null default now(),
+                command text,
+                action text not null check (action in ('insert', 'update', 'delete')),
+                field text,
+                old_value text,
+                new_value text
+            );
+            create index todo_history_todo_id_idx on todo_history (todo_id);
+            create or replace function todo_history() returns trigger language plpgsql as $$
+            declare
+                command text := nullif(current_setting('todo.command', true), '');
+            begin
+                if tg_op = 'INSERT' then
+                    insert into todo_history (todo_id, owner, command, action, new_value)
+                        values (new.id, new.owner, command, 'insert', to_jsonb(new)::text);
+                elsif tg_op = 'DELETE' then
+                    insert into todo_history (todo_id, owner, command, action, old_value)
+                        values (old.id, old.owner, command, 'delete', to_jsonb(old)::text);
+                else
+                    insert into todo_history
+                            (todo_id, owner, command, action, field, old_value, new_value)
+                        select new.id, new.owner, command, 'update',
+                                o.key, o.value #>> '{}', n.value #>> '{}'
+                        from jsonb_each(to_jsonb(old)) o
+                            join jsonb_each(to_jsonb(new)) n using (key)
+                        where o.value is distinct from n.value;
+                end if;
+                return null;
+            end
+            $$;
+            create trigger todo_history after insert or update or delete on todo
+                for each row execute function todo_history()",
+        down: "
+            drop trigger todo_history on todo;
+            drop function todo_history();
+            drop table todo_history",
+    },
+    Migration {
+        version: 16,
+        name: "add task uuids",
+        up: "
+            alter table todo add column uuid text not null default gen_random_uuid()::text;
+            create unique index todo_uuid_idx on todo (uuid)",
+        down: "alter table todo drop column uuid",
+    },
+    Migration {
+        version: 17,
+        name: "add task review time",
+        up: "alter table todo add column reviewed_time timestamptz",
+        down: "alter table todo drop column reviewed_time",
+    },
+    Migration {
+        version: 18,
+        name: "add task archive time",
+        up: "alter table todo add column archived_time timestamptz",
+        down: "alter table todo drop column archived_time",
+    },
+    Migration {
+        version: 19,
+        name: "add annotations",
+        up: "
+            create table annotation (
+                id serial primary key,
+                todo_id integer not null references todo (id) on delete cascade,
+                time timestamp with time zone not null default now(),
+                text text not null
+            );
+            create index annotation_todo_id_idx on annotation (todo_id)",
+        down: "drop table annotation",
+    },
+    Migration {
+        version: 20,
+        name: "add attachments",
+        up: "
+            create table attachment (
+                id serial primary key,
+                todo_id integer not null references todo (id) on delete cascade,
+                time timestamp with time zone not null default now(),
+                name text not null,
+                size bigint not null,
+                sha256 text not null,
+                content bytea null,
+                path text null,
+                encrypted boolean not null default false
+            );
+            create index attachment_todo_id_idx on attachment (todo_id)",
+        down: "drop table attachment",
+    },
+    Migration {
+        version: 21,
+        name: "add manual sort order",
+        up: "alter table todo add column sort_order double precision",
+        down: "alter table todo drop column sort_order",
+    },
+    Migration {
+        version: 22,
+        name: "add pinned tasks",
+        up: "alter table todo add column pinned boolean not null default false",
+        down: "alter table todo drop column pinned",
+    },
+    Migration {
+        version: 23,
+        name: "add estimates",
+        up: "
+            alter table todo add column if not exists estimate integer null;
+            alter table template add column if not exists estimate integer null",
+        down: "
+            alter table todo drop column estimate;
+            alter table template drop column estimate",
+    },
+    Migration {
+        version: 24,
+        name: "add trash",
+        up: "
+            create table trash (
+                todo_id integer primary key,
+                owner text not null,
+                time timestamptz not null default now(),
+                task jsonb not null,
+                time_entries jsonb not null,
+                annotations jsonb not null,
+                attachments jsonb not null
+            )",
+        down: "drop table trash",
+    },
+    Migration {
+        version: 25,
+        name: "add contexts",
+        up: "
+            create table context (
+                owner text not null,
+                name text not null,
+                filter text,
+                primary key (owner, name)
+            )",
+        down: "drop table context",
+    },
+    Migration {
+        version: 26,
+        name: "add sync conflicts",
+        up: "
+            create table sync_conflict (
+                id serial primary key,
+                owner text not null,
+                target text not null,
+                item text not null,
+                local text,
+                remote text,
+                detected_time timestamptz not null default now()
+            )",
+        down: "drop table sync_conflict",
+    },
+    Migration {
+        version: 27,
+        name: "add dependencies",
+        up: "
+            create table dependency (
+                todo_id integer not null references todo (id) on delete cascade,
+                depends_on integer not null references todo (id) on delete cascade,
+                primary key (todo_id, depends_on),
+                check (todo_id <> depends_on)
+            )",
+        down: "drop table dependency",
+    },
+    Migration {
+        version: 28,
+        name: "add assignees",
+        up: "alter table todo add column assignee text null",
+        down: "alter table todo drop column assignee",
+    },
+    Migration {
+        version: 29,
+        name: "add waiting",
+        up: "
+            alter table todo drop constraint todo_status_check;
+            alter table todo add constraint todo_status_check
+                check (status in ('todo', 'in-progress', 'waiting', 'done', 'cancelled'));
+            alter table todo add column waiting_until timestamptz null;
+            alter table todo add column waiting_for text null",
+        down: "
+            update todo set status = 'todo' where status = 'waiting';
+            alter table todo drop column waiting_for;
+            alter table todo drop column waiting_until;
+            alter table todo drop constraint todo_status_check;
+            alter table todo add constraint todo_status_check
+                check (status in ('todo', 'in-progress', 'done', 'cancelled'))",
+    },
+    Migration {
+        version: 30,
+        name: "add the inbox",
+        up: "alter table todo add column inbox boolean not null default false",
+        down: "alter table todo drop column inbox",
+    },
+    Migration {
+        version: 31,
+        name: "add external ids",
+        up: "
+            alter table todo add column external_id text null;
+            update todo set external_id = 'github:' || github_repo || '#' || github_issue
+                where id in (select min(id) from todo where github_issue is not null
+                    group by owner, github_repo, github_issue);
+            update todo set external_id = 'ical:' || caldav_uid
+                where external_id is null and id in (select min(id) from todo
+                    where caldav_uid is not null group by owner, caldav_uid);
+            create unique index todo_owner_external_id_idx on todo (owner, external_id)",
+        down: "
+            drop index todo_owner_external_id_idx;
+            alter table todo drop column external_id",
+    },
+    Migration {
+        version: 32,
+        name: "add dependencies to the trash",
+        // Null for the tasks trashed before.
+        up: "alter table trash add column dependencies jsonb null",
+        down: "alter table trash drop column dependencies",
+    },
+];
+
+/// The schema version this version of todo works with: that of its latest migration.
+pub const SCHEMA_VERSION: i32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Indexes created by the migrations, as `(table, index)`, checked by `todo doctor`.
+pub const INDEXES: &[(&str, &str)] = &[
+    ("todo", "todo_owner_status_idx"),
+    ("time_entry", "time_entry_todo_id_idx"),
+    ("todo_history", "todo_history_todo_id_idx"),
+    ("todo", "todo_uuid_idx"),
+    ("annotation", "annotation_todo_id_idx"),
+    ("attachment", "attachment_todo_id_idx"),
+    ("todo", "todo_owner_external_id_idx"),
+];
+
+#[derive(Debug)]
+pub enum Action {
+    Up { to: Option<i32> },
+    Down { to: Option<i32> },
+    Status,
+}
+
+pub fn run(client: &mut Client, ctx: &Context, action: Action) -> Result<(), postgres::Error> {
+    match action {
+        Action::Up { to } if ctx.dry_run => {
+            let pending = migrate::pending(client, MIGRATIONS, to)?;
+            if pending.is_empty() {
+                println!("{}", tr!("Database is up to date"));
+            }
+            for migration in pending {
+                println!(
+                    "-- {}: {}\n{};",
+                    migration.version,
+                    migration.name,
+                    migration.up.trim()
+                );
+            }
+        }
+        Action::Up { to } => {
+            let applied = up(client, ctx, to)?;
+            if applied == 0 {
+                println!("{}", tr!("Database is up to date"));
+            }
+        }
+        Action::Down { to } => {
+            let target = match to {
+                Some(to) => to,
+                // Revert only the latest migration.
+                None => {
+                    let applied: Vec<i32> = migrate::status(client, MIGRATIONS)?
+                        .iter()
+                        .filter(|(_, applied)| applied.is_some())
+                        .map(|(m, _)| m.version)
+                        .collect();
+                    applied.iter().rev().nth(1).copied().unwrap_or(0)
+                }
+            };
+            if ctx.dry_run {
+                for migration in migrate::revertible(client, MIGRATIONS, target)? {
+                    println!(
+                        "-- {}: {}\n{};",
+                        migration.version,
+                        migration.name,
+                        migration.down.trim()
+                    );
+                }
+                return Ok(());
+            }
+            let reverted = migrate::down(client, MIGRATIONS, target)?;
+            if reverted.is_empty() {
+                println!("{}", tr!("Nothing to revert"));
+            }
+            for migration in reverted {
+                println!(
+                    "{}",
+                    tr!("Reverted {}: {}", migration.version, migration.name)
+                );
+            }
+        }
+        Action::Status => {
+            for (migration, applied) in migrate::status(client, MIGRATIONS)? {
+                println!(
+                    "{:>4}  {:<8}  {}",
+                    migration.version,
+                    if applied.is_some() {
+                        "applied"
+                    } else {
+                        "pending"
+                    },
+                    migration.name
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the database is at `SCHEMA_VERSION`, so commands don't run against a schema they
+/// don't know, such as one with a column missing or renamed.
+pub fn check(client: &mut Client) -> Result<(), Error> {
+    let current = migrate::current_version(client)?;
+    if current < SCHEMA_VERSION {
+        Err(Error::Other(tr!(
+            "The database is at schema version {}, this version of todo needs {}; run 'todo migrate' first",
+            current,
+            SCHEMA_VERSION
+        )))
+    } else if current > SCHEMA_VERSION {
+        Err(Error::Other(tr!(
+            "The database is at schema version {}, newer than this version of todo supports ({}); upgrade todo, or run 'todo migrate down --to {}'",
+            current,
+            SCHEMA_VERSION,
+            SCHEMA_VERSION
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Applies pending migrations up to version `to` (or all of them), returning how many were
+/// applied.
+pub fn up(client: &mut Client, ctx: &Context, to: Option<i32>) -> Result<usize, postgres::Error> {
+    client.execute("select set_config('todo.user', $1, false)", &[&ctx.user])?;
+    let applied = migrate::up(client, MIGRATIONS, to)?;
+    for migration in &applied {
+        println!(
+            "{}",
+            tr!("Applied {}: {}", migration.version, migration.name)
+        );
+    }
+    Ok(applied.len())
+}
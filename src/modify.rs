@@ -0,0 +1,124 @@
+// Changes to every task matching a filter, e.g. `todo modify project:home --set priority=high`.
+// The changes can set the priority, project, context, due time, estimate and custom fields, in one
+// UPDATE after showing the tasks and asking for confirmation.
+
+use postgres::types::ToSql;
+use std::time::SystemTime;
+
+use crate::context;
+use crate::error::Error;
+use crate::fields;
+use crate::filter::Filter;
+use crate::json::Json;
+use crate::time;
+use crate::{confirm, parse_estimate, parse_priority, webhook, Context};
+use todo_rust::client::Client;
+
+/// Applies `changes`, each `key=value`, to the user's tasks matching `filter`. Empty values
+/// remove an attribute.
+pub fn run(
+    client: &mut Client,
+    ctx: &Context,
+    filter: Filter,
+    changes: &[String],
+) -> Result<(), Error> {
+    if changes.is_empty() {
+        return Err(Error::Usage(tr!(
+            "Expected at least one '--set key=value' to 'modify' command"
+        )));
+    }
+    let ids: Vec<i32> = filter.tasks(client, ctx)?.iter().map(|t| t.id).collect();
+    if ids.is_empty() {
+        return Err(Error::NotFound(tr!("No tasks match the filter")));
+    }
+
+    let mut assignments = Vec::new();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = Vec::new();
+    let mut set_fields = Vec::new();
+    let mut cleared_fields: Vec<String> = Vec::new();
+    for change in changes {
+        let (key, value) = change.split_once('=').ok_or_else(|| {
+            Error::Usage(tr!("Expected 'key=value' for '--set', got: {}", change))
+        })?;
+        let value = Some(value).filter(|v| !v.is_empty());
+        let param: Box<dyn ToSql + Sync> = match key {
+            "priority" => Box::new(
+                value
+                    .map(parse_priority)
+                    .transpose()
+                    .map_err(Error::Usage)?,
+            ),
+            "project" => Box::new(value.map(str::to_string)),
+            "context" => Box::new(
+                value
+                    .map(context::parse_name)
+                    .transpose()
+                    .map_err(Error::Usage)?,
+            ),
+            "estimate" => Box::new(
+                value
+                    .map(parse_estimate)
+                    .transpose()
+                    .map_err(Error::Usage)?,
+            ),
+            "due" => Box::new(
+                value
+                    .map(|d| time::parse_time(client, d))
+                    .transpose()
+                    .map_err(Error::Usage)? as Option<SystemTime>,
+            ),
+            _ => {
+                match value {
+                    Some(_) => {
+                        set_fields.push(fields::parse(&ctx.fields, change).map_err(Error::Usage)?)
+                    }
+                    None if ctx.fields.iter().any(|d| d.name == key) => {
+                        cleared_fields.push(key.to_string())
+                    }
+                    None => {
+                        return Err(Error::Usage(tr!(
+                            "Unknown field: {}, define it in the [fields] section of the config",
+                            key
+                        )))
+                    }
+                }
+                continue;
+            }
+        };
+        let column = if key == "due" { "due_time" } else { key };
+        params.push(param);
+        assignments.push(format!("{} = ${}", column, params.len()));
+    }
+    if !set_fields.is_empty() || !cleared_fields.is_empty() {
+        // `||` replaces the given fields and keeps the others, `-` removes the cleared ones.
+        params.push(Box::new(Json::Object(set_fields)));
+        params.push(Box::new(cleared_fields));
+        assignments.push(format!(
+            "fields = (fields || ${}::jsonb) - ${}::text[]",
+            params.len() - 1,
+            params.len()
+        ));
+    }
+    params.push(Box::new(ids.clone()));
+    params.push(Box::new(ctx.user.clone()));
+    let sql = format!(
+        "update todo set {} where id = any(${}) and owner = ${}",
+        assignments.join(", "),
+        params.len() - 1,
+        params.len()
+    );
+
+    confirm(client, ctx, &tr!("Modify these tasks?"), &ids)?;
+    let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(|p| p.as_ref()).collect();
+    let mut transaction = client.transaction()?;
+    let modified = ctx.execute(&mut transaction, &sql, &params)?;
+    ctx.finish(transaction)?;
+    if ctx.dry_run {
+        println!("{}", tr!("Would modify {} tasks", modified));
+        return Ok(());
+    }
+    println!("{}", tr!("Modified {} tasks", modified));
+    let tasks = webhook::tasks(client, ctx, &ids)?;
+    webhook::send(ctx, webhook::Event::Modified, &tasks);
+    Ok(())
+}
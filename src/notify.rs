@@ -0,0 +1,27 @@
+use std::process::Command;
+
+/// Shows a desktop notification using the platform's notification tool, falling back to printing
+/// it when that is not available.
+pub fn send(summary: &str, body: &str) {
+    let status = if cfg!(target_os = "macos") {
+        Command::new("osascript")
+            .arg("-e")
+            .arg(format!(
+                "display notification {} with title {}",
+                applescript_string(body),
+                applescript_string(summary)
+            ))
+            .status()
+    } else {
+        Command::new("notify-send")
+            .args(["--app-name=todo", summary, body])
+            .status()
+    };
+    if !matches!(status, Ok(s) if s.success()) {
+        println!("{}: {}", summary, body);
+    }
+}
+
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
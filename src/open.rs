@@ -0,0 +1,89 @@
+// `todo open`: opens the web addresses in a task, such as the pull request in
+// "review PR https://github.com/…", in the browser. Addresses are looked for in the name, the note
+// and the linked GitHub issue.
+
+use std::env;
+use std::process::Command;
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::{typed_query, Context, TODO_TABLE};
+use todo_rust::client::Client;
+
+/// Opens the addresses in task `id`, or with `print` prints them, one per line.
+pub fn run(client: &mut Client, ctx: &Context, id: i32, print: bool) -> Result<(), Error> {
+    let task = typed_query::from(TODO_TABLE)
+        .where_(|t| {
+            t.owner.clone().eq(typed_query::Constant {
+                value: ctx.user.clone(),
+            })
+        })
+        .where_(|t| t.id.clone().eq(typed_query::Constant { value: id }))
+        .query(client)?
+        .pop()
+        .ok_or_else(|| Error::NotFound(tr!("No task with id {}", id)))?;
+    let mut text = ctx.decrypt(&task.name, Location::task("name", &task.uuid));
+    if let Some(note) = &task.description {
+        text.push('\n');
+        text.push_str(&ctx.decrypt(note, Location::task("description", &task.uuid)));
+    }
+    let mut urls = find_urls(&text);
+    if let Some(url) = task.github_url.filter(|u| !urls.contains(u)) {
+        urls.push(url);
+    }
+    if urls.is_empty() {
+        return Err(Error::NotFound(tr!("Task {} has no web addresses", id)));
+    }
+    for url in &urls {
+        if print || ctx.dry_run {
+            println!("{}", url);
+        } else {
+            open(url)?;
+        }
+    }
+    Ok(())
+}
+
+// The `http` and `https` addresses in `text`, in order and without duplicates. Punctuation
+// ending a sentence, or closing parentheses around an address, isn't part of it.
+fn find_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let start = match word.find("https://").or_else(|| word.find("http://")) {
+            Some(start) => start,
+            None => continue,
+        };
+        let url = word[start..].trim_end_matches(|c: char| ".,;:!?)]}>'\"".contains(c));
+        if url.contains("://") && !url.ends_with("://") && !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
+}
+
+// Opens `url` with `$BROWSER`, or the platform's opener.
+fn open(url: &str) -> Result<(), Error> {
+    let opener = env::var("BROWSER")
+        .ok()
+        .filter(|b| !b.is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "macos") {
+                "open".to_string()
+            } else {
+                "xdg-open".to_string()
+            }
+        });
+    let status = Command::new(&opener)
+        .arg(url)
+        .status()
+        .map_err(|e| Error::Other(tr!("Failed to start '{}': {}", opener, e)))?;
+    if !status.success() {
+        return Err(Error::Other(tr!(
+            "'{}' failed to open {} ({})",
+            opener,
+            url,
+            status
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+// The manual order of tasks, shown by `list --sort manual`. Tasks have a fractional
+// `sort_order`, so moving one only changes that task: it gets a value between its new neighbours.
+// Only when the gap between them gets too small are the user's tasks numbered again.
+
+use crate::error::Error;
+use crate::task_ref::TaskRef;
+use crate::Context;
+use todo_rust::client::{Client, Transaction};
+
+// Below this gap between neighbours, the tasks are renumbered.
+const MIN_GAP: f64 = 1e-9;
+
+#[derive(Debug)]
+pub enum Position {
+    Top,
+    Bottom,
+    Before(TaskRef),
+}
+
+/// Moves task `id` to `position` in the user's manual order.
+pub fn move_task(
+    client: &mut Client,
+    ctx: &Context,
+    id: i32,
+    position: Position,
+) -> Result<(), Error> {
+    let other = match &position {
+        Position::Before(other) => Some(other.resolve(client, ctx)?),
+        _ => None,
+    };
+    if other == Some(id) {
+        return Err(Error::Usage(tr!("Can't move task {} before itself", id)));
+    }
+    let mut transaction = client.transaction()?;
+    // Tasks that were never moved follow the moved ones, in the order they were created.
+    ctx.execute(
+        &mut transaction,
+        "update todo t set sort_order = coalesce(
+                 (select max(sort_order) from todo where owner = $1), 0) + n.rank
+             from (select id, row_number() over (order by created_time, id) as rank
+                 from todo where owner = $1 and sort_order is null) n
+             where t.id = n.id",
+        &[&ctx.user],
+    )?;
+    let order = match other {
+        None => {
+            let top = matches!(position, Position::Top);
+            let row = transaction.query_one(
+                "select min(sort_order), max(sort_order) from todo
+                 where owner = $1 and id <> $2",
+                &[&ctx.user, &id],
+            )?;
+            let (min, max): (Option<f64>, Option<f64>) = (row.get(0), row.get(1));
+            match (top, min, max) {
+                (true, Some(min), _) => min - 1.0,
+                (false, _, Some(max)) => max + 1.0,
+                _ => 1.0,
+            }
+        }
+        Some(other) => match before(&mut transaction, ctx, id, other)? {
+            Some(order) => order,
+            None => {
+                ctx.execute(
+                    &mut transaction,
+                    "update todo t set sort_order = n.rank
+                         from (select id, row_number() over (order by sort_order, id) as rank
+                             from todo where owner = $1) n
+                         where t.id = n.id",
+                    &[&ctx.user],
+                )?;
+                before(&mut transaction, ctx, id, other)?.unwrap_or_default()
+            }
+        },
+    };
+    ctx.execute(
+        &mut transaction,
+        "update todo set sort_order = $1 where id = $2 and owner = $3",
+        &[&order, &id, &ctx.user],
+    )?;
+    ctx.finish(transaction)?;
+    println!("{}: {}", id, tr!("moved"));
+    Ok(())
+}
+
+// The sort order that puts task `id` right before `other`, or `None` if the gap is too small.
+fn before(
+    transaction: &mut Transaction,
+    ctx: &Context,
+    id: i32,
+    other: i32,
+) -> Result<Option<f64>, Error> {
+    let row = transaction.query_one(
+        "select o.sort_order,
+                 (select max(sort_order) from todo
+                     where owner = $1 and id <> $2 and sort_order < o.sort_order)
+             from todo o
+             where o.id = $3",
+        &[&ctx.user, &id, &other],
+    )?;
+    let next: f64 = row.get(0);
+    let previous: Option<f64> = row.get(1);
+    Ok(match previous {
+        Some(previous) if next - previous < MIN_GAP => None,
+        Some(previous) => Some((previous + next) / 2.0),
+        None => Some(next - 1.0),
+    })
+}
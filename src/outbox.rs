@@ -0,0 +1,273 @@
+// Tasks added while the database, or the `todo serve` daemon in remote mode, can't be reached.
+// They're kept in the data directory with a UUID, shown by a temporary id made from it such as
+// `~1f0c9a3e`, and created by the next command that reaches the database or the daemon. They're
+// created by their UUID, so a task that was created before a failure isn't created twice. The
+// outbox is replaced as a whole after every change, so it never holds a task partly.
+//
+// Only options that don't need the database can be given, so not due times or templates. Names
+// and notes are kept as they would be stored, so with encryption enabled they stay encrypted.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::json::Json;
+use crate::task_ref::new_uuid;
+use crate::time::format_iso;
+use crate::webhook;
+use crate::{alias, cache, data_dir, output, priority_key, Context, TaskOptions};
+use todo_rust::client::Client;
+
+/// A task waiting to be created.
+pub struct Pending {
+    pub uuid: String,
+    pub name: String,
+    note: Option<String>,
+    project: Option<String>,
+    priority: Option<i16>,
+    context: Option<String>,
+    inbox: bool,
+    created: SystemTime,
+}
+
+impl Pending {
+    /// The id the task is shown by until it is created.
+    pub fn temporary_id(&self) -> String {
+        format!("~{}", &self.uuid[..8])
+    }
+}
+
+fn path(ctx: &Context) -> Option<PathBuf> {
+    let name = match &ctx.profile {
+        Some(profile) => format!("outbox-{}-{}.json", profile, ctx.user),
+        None => format!("outbox-{}.json", ctx.user),
+    };
+    data_dir().map(|dir| dir.join(name))
+}
+
+/// Keeps a task added by `add` (or `capture`, with `inbox`) to create it later.
+pub fn queue(ctx: &Context, name: &str, options: TaskOptions, inbox: bool) -> Result<(), Error> {
+    if options.due.is_some() || options.estimate.is_some() || !options.fields.is_empty() {
+        return Err(Error::Usage(tr!(
+            "Only --note, --project, --priority and --context can be given while the database can't be reached"
+        )));
+    }
+    let uuid = new_uuid();
+    let task = Pending {
+        name: ctx.encrypt(name, Location::task("name", &uuid)),
+        note: options
+            .note
+            .map(|n| ctx.encrypt(&n, Location::task("description", &uuid))),
+        uuid,
+        project: options.project,
+        priority: options.priority,
+        context: options.context,
+        inbox,
+        created: SystemTime::now(),
+    };
+    if ctx.dry_run {
+        println!("{}", task_json(&task));
+        return Ok(());
+    }
+    let mut tasks = load(ctx);
+    let id = task.temporary_id();
+    tasks.push(task);
+    save(ctx, &tasks)?;
+    if !ctx.output.is_text() {
+        output::record(ctx.output, &[&id]);
+    } else if ctx.verbosity >= 0 {
+        println!(
+            "{}",
+            tr!(
+                "Queued task {}, to be created once the database can be reached",
+                id
+            )
+        );
+    }
+    Ok(())
+}
+
+/// The tasks waiting to be created.
+pub fn load(ctx: &Context) -> Vec<Pending> {
+    let json = match path(ctx).and_then(|path| fs::read_to_string(path).ok()) {
+        Some(text) => text,
+        None => return Vec::new(),
+    };
+    match Json::parse(&json) {
+        Ok(json) => json
+            .get("tasks")
+            .and_then(Json::as_array)
+            .map(|tasks| tasks.iter().filter_map(task).collect())
+            .unwrap_or_default(),
+        Err(e) => {
+            log::warn!("{}", tr!("Ignoring the invalid outbox: {}", e));
+            Vec::new()
+        }
+    }
+}
+
+// Replaces the outbox with `tasks`, by writing a new file and moving it over the old one.
+fn save(ctx: &Context, tasks: &[Pending]) -> Result<(), Error> {
+    let path = path(ctx).ok_or_else(|| Error::Other(tr!("No data directory for the outbox")))?;
+    let failed = |e: std::io::Error| {
+        Error::Other(tr!("Failed to write the outbox {}: {}", path.display(), e))
+    };
+    if tasks.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(failed(e)),
+            _ => Ok(()),
+        };
+    }
+    let json = Json::object(vec![(
+        "tasks",
+        Json::Array(tasks.iter().map(task_json).collect()),
+    )]);
+    let new = path.with_extension("json.new");
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| cache::write_private(&new, &json.to_string()))
+        .and_then(|_| fs::rename(&new, &path))
+        .map_err(failed)
+}
+
+/// Creates the tasks in the outbox in the database, in one transaction.
+pub fn flush(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let tasks = load(ctx);
+    if tasks.is_empty() || ctx.dry_run {
+        return Ok(());
+    }
+    let mut transaction = client.transaction()?;
+    let mut ids = Vec::new();
+    for task in &tasks {
+        let inserted = transaction.query_opt(
+            "insert into todo (uuid, name, description, project, priority, context, inbox,
+                 created_time, owner)
+                 values ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 on conflict (uuid) do nothing
+                 returning id",
+            &[
+                &task.uuid,
+                &task.name,
+                &task.note,
+                &task.project,
+                &task.priority,
+                &task.context,
+                &task.inbox,
+                &task.created,
+                &ctx.user,
+            ],
+        )?;
+        let id: i32 = match inserted {
+            Some(row) => row.get(0),
+            None => transaction
+                .query_one(
+                    "select id from todo where uuid = $1 and owner = $2",
+                    &[&task.uuid, &ctx.user],
+                )?
+                .get(0),
+        };
+        ids.push(id);
+    }
+    transaction.commit()?;
+    save(ctx, &[])?;
+    for (task, id) in tasks.iter().zip(&ids) {
+        created(ctx, task, *id);
+    }
+    let tasks = webhook::tasks(client, ctx, &ids)?;
+    webhook::send(ctx, webhook::Event::Created, &tasks);
+    Ok(())
+}
+
+/// Creates the tasks in the outbox with `create`, which returns the id of a task, one by one.
+/// The outbox keeps the tasks that weren't created yet when creating one fails.
+pub fn flush_with<F>(ctx: &Context, mut create: F) -> Result<(), Error>
+where
+    F: FnMut(&Json) -> Result<i32, Error>,
+{
+    let mut tasks = load(ctx);
+    if ctx.dry_run {
+        return Ok(());
+    }
+    while let Some(task) = tasks.first() {
+        let body = Json::object(vec![
+            ("uuid", Json::from(task.uuid.as_str())),
+            (
+                "name",
+                Json::from(ctx.decrypt(&task.name, Location::task("name", &task.uuid))),
+            ),
+            (
+                "note",
+                Json::from(
+                    task.note
+                        .as_deref()
+                        .map(|n| ctx.decrypt(n, Location::task("description", &task.uuid))),
+                ),
+            ),
+            ("project", Json::from(task.project.clone())),
+            ("priority", Json::from(task.priority.map(priority_key))),
+            ("context", Json::from(task.context.clone())),
+            ("inbox", Json::from(task.inbox)),
+            ("created", Json::from(format_iso(task.created))),
+        ]);
+        let id = create(&body)?;
+        created(ctx, task, id);
+        tasks.remove(0);
+        save(ctx, &tasks)?;
+    }
+    Ok(())
+}
+
+fn created(ctx: &Context, task: &Pending, id: i32) {
+    if ctx.verbosity >= 0 && ctx.output.is_text() {
+        println!(
+            "{}",
+            tr!(
+                "Created queued task {} as {} ({})",
+                task.temporary_id(),
+                id,
+                alias::encode(id)
+            )
+        );
+    }
+}
+
+fn task_json(task: &Pending) -> Json {
+    Json::object(vec![
+        ("uuid", Json::from(task.uuid.as_str())),
+        ("name", Json::from(task.name.as_str())),
+        ("note", Json::from(task.note.clone())),
+        ("project", Json::from(task.project.clone())),
+        ("priority", Json::from(task.priority.map(i32::from))),
+        ("context", Json::from(task.context.clone())),
+        ("inbox", Json::from(task.inbox)),
+        (
+            "created",
+            Json::from(
+                task.created
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs_f64())
+                    .unwrap_or_default(),
+            ),
+        ),
+    ])
+}
+
+fn task(json: &Json) -> Option<Pending> {
+    let text = |key: &str| json.get(key).and_then(Json::as_str).map(str::to_string);
+    Some(Pending {
+        uuid: text("uuid").filter(|u| u.len() >= 8)?,
+        name: text("name")?,
+        note: text("note"),
+        project: text("project"),
+        priority: json
+            .get("priority")
+            .and_then(Json::as_i64)
+            .map(|p| p as i16),
+        context: text("context"),
+        inbox: json.get("inbox") == Some(&Json::Bool(true)),
+        created: UNIX_EPOCH
+            + std::time::Duration::from_secs_f64(json.get("created")?.as_f64()?.max(0.0)),
+    })
+}
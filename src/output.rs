@@ -0,0 +1,59 @@
+// Output for scripts: one record per task with tab-separated fields, ended by a newline (`tsv`) or
+// a NUL byte (`null`, for `xargs -0` and `fzf --read0`). There are no headers or colors, times are
+// ISO 8601 in UTC, and values are escaped like PostgreSQL's COPY text format, so a field never
+// contains a tab, newline or NUL. With `jsonl`, a record is a JSON array of the fields on a line
+// of its own, and `list` writes the tasks as JSON objects, as they are read from the database.
+
+use crate::json::Json;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Tsv,
+    Null,
+    Jsonl,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Format> {
+        match s {
+            "text" => Some(Format::Text),
+            "tsv" => Some(Format::Tsv),
+            "null" => Some(Format::Null),
+            "jsonl" => Some(Format::Jsonl),
+            _ => None,
+        }
+    }
+
+    /// Whether the output is meant for people, with colors and translated, relative times.
+    pub fn is_text(self) -> bool {
+        self == Format::Text
+    }
+}
+
+/// Writes one record in `format`, which must not be `Text`.
+pub fn record(format: Format, fields: &[&str]) {
+    if format == Format::Jsonl {
+        let fields = fields.iter().map(|f| Json::from(*f)).collect();
+        println!("{}", Json::Array(fields));
+        return;
+    }
+    let fields: Vec<String> = fields.iter().map(|f| escape(f)).collect();
+    let end = if format == Format::Null { '\0' } else { '\n' };
+    print!("{}{}", fields.join("\t"), end);
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
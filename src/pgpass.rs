@@ -0,0 +1,233 @@
+// The connection files libpq reads, which the postgres crate doesn't: `pg_service.conf`, for
+// named sets of connection parameters selected with `service=<name>` (or `$PGSERVICE`), and
+// `.pgpass`, for passwords. Only `key=value` connection strings are resolved; URLs are used as
+// they are.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const DEFAULT_HOST: &str = "localhost";
+const DEFAULT_PORT: &str = "5432";
+
+/// Completes a connection string with the parameters of its service and a password from
+/// `.pgpass`. Parameters in the connection string take precedence over the service's.
+pub fn resolve(connection: &str) -> Result<String, String> {
+    if connection.contains("://") {
+        return Ok(connection.to_string());
+    }
+    let mut params = parse(connection)?;
+    let service = take(&mut params, "service").or_else(|| env::var("PGSERVICE").ok());
+    if let Some(service) = service.filter(|s| !s.is_empty()) {
+        for (key, value) in service_params(&service)? {
+            if !params.iter().any(|(k, _)| *k == key) {
+                params.push((key, value));
+            }
+        }
+    }
+    if !params.iter().any(|(k, _)| k == "password") {
+        let get = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+        };
+        let host = get("host").unwrap_or_else(|| DEFAULT_HOST.to_string());
+        let port = get("port").unwrap_or_else(|| DEFAULT_PORT.to_string());
+        let user = get("user")
+            .or_else(|| env::var("USER").ok())
+            .unwrap_or_default();
+        let dbname = get("dbname").unwrap_or_else(|| user.clone());
+        if let Some(password) = password(&host, &port, &dbname, &user) {
+            params.push(("password".to_string(), password));
+        }
+    }
+    Ok(params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, quote(value)))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Adds a password to a `key=value` connection string.
+pub fn with_password(connection: &str, password: &str) -> String {
+    format!("{} password={}", connection, quote(password))
+}
+
+/// The value of `key` in a `key=value` connection string.
+pub fn param(connection: &str, key: &str) -> Option<String> {
+    parse(connection)
+        .ok()?
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v)
+}
+
+/// Sets `key` in a `key=value` connection string.
+pub fn with_param(connection: &str, key: &str, value: &str) -> Result<String, String> {
+    let mut params = parse(connection)?;
+    take(&mut params, key);
+    params.push((key.to_string(), value.to_string()));
+    Ok(params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, quote(value)))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+// Splits a connection string into its parameters. Values may be single-quoted, with `\'` and
+// `\\` as escapes.
+fn parse(connection: &str) -> Result<Vec<(String, String)>, String> {
+    let mut params = Vec::new();
+    let mut chars = connection.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Ok(params);
+        }
+        let key: String = chars
+            .by_ref()
+            .take_while(|&c| c != '=')
+            .collect::<String>()
+            .trim()
+            .to_string();
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'\'') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('\\') => value.extend(chars.next()),
+                    Some('\'') => break,
+                    Some(c) => value.push(c),
+                    None => return Err(tr!("Unterminated quote in connection string")),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+        }
+        if key.is_empty() {
+            return Err(tr!("Invalid connection string: {}", connection));
+        }
+        params.push((key, value));
+    }
+}
+
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '\'' || c == '\\') {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn take(params: &mut Vec<(String, String)>, key: &str) -> Option<String> {
+    let i = params.iter().position(|(k, _)| k == key)?;
+    Some(params.remove(i).1)
+}
+
+fn home() -> Option<PathBuf> {
+    env::var_os("HOME").map(PathBuf::from)
+}
+
+// The parameters of `service`, from the user's service file, or else the system-wide one.
+fn service_params(service: &str) -> Result<Vec<(String, String)>, String> {
+    let files = [
+        env::var_os("PGSERVICEFILE")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|h| h.join(".pg_service.conf"))),
+        env::var_os("PGSYSCONFDIR").map(|d| PathBuf::from(d).join("pg_service.conf")),
+    ];
+    for file in files.iter().flatten() {
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        if let Some(params) = find_service(&text, service) {
+            return Ok(params);
+        }
+    }
+    Err(tr!("Unknown database service: {}", service))
+}
+
+// Reads the `[service]` section of a service file, which has `key=value` lines.
+fn find_service(text: &str, service: &str) -> Option<Vec<(String, String)>> {
+    let mut params = None;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if params.is_some() {
+                break;
+            }
+            if name.trim() == service {
+                params = Some(Vec::new());
+            }
+            continue;
+        }
+        if let (Some(params), Some((key, value))) = (&mut params, line.split_once('=')) {
+            params.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    params
+}
+
+// Looks up the password in `$PGPASSFILE` or `~/.pgpass`, whose lines are
+// `host:port:database:user:password`, where `*` matches anything and `\` escapes `:` and `\`.
+// Like libpq, a file others can read is ignored.
+fn password(host: &str, port: &str, dbname: &str, user: &str) -> Option<String> {
+    let file = env::var_os("PGPASSFILE")
+        .map(PathBuf::from)
+        .or_else(|| home().map(|h| h.join(".pgpass")))?;
+    let text = fs::read_to_string(&file).ok()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&file).ok()?.permissions().mode();
+        if mode & 0o077 != 0 {
+            log::warn!(
+                "{}",
+                tr!(
+                    "Ignoring {}, since others can read it; restrict it with 'chmod 0600'",
+                    file.display()
+                )
+            );
+            return None;
+        }
+    }
+    let wanted = [host, port, dbname, user];
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(split_pgpass_line)
+        .find(|fields| {
+            fields.len() == 5
+                && fields
+                    .iter()
+                    .zip(wanted.iter())
+                    .all(|(field, wanted)| field == "*" || field == wanted)
+        })
+        .map(|mut fields| fields.remove(4))
+}
+
+fn split_pgpass_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => fields.last_mut().unwrap().extend(chars.next()),
+            ':' => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
@@ -0,0 +1,65 @@
+// External subcommands, like git's: `todo foo` runs an executable named `todo-foo` from `$PATH`
+// when there is no built-in `foo` command. The plugin gets the remaining arguments, and the global
+// options and database connection in `TODO_*` environment variables.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use crate::config;
+use crate::error::{Error, EXIT_OTHER};
+use crate::output;
+use crate::Context;
+
+const PREFIX: &str = "todo-";
+
+/// Finds the plugin for command `name` on `$PATH`.
+pub fn find(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.starts_with('-') || name.contains(std::path::is_separator) {
+        return None;
+    }
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(format!("{}{}", PREFIX, name)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Runs a plugin and exits with its exit code if it fails.
+pub fn run(ctx: &Context, plugin: &Path, args: &[String]) -> Result<(), Error> {
+    let output = match ctx.output {
+        output::Format::Text => "text",
+        output::Format::Tsv => "tsv",
+        output::Format::Null => "null",
+        output::Format::Jsonl => "jsonl",
+    };
+    let flag = |set: bool| if set { "1" } else { "" };
+    let mut command = Command::new(plugin);
+    command
+        .args(args)
+        .env("TODO_USER", &ctx.user)
+        .env("TODO_CONNECTION", &ctx.connection)
+        .env("TODO_OUTPUT", output)
+        .env("TODO_VERBOSITY", ctx.verbosity.to_string())
+        .env("TODO_DRY_RUN", flag(ctx.dry_run))
+        .env("TODO_YES", flag(ctx.assume_yes))
+        .env("TODO_UTC", flag(ctx.utc))
+        .env("TODO_ABSOLUTE_TIMES", flag(ctx.absolute_times));
+    // So the plugin can run todo commands with the same settings.
+    if let Ok(exe) = env::current_exe() {
+        command.env("TODO_EXE", exe);
+    }
+    if let Some(config) = config::path().filter(|p| p.exists()) {
+        command.env("TODO_CONFIG", config);
+    }
+    if let Some(profile) = &ctx.profile {
+        command.env("TODO_PROFILE", profile);
+    }
+    let status = command
+        .status()
+        .map_err(|e| Error::Other(tr!("Failed to run {}: {}", plugin.display(), e)))?;
+    if !status.success() {
+        // The plugin has reported the problem itself.
+        process::exit(status.code().unwrap_or(EXIT_OTHER));
+    }
+    Ok(())
+}
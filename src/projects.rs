@@ -0,0 +1,79 @@
+use std::time::SystemTime;
+
+use crate::error::Error;
+use crate::output;
+use crate::status;
+use crate::time;
+use crate::Context;
+use todo_rust::client::Client;
+
+// The width of the progress bars, in characters.
+const WIDTH: i64 = 20;
+const DONE_BAR: &str = "█";
+const OPEN_BAR: &str = "░";
+
+/// Shows every project with how many of its tasks are open, how much of it is done and the
+/// nearest due time of its open tasks. Cancelled tasks don't count.
+pub fn run(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let rows = client.query(
+        "select project,
+                 count(*) filter (where status = any($2)),
+                 count(*) filter (where status = any($2) or status = 'done'),
+                 min(due_time) filter (where status = any($2))
+             from todo
+             where owner = $1 and project is not null
+             group by project
+             order by project",
+        &[&ctx.user, &status::OPEN],
+    )?;
+    let projects: Vec<(String, i64, i64, Option<SystemTime>)> = rows
+        .iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+        .filter(|(_, _, total, _)| *total > 0)
+        .collect();
+    // Rounds down, so only finished projects show 100%.
+    let percentage = |open: i64, total: i64| (total - open) * 100 / total;
+
+    if !ctx.output.is_text() {
+        for (project, open, total, due) in &projects {
+            output::record(
+                ctx.output,
+                &[
+                    project,
+                    &open.to_string(),
+                    &total.to_string(),
+                    &percentage(*open, *total).to_string(),
+                    &due.map(time::format_iso).unwrap_or_default(),
+                ],
+            );
+        }
+        return Ok(());
+    }
+    if projects.is_empty() {
+        println!("{}", tr!("No projects"));
+        return Ok(());
+    }
+    let width = projects
+        .iter()
+        .map(|(project, ..)| project.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (project, open, total, due) in &projects {
+        let done = (total - open) * WIDTH / total;
+        let mut line = format!(
+            "{:<width$}  {}{}  {:>3}%  {}",
+            project,
+            DONE_BAR.repeat(done as usize),
+            OPEN_BAR.repeat((WIDTH - done) as usize),
+            percentage(*open, *total),
+            tr!("{} of {} open", open, total),
+            width = width
+        );
+        if let Some(due) = due {
+            line.push_str(", ");
+            line.push_str(&tr!("next due {}", ctx.format_time(*due)));
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+// `todo prompt`: a one-line summary of what is due, such as `3 due today, 1 overdue`, for shell
+// prompts and status lines. It runs often, so it is a single query, and it skips the work other
+// commands do on startup. With nothing due it prints nothing.
+
+use std::time::SystemTime;
+
+use crate::error::Error;
+use crate::output;
+use crate::status;
+use crate::time::local_day;
+use crate::Context;
+use todo_rust::client::Client;
+
+pub fn run(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    // Everything due today is due within a day from now.
+    let rows = client.query(
+        "select due_time
+             from todo
+             where owner = $1 and status = any($2) and archived_time is null
+                 and due_time < now() + interval '1 day'
+                 and (snoozed_until is null or snoozed_until <= now())",
+        &[&ctx.user, &status::OPEN],
+    )?;
+    let now = SystemTime::now();
+    let zone = ctx.timezone.as_ref();
+    let today = local_day(now, zone);
+    let (mut overdue, mut due_today) = (0, 0);
+    for row in rows {
+        let due: SystemTime = row.get(0);
+        if due < now {
+            overdue += 1;
+        } else if local_day(due, zone) == today {
+            due_today += 1;
+        }
+    }
+
+    if !ctx.output.is_text() {
+        output::record(ctx.output, &[&due_today.to_string(), &overdue.to_string()]);
+        return Ok(());
+    }
+    let mut parts = Vec::new();
+    if due_today > 0 {
+        parts.push(tr!("{} due today", due_today));
+    }
+    if overdue > 0 {
+        parts.push(tr!("{} overdue", overdue));
+    }
+    if !parts.is_empty() {
+        println!("{}", parts.join(", "));
+    }
+    Ok(())
+}
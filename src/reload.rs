@@ -0,0 +1,83 @@
+// Reloading the config file in the modes that keep running, `remind --daemon`, `serve` and
+// `watch`, so changes to reports, webhooks, hooks, reminders and the log level apply without a
+// restart. The modes check the file before doing their work, and read it as on startup, with the
+// options given on the command line. A config that fails to load leaves the settings as they were.
+// Database connections are kept, so changes to the connection need a restart.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::config::{self, Config};
+use crate::logger;
+use crate::{parse_options, Context};
+
+pub struct Watcher {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    config: Config,
+}
+
+impl Watcher {
+    pub fn new() -> Watcher {
+        let path = config::path();
+        Watcher {
+            modified: modified(&path),
+            config: Config::load().unwrap_or_default(),
+            path,
+        }
+    }
+
+    /// The settings read again from the config file, if it changed since the last check.
+    pub fn check(&mut self, ctx: &Context) -> Option<Context> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        let reloaded = Config::load().and_then(|config| {
+            let changes = config.changes(&self.config);
+            if changes.is_empty() {
+                return Ok(None);
+            }
+            let settings = settings(ctx, config.clone())?;
+            self.config = config;
+            Ok(Some((settings, changes)))
+        });
+        match reloaded {
+            Ok(Some((settings, changes))) => {
+                logger::set_verbosity(settings.verbosity);
+                log::info!("{}", tr!("Reloaded the config: {}", changes.join(", ")));
+                Some(settings)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                log::warn!(
+                    "{}",
+                    tr!("Failed to reload the config, keeping the settings: {}", e)
+                );
+                None
+            }
+        }
+    }
+}
+
+/// The settings read from the config file as on startup, for a mode that needs a copy of its own.
+pub fn read(ctx: &Context) -> Result<Context, String> {
+    settings(ctx, Config::load()?)
+}
+
+fn modified(path: &Option<PathBuf>) -> Option<SystemTime> {
+    fs::metadata(path.as_ref()?).and_then(|m| m.modified()).ok()
+}
+
+fn settings(ctx: &Context, config: Config) -> Result<Context, String> {
+    let config = match &ctx.profile {
+        Some(profile) => config.with_profile(profile)?,
+        None => config,
+    };
+    let mut options = ctx.options.clone();
+    let mut settings = parse_options(&mut options, &config)?;
+    settings.options = ctx.options.clone();
+    Ok(settings)
+}
@@ -0,0 +1,62 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::notify;
+use crate::reload;
+use crate::status;
+use crate::time::format_duration;
+use crate::Context;
+use todo_rust::client::Client;
+
+/// Sends a notification for every open task whose reminder is due, and marks those tasks as
+/// reminded so they are only notified once (until snoozed). Returns the number of notifications.
+pub fn check(client: &mut Client, ctx: &Context) -> Result<usize, Error> {
+    let due = client.query(
+        "select id, name, extract(epoch from due_time - now())::float8, uuid
+             from todo
+             where owner = $1
+               and status = any($2)
+               and due_time is not null
+               and reminded_time is null
+               and due_time - make_interval(secs => coalesce(remind_before, 0)) <= now()
+               and (snoozed_until is null or snoozed_until <= now())
+             order by due_time",
+        &[&ctx.user, &status::OPEN],
+    )?;
+    for row in &due {
+        let id: i32 = row.get(0);
+        let name: String = row.get(1);
+        let until_due: f64 = row.get(2);
+        let summary = if until_due >= 60.0 {
+            tr!("Due in {}", format_duration(until_due))
+        } else if until_due <= -60.0 {
+            tr!("Overdue by {}", format_duration(-until_due))
+        } else {
+            tr!("Due now")
+        };
+        let name = ctx.decrypt(&name, Location::task("name", row.get(3)));
+        notify::send(&summary, &format!("{}: {}", id, name));
+        client.execute(
+            "update todo set reminded_time = now(), snoozed_until = null where id = $1",
+            &[&id],
+        )?;
+    }
+    Ok(due.len())
+}
+
+/// Checks for due reminders every `interval`, by default `remind.interval` of the config,
+/// forever. Changes to the config are applied as they are made.
+pub fn daemon(client: &mut Client, ctx: &Context, interval: Option<Duration>) -> Result<(), Error> {
+    let mut watcher = reload::Watcher::new();
+    let mut reloaded = None;
+    loop {
+        if let Some(settings) = watcher.check(reloaded.as_ref().unwrap_or(ctx)) {
+            reloaded = Some(settings);
+        }
+        let ctx = reloaded.as_ref().unwrap_or(ctx);
+        check(client, ctx)?;
+        thread::sleep(interval.unwrap_or(ctx.remind_interval));
+    }
+}
@@ -0,0 +1,350 @@
+// Remote mode: with `remote_url` in the config, commands are sent to a `todo serve` daemon over
+// HTTP instead of to the database, so a client doesn't need database credentials. Only the
+// commands the REST API offers are available: add, list, show, complete and remove. The server
+// decides whose tasks these are, and decrypts them.
+
+use std::time::SystemTime;
+
+use crate::error::Error;
+use crate::http;
+use crate::ical;
+use crate::json::Json;
+use crate::outbox;
+use crate::output;
+use crate::status::{self, Status};
+use crate::task_ref::TaskRef;
+use crate::{alias, ask, fields, parse_priority, priority_key, priority_name, show, time, urgency};
+use crate::{is_stale, stale_filter, Command, Context, TodoRecord};
+
+pub fn run(ctx: &Context, url: &str, command: Command) -> Result<(), Error> {
+    let url = url.trim_end_matches('/');
+    let flushed = outbox::flush_with(ctx, |body| {
+        let created = request(ctx, "POST", &format!("{}/tasks", url), Some(body))?;
+        Ok(task(&created)?.id)
+    });
+    match flushed {
+        // Queued tasks wait for the daemon, while adding tasks queues them too.
+        Err(Error::Unreachable(e)) if matches!(command, Command::Add { .. }) => {
+            log::info!("{}", e)
+        }
+        result => result?,
+    }
+    match command {
+        Command::Add {
+            name,
+            template,
+            options,
+            quiet,
+            json,
+            unique,
+        } => {
+            if template.is_some()
+                || unique
+                || options.estimate.is_some()
+                || !options.fields.is_empty()
+            {
+                return Err(unsupported());
+            }
+            let name = name.ok_or_else(|| Error::Usage(tr!("Missing task name")))?;
+            let body = Json::object(vec![
+                ("name", Json::from(name.as_str())),
+                ("due", Json::from(options.due.clone())),
+                ("note", Json::from(options.note.clone())),
+                ("project", Json::from(options.project.clone())),
+                ("priority", Json::from(options.priority.map(priority_key))),
+                ("context", Json::from(options.context.clone())),
+            ]);
+            if ctx.dry_run {
+                println!("POST {}/tasks\n  -- {}", url, body);
+                return Ok(());
+            }
+            let created = match request(ctx, "POST", &format!("{}/tasks", url), Some(&body)) {
+                Err(Error::Unreachable(e)) if options.due.is_none() => {
+                    log::info!("{}", e);
+                    return outbox::queue(ctx, &name, options, false);
+                }
+                result => result?,
+            };
+            let task = task(&created)?;
+            if json {
+                println!("{}", created);
+            } else if !ctx.output.is_text() {
+                output::record(ctx.output, &[&task.id.to_string(), &alias::encode(task.id)]);
+            } else if !quiet && ctx.verbosity >= 0 {
+                println!(
+                    "{}",
+                    tr!("Created task {} ({})", task.id, alias::encode(task.id))
+                );
+            }
+        }
+        Command::List {
+            statuses,
+            fields: filters,
+            sort: _,
+            archived: _,
+            pinned,
+            stale,
+            assigned,
+            due_before,
+            format,
+        } => {
+            let filters = fields::parse_all(&ctx.fields, &filters).map_err(Error::Usage)?;
+            let assigned = assigned.map(|a| a.resolve(ctx));
+            let stale = stale_filter(ctx, stale)?;
+            // The tasks are filtered here, where there is no database to interpret times.
+            let due_before = due_before
+                .map(|t| {
+                    time::parse_offline(&t).ok_or_else(|| Error::Usage(tr!("Invalid time: {}", t)))
+                })
+                .transpose()?;
+            let status = if statuses == status::OPEN {
+                String::new()
+            } else if statuses == Status::ALL {
+                "?status=all".to_string()
+            } else {
+                format!("?status={}", statuses[0].as_str())
+            };
+            // The tasks come in pages, each linking to the next.
+            let mut tasks = Vec::new();
+            let mut next = Some(format!("{}/tasks{}", url, status));
+            while let Some(page) = next {
+                let (page, response) = fetch(ctx, "GET", &page, None)?;
+                for json in page
+                    .as_array()
+                    .ok_or_else(|| Error::Other(tr!("Invalid response from {}", url)))?
+                {
+                    tasks.push(task(json)?);
+                }
+                next = response
+                    .header("Link")
+                    .and_then(next_page)
+                    .map(|path| format!("{}{}", url, path));
+            }
+            let format = format
+                .as_ref()
+                .or(ctx.list_format.as_ref().filter(|_| ctx.output.is_text()));
+            let now = SystemTime::now();
+            for task in tasks {
+                if pinned && !task.pinned
+                    || stale.is_some_and(|after| !is_stale(&task, after, now))
+                    || assigned.as_ref().is_some_and(|a| !a.matches(&task))
+                    || due_before
+                        .is_some_and(|before| task.due_time.is_none_or(|due| due >= before))
+                    || !filters
+                        .iter()
+                        .all(|(name, value)| task.fields.get(name) == Some(value))
+                {
+                    continue;
+                }
+                let score = urgency::score(&task, now, &ctx.urgency);
+                if let Some(format) = format {
+                    println!("{}", format.render(ctx, &task, score));
+                } else if !ctx.output.is_text() {
+                    output::record(
+                        ctx.output,
+                        &[
+                            &task.id.to_string(),
+                            &alias::encode(task.id),
+                            task.status.as_str(),
+                            &task.name,
+                            &time::format_iso(task.created_time),
+                            &task.due_time.map(time::format_iso).unwrap_or_default(),
+                            task.priority.map(priority_key).unwrap_or_default(),
+                            task.project.as_deref().unwrap_or_default(),
+                            task.context.as_deref().unwrap_or_default(),
+                        ],
+                    );
+                } else {
+                    println!(
+                        "{} ({}): {} ({})",
+                        task.id,
+                        alias::encode(task.id),
+                        task.name,
+                        details(ctx, &task).join(", ")
+                    );
+                }
+            }
+        }
+        Command::Show { id } => {
+            let task = task(&request(ctx, "GET", &task_url(url, &id), None)?)?;
+            show(ctx, &task);
+        }
+        Command::Complete { ids } => {
+            for id in ids {
+                let url = format!("{}/complete", task_url(url, &id));
+                if ctx.dry_run {
+                    println!("POST {}", url);
+                    continue;
+                }
+                let task = task(&request(ctx, "POST", &url, None)?)?;
+                println!("{}: {}", task.id, tr!("completed"));
+            }
+        }
+        Command::Remove { ids, force: false } => {
+            if !ctx.assume_yes && !ctx.dry_run {
+                let mut tasks = Vec::new();
+                for id in &ids {
+                    let task = task(&request(ctx, "GET", &task_url(url, id), None)?)?;
+                    tasks.push(format!("{}: {}", task.id, task.name));
+                }
+                ask(&tr!("Remove these tasks?"), &tasks)?;
+            }
+            for id in ids {
+                let url = task_url(url, &id);
+                if ctx.dry_run {
+                    println!("DELETE {}", url);
+                    continue;
+                }
+                request(ctx, "DELETE", &url, None)?;
+                println!("{}: {}", id, tr!("removed"));
+            }
+        }
+        _ => return Err(unsupported()),
+    }
+    Ok(())
+}
+
+fn unsupported() -> Error {
+    Error::Usage(tr!(
+        "This command is not available in remote mode, remove 'remote_url' from the config to use it"
+    ))
+}
+
+fn task_url(url: &str, id: &TaskRef) -> String {
+    format!("{}/tasks/{}", url, id)
+}
+
+// Sends a request to the API, returning the response body. Errors reported by the server keep
+// their kind, so they end the command with the same exit code as they would locally.
+fn request(ctx: &Context, method: &str, url: &str, body: Option<&Json>) -> Result<Json, Error> {
+    fetch(ctx, method, url, body).map(|(json, _)| json)
+}
+
+// Like `request`, also returning the response for its headers.
+fn fetch(
+    ctx: &Context,
+    method: &str,
+    url: &str,
+    body: Option<&Json>,
+) -> Result<(Json, http::Response), Error> {
+    let body = body.map(|b| b.to_string());
+    let authorization = ctx.remote_token.as_ref().map(|t| format!("Bearer {}", t));
+    let mut headers = vec![("Content-Type", "application/json")];
+    if let Some(authorization) = &authorization {
+        headers.push(("Authorization", authorization));
+    }
+    // Without a response, the daemon couldn't be reached.
+    let response =
+        http::request(method, url, &headers, body.as_deref()).map_err(Error::Unreachable)?;
+    let json = if response.body.trim().is_empty() {
+        Json::Null
+    } else {
+        Json::parse(&response.body)
+            .map_err(|_| Error::Other(tr!("Invalid response from {}", url)))?
+    };
+    if response.is_success() {
+        return Ok((json, response));
+    }
+    let message = json
+        .get("error")
+        .and_then(|e| e.as_str())
+        .map(|e| e.to_string())
+        .unwrap_or_else(|| tr!("Request to {} failed: {}", url, response.status));
+    Err(match response.status {
+        400 => Error::Usage(message),
+        404 => Error::NotFound(message),
+        _ => Error::Other(message),
+    })
+}
+
+// The path of the next page in a `Link` header, such as `</tasks?cursor=...>; rel="next"`.
+fn next_page(link: &str) -> Option<&str> {
+    link.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|p| p.trim() == "rel=\"next\"")
+            .then(|| target.trim().trim_start_matches('<').trim_end_matches('>'))
+    })
+}
+
+// Reads a task as served by `todo serve`. Attributes the API doesn't serve are left empty.
+fn task(json: &Json) -> Result<TodoRecord, Error> {
+    let invalid = || Error::Other(tr!("Invalid task from the server: {}", json));
+    let text = |key: &str| {
+        json.get(key)
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+    let time = |key: &str| text(key).and_then(|t| parse_iso(&t));
+    Ok(TodoRecord {
+        id: json
+            .get("id")
+            .and_then(|v| v.as_i64())
+            .ok_or_else(invalid)? as i32,
+        name: text("name").ok_or_else(invalid)?,
+        created_time: time("created").ok_or_else(invalid)?,
+        completed_time: time("completed"),
+        due_time: time("due"),
+        remind_before: None,
+        reminded_time: None,
+        snoozed_until: None,
+        description: text("note"),
+        project: text("project"),
+        github_repo: None,
+        github_issue: None,
+        github_url: None,
+        github_close: false,
+        caldav_uid: None,
+        caldav_href: None,
+        caldav_etag: None,
+        caldav_state: None,
+        owner: None,
+        priority: text("priority").and_then(|p| parse_priority(&p).ok()),
+        status: text("status")
+            .and_then(|s| Status::parse(&s))
+            .ok_or_else(invalid)?,
+        context: text("context"),
+        fields: json.get("fields").cloned().unwrap_or(Json::Null),
+        uuid: text("uuid").unwrap_or_default(),
+        reviewed_time: None,
+        archived_time: None,
+        sort_order: None,
+        pinned: false,
+        estimate: json
+            .get("estimate")
+            .and_then(|v| v.as_i64())
+            .map(|e| e as i32),
+        assignee: text("assignee"),
+        waiting_until: time("waiting_until"),
+        waiting_for: text("waiting_for"),
+        inbox: json.get("inbox") == Some(&Json::Bool(true)),
+        external_id: text("external_id"),
+    })
+}
+
+// Parses a time as formatted by `time::format_iso`, such as `2021-08-01T14:00:00Z`.
+fn parse_iso(s: &str) -> Option<SystemTime> {
+    ical::parse_time(&s.replace(['-', ':'], ""))
+}
+
+fn details(ctx: &Context, task: &TodoRecord) -> Vec<String> {
+    let mut details = vec![ctx.format_time(task.created_time)];
+    if task.status != Status::Todo {
+        details.push(task.status.label());
+    }
+    if let Some(context) = &task.context {
+        details.push(format!("@{}", context));
+    }
+    if let Some(priority) = task.priority {
+        details.push(tr!("{} priority", priority_name(priority)));
+    }
+    if let Some(due) = task.due_time {
+        details.push(tr!("due {}", ctx.format_time(due)));
+    }
+    if let Some(estimate) = task.estimate {
+        details.push(tr!("estimate {}", time::format_duration(estimate as f64)));
+    }
+    details.extend(fields::describe(&ctx.fields, &task.fields));
+    details
+}
@@ -0,0 +1,208 @@
+// Named reports, defined in the config and run with `todo report <name>`, such as:
+//
+//     [reports.standup]
+//     description = "What's on for today"
+//     filter = "status:in-progress project:work"
+//     columns = ["id", "name", "due", "field:effort"]
+//     sort = "due"
+//     group = "context"
+//
+// The filter takes the terms of `modify`, the columns and group are `list --format` placeholders,
+// and the sort is a `list --sort` key. Everything is optional: by default a report shows the id
+// and name of the open tasks, by creation, but a report needs at least one key to be defined.
+
+use std::time::SystemTime;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::filter::Filter;
+use crate::line_format::LineFormat;
+use crate::output;
+use crate::urgency;
+use crate::{parse_sort, sort_tasks, Context, SortKey, TodoRecord};
+use todo_rust::client::Client;
+
+const DEFAULT_COLUMNS: &[&str] = &["id", "name"];
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    name: String,
+    description: Option<String>,
+    filter: Filter,
+    columns: Vec<Column>,
+    sort: SortKey,
+    group: Option<Column>,
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    name: String,
+    format: LineFormat,
+}
+
+impl Column {
+    fn parse(name: &str) -> Result<Column, String> {
+        Ok(Column {
+            name: name.strip_prefix("field:").unwrap_or(name).to_string(),
+            format: LineFormat::parse(&format!("{{{}}}", name))?,
+        })
+    }
+}
+
+/// Reads the reports from the `[reports]` section of the config.
+pub fn load(config: &Config) -> Result<Vec<Report>, String> {
+    config
+        .tables("reports")
+        .into_iter()
+        .map(|name| {
+            let key = |key: &str| format!("reports.{}.{}", name, key);
+            let invalid = |e: String| tr!("Invalid report '{}': {}", name, e);
+            let terms: Vec<String> = config
+                .get(&key("filter"))
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            let mut columns = config.get_list(&key("columns"));
+            if columns.is_empty() {
+                columns = DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect();
+            }
+            Ok(Report {
+                name: name.to_string(),
+                description: config.get(&key("description")).map(str::to_string),
+                filter: Filter::parse(&terms).map_err(invalid)?,
+                columns: columns
+                    .iter()
+                    .map(|c| Column::parse(c))
+                    .collect::<Result<_, _>>()
+                    .map_err(invalid)?,
+                sort: parse_sort(config.get(&key("sort"))).map_err(invalid)?,
+                group: config
+                    .get(&key("group"))
+                    .map(Column::parse)
+                    .transpose()
+                    .map_err(invalid)?,
+            })
+        })
+        .collect()
+}
+
+/// Runs the report `name`, or without a name lists the reports.
+pub fn run(client: &mut Client, ctx: &Context, name: Option<&str>) -> Result<(), Error> {
+    let name = match name {
+        Some(name) => name,
+        None => {
+            list(ctx);
+            return Ok(());
+        }
+    };
+    let report = ctx.reports.iter().find(|r| r.name == name).ok_or_else(|| {
+        Error::NotFound(tr!(
+            "Unknown report: {}, define it in the [reports] section of the config",
+            name
+        ))
+    })?;
+
+    let now = SystemTime::now();
+    let mut rows: Vec<(f64, TodoRecord)> = report
+        .filter
+        .tasks(client, ctx)?
+        .into_iter()
+        .map(|row| (urgency::score(&row, now, &ctx.urgency), row))
+        .collect();
+    sort_tasks(&mut rows, report.sort);
+
+    // Groups keep the order of their first task.
+    let mut groups: Vec<(String, Vec<Vec<String>>)> = Vec::new();
+    for (score, row) in &rows {
+        let group = report
+            .group
+            .as_ref()
+            .map(|g| g.format.render(ctx, row, *score))
+            .unwrap_or_default();
+        let cells = report
+            .columns
+            .iter()
+            .map(|c| c.format.render(ctx, row, *score))
+            .collect();
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, cells_of_group)) => cells_of_group.push(cells),
+            None => groups.push((group, vec![cells])),
+        }
+    }
+
+    if !ctx.output.is_text() {
+        for (group, rows) in &groups {
+            for cells in rows {
+                let mut record: Vec<&str> = Vec::new();
+                if report.group.is_some() {
+                    record.push(group);
+                }
+                record.extend(cells.iter().map(String::as_str));
+                output::record(ctx.output, &record);
+            }
+        }
+        return Ok(());
+    }
+    if rows.is_empty() {
+        println!("{}", tr!("No tasks match the report"));
+        return Ok(());
+    }
+    let headers: Vec<String> = report.columns.iter().map(|c| c.name.clone()).collect();
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for cells in groups.iter().flat_map(|(_, rows)| rows) {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    println!("{}", table_row(&headers, &widths));
+    for (i, (group, rows)) in groups.iter().enumerate() {
+        if let Some(column) = &report.group {
+            if i > 0 {
+                println!();
+            }
+            let group = if group.is_empty() {
+                tr!("(no {})", column.name)
+            } else {
+                group.clone()
+            };
+            println!("{}:", group);
+        }
+        for cells in rows {
+            println!("{}", table_row(cells, &widths));
+        }
+    }
+    Ok(())
+}
+
+fn list(ctx: &Context) {
+    if ctx.reports.is_empty() && ctx.output.is_text() {
+        println!(
+            "{}",
+            tr!("No reports, define them in the [reports] section of the config")
+        );
+    }
+    for report in &ctx.reports {
+        let description = report.description.as_deref().unwrap_or_default();
+        if !ctx.output.is_text() {
+            output::record(ctx.output, &[&report.name, description]);
+        } else if description.is_empty() {
+            println!("{}", report.name);
+        } else {
+            println!("{}: {}", report.name, description);
+        }
+    }
+}
+
+// Pads all cells but the last to the width of their column.
+fn table_row(cells: &[String], widths: &[usize]) -> String {
+    let mut line = String::new();
+    for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        if i + 1 == cells.len() {
+            line.push_str(cell);
+        } else {
+            line.push_str(&format!("{:width$}  ", cell, width = width));
+        }
+    }
+    line
+}
@@ -0,0 +1,124 @@
+use std::io::{self, IsTerminal, Write};
+
+use crate::error::Error;
+use crate::status;
+use crate::store::{self, Postgres};
+use crate::time;
+use crate::trash;
+use crate::webhook;
+use crate::{run_bulk, show, typed_query, Context, TODO_TABLE};
+use todo_rust::client::Client;
+
+/// Goes through the open tasks that weren't changed or reviewed in the last `days` days, oldest
+/// first, asking for each whether to keep, reschedule, complete or delete it. Kept tasks count as
+/// reviewed, so they aren't asked about again for a while.
+pub fn run(client: &mut Client, ctx: &Context, days: i32) -> Result<(), Error> {
+    if !io::stdin().is_terminal() {
+        return Err(Error::Usage(tr!("'review' needs a terminal")));
+    }
+    let ids: Vec<i32> = client
+        .query(
+            "select t.id from todo t
+             where t.owner = $1 and t.status = any($2)
+                 and greatest(t.created_time, t.reviewed_time,
+                     (select max(h.time) from todo_history h where h.todo_id = t.id))
+                     < now() - $3::int * interval '1 day'",
+            &[&ctx.user, &status::OPEN, &days],
+        )?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+    let tasks = typed_query::from(TODO_TABLE)
+        .where_(|t| {
+            t.id.clone().in_(
+                ids.iter()
+                    .map(|&value| typed_query::Constant { value })
+                    .collect(),
+            )
+        })
+        .order_by(|t| typed_query::asc(&t.created_time))
+        .query(client)?;
+    if tasks.is_empty() {
+        println!("{}", tr!("Nothing to review"));
+        return Ok(());
+    }
+    let total = tasks.len();
+    for (n, task) in tasks.iter().enumerate() {
+        println!();
+        println!("{}", tr!("Task {} of {}:", n + 1, total));
+        show(ctx, task);
+        loop {
+            let answer = ask(&tr!(
+                "[k]eep, [r]eschedule, [c]omplete, [d]elete, [s]kip or [q]uit? "
+            ))?;
+            match answer.as_str() {
+                "k" | "keep" => mark_reviewed(client, ctx, task.id)?,
+                "r" | "reschedule" => {
+                    let due = ask(&tr!("New due time: "))?;
+                    let due = match time::parse_time(client, &due) {
+                        Ok(due) => due,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+                    run_bulk(client, ctx, &[task.id], &tr!("rescheduled"), |t, id| {
+                        ctx.execute(
+                            t,
+                            "update todo set due_time = $1, reviewed_time = now()
+                             where id = $2 and owner = $3",
+                            &[&due, &id, &ctx.user],
+                        )
+                    })?;
+                }
+                "c" | "complete" => store::complete(&mut Postgres(client), ctx, &[task.id])?,
+                "d" | "delete" => delete(client, ctx, task.id)?,
+                "s" | "skip" | "" => {}
+                "q" | "quit" => return Ok(()),
+                _ => continue,
+            }
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Asks a question on stderr, returning the answer in lower case.
+fn ask(question: &str) -> Result<String, Error> {
+    Ok(prompt(question)?.to_lowercase())
+}
+
+/// Asks a question on stderr, returning the answer without surrounding whitespace. Fails when
+/// the input is closed.
+pub fn prompt(question: &str) -> Result<String, Error> {
+    eprint!("{}", question);
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    let read = io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| Error::Other(e.to_string()))?;
+    // The input was closed.
+    if read == 0 {
+        return Err(Error::Other(tr!("Aborted")));
+    }
+    Ok(answer.trim().to_string())
+}
+
+fn mark_reviewed(client: &mut Client, ctx: &Context, id: i32) -> Result<(), Error> {
+    run_bulk(client, ctx, &[id], &tr!("kept"), |t, id| {
+        ctx.execute(
+            t,
+            "update todo set reviewed_time = now() where id = $1 and owner = $2",
+            &[&id, &ctx.user],
+        )
+    })
+}
+
+pub fn delete(client: &mut Client, ctx: &Context, id: i32) -> Result<(), Error> {
+    let tasks = webhook::tasks(client, ctx, &[id])?;
+    run_bulk(client, ctx, &[id], &tr!("moved to the trash"), |t, id| {
+        trash::remove(t, ctx, id)
+    })?;
+    webhook::send(ctx, webhook::Event::Deleted, &tasks);
+    Ok(())
+}
@@ -0,0 +1,883 @@
+// A small HTTP server exposing the user's tasks as JSON:
+//
+//   GET    /tasks                 open tasks, or those matching filter terms as parameters
+//   GET    /tasks/<id>            one task
+//   POST   /tasks                 create a task from `{"name": ..., "due": ..., ...}`
+//   POST   /tasks/<id>/complete   complete a task, or 409 if it is finished already
+//   DELETE /tasks/<id>            delete a task
+//   GET    /metrics               metrics for Prometheus
+//
+// The parameters of `GET /tasks` are the terms of filters, such as `?project=home&status=all`
+// for `project:home status:all`, and `id` for ids. Its tasks come in pages of `limit` tasks, 100
+// by default, with a `Link` header to the next page. Responses to GET requests have an `ETag`,
+// so clients sending it back in `If-None-Match` get `304 Not Modified` when nothing changed.
+//
+// With `serve.tokens` in the config, clients have to send one of them as `Authorization: Bearer
+// <token>`, and with `serve.rate_limit` they can only make that many requests a minute.
+//
+// Every connection is handled on its own thread, using a database connection from a pool. Up to
+// 64 connections are handled at once, others are answered with `503 Service Unavailable`.
+// Changes to the config are applied from the next connection on.
+//
+// The server blocks like the commands do, as typed_query only runs statements on blocking
+// connections and the requests share the commands' code for tasks. No more requests than the pool
+// has connections can use the database at once, so threads cost little next to async tasks.
+//
+// With `--grpc`, the same API is served over gRPC instead, see grpc.rs.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::Config;
+use crate::crypto::{self, Location};
+use crate::error::Error;
+use crate::filter::Filter;
+use crate::github;
+use crate::history;
+use crate::json::Json;
+use crate::metrics::{Connections, Metrics};
+use crate::reload;
+use crate::task_ref::{new_uuid, TaskRef};
+use crate::trash;
+use crate::webhook;
+use crate::{
+    complete_update, connect, context, parse_priority, session, task_json, time, typed_query,
+    Context, TodoRecord, TODO_TABLE,
+};
+use todo_rust::client::{Client, Transaction};
+use todo_rust::typed_query::Statement;
+
+// Requests with larger bodies, or larger request lines and headers together, are refused.
+const MAX_BODY: usize = 1 << 20;
+const MAX_HEADERS: u64 = 64 << 10;
+
+// How long reading a request or writing a response may stall before the connection is dropped.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Connections handled at once, each on its own thread. More are refused, and answering those may
+// stall the listener for this long.
+const MAX_CONNECTIONS: usize = 64;
+const REJECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The tasks on a page of `GET /tasks`, by default and at most.
+const PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Who may use the server, and how much.
+#[derive(Debug, Default)]
+pub struct Settings {
+    /// The tokens clients have to send, or none to let everyone in.
+    tokens: Vec<String>,
+    /// How many requests a client may make a minute.
+    rate_limit: Option<u32>,
+}
+
+impl Settings {
+    pub fn load(config: &Config) -> Result<Settings, String> {
+        let rate_limit = config
+            .get("serve.rate_limit")
+            .map(|v| {
+                v.parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| tr!("Invalid {} in the config file: {}", "serve.rate_limit", v))
+            })
+            .transpose()?;
+        Ok(Settings {
+            tokens: config.get_list("serve.tokens"),
+            rate_limit,
+        })
+    }
+
+    // Whether the request carries one of the tokens, if any are needed.
+    fn allows(&self, request: &Request) -> bool {
+        self.tokens.is_empty()
+            || request
+                .header("authorization")
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .is_some_and(|token| {
+                    // Every token is compared in full, so the time taken doesn't tell how much of
+                    // one a guess got right.
+                    self.tokens.iter().fold(false, |found, t| {
+                        crypto::constant_time_eq(t.as_bytes(), token.trim().as_bytes()) | found
+                    })
+                })
+    }
+}
+
+/// The requests every client made in the current minute, counted from its first one.
+struct RateLimiter {
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    // Counts a request from `client`, returning how many seconds it has to wait when it made more
+    // than `limit`.
+    fn check(&self, limit: Option<u32>, client: IpAddr) -> Option<u64> {
+        let limit = limit?;
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|_, (start, _)| now.duration_since(*start) < MINUTE);
+        let (start, count) = clients.entry(client).or_insert((now, 0));
+        if *count >= limit {
+            return Some((MINUTE - now.duration_since(*start)).as_secs() + 1);
+        }
+        *count += 1;
+        None
+    }
+}
+
+/// Database connections shared by the request threads.
+struct Pool {
+    clients: Mutex<Vec<Client>>,
+    available: Condvar,
+    size: usize,
+}
+
+impl Pool {
+    fn new(ctx: &Context, size: usize) -> Result<Pool, Error> {
+        let clients = (0..size)
+            .map(|_| connect_shared(ctx))
+            .collect::<Result<_, _>>()?;
+        Ok(Pool {
+            clients: Mutex::new(clients),
+            available: Condvar::new(),
+            size,
+        })
+    }
+
+    fn connections(&self) -> Connections {
+        Connections {
+            size: self.size,
+            idle: self.clients.lock().unwrap().len(),
+        }
+    }
+
+    // Runs `f` in a transaction with the user's identity, on a connection, waiting for one to
+    // become available. The transaction is committed if `f` succeeds. Lost connections are
+    // replaced.
+    fn with<T>(
+        &self,
+        ctx: &Context,
+        f: impl FnOnce(&mut Transaction) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut clients = self.clients.lock().unwrap();
+        let client = loop {
+            match clients.pop() {
+                Some(client) => break client,
+                None => clients = self.available.wait(clients).unwrap(),
+            }
+        };
+        drop(clients);
+        let mut pooled = Pooled {
+            pool: self,
+            client: Some(client),
+        };
+        let client = pooled.client.as_mut().unwrap();
+        if client.is_closed() {
+            *client = connect_shared(ctx)?;
+        }
+        let mut transaction = session::transaction(client, ctx.role.as_deref(), &ctx.user)?;
+        let value = f(&mut transaction)?;
+        transaction.commit()?;
+        Ok(value)
+    }
+
+    fn release(&self, client: Client) {
+        self.clients.lock().unwrap().push(client);
+        self.available.notify_one();
+    }
+}
+
+/// A connection taken from the pool, which goes back to it when dropped, also when a request
+/// thread panics.
+struct Pooled<'a> {
+    pool: &'a Pool,
+    client: Option<Client>,
+}
+
+impl Drop for Pooled<'_> {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            self.pool.release(client);
+        }
+    }
+}
+
+// Connects for the pool. The identity is only set in the transactions of requests, so a
+// connection doesn't keep that of the user it was made for.
+fn connect_shared(ctx: &Context) -> Result<Client, Error> {
+    let mut client = connect(ctx)?;
+    session::reset_identity(&mut client)?;
+    Ok(client)
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    // With lowercase names.
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Option<String>,
+}
+
+impl Response {
+    fn json(status: u16, body: Json) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: Some(body.to_string()),
+        }
+    }
+
+    fn empty(status: u16) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response::json(status, Json::object(vec![("error", Json::from(message))]))
+    }
+
+    fn with_header(mut self, name: &'static str, value: String) -> Response {
+        self.headers.push((name, value));
+        self
+    }
+}
+
+/// Serves the API on `listen` (such as `127.0.0.1:8080`) until the process is stopped.
+pub fn run(ctx: &Context, listen: &str, pool_size: usize) -> Result<(), Error> {
+    let listener = TcpListener::bind(listen)
+        .map_err(|e| Error::Other(tr!("Failed to listen on {}: {}", listen, e)))?;
+    let server = Server::new(ctx, pool_size)?;
+    println!("{}", tr!("Listening on http://{}", listen));
+    let mut watcher = reload::Watcher::new();
+    let mut reloaded: Option<Arc<Context>> = None;
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Some(settings) = watcher.check(reloaded.as_deref().unwrap_or(ctx)) {
+                        reloaded = Some(Arc::new(settings));
+                    }
+                    let active = match Active::take(&server.active) {
+                        Some(active) => active,
+                        None => {
+                            reject(&server, stream);
+                            continue;
+                        }
+                    };
+                    let settings = reloaded.clone();
+                    let server = &server;
+                    scope.spawn(move || {
+                        handle(settings.as_deref().unwrap_or(ctx), server, stream);
+                        drop(active);
+                    });
+                }
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// What the request threads share.
+pub struct Server {
+    pool: Pool,
+    limiter: RateLimiter,
+    metrics: Metrics,
+    /// The connections being handled.
+    active: AtomicUsize,
+}
+
+impl Server {
+    /// Connects the pool of `pool_size` database connections.
+    pub fn new(ctx: &Context, pool_size: usize) -> Result<Server, Error> {
+        Ok(Server {
+            pool: Pool::new(ctx, pool_size.max(1))?,
+            limiter: RateLimiter {
+                clients: Mutex::new(HashMap::new()),
+            },
+            metrics: Metrics::new(),
+            active: AtomicUsize::new(0),
+        })
+    }
+}
+
+/// A request to the API made over another transport, such as gRPC, as the equivalent HTTP
+/// request.
+pub struct Call {
+    pub method: &'static str,
+    pub path: String,
+    pub query: String,
+    pub body: String,
+    /// The `Authorization` the client sent, such as `Bearer <token>`.
+    pub authorization: Option<String>,
+    pub peer: Option<IpAddr>,
+}
+
+/// How the API answered a `Call`.
+pub struct Answer {
+    pub status: u16,
+    /// The JSON body, or `null` without one.
+    pub body: Json,
+    /// The cursor of the next page of tasks, if there is one.
+    pub next: Option<String>,
+}
+
+/// Answers `call` like the equivalent HTTP request, so it is authorized, limited and counted the
+/// same way.
+pub fn answer(ctx: &Context, server: &Server, call: Call) -> Answer {
+    let start = Instant::now();
+    let request = Request {
+        method: call.method.to_string(),
+        path: call.path,
+        query: call.query,
+        headers: call
+            .authorization
+            .map(|a| ("authorization".to_string(), a))
+            .into_iter()
+            .collect(),
+        body: call.body,
+    };
+    let response = respond(ctx, server, call.peer, &request);
+    log::info!("{} {} {}", request.method, request.path, response.status);
+    server
+        .metrics
+        .record(&request.method, response.status, start.elapsed());
+    // The cursor is the last parameter of the next page's link.
+    let next = response
+        .headers
+        .iter()
+        .find(|(name, _)| *name == "Link")
+        .and_then(|(_, link)| link.rsplit_once("cursor="))
+        .and_then(|(_, rest)| rest.split_once('>'))
+        .map(|(cursor, _)| cursor.to_string());
+    Answer {
+        status: response.status,
+        body: response
+            .body
+            .as_deref()
+            .and_then(|body| Json::parse(body).ok())
+            .unwrap_or(Json::Null),
+        next,
+    }
+}
+
+/// One of the connections being handled, counted until it is dropped.
+struct Active<'a>(&'a AtomicUsize);
+
+impl<'a> Active<'a> {
+    // Counts a new connection, unless there are `MAX_CONNECTIONS` already.
+    fn take(active: &'a AtomicUsize) -> Option<Active<'a>> {
+        active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < MAX_CONNECTIONS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Active(active))
+    }
+}
+
+impl Drop for Active<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Answers a connection there is no thread for with `503 Service Unavailable`, without reading
+// the request.
+fn reject(server: &Server, mut stream: TcpStream) {
+    let response = Response::error(503, "Too many connections");
+    server.metrics.record("", response.status, Duration::ZERO);
+    let written = stream
+        .set_write_timeout(Some(REJECT_TIMEOUT))
+        .and_then(|_| write_response(&mut stream, &response));
+    if let Err(e) = written {
+        log::info!("Failed to send response: {}", e);
+    }
+}
+
+fn handle(ctx: &Context, server: &Server, mut stream: TcpStream) {
+    let start = Instant::now();
+    let peer = stream.peer_addr().ok().map(|a| a.ip());
+    if let Err(e) = stream
+        .set_read_timeout(Some(IO_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+    {
+        log::info!("Failed to set timeouts: {}", e);
+        return;
+    }
+    let (method, response) = match read_request(&mut stream) {
+        Ok(request) => {
+            let response = respond(ctx, server, peer, &request);
+            log::info!("{} {} {}", request.method, request.path, response.status);
+            (request.method, response)
+        }
+        Err(response) => (String::new(), response),
+    };
+    server
+        .metrics
+        .record(&method, response.status, start.elapsed());
+    if let Err(e) = write_response(&mut stream, &response) {
+        log::info!("Failed to send response: {}", e);
+    }
+}
+
+fn respond(ctx: &Context, server: &Server, peer: Option<IpAddr>, request: &Request) -> Response {
+    let limit = ctx.serve.rate_limit;
+    if let Some(wait) = peer.and_then(|peer| server.limiter.check(limit, peer)) {
+        return Response::error(429, "Too many requests")
+            .with_header("Retry-After", wait.to_string());
+    }
+    if !ctx.serve.allows(request) {
+        return Response::error(401, "Unauthorized")
+            .with_header("WWW-Authenticate", "Bearer".to_string());
+    }
+    let mut events = Vec::new();
+    let response = if request.path == "/metrics" {
+        metrics(ctx, server, request)
+    } else {
+        server
+            .pool
+            .with(ctx, |client| route(client, ctx, request, &mut events))
+    };
+    let response = match response {
+        Ok(response) => {
+            send(ctx, &events);
+            response
+        }
+        Err(e) => error_response(e),
+    };
+    tag(request, response)
+}
+
+// Sends the events of the changes a request made, once they are committed.
+fn send(ctx: &Context, events: &[(webhook::Event, TodoRecord)]) {
+    for (event, task) in events {
+        webhook::send(ctx, *event, std::slice::from_ref(task));
+        if let webhook::Event::Completed = event {
+            ctx.journal.record(ctx, task);
+        }
+    }
+}
+
+fn metrics(ctx: &Context, server: &Server, request: &Request) -> Result<Response, Error> {
+    if request.method != "GET" {
+        return Ok(Response::error(405, "Method not allowed"));
+    }
+    // Before taking a connection for the task counts.
+    let connections = server.pool.connections();
+    server.pool.with(ctx, |client| {
+        let text = server.metrics.render(client, ctx, connections)?;
+        Ok(Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4",
+            headers: Vec::new(),
+            body: Some(text),
+        })
+    })
+}
+
+// Gives a successful GET response an `ETag`, a hash of its headers and body, answering with
+// `304 Not Modified` instead when the client already has it.
+fn tag(request: &Request, response: Response) -> Response {
+    if request.method != "GET" || response.status != 200 {
+        return response;
+    }
+    let body = response.body.as_deref().unwrap_or_default();
+    let etag = format!(
+        "\"{}\"",
+        crypto::sha256(&format!("{:?}\n{}", response.headers, body))
+    );
+    let cached = request.header("if-none-match").is_some_and(|tags| {
+        tags.split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == etag || tag == "*")
+    });
+    if cached {
+        Response::empty(304).with_header("ETag", etag)
+    } else {
+        response.with_header("ETag", etag)
+    }
+}
+
+fn error_response(e: Error) -> Response {
+    let status = match e {
+        Error::Usage(_) => 400,
+        Error::NotFound(_) => 404,
+        _ => {
+            log::error!("{}", e);
+            500
+        }
+    };
+    Response::error(status, &e.to_string())
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request, Response> {
+    let bad_request = |_| Response::error(400, "Bad request");
+    let too_large = || Response::error(431, "Request header fields too large");
+    let mut reader = BufReader::new(stream).take(MAX_HEADERS);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(bad_request)?;
+    if reader.limit() == 0 {
+        return Err(too_large());
+    }
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return Err(Response::error(400, "Bad request")),
+    };
+    let mut length = 0;
+    let mut headers = Vec::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(bad_request)?;
+        if reader.limit() == 0 {
+            return Err(too_large());
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| Response::error(400, "Bad request"))?;
+            }
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    if length > MAX_BODY {
+        return Err(Response::error(413, "Request body too large"));
+    }
+    reader.set_limit(length as u64);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+    let body = String::from_utf8(body).map_err(|_| Response::error(400, "Bad request"))?;
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query: query.to_string(),
+        headers,
+        body,
+    })
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+    let body = response.body.as_deref().unwrap_or_default();
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let headers: String = response
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, value))
+        .collect();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        response.content_type,
+        body.len(),
+        headers,
+        body
+    )?;
+    stream.flush()
+}
+
+// The changes to send events for once the request's transaction is committed.
+type Events = Vec<(webhook::Event, TodoRecord)>;
+
+fn route(
+    client: &mut Transaction,
+    ctx: &Context,
+    request: &Request,
+    events: &mut Events,
+) -> Result<Response, Error> {
+    history::set_source(
+        client,
+        &format!("serve {} {}", request.method, request.path),
+    )?;
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["tasks"]) => list(client, ctx, &request.query),
+        ("POST", ["tasks"]) => create(client, ctx, &request.body, events),
+        ("GET", ["tasks", id]) => {
+            let task = find(client, ctx, id)?;
+            Ok(Response::json(200, task_json(ctx, &task)))
+        }
+        ("POST", ["tasks", id, "complete"]) => complete(client, ctx, id, events),
+        ("DELETE", ["tasks", id]) => delete(client, ctx, id, events),
+        (_, ["tasks"]) | (_, ["tasks", _]) | (_, ["tasks", _, "complete"]) => {
+            Ok(Response::error(405, "Method not allowed"))
+        }
+        _ => Ok(Response::error(404, "Not found")),
+    }
+}
+
+fn list(client: &mut Transaction, ctx: &Context, query: &str) -> Result<Response, Error> {
+    let mut terms = Vec::new();
+    let mut cursor = None;
+    let mut limit = PAGE_SIZE;
+    for (name, value) in query_params(query)? {
+        match name.as_str() {
+            "cursor" => cursor = Some(parse_cursor(&value)?),
+            "limit" => {
+                limit = value
+                    .parse()
+                    .ok()
+                    .filter(|l| (1..=MAX_PAGE_SIZE).contains(l))
+                    .ok_or_else(|| {
+                        Error::Usage(tr!(
+                            "Invalid limit: {}, expected 1 to {}",
+                            value,
+                            MAX_PAGE_SIZE
+                        ))
+                    })?
+            }
+            "id" => terms.push(value),
+            _ => terms.push(format!("{}:{}", name, value)),
+        }
+    }
+    let filter = Filter::parse(&terms).map_err(Error::Usage)?;
+    let mut tasks = filter.page(client, ctx, cursor, limit + 1)?;
+    let more = tasks.len() > limit;
+    tasks.truncate(limit);
+    let response = Response::json(
+        200,
+        Json::Array(tasks.iter().map(|t| task_json(ctx, t)).collect()),
+    );
+    match tasks.last().filter(|_| more) {
+        Some(last) => {
+            // The same parameters, but starting after the last task.
+            let mut params: Vec<&str> = query
+                .split('&')
+                .filter(|p| !p.is_empty() && !p.starts_with("cursor="))
+                .collect();
+            let cursor = format!("cursor={}", format_cursor(last));
+            params.push(&cursor);
+            Ok(response.with_header(
+                "Link",
+                format!("</tasks?{}>; rel=\"next\"", params.join("&")),
+            ))
+        }
+        None => Ok(response),
+    }
+}
+
+// Cursors point at the last task of a page by its id, the order of the pages.
+fn format_cursor(task: &TodoRecord) -> String {
+    base64::encode_config(task.id.to_string(), base64::URL_SAFE_NO_PAD)
+}
+
+fn parse_cursor(cursor: &str) -> Result<i32, Error> {
+    base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+        .ok()
+        .and_then(|c| String::from_utf8(c).ok())
+        .and_then(|c| c.parse().ok())
+        .ok_or_else(|| Error::Usage(tr!("Invalid cursor: {}", cursor)))
+}
+
+fn create(
+    client: &mut Transaction,
+    ctx: &Context,
+    body: &str,
+    events: &mut Events,
+) -> Result<Response, Error> {
+    let body = Json::parse(body).map_err(Error::Usage)?;
+    let text = |key: &str| {
+        body.get(key)
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+    let name = text("name")
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| Error::Usage(tr!("Missing task name")))?;
+    let due_time = text("due")
+        .map(|d| time::parse_time(client, &d))
+        .transpose()
+        .map_err(Error::Usage)?;
+    let priority = text("priority")
+        .map(|p| parse_priority(&p))
+        .transpose()
+        .map_err(Error::Usage)?;
+    let context = match text("context") {
+        Some(c) => Some(context::parse_name(&c).map_err(Error::Usage)?),
+        None => context::active(client, ctx)?,
+    };
+    let created_time = text("created")
+        .map(|c| time::parse_time(client, &c))
+        .transpose()
+        .map_err(Error::Usage)?;
+    let inbox = body.get("inbox") == Some(&Json::Bool(true));
+    // A task with a UUID (queued while the server couldn't be reached) is created only once, so
+    // sending it again answers with the task.
+    let uuid = text("uuid").unwrap_or_else(new_uuid);
+    let row = client.query_opt(
+        "insert into todo (name, due_time, description, project, priority, context, owner, inbox,
+                 uuid, created_time)
+             values ($1, $2, $3, $4, $5, $6, $7, $8, $9, coalesce($10, now()))
+             on conflict (uuid) do nothing
+             returning id",
+        &[
+            &ctx.encrypt(&name, Location::task("name", &uuid)),
+            &due_time,
+            &text("note").map(|n| ctx.encrypt(&n, Location::task("description", &uuid))),
+            &text("project"),
+            &priority,
+            &context,
+            &ctx.user,
+            &inbox,
+            &uuid,
+            &created_time,
+        ],
+    )?;
+    let (id, status): (i32, u16) = match row {
+        Some(row) => (row.get(0), 201),
+        None => {
+            let row = client
+                .query_opt(
+                    "select id from todo where uuid = $1 and owner = $2",
+                    &[&uuid, &ctx.user],
+                )?
+                .ok_or_else(|| Error::Usage(tr!("The UUID is in use by another task")))?;
+            (row.get(0), 200)
+        }
+    };
+    let task = find(client, ctx, &id.to_string())?;
+    let response = Response::json(status, task_json(ctx, &task));
+    if status == 201 {
+        events.push((webhook::Event::Created, task));
+    }
+    Ok(response)
+}
+
+fn complete(
+    client: &mut Transaction,
+    ctx: &Context,
+    id: &str,
+    events: &mut Events,
+) -> Result<Response, Error> {
+    let task = find(client, ctx, id)?;
+    // Like `todo complete`, finished tasks are left alone rather than completed again.
+    if complete_update(ctx, task.id, SystemTime::now()).execute(client)? == 0 {
+        return Ok(Response::error(
+            409,
+            &tr!("Task {} is already finished", task.id),
+        ));
+    }
+    github::close_completed(client, ctx, &[task.id])?;
+    let task = find(client, ctx, &task.id.to_string())?;
+    let response = Response::json(200, task_json(ctx, &task));
+    events.push((webhook::Event::Completed, task));
+    Ok(response)
+}
+
+fn delete(
+    client: &mut Transaction,
+    ctx: &Context,
+    id: &str,
+    events: &mut Events,
+) -> Result<Response, Error> {
+    let task = find(client, ctx, id)?;
+    trash::remove(client, ctx, task.id)?;
+    events.push((webhook::Event::Deleted, task));
+    Ok(Response::empty(204))
+}
+
+// Finds a task of the user by id, alias or UUID.
+fn find(client: &mut Transaction, ctx: &Context, id: &str) -> Result<TodoRecord, Error> {
+    let id = TaskRef::parse(id)
+        .ok_or_else(|| Error::NotFound(tr!("No task with id {}", id)))?
+        .resolve(client, ctx)?;
+    typed_query::from(TODO_TABLE)
+        .where_(|t| {
+            t.owner.clone().eq(typed_query::Constant {
+                value: ctx.user.clone(),
+            })
+        })
+        .where_(|t| t.id.clone().eq(typed_query::Constant { value: id }))
+        .query(client)?
+        .pop()
+        .ok_or_else(|| Error::NotFound(tr!("No task with id {}", id)))
+}
+
+// The parameters in a query string, percent-decoded.
+fn query_params(query: &str) -> Result<Vec<(String, String)>, Error> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((decode(name)?, decode(value)?))
+        })
+        .collect()
+}
+
+// Decodes a percent-encoded part of a query string, in which `+` is a space.
+fn decode(text: &str) -> Result<String, Error> {
+    let invalid = || Error::Usage(tr!("Invalid query string: {}", text));
+    let mut bytes = Vec::new();
+    let mut rest = text.bytes();
+    while let Some(b) = rest.next() {
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex: Vec<u8> = rest.by_ref().take(2).collect();
+                let hex = std::str::from_utf8(&hex)
+                    .ok()
+                    .filter(|h| h.len() == 2)
+                    .ok_or_else(invalid)?;
+                bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            }
+            _ => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
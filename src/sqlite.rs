@@ -0,0 +1,473 @@
+// SQLite mode: with `--backend sqlite` (or `database.backend = "sqlite"` in the config), tasks are
+// kept in a SQLite file instead of a PostgreSQL database, so no database server is needed. Only
+// the basic commands of store.rs are available: add, list, show, note, complete, reopen, cancel
+// and remove. Values are encrypted as in PostgreSQL, when encryption is enabled.
+
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, Transaction};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::json::Json;
+use crate::status::{self, Status};
+use crate::store::{self, Added, Change, Listing, NewTask, Store};
+use crate::task_ref::TaskRef;
+use crate::template::Template;
+use crate::{alias, time, Command, Context, TodoRecord};
+
+// The schema, a statement for each version. Versions are only ever added; the database's
+// `user_version` is the number it has.
+const SCHEMA: &[&str] = &["create table todo (
+    id integer primary key autoincrement,
+    uuid text not null unique,
+    owner text not null,
+    name text not null,
+    description text,
+    status text not null default 'todo',
+    created_time integer not null,
+    completed_time integer,
+    due_time integer,
+    project text,
+    priority integer,
+    context text,
+    estimate integer,
+    fields text not null default '{}'
+)"];
+
+// The columns `task` reads.
+const COLUMNS: &str = "id, uuid, name, description, status, created_time, completed_time, \
+                       due_time, project, priority, context, estimate, fields";
+
+/// The SQLite file: `database.path`, or `todo.sqlite3` in the data directory.
+pub fn path(config: &Config) -> Option<PathBuf> {
+    config
+        .get("database.path")
+        .map(PathBuf::from)
+        .or_else(|| crate::data_dir().map(|dir| dir.join("todo.sqlite3")))
+}
+
+pub fn run(ctx: &Context, path: &Path, command: Command) -> Result<(), Error> {
+    let mut store = Sqlite(open(path)?);
+    match store::run(&mut store, ctx, command)? {
+        Some(_) => Err(unsupported()),
+        None => Ok(()),
+    }
+}
+
+fn unsupported() -> Error {
+    Error::Usage(tr!(
+        "This command is not available with the SQLite backend, use '--backend postgres'"
+    ))
+}
+
+/// The tasks in a SQLite file.
+pub struct Sqlite(Connection);
+
+impl Store for Sqlite {
+    fn resolve(&mut self, ctx: &Context, task: &TaskRef) -> Result<i32, Error> {
+        resolve(&self.0, ctx, task)
+    }
+
+    fn resolve_all(&mut self, ctx: &Context, tasks: &[TaskRef]) -> Result<Vec<i32>, Error> {
+        let mut ids = Vec::new();
+        let mut errors = Vec::new();
+        for task in tasks {
+            let found = match task {
+                TaskRef::Id(id) => Ok(*id),
+                _ => resolve(&self.0, ctx, task),
+            };
+            match found {
+                Ok(id) if !ids.contains(&id) => ids.push(id),
+                Ok(_) => {}
+                Err(Error::NotFound(message)) => errors.push(message),
+                Err(e) => return Err(e),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Error::NotFound(errors.join("\n")));
+        }
+        Ok(ids)
+    }
+
+    fn parse_time(&mut self, input: &str) -> Result<SystemTime, String> {
+        time::parse_offline(input).ok_or_else(|| tr!("Invalid time: {}", input))
+    }
+
+    fn template(&mut self, _ctx: &Context, _name: &str) -> Result<Template, Error> {
+        Err(unsupported())
+    }
+
+    // There are no contexts.
+    fn active_context(&mut self, _ctx: &Context) -> Result<Option<String>, Error> {
+        Ok(None)
+    }
+
+    fn add(&mut self, ctx: &Context, task: NewTask, unique: Option<&str>) -> Result<Added, Error> {
+        // Taking the write lock right away, so another `add --unique` waits for this one.
+        let transaction = self
+            .0
+            .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        if let Some(name) = unique {
+            if let Some(task) = find_open(&transaction, ctx, name)? {
+                return Ok(Added::Existing(task));
+            }
+        }
+        execute(
+            ctx,
+            &transaction,
+            "insert into todo (uuid, owner, name, description, created_time, due_time, project,
+                 priority, context, estimate, fields)
+             values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                task.uuid,
+                ctx.user,
+                task.name,
+                task.description,
+                to_micros(SystemTime::now()),
+                task.due_time.map(to_micros),
+                task.project,
+                task.priority,
+                task.context,
+                task.estimate,
+                task.fields.to_string(),
+            ],
+        )?;
+        if ctx.dry_run {
+            return Ok(Added::DryRun);
+        }
+        let id = transaction.last_insert_rowid() as i32;
+        let task = load(&transaction, ctx, id)?
+            .ok_or_else(|| Error::Other(tr!("The task was not created")))?;
+        transaction.commit()?;
+        Ok(Added::Created(task))
+    }
+
+    fn load(&mut self, ctx: &Context, ids: &[i32]) -> Result<Vec<TodoRecord>, Error> {
+        let mut tasks = Vec::new();
+        for &id in ids {
+            tasks.extend(load(&self.0, ctx, id)?);
+        }
+        tasks.sort_by_key(|task| task.id);
+        Ok(tasks)
+    }
+
+    fn list(&mut self, ctx: &Context, statuses: &[Status]) -> Result<Listing, Error> {
+        let placeholders = vec!["?"; statuses.len()].join(", ");
+        let sql = format!(
+            "select {} from todo where owner = ? and status in ({}) order by created_time, id",
+            COLUMNS, placeholders
+        );
+        let params =
+            std::iter::once(ctx.user.as_str()).chain(statuses.iter().map(|status| status.as_str()));
+        let mut statement = self.0.prepare(&sql)?;
+        let tasks = statement
+            .query_map(params_from_iter(params), task)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Listing {
+            tasks,
+            ..Listing::default()
+        })
+    }
+
+    // There is no trash, so removed tasks are always deleted.
+    fn change(
+        &mut self,
+        ctx: &Context,
+        ids: &[i32],
+        change: &Change,
+        done: &str,
+    ) -> Result<Vec<i32>, Error> {
+        let transaction = self.0.transaction()?;
+        let t = &transaction;
+        let missing = store::each(ids, done, |id| {
+            let changed = match change {
+                Change::Complete(now) => execute(
+                    ctx,
+                    t,
+                    "update todo set status = ?1, completed_time = ?2
+                     where id = ?3 and owner = ?4 and status in (?5, ?6, ?7)",
+                    params![
+                        Status::Done.as_str(),
+                        to_micros(*now),
+                        id,
+                        ctx.user,
+                        status::UNFINISHED[0].as_str(),
+                        status::UNFINISHED[1].as_str(),
+                        status::UNFINISHED[2].as_str()
+                    ],
+                )?,
+                Change::Reopen => set_status(ctx, t, id, Status::Todo)?,
+                Change::Cancel => set_status(ctx, t, id, Status::Cancelled)?,
+                Change::Note(text) => match load(t, ctx, id)? {
+                    Some(task) => {
+                        let location = Location::task("description", &task.uuid);
+                        execute(
+                            ctx,
+                            t,
+                            "update todo set description = ?1 where id = ?2",
+                            params![
+                                store::add_note(ctx, task.description.as_deref(), text, location),
+                                id
+                            ],
+                        )?
+                    }
+                    None => 0,
+                },
+                Change::Remove { .. } => execute(
+                    ctx,
+                    t,
+                    "delete from todo where id = ?1 and owner = ?2",
+                    params![id, ctx.user],
+                )?,
+            };
+            Ok(changed as u64)
+        })?;
+        finish(ctx, transaction)?;
+        Ok(missing)
+    }
+}
+
+// Opens the database at `path`, creating it and bringing its schema up to date.
+fn open(path: &Path) -> Result<Connection, Error> {
+    let cannot_open = |e: &dyn std::fmt::Display| {
+        Error::Other(tr!("Could not open the database {}: {}", path.display(), e))
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| cannot_open(&e))?;
+    }
+    let mut connection = Connection::open(path).map_err(|e| cannot_open(&e))?;
+    connection
+        .busy_timeout(Duration::from_secs(5))
+        .map_err(|e| cannot_open(&e))?;
+    let transaction =
+        connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    let version: i64 = transaction.query_row("pragma user_version", [], |row| row.get(0))?;
+    for statement in SCHEMA.iter().skip(version as usize) {
+        transaction.execute_batch(statement)?;
+    }
+    transaction.pragma_update(None, "user_version", SCHEMA.len() as i64)?;
+    transaction.commit()?;
+    Ok(connection)
+}
+
+// Runs a modifying statement, printing it with `--dry-run`.
+fn execute(
+    ctx: &Context,
+    connection: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<usize, rusqlite::Error> {
+    if ctx.dry_run {
+        let sql: Vec<&str> = sql.split_whitespace().collect();
+        println!("{}", sql.join(" "));
+    }
+    connection.execute(sql, params)
+}
+
+// Gives the user's task `id` the status of a task that isn't done.
+fn set_status(
+    ctx: &Context,
+    connection: &Connection,
+    id: i32,
+    status: Status,
+) -> Result<usize, rusqlite::Error> {
+    execute(
+        ctx,
+        connection,
+        "update todo set status = ?1, completed_time = null where id = ?2 and owner = ?3",
+        params![status.as_str(), id, ctx.user],
+    )
+}
+
+// Commits a transaction, or rolls it back with `--dry-run`.
+fn finish(ctx: &Context, transaction: Transaction) -> Result<(), rusqlite::Error> {
+    if ctx.dry_run {
+        transaction.rollback()
+    } else {
+        transaction.commit()
+    }
+}
+
+// Returns the id of the user's task `task` refers to. A prefix, or an alias that isn't a task's,
+// has to be the start of the alias or UUID of only one of them.
+fn resolve(connection: &Connection, ctx: &Context, task: &TaskRef) -> Result<i32, Error> {
+    let not_found = || Error::NotFound(tr!("No task with id {}", task));
+    let id = match task {
+        TaskRef::Id(id) => Some(*id),
+        TaskRef::Alias(a) => alias::decode(a),
+        TaskRef::Uuid(_) | TaskRef::Prefix(_) => None,
+    };
+    let found = match (task, id) {
+        (TaskRef::Uuid(uuid), _) => connection
+            .query_row(
+                "select id from todo where uuid = ?1 and owner = ?2",
+                params![uuid, ctx.user],
+                |row| row.get(0),
+            )
+            .optional()?,
+        (_, Some(id)) => connection
+            .query_row(
+                "select id from todo where id = ?1 and owner = ?2",
+                params![id, ctx.user],
+                |row| row.get(0),
+            )
+            .optional()?,
+        (_, None) => None,
+    };
+    let prefix = match (found, task) {
+        (Some(id), _) => return Ok(id),
+        (None, TaskRef::Alias(prefix)) | (None, TaskRef::Prefix(prefix)) => prefix.to_lowercase(),
+        (None, _) => return Err(not_found()),
+    };
+    let mut statement = connection.prepare(&format!(
+        "select {} from todo where owner = ?1 order by id",
+        COLUMNS
+    ))?;
+    let matches: Vec<TodoRecord> = statement
+        .query_map(params![ctx.user], self::task)?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|t| alias::encode(t.id).starts_with(&prefix) || t.uuid.starts_with(&prefix))
+        .collect();
+    match matches.as_slice() {
+        [found] => Ok(found.id),
+        [] => Err(not_found()),
+        _ => {
+            let mut message = tr!("'{}' matches several tasks:", task);
+            for found in &matches {
+                message.push_str(&format!(
+                    "\n  {} ({}): {}",
+                    found.id,
+                    alias::encode(found.id),
+                    ctx.decrypt(&found.name, Location::task("name", &found.uuid))
+                ));
+            }
+            Err(Error::Usage(message))
+        }
+    }
+}
+
+// Loads the user's task with `id`.
+fn load(connection: &Connection, ctx: &Context, id: i32) -> Result<Option<TodoRecord>, Error> {
+    Ok(connection
+        .query_row(
+            &format!("select {} from todo where id = ?1 and owner = ?2", COLUMNS),
+            params![id, ctx.user],
+            task,
+        )
+        .optional()?)
+}
+
+// Finds an unfinished task of the user named `name`.
+fn find_open(
+    connection: &Connection,
+    ctx: &Context,
+    name: &str,
+) -> Result<Option<TodoRecord>, Error> {
+    let mut statement = connection.prepare(&format!(
+        "select {} from todo where owner = ?1 and status in (?2, ?3, ?4) order by id",
+        COLUMNS
+    ))?;
+    let tasks = statement
+        .query_map(
+            params![
+                ctx.user,
+                status::UNFINISHED[0].as_str(),
+                status::UNFINISHED[1].as_str(),
+                status::UNFINISHED[2].as_str()
+            ],
+            task,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tasks
+        .into_iter()
+        .find(|task| ctx.decrypt(&task.name, Location::task("name", &task.uuid)) == name))
+}
+
+// Reads a task selected with `COLUMNS`. What SQLite mode doesn't keep is left empty.
+fn task(row: &Row) -> Result<TodoRecord, rusqlite::Error> {
+    let status: String = row.get(4)?;
+    let fields: String = row.get(12)?;
+    Ok(TodoRecord {
+        id: row.get(0)?,
+        uuid: row.get(1)?,
+        name: row.get(2)?,
+        description: row.get(3)?,
+        status: Status::parse(&status).unwrap_or(Status::Todo),
+        created_time: from_micros(row.get(5)?),
+        completed_time: row.get::<_, Option<i64>>(6)?.map(from_micros),
+        due_time: row.get::<_, Option<i64>>(7)?.map(from_micros),
+        project: row.get(8)?,
+        priority: row.get(9)?,
+        context: row.get(10)?,
+        estimate: row.get(11)?,
+        fields: Json::parse(&fields).unwrap_or(Json::Null),
+        remind_before: None,
+        reminded_time: None,
+        snoozed_until: None,
+        github_repo: None,
+        github_issue: None,
+        github_url: None,
+        github_close: false,
+        caldav_uid: None,
+        caldav_href: None,
+        caldav_etag: None,
+        caldav_state: None,
+        owner: None,
+        reviewed_time: None,
+        archived_time: None,
+        sort_order: None,
+        pinned: false,
+        assignee: None,
+        waiting_until: None,
+        waiting_for: None,
+        inbox: false,
+        external_id: None,
+    })
+}
+
+// Times are kept as microseconds since the epoch, like PostgreSQL keeps them.
+fn to_micros(t: SystemTime) -> i64 {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_micros() as i64,
+        Err(e) => -(e.duration().as_micros() as i64),
+    }
+}
+
+fn from_micros(micros: i64) -> SystemTime {
+    let duration = Duration::from_micros(micros.unsigned_abs());
+    if micros >= 0 {
+        UNIX_EPOCH + duration
+    } else {
+        UNIX_EPOCH - duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_keep_their_microseconds() {
+        let t = UNIX_EPOCH + Duration::from_micros(1_628_000_000_123_456);
+        assert_eq!(from_micros(to_micros(t)), t);
+        let before = UNIX_EPOCH - Duration::from_micros(5);
+        assert_eq!(from_micros(to_micros(before)), before);
+    }
+
+    #[test]
+    fn open_creates_the_schema_once() {
+        let dir = std::env::temp_dir().join(format!("todo-sqlite-{}", std::process::id()));
+        let path = dir.join("todo.sqlite3");
+        open(&path).unwrap();
+        let connection = open(&path).unwrap();
+        let version: i64 = connection
+            .query_row("pragma user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA.len() as i64);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::status;
+use crate::time::format_duration;
+use crate::Context;
+use todo_rust::client::Client;
+
+const DAYS: i32 = 7;
+const WEEKS: i32 = 4;
+const OLDEST: i64 = 5;
+
+pub fn run(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let counts = client.query_one(
+        "select count(*) filter (where status = any($2)),
+                 count(*) filter (where status = 'done'),
+                 count(*) filter (where status = 'cancelled')
+             from todo
+             where owner = $1",
+        &[&ctx.user, &status::OPEN],
+    )?;
+    let open: i64 = counts.get(0);
+    let completed: i64 = counts.get(1);
+    let cancelled: i64 = counts.get(2);
+    println!("{}", tr!("Open:      {}", open));
+    println!("{}", tr!("Completed: {}", completed));
+    println!("{}", tr!("Cancelled: {}", cancelled));
+
+    let average: Option<f64> = client
+        .query_one(
+            "select extract(epoch from avg(completed_time - created_time))::float8
+             from todo
+             where status = 'done' and owner = $1",
+            &[&ctx.user],
+        )?
+        .get(0);
+    if let Some(seconds) = average {
+        println!(
+            "{}",
+            tr!("Average time to completion: {}", format_duration(seconds))
+        );
+    }
+
+    println!();
+    println!("{}", tr!("Completed per day:"));
+    print_completions(client, ctx, "day", "YYYY-MM-DD", DAYS)?;
+    println!();
+    println!("{}", tr!("Completed per week:"));
+    print_completions(client, ctx, "week", "IYYY-\"W\"IW", WEEKS)?;
+
+    // Tasks without an estimate are counted, so the totals can be judged.
+    let estimates = client.query(
+        "select project, sum(estimate)::float8, count(estimate), count(*)
+             from todo
+             where owner = $1 and status = any($2)
+             group by project
+             having count(estimate) > 0
+             order by project nulls last",
+        &[&ctx.user, &status::OPEN],
+    )?;
+    if !estimates.is_empty() {
+        println!();
+        println!("{}", tr!("Estimated open work per project:"));
+        for row in estimates {
+            let project: Option<String> = row.get(0);
+            let total: f64 = row.get(1);
+            let estimated: i64 = row.get(2);
+            let count: i64 = row.get(3);
+            let project = project.unwrap_or_else(|| tr!("(no project)"));
+            if estimated == count {
+                println!("  {}  {}", project, format_duration(total));
+            } else {
+                println!(
+                    "  {}  {}",
+                    project,
+                    tr!(
+                        "{} ({} of {} tasks estimated)",
+                        format_duration(total),
+                        estimated,
+                        count
+                    )
+                );
+            }
+        }
+    }
+
+    let oldest = client.query(
+        "select id, name, extract(epoch from now() - created_time)::float8, uuid
+             from todo
+             where owner = $1 and status = any($2)
+             order by created_time
+             limit $3",
+        &[&ctx.user, &status::OPEN, &OLDEST],
+    )?;
+    if !oldest.is_empty() {
+        println!();
+        println!("{}", tr!("Oldest open tasks:"));
+        for row in oldest {
+            let id: i32 = row.get(0);
+            let name: String = row.get(1);
+            let age: f64 = row.get(2);
+            println!(
+                "  {}: {} ({})",
+                id,
+                ctx.decrypt(&name, Location::task("name", row.get(3))),
+                format_duration(age)
+            );
+        }
+    }
+    Ok(())
+}
+
+// Prints the number of completions in each of the last `count` periods (`day` or `week`),
+// including periods without any completions.
+fn print_completions(
+    client: &mut Client,
+    ctx: &Context,
+    period: &str,
+    label_format: &str,
+    count: i32,
+) -> Result<(), postgres::Error> {
+    let rows = client.query(
+        "select to_char(p.start, $2), count(t.id)
+             from generate_series(
+                 date_trunc($1, now()) - ($3 - 1) * ('1 ' || $1)::interval,
+                 date_trunc($1, now()),
+                 ('1 ' || $1)::interval
+             ) as p(start)
+             left join todo t
+                 on t.status = 'done' and t.owner = $4 and date_trunc($1, t.completed_time) = p.start
+             group by p.start
+             order by p.start",
+        &[&period, &label_format, &count, &ctx.user],
+    )?;
+    for row in rows {
+        let label: String = row.get(0);
+        let n: i64 = row.get(1);
+        println!("  {}  {}", label, n);
+    }
+    Ok(())
+}
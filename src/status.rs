@@ -0,0 +1,86 @@
+use postgres::types::private::BytesMut;
+use postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use std::error::Error;
+
+/// Where a task is in its workflow. Stored as text in the `status` column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Todo,
+    InProgress,
+    Done,
+    Cancelled,
+    /// Waiting for someone else until a follow-up date, see `todo wait`.
+    Waiting,
+}
+
+/// The statuses of tasks that still need work.
+pub const OPEN: &[Status] = &[Status::Todo, Status::InProgress];
+
+/// The statuses of tasks that are not finished: the open ones and those waiting for someone.
+pub const UNFINISHED: &[Status] = &[Status::Todo, Status::InProgress, Status::Waiting];
+
+impl Status {
+    pub const ALL: &'static [Status] = &[
+        Status::Todo,
+        Status::InProgress,
+        Status::Done,
+        Status::Cancelled,
+        Status::Waiting,
+    ];
+
+    /// The name used in the database and on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Todo => "todo",
+            Status::InProgress => "in-progress",
+            Status::Done => "done",
+            Status::Cancelled => "cancelled",
+            Status::Waiting => "waiting",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Status> {
+        Status::ALL
+            .iter()
+            .copied()
+            .find(|status| status.as_str() == s)
+    }
+
+    /// The translated name, for showing to the user.
+    pub fn label(self) -> String {
+        match self {
+            Status::Todo => tr!("to do"),
+            Status::InProgress => tr!("in progress"),
+            Status::Done => tr!("done"),
+            Status::Cancelled => tr!("cancelled"),
+            Status::Waiting => tr!("waiting"),
+        }
+    }
+}
+
+impl ToSql for Status {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.as_str().to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&str as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for Status {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Status, Box<dyn Error + Sync + Send>> {
+        let s = <&str as FromSql>::from_sql(ty, raw)?;
+        Status::parse(s).ok_or_else(|| format!("unknown status: {}", s).into())
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&str as FromSql>::accepts(ty)
+    }
+}
@@ -0,0 +1,679 @@
+// The basic task commands, written once against a `Store`: add, list, show, note, complete,
+// reopen, cancel and remove work the same whether the tasks are kept in PostgreSQL or, with
+// `--backend sqlite`, in a SQLite file (see sqlite.rs). A store only reads and writes tasks;
+// parsing the options, asking for confirmation, printing and sending events happen here.
+//
+// What only PostgreSQL has, such as templates, contexts, time tracking and the trash, is behind
+// methods that other stores answer without it.
+
+use std::time::SystemTime;
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::json::Json;
+use crate::status::Status;
+use crate::task_ref::{self, TaskRef};
+use crate::template::Template;
+use crate::webhook::{self, Event};
+use crate::{alias, annotation, cache, context, dependency, fields, github, output, template};
+use crate::{apply_bulk, find_open, in_context, list_matches, print_list, show, stale_filter};
+use crate::{ask, changed_ids, complete_update, join_ids, parse_estimate, report_missing};
+use crate::{task_json, time, timetrack, trash, typed_query};
+use crate::{Command, Context, SortKey, TodoRecord, TodoRecordInsert, TODO_TABLE};
+use todo_rust::client::Client;
+use todo_rust::typed_query::Statement;
+
+/// Where tasks are kept.
+pub trait Store {
+    /// Returns the id of the user's task `task` refers to.
+    fn resolve(&mut self, ctx: &Context, task: &TaskRef) -> Result<i32, Error>;
+
+    /// Resolves every task like `task_ref::resolve_all`: ids are kept as they are, the others
+    /// have to be the user's.
+    fn resolve_all(&mut self, ctx: &Context, tasks: &[TaskRef]) -> Result<Vec<i32>, Error>;
+
+    /// Parses a time given on the command line, such as a due time.
+    fn parse_time(&mut self, input: &str) -> Result<SystemTime, String>;
+
+    /// The user's template `name`.
+    fn template(&mut self, ctx: &Context, name: &str) -> Result<Template, Error>;
+
+    /// The user's active context, which new tasks are added to.
+    fn active_context(&mut self, ctx: &Context) -> Result<Option<String>, Error>;
+
+    /// Creates `task`, unless `unique` is given and the user has an unfinished task with that
+    /// name. With `--dry-run`, prints what would be done instead.
+    fn add(&mut self, ctx: &Context, task: NewTask, unique: Option<&str>) -> Result<Added, Error>;
+
+    /// The user's tasks with `ids`, by id.
+    fn load(&mut self, ctx: &Context, ids: &[i32]) -> Result<Vec<TodoRecord>, Error>;
+
+    /// The user's tasks with `statuses` in the active context, oldest first.
+    fn list(&mut self, ctx: &Context, statuses: &[Status]) -> Result<Listing, Error>;
+
+    /// Like `list`, passing the tasks to `f` as they are read.
+    fn for_each(
+        &mut self,
+        ctx: &Context,
+        statuses: &[Status],
+        f: &mut dyn FnMut(&TodoRecord),
+    ) -> Result<(), Error> {
+        self.list(ctx, statuses)?.tasks.iter().for_each(f);
+        Ok(())
+    }
+
+    /// Shows what else the task has besides its fields, after them.
+    fn show_details(&mut self, _ctx: &Context, _id: i32) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Makes `change` to each of the user's tasks `ids` in one transaction, printing `done` for
+    /// those it changed. Returns the others, which aren't the user's or, for `Complete`, aren't
+    /// unfinished.
+    fn change(
+        &mut self,
+        ctx: &Context,
+        ids: &[i32],
+        change: &Change,
+        done: &str,
+    ) -> Result<Vec<i32>, Error>;
+
+    /// Whether removed tasks go to the trash, unless forced.
+    fn has_trash(&self) -> bool {
+        false
+    }
+
+    /// Called after the tasks `ids` were completed.
+    fn completed(&mut self, _ctx: &Context, _ids: &[i32]) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A task for `Store::add` to create. The name and description are encrypted already.
+pub struct NewTask {
+    pub uuid: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub due_time: Option<SystemTime>,
+    pub project: Option<String>,
+    pub priority: Option<i16>,
+    pub context: Option<String>,
+    pub estimate: Option<i32>,
+    pub fields: Json,
+}
+
+/// What `Store::add` did.
+pub enum Added {
+    Created(TodoRecord),
+    /// With `unique`, the task that was there already.
+    Existing(TodoRecord),
+    DryRun,
+}
+
+/// The tasks `list` shows, with the active context, if all of them are in it, and the task whose
+/// time is being tracked.
+#[derive(Default)]
+pub struct Listing {
+    pub tasks: Vec<TodoRecord>,
+    pub active: Option<String>,
+    pub tracking: Option<(i32, f64)>,
+}
+
+/// A change `Store::change` makes to tasks.
+pub enum Change<'a> {
+    /// Completes unfinished tasks at the given time.
+    Complete(SystemTime),
+    Reopen,
+    Cancel,
+    /// Adds a line to the description.
+    Note(&'a str),
+    /// Removes the tasks, for good if forced or the store has no trash.
+    Remove {
+        force: bool,
+    },
+}
+
+/// Runs `command` if it is one of the basic commands, returning it otherwise.
+pub fn run(
+    store: &mut dyn Store,
+    ctx: &Context,
+    command: Command,
+) -> Result<Option<Command>, Error> {
+    match command {
+        Command::Add {
+            name,
+            template,
+            options,
+            quiet,
+            json,
+            unique,
+        } => {
+            // Options given to `add` override the template's.
+            let template = match template {
+                Some(name) => store.template(ctx, &name)?,
+                None => Template::default(),
+            };
+            let name = name.unwrap_or(template.name);
+            let mut fields = template.fields;
+            for (name, value) in
+                fields::parse_all(&ctx.fields, &options.fields).map_err(Error::Usage)?
+            {
+                fields.retain(|(n, _)| *n != name);
+                fields.push((name, value));
+            }
+            // New tasks go into the active context, unless another one is given.
+            let context = match options.context.or(template.context) {
+                Some(context) => Some(context),
+                None => store.active_context(ctx)?,
+            };
+            let due_time = options
+                .due
+                .or(template.due)
+                .map(|d| store.parse_time(&d))
+                .transpose()
+                .map_err(Error::Usage)?;
+            let estimate = match options.estimate {
+                Some(estimate) => Some(parse_estimate(&estimate).map_err(Error::Usage)?),
+                None => template.estimate,
+            };
+            let uuid = task_ref::new_uuid();
+            let task = NewTask {
+                name: ctx.encrypt(&name, Location::task("name", &uuid)),
+                description: options
+                    .note
+                    .or(template.note)
+                    .map(|n| ctx.encrypt(&n, Location::task("description", &uuid))),
+                uuid,
+                due_time,
+                project: options.project.or(template.project),
+                priority: options.priority.or(template.priority),
+                context,
+                estimate,
+                fields: Json::Object(fields),
+            };
+            let unique = if unique { Some(name.as_str()) } else { None };
+            let (task, message, created) = match store.add(ctx, task, unique)? {
+                Added::Created(task) => {
+                    let message = tr!("Created task {} ({})", task.id, alias::encode(task.id));
+                    (task, message, true)
+                }
+                Added::Existing(task) => {
+                    let message = tr!(
+                        "Task {} ({}) already exists",
+                        task.id,
+                        alias::encode(task.id)
+                    );
+                    (task, message, false)
+                }
+                Added::DryRun => return Ok(None),
+            };
+            if json {
+                println!("{}", task_json(ctx, &task));
+            } else if !ctx.output.is_text() {
+                output::record(ctx.output, &[&task.id.to_string(), &alias::encode(task.id)]);
+            } else if !quiet && ctx.verbosity >= 0 {
+                println!("{}", message);
+            }
+            if created {
+                let tasks = events(store, ctx, &[task.id])?;
+                webhook::send(ctx, Event::Created, &tasks);
+            }
+        }
+        Command::List {
+            statuses,
+            fields: filters,
+            sort,
+            archived,
+            pinned,
+            stale,
+            assigned,
+            due_before,
+            format,
+        } => {
+            let filters = fields::parse_all(&ctx.fields, &filters).map_err(Error::Usage)?;
+            let assigned = assigned.map(|a| a.resolve(ctx));
+            let stale = stale_filter(ctx, stale)?;
+            let due_before = due_before
+                .map(|t| store.parse_time(&t))
+                .transpose()
+                .map_err(Error::Usage)?;
+            let matches = |task: &TodoRecord| {
+                let assigned = assigned.as_ref();
+                list_matches(
+                    task, archived, pinned, stale, &filters, assigned, due_before,
+                )
+            };
+            // In the order they were created, JSON lines are written as the tasks arrive, so large
+            // lists aren't kept in memory.
+            if ctx.output == output::Format::Jsonl && sort == SortKey::Created {
+                return store
+                    .for_each(ctx, &statuses, &mut |task| {
+                        if matches(task) {
+                            println!("{}", task_json(ctx, task));
+                        }
+                    })
+                    .map(|()| None);
+            }
+            let listing = store.list(ctx, &statuses)?;
+            let tasks = listing.tasks.into_iter().filter(|t| matches(t)).collect();
+            print_list(
+                ctx,
+                tasks,
+                sort,
+                format.as_ref(),
+                &listing.active,
+                listing.tracking,
+            );
+        }
+        Command::Show { id } => {
+            let id = store.resolve(ctx, &id)?;
+            match store.load(ctx, &[id])?.first() {
+                Some(task) => show(ctx, task),
+                None => return Err(Error::NotFound(tr!("No task with id {}", id))),
+            }
+            store.show_details(ctx, id)?;
+        }
+        Command::Note { id, text } => {
+            let ids = [store.resolve(ctx, &id)?];
+            let missing = store.change(ctx, &ids, &Change::Note(&text), &tr!("note added"))?;
+            let tasks = events(store, ctx, &changed_ids(&ids, &missing))?;
+            webhook::send(ctx, Event::Modified, &tasks);
+            report_missing(&missing)?;
+        }
+        Command::Complete { ids } => {
+            let ids = store.resolve_all(ctx, &ids)?;
+            if ids.len() > ctx.confirm_threshold {
+                confirm(store, ctx, &tr!("Complete these tasks?"), &ids)?;
+            }
+            complete(store, ctx, &ids)?
+        }
+        Command::Reopen { ids } => {
+            let ids = store.resolve_all(ctx, &ids)?;
+            if ids.len() > ctx.confirm_threshold {
+                confirm(store, ctx, &tr!("Reopen these tasks?"), &ids)?;
+            }
+            let missing = store.change(ctx, &ids, &Change::Reopen, &tr!("reopened"))?;
+            let tasks = events(store, ctx, &changed_ids(&ids, &missing))?;
+            webhook::send(ctx, Event::Modified, &tasks);
+            report_missing(&missing)?;
+        }
+        Command::Cancel { ids } => {
+            let ids = store.resolve_all(ctx, &ids)?;
+            if ids.len() > ctx.confirm_threshold {
+                confirm(store, ctx, &tr!("Cancel these tasks?"), &ids)?;
+            }
+            let missing = store.change(ctx, &ids, &Change::Cancel, &tr!("cancelled"))?;
+            let tasks = events(store, ctx, &changed_ids(&ids, &missing))?;
+            webhook::send(ctx, Event::Modified, &tasks);
+            report_missing(&missing)?;
+        }
+        Command::Remove { ids, force } => {
+            let ids = store.resolve_all(ctx, &ids)?;
+            confirm(store, ctx, &tr!("Remove these tasks?"), &ids)?;
+            // The tasks are loaded before they are deleted, so only those deleted are sent.
+            let tasks = events(store, ctx, &ids)?;
+            let done = if force || !store.has_trash() {
+                tr!("removed")
+            } else {
+                tr!("moved to the trash")
+            };
+            let missing = store.change(ctx, &ids, &Change::Remove { force }, &done)?;
+            let tasks: Vec<TodoRecord> = tasks
+                .into_iter()
+                .filter(|task| !missing.contains(&task.id))
+                .collect();
+            webhook::send(ctx, Event::Deleted, &tasks);
+            report_missing(&missing)?;
+        }
+        command => return Ok(Some(command)),
+    }
+    Ok(None)
+}
+
+/// Completes the user's tasks `ids` that are unfinished, failing with those that aren't.
+pub fn complete(store: &mut dyn Store, ctx: &Context, ids: &[i32]) -> Result<(), Error> {
+    let now = SystemTime::now();
+    // Only unfinished tasks are completed, so completing a task twice is reported rather than moving
+    // its completion time.
+    let missing = store.change(ctx, ids, &Change::Complete(now), &tr!("completed"))?;
+    let completed = changed_ids(ids, &missing);
+    if !ctx.dry_run {
+        store.completed(ctx, &completed)?;
+    }
+    let tasks = events(store, ctx, &completed)?;
+    webhook::send(ctx, Event::Completed, &tasks);
+    for task in &tasks {
+        ctx.journal.record(ctx, task);
+    }
+    match missing.as_slice() {
+        [] => Ok(()),
+        [id] => Err(Error::NotFound(tr!("No open task with id {}", id))),
+        _ => Err(Error::NotFound(tr!(
+            "No open tasks with ids {}",
+            join_ids(&missing)
+        ))),
+    }
+}
+
+/// Applies `op` to every id, printing `done` for each task it changed, and returns the ids it
+/// changed nothing for.
+pub fn each<F>(ids: &[i32], done: &str, mut op: F) -> Result<Vec<i32>, Error>
+where
+    F: FnMut(i32) -> Result<u64, Error>,
+{
+    let mut missing = Vec::new();
+    for &id in ids {
+        if op(id)? == 0 {
+            missing.push(id);
+        } else {
+            println!("{}: {}", id, done);
+        }
+    }
+    Ok(missing)
+}
+
+/// The description with a line added by `note`. The description is stored at `location`.
+pub fn add_note(
+    ctx: &Context,
+    description: Option<&str>,
+    text: &str,
+    location: Location,
+) -> String {
+    let description = description
+        .map(|d| ctx.decrypt(d, location))
+        .filter(|d| !d.is_empty());
+    let description = match description {
+        Some(d) => format!("{}\n{}", d, text),
+        None => text.to_string(),
+    };
+    ctx.encrypt(&description, location)
+}
+
+// Loads the tasks to send with an event. Without webhooks, hooks or a journal there is nothing to
+// load.
+fn events(store: &mut dyn Store, ctx: &Context, ids: &[i32]) -> Result<Vec<TodoRecord>, Error> {
+    if (ctx.webhooks.is_empty() && ctx.hooks.is_empty() && ctx.journal.is_empty()) || ctx.dry_run {
+        return Ok(Vec::new());
+    }
+    store.load(ctx, ids)
+}
+
+// Shows the user's tasks with the given ids and asks whether to go on, failing if the answer
+// isn't yes. Doesn't ask with `--yes`, when stdin isn't a terminal, or for dry runs.
+fn confirm(store: &mut dyn Store, ctx: &Context, question: &str, ids: &[i32]) -> Result<(), Error> {
+    if ctx.assume_yes || ctx.dry_run {
+        return Ok(());
+    }
+    let tasks = store.load(ctx, ids)?;
+    if tasks.is_empty() {
+        // Nothing would be affected; the command reports the missing ids.
+        return Ok(());
+    }
+    let tasks: Vec<String> = tasks
+        .iter()
+        .map(|task| {
+            let name = ctx.decrypt(&task.name, Location::task("name", &task.uuid));
+            format!("{}: {}", task.id, name)
+        })
+        .collect();
+    ask(question, &tasks)
+}
+
+/// The tasks in the PostgreSQL database.
+pub struct Postgres<'a>(pub &'a mut Client);
+
+impl Store for Postgres<'_> {
+    fn resolve(&mut self, ctx: &Context, task: &TaskRef) -> Result<i32, Error> {
+        task.resolve(self.0, ctx)
+    }
+
+    fn resolve_all(&mut self, ctx: &Context, tasks: &[TaskRef]) -> Result<Vec<i32>, Error> {
+        task_ref::resolve_all(self.0, ctx, tasks)
+    }
+
+    fn parse_time(&mut self, input: &str) -> Result<SystemTime, String> {
+        time::parse_time(self.0, input)
+    }
+
+    fn template(&mut self, ctx: &Context, name: &str) -> Result<Template, Error> {
+        template::load(self.0, ctx, name)
+    }
+
+    fn active_context(&mut self, ctx: &Context) -> Result<Option<String>, Error> {
+        Ok(context::active(self.0, ctx)?)
+    }
+
+    fn add(&mut self, ctx: &Context, task: NewTask, unique: Option<&str>) -> Result<Added, Error> {
+        let mut transaction = self.0.transaction()?;
+        if let Some(name) = unique {
+            // Held until the task is created, so another `add --unique` of the same user waits
+            // for it and finds it, instead of both finding none.
+            let key = format!("todo-rust add {}", ctx.user);
+            transaction.execute("select pg_advisory_xact_lock(hashtext($1))", &[&key])?;
+            if let Some(task) = find_open(&mut transaction, ctx, name)? {
+                return Ok(Added::Existing(task));
+            }
+        }
+        let insert = typed_query::insert_into(TODO_TABLE).values(TodoRecordInsert {
+            // TODO make a default value for this?
+            id: typed_query::WithDefault::Default,
+            name: task.name,
+            created_time: typed_query::WithDefault::Default,
+            completed_time: Option::None,
+            due_time: task.due_time,
+            remind_before: Option::None,
+            reminded_time: Option::None,
+            snoozed_until: Option::None,
+            description: task.description,
+            project: task.project,
+            github_repo: Option::None,
+            github_issue: Option::None,
+            github_url: Option::None,
+            github_close: typed_query::WithDefault::Default,
+            caldav_uid: Option::None,
+            caldav_href: Option::None,
+            caldav_etag: Option::None,
+            caldav_state: Option::None,
+            owner: Some(ctx.user.clone()),
+            priority: task.priority,
+            status: typed_query::WithDefault::Default,
+            context: task.context,
+            fields: typed_query::WithDefault::Value(task.fields),
+            uuid: typed_query::WithDefault::Value(task.uuid),
+            reviewed_time: None,
+            archived_time: None,
+            sort_order: None,
+            pinned: typed_query::WithDefault::Default,
+            estimate: task.estimate,
+            assignee: None,
+            waiting_until: None,
+            waiting_for: None,
+            inbox: typed_query::WithDefault::Default,
+            external_id: None,
+        });
+        if ctx.dry_run {
+            println!("{}", insert.dry_run());
+            return Ok(Added::DryRun);
+        }
+        let task = insert
+            .returning(&mut transaction)?
+            .pop()
+            .ok_or_else(|| Error::Other(tr!("The task was not created")))?;
+        transaction.commit()?;
+        Ok(Added::Created(task))
+    }
+
+    fn load(&mut self, ctx: &Context, ids: &[i32]) -> Result<Vec<TodoRecord>, Error> {
+        Ok(typed_query::from(TODO_TABLE)
+            .where_(|t| {
+                t.owner.clone().eq(typed_query::Constant {
+                    value: ctx.user.clone(),
+                })
+            })
+            .where_(|t| {
+                t.id.clone().in_(
+                    ids.iter()
+                        .map(|&value| typed_query::Constant { value })
+                        .collect(),
+                )
+            })
+            .order_by(|t| typed_query::asc(&t.id))
+            .query(self.0)?)
+    }
+
+    fn list(&mut self, ctx: &Context, statuses: &[Status]) -> Result<Listing, Error> {
+        let tracking = timetrack::active(self.0, ctx)?;
+        let active = context::active(self.0, ctx)?;
+        let context_filter = context::filter(self.0, ctx, &active)?;
+        let query = statuses_query(ctx, statuses).order_by(|t| typed_query::asc(&t.created_time));
+        // A context with a filter has the tasks matching it too, so not all tasks shown are
+        // in the context.
+        let (tasks, active) = match context_filter {
+            Some(filter) => {
+                let mut tasks = Vec::new();
+                for task in query.query(self.0)? {
+                    if task.context == active || filter.matches(ctx, &task)? {
+                        tasks.push(task);
+                    }
+                }
+                (tasks, None)
+            }
+            None => (in_context(query, &active).query(self.0)?, active),
+        };
+        cache::save(ctx, statuses, &active, &tasks);
+        Ok(Listing {
+            tasks,
+            active,
+            tracking,
+        })
+    }
+
+    // The tasks aren't cached, since they aren't kept.
+    fn for_each(
+        &mut self,
+        ctx: &Context,
+        statuses: &[Status],
+        f: &mut dyn FnMut(&TodoRecord),
+    ) -> Result<(), Error> {
+        let active = context::active(self.0, ctx)?;
+        let context_filter = context::filter(self.0, ctx, &active)?;
+        let query = match &context_filter {
+            Some(_) => statuses_query(ctx, statuses),
+            None => in_context(statuses_query(ctx, statuses), &active),
+        };
+        let mut failed = None;
+        query
+            .order_by(|t| typed_query::asc(&t.created_time))
+            .for_each(self.0, |task| {
+                let in_context = match &context_filter {
+                    Some(filter) if failed.is_none() && task.context != active => {
+                        filter.matches(ctx, &task).unwrap_or_else(|e| {
+                            failed = Some(e);
+                            false
+                        })
+                    }
+                    _ => failed.is_none(),
+                };
+                if in_context {
+                    f(&task);
+                }
+            })?;
+        failed.map_or(Ok(()), Err)
+    }
+
+    fn show_details(&mut self, ctx: &Context, id: i32) -> Result<(), Error> {
+        annotation::show(self.0, ctx, id)?;
+        dependency::show(self.0, ctx, id)
+    }
+
+    fn change(
+        &mut self,
+        ctx: &Context,
+        ids: &[i32],
+        change: &Change,
+        done: &str,
+    ) -> Result<Vec<i32>, Error> {
+        let user = ctx.user.as_str();
+        apply_bulk(self.0, ctx, ids, done, |t, id| match change {
+            Change::Complete(now) => {
+                let update = complete_update(ctx, id, *now);
+                if ctx.dry_run {
+                    println!("{}", update.dry_run());
+                }
+                update.execute(t)
+            }
+            Change::Reopen => ctx.execute(
+                t,
+                "update todo set status = $1, completed_time = null
+                 where id = $2 and owner = $3",
+                &[&Status::Todo, &id, &user],
+            ),
+            Change::Cancel => ctx.execute(
+                t,
+                "update todo set status = $1, completed_time = null
+                 where id = $2 and owner = $3",
+                &[&Status::Cancelled, &id, &user],
+            ),
+            // The description is updated here rather than in SQL, since it may be encrypted.
+            Change::Note(text) => {
+                let row = t.query_opt(
+                    "select description, uuid from todo where id = $1 and owner = $2 for update",
+                    &[&id, &user],
+                )?;
+                let (description, uuid): (Option<&str>, String) = match &row {
+                    Some(row) => (row.get(0), row.get(1)),
+                    None => return Ok(0),
+                };
+                let location = Location::task("description", &uuid);
+                ctx.execute(
+                    t,
+                    "update todo set description = $1 where id = $2",
+                    &[&add_note(ctx, description, text, location), &id],
+                )
+            }
+            Change::Remove { force: true } => {
+                let delete = typed_query::delete_from(TODO_TABLE)
+                    .where_(|t| t.id.clone().eq(typed_query::Constant { value: id }))
+                    .where_(|t| {
+                        t.owner.clone().eq(typed_query::Constant {
+                            value: ctx.user.clone(),
+                        })
+                    });
+                if ctx.dry_run {
+                    println!("{}", delete.dry_run());
+                }
+                delete.execute(t)
+            }
+            Change::Remove { force: false } => trash::remove(t, ctx, id),
+        })
+    }
+
+    fn has_trash(&self) -> bool {
+        true
+    }
+
+    fn completed(&mut self, ctx: &Context, ids: &[i32]) -> Result<(), Error> {
+        github::close_completed(self.0, ctx, ids)
+    }
+}
+
+// The user's tasks with `statuses`.
+fn statuses_query(
+    ctx: &Context,
+    statuses: &[Status],
+) -> typed_query::Query<crate::TodoColumns, TodoRecord> {
+    typed_query::from(TODO_TABLE)
+        .where_(|t| {
+            t.owner.clone().eq(typed_query::Constant {
+                value: ctx.user.clone(),
+            })
+        })
+        .where_(|t| {
+            t.status.clone().in_(
+                statuses
+                    .iter()
+                    .map(|&value| typed_query::Constant { value })
+                    .collect(),
+            )
+        })
+}
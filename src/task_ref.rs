@@ -0,0 +1,439 @@
+use postgres::Row;
+use rand::RngCore;
+use std::fmt;
+
+use crate::alias;
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::status;
+use crate::Context;
+use todo_rust::client::GenericClient;
+
+// The most similar tasks suggested when a task isn't found.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// A task as given on the command line: its id, the alias of its id, its UUID, or the start of
+/// an alias or UUID that only one of the user's tasks has. Ids and aliases are known when parsing,
+/// while the others have to be looked up. An alias that none of the user's tasks has can still be
+/// the start of a UUID.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TaskRef {
+    Id(i32),
+    Alias(String),
+    Uuid(String),
+    Prefix(String),
+}
+
+impl TaskRef {
+    pub fn parse(s: &str) -> Option<TaskRef> {
+        if let Ok(id) = s.parse::<i32>() {
+            return Some(TaskRef::Id(id));
+        }
+        if alias::decode(s).is_some() {
+            return Some(TaskRef::Alias(s.to_string()));
+        }
+        if is_uuid(s) {
+            return Some(TaskRef::Uuid(s.to_lowercase()));
+        }
+        if !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Some(TaskRef::Prefix(s.to_lowercase()));
+        }
+        None
+    }
+
+    // The id this refers to, if it can be told without looking it up.
+    fn id(&self) -> Option<i32> {
+        match self {
+            TaskRef::Id(id) => Some(*id),
+            TaskRef::Alias(alias) => alias::decode(alias),
+            TaskRef::Uuid(_) | TaskRef::Prefix(_) => None,
+        }
+    }
+
+    /// Returns the id of the task, checking that it is one of the user's tasks. If it isn't, the
+    /// error suggests tasks with a similar id or alias.
+    pub fn resolve(&self, client: &mut impl GenericClient, ctx: &Context) -> Result<i32, Error> {
+        Lookup::new(client, ctx, &[self])?.find(client, ctx, self)
+    }
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskRef::Id(id) => write!(f, "{}", id),
+            TaskRef::Alias(s) | TaskRef::Uuid(s) | TaskRef::Prefix(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Resolves every task, keeping the order and dropping duplicates. Ids are kept as they are, so
+/// that commands can report the ones that aren't the user's, and the other tasks are looked up
+/// together. Fails if any of those isn't one of the user's tasks.
+pub fn resolve_all(
+    client: &mut impl GenericClient,
+    ctx: &Context,
+    tasks: &[TaskRef],
+) -> Result<Vec<i32>, Error> {
+    let lookups: Vec<&TaskRef> = tasks
+        .iter()
+        .filter(|task| !matches!(task, TaskRef::Id(_)))
+        .collect();
+    resolve_with(client, ctx, tasks, &lookups)
+}
+
+/// Like `resolve_all`, but also checks that the ids are the user's.
+pub fn resolve_existing(
+    client: &mut impl GenericClient,
+    ctx: &Context,
+    tasks: &[TaskRef],
+) -> Result<Vec<i32>, Error> {
+    let lookups: Vec<&TaskRef> = tasks.iter().collect();
+    resolve_with(client, ctx, tasks, &lookups)
+}
+
+// Resolves every task, looking up those in `lookups` and keeping the other ids as they are.
+fn resolve_with(
+    client: &mut impl GenericClient,
+    ctx: &Context,
+    tasks: &[TaskRef],
+    lookups: &[&TaskRef],
+) -> Result<Vec<i32>, Error> {
+    let mut lookup = Lookup::new(client, ctx, lookups)?;
+    let mut ids = Vec::new();
+    let mut errors = Vec::new();
+    for task in tasks {
+        let found = match task {
+            TaskRef::Id(id) if !lookups.contains(&task) => Ok(*id),
+            _ => lookup.find(client, ctx, task),
+        };
+        match found {
+            Ok(id) if !ids.contains(&id) => ids.push(id),
+            Ok(_) => {}
+            Err(Error::NotFound(message)) => errors.push(message),
+            Err(e) => return Err(e),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Error::NotFound(errors.join("\n")));
+    }
+    Ok(ids)
+}
+
+// The tasks some references could be, looked up with one query: those with the ids of the
+// references and their aliases, and those whose UUID starts with any of the others. All of the
+// user's tasks are only loaded when a reference can be the start of an alias, or to suggest
+// similar tasks, and then only once.
+struct Lookup {
+    rows: Vec<Row>,
+    // All of the user's tasks, open ones first.
+    all: Option<Vec<Row>>,
+}
+
+impl Lookup {
+    fn new(
+        client: &mut impl GenericClient,
+        ctx: &Context,
+        tasks: &[&TaskRef],
+    ) -> Result<Lookup, Error> {
+        let ids: Vec<i32> = tasks.iter().filter_map(|task| task.id()).collect();
+        // Aliases can also be the start of a UUID, which matters for them either way.
+        let patterns: Vec<String> = tasks
+            .iter()
+            .filter_map(|task| match task {
+                TaskRef::Id(_) => None,
+                TaskRef::Uuid(uuid) => Some(uuid.clone()),
+                TaskRef::Alias(s) | TaskRef::Prefix(s) => Some(format!("{}%", s.to_lowercase())),
+            })
+            .collect();
+        let rows = if tasks.is_empty() {
+            Vec::new()
+        } else {
+            client.query(
+                "select id, name, uuid from todo
+                 where owner = $1 and (id = any($2) or uuid like any($3))
+                 order by id",
+                &[&ctx.user, &ids, &patterns],
+            )?
+        };
+        Ok(Lookup { rows, all: None })
+    }
+
+    // All of the user's tasks, loading them the first time.
+    fn all(&mut self, client: &mut impl GenericClient, ctx: &Context) -> Result<&[Row], Error> {
+        if self.all.is_none() {
+            self.all = Some(client.query(
+                "select id, name, uuid from todo where owner = $1
+                 order by status = any($2) desc, id",
+                &[&ctx.user, &status::OPEN],
+            )?);
+        }
+        Ok(self.all.as_deref().unwrap_or_default())
+    }
+
+    // The id of the task, which must have been one of the references the lookup was made for.
+    fn find(
+        &mut self,
+        client: &mut impl GenericClient,
+        ctx: &Context,
+        task: &TaskRef,
+    ) -> Result<i32, Error> {
+        let found = match task {
+            TaskRef::Prefix(prefix) => return self.find_prefix(client, ctx, prefix),
+            TaskRef::Alias(alias) => return self.find_alias(client, ctx, task, alias),
+            TaskRef::Uuid(uuid) => self.rows.iter().find(|row| row.get::<_, &str>(2) == uuid),
+            TaskRef::Id(id) => self.rows.iter().find(|row| row.get::<_, i32>(0) == *id),
+        };
+        match found {
+            Some(row) => Ok(row.get(0)),
+            None => Err(Error::NotFound(self.not_found(client, ctx, task)?)),
+        }
+    }
+
+    // Aliases can also be the start of a UUID, such as `beef12`. Finds the task with `alias`, or
+    // else the only task whose alias or UUID starts with it. Fails if the alias is one task's and
+    // the start of another's UUID.
+    fn find_alias(
+        &mut self,
+        client: &mut impl GenericClient,
+        ctx: &Context,
+        task: &TaskRef,
+        alias: &str,
+    ) -> Result<i32, Error> {
+        let prefix = alias.to_lowercase();
+        let id = alias::decode(alias);
+        let by_alias = match self
+            .rows
+            .iter()
+            .find(|row| Some(row.get::<_, i32>(0)) == id)
+        {
+            Some(row) => row,
+            None => {
+                return match self.find_prefix(client, ctx, &prefix) {
+                    Err(Error::NotFound(_)) => {
+                        Err(Error::NotFound(self.not_found(client, ctx, task)?))
+                    }
+                    result => result,
+                }
+            }
+        };
+        let by_uuid: Vec<&Row> = self
+            .rows
+            .iter()
+            .filter(|row| {
+                Some(row.get::<_, i32>(0)) != id && row.get::<_, &str>(2).starts_with(&prefix)
+            })
+            .collect();
+        if by_uuid.is_empty() {
+            return Ok(by_alias.get(0));
+        }
+        Err(Error::Usage(ambiguous(
+            ctx,
+            alias,
+            std::iter::once(by_alias).chain(by_uuid),
+        )))
+    }
+
+    // Finds the only task of the user whose alias or UUID starts with `prefix`.
+    fn find_prefix(
+        &mut self,
+        client: &mut impl GenericClient,
+        ctx: &Context,
+        prefix: &str,
+    ) -> Result<i32, Error> {
+        let mut matches: Vec<&Row> = self
+            .all(client, ctx)?
+            .iter()
+            .filter(|row| {
+                alias::encode(row.get(0)).starts_with(prefix)
+                    || row.get::<_, &str>(2).starts_with(prefix)
+            })
+            .collect();
+        matches.sort_by_key(|row| row.get::<_, i32>(0));
+        match matches.as_slice() {
+            [row] => Ok(row.get(0)),
+            [] => Err(Error::NotFound(tr!("No task with id {}", prefix))),
+            _ => Err(Error::Usage(ambiguous(ctx, prefix, matches))),
+        }
+    }
+
+    // Describes a task that wasn't found, with the user's tasks whose id or alias differ from it
+    // by a single character, open tasks first.
+    fn not_found(
+        &mut self,
+        client: &mut impl GenericClient,
+        ctx: &Context,
+        task: &TaskRef,
+    ) -> Result<String, Error> {
+        let mut message = tr!("No task with id {}", task);
+        let id = match task.id() {
+            Some(id) => id,
+            None => return Ok(message),
+        };
+        let (id_text, alias) = (id.to_string(), alias::encode(id));
+        let similar: Vec<String> = self
+            .all(client, ctx)?
+            .iter()
+            .filter(|row| {
+                let candidate: i32 = row.get(0);
+                distance(&candidate.to_string(), &id_text) == 1
+                    || distance(&alias::encode(candidate), &alias) == 1
+            })
+            .take(MAX_SUGGESTIONS)
+            .map(|row| {
+                let candidate: i32 = row.get(0);
+                format!(
+                    "  {} ({}): {}",
+                    candidate,
+                    alias::encode(candidate),
+                    ctx.decrypt(row.get(1), Location::task("name", row.get(2)))
+                )
+            })
+            .collect();
+        if !similar.is_empty() {
+            message.push_str(&format!(
+                "\n{}\n{}",
+                tr!("Did you mean:"),
+                similar.join("\n")
+            ));
+        }
+        Ok(message)
+    }
+}
+
+// Describes a task reference that matches several tasks, listing them.
+fn ambiguous<'a>(ctx: &Context, task: &str, rows: impl IntoIterator<Item = &'a Row>) -> String {
+    let mut message = tr!("'{}' matches several tasks:", task);
+    for row in rows {
+        let id: i32 = row.get(0);
+        message.push_str(&format!(
+            "\n  {} ({}): {}",
+            id,
+            alias::encode(id),
+            ctx.decrypt(row.get(1), Location::task("name", row.get(2)))
+        ));
+    }
+    message
+}
+
+// The Levenshtein distance between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A random (version 4) UUID, such as `1f0c9a3e-5b7d-4c2e-9f61-0a8b3c4d5e6f`, for tasks whose
+/// values are encrypted before they are created.
+pub fn new_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+// Whether `s` is a UUID in the usual form, such as `0b5e1c8e-4f3a-4d6b-9c2e-7a1f3e5d9b20`.
+fn is_uuid(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+    groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12])
+        && groups
+            .iter()
+            .all(|g| g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+
+    #[test]
+    fn aliases_fall_back_to_uuid_prefixes() {
+        let mut client = match test_db::connect("task_ref_alias") {
+            Some(client) => client,
+            None => return,
+        };
+        let ctx = test_db::context();
+        crate::migrations::up(&mut client, &ctx, None).unwrap();
+        let id = (1..)
+            .find(|&id| alias::encode(id).bytes().all(|c| c.is_ascii_hexdigit()))
+            .unwrap();
+        let hex_alias = alias::encode(id);
+        client
+            .execute(
+                "insert into todo (id, name, owner, uuid) values
+                     (1, 'a', 'alice', 'fa83' || gen_random_uuid()), ($1, 'b', 'alice', default)",
+                &[&id],
+            )
+            .unwrap();
+        let fa83 = TaskRef::parse("fa83").unwrap();
+        assert_eq!(fa83, TaskRef::Alias("fa83".to_string()));
+        assert_eq!(fa83.resolve(&mut client, &ctx).unwrap(), 1);
+        let by_alias = TaskRef::parse(&hex_alias).unwrap();
+        assert_eq!(by_alias.resolve(&mut client, &ctx).unwrap(), id);
+        client
+            .execute(
+                "insert into todo (id, name, owner, uuid)
+                     values ($1, 'c', 'alice', $2 || gen_random_uuid())",
+                &[&(id + 1), &hex_alias],
+            )
+            .unwrap();
+        assert!(matches!(
+            by_alias.resolve(&mut client, &ctx),
+            Err(Error::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn keeps_ids_and_looks_up_the_rest() {
+        let mut client = match test_db::connect("task_ref_all") {
+            Some(client) => client,
+            None => return,
+        };
+        let ctx = test_db::context();
+        crate::migrations::up(&mut client, &ctx, None).unwrap();
+        client
+            .batch_execute(
+                "insert into todo (id, name, owner) values (1, 'a', 'alice'), (2, 'b', 'alice'),
+                     (3, 'c', 'bob')",
+            )
+            .unwrap();
+        let uuid: String = client
+            .query_one("select uuid from todo where id = 2", &[])
+            .unwrap()
+            .get(0);
+        let tasks = [
+            TaskRef::Id(7),
+            TaskRef::Alias(alias::encode(1)),
+            TaskRef::Uuid(uuid),
+            TaskRef::Id(1),
+        ];
+        assert_eq!(
+            resolve_all(&mut client, &ctx, &tasks).unwrap(),
+            vec![7, 1, 2]
+        );
+        assert!(matches!(
+            resolve_existing(&mut client, &ctx, &tasks),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            resolve_all(&mut client, &ctx, &[TaskRef::Alias(alias::encode(3))]),
+            Err(Error::NotFound(_))
+        ));
+    }
+}
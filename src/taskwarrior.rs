@@ -0,0 +1,178 @@
+// Import of tasks exported with `task export`: a JSON array of tasks, or one task per line as
+// written by older versions. Tasks keep their UUIDs, and get `taskwarrior:<uuid>` as their external
+// id, so importing again updates the tasks imported before instead of adding them twice.
+
+use postgres::types::ToSql;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::json::Json;
+use crate::status::Status;
+use crate::Context;
+use todo_rust::client::Client;
+
+// The custom field taskwarrior's tags are stored in, if the config defines it.
+const TAGS_FIELD: &str = "tags";
+
+/// Imports the tasks in `path`, or on stdin if it is `-`.
+pub fn import(client: &mut Client, ctx: &Context, path: &Path) -> Result<(), Error> {
+    let text = if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| Error::Other(e.to_string()))?;
+        text
+    } else {
+        fs::read_to_string(path)
+            .map_err(|e| Error::Other(tr!("Failed to read {}: {}", path.display(), e)))?
+    };
+    let tasks = parse(&text).map_err(|e| Error::Other(tr!("Invalid taskwarrior export: {}", e)))?;
+    let keep_tags = ctx.fields.iter().any(|d| d.name == TAGS_FIELD);
+
+    let mut transaction = client.transaction()?;
+    let (mut imported, mut updated, mut unchanged, mut dropped_tags) = (0, 0, 0, false);
+    for task in &tasks {
+        let text = |key: &str| task.get(key).and_then(|v| v.as_str());
+        let uuid = text("uuid").ok_or_else(|| Error::Other(tr!("Task without a UUID")))?;
+        let status = match text("status") {
+            Some("completed") => Status::Done,
+            Some("deleted") => Status::Cancelled,
+            // Recurring tasks are templates for their instances, which are exported as well.
+            Some("recurring") => continue,
+            _ => Status::Todo,
+        };
+        let priority: Option<i16> = match text("priority") {
+            Some("H") => Some(1),
+            Some("M") => Some(2),
+            Some("L") => Some(3),
+            _ => None,
+        };
+        let tags: Vec<&str> = task
+            .get("tags")
+            .and_then(|t| t.as_array())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|t| t.as_str())
+            .collect();
+        let mut fields = Vec::new();
+        if keep_tags && !tags.is_empty() {
+            fields.push((TAGS_FIELD.to_string(), Json::from(tags.join(" ").as_str())));
+        }
+        let completed = text("end").filter(|_| status == Status::Done);
+        let external_id = format!("taskwarrior:{}", uuid);
+        // Tasks imported before external ids existed are only known by their UUID.
+        ctx.execute(
+            &mut transaction,
+            "update todo set external_id = $1
+                 where uuid = $2 and owner = $3 and external_id is null",
+            &[&external_id, &uuid, &ctx.user],
+        )?;
+        let sql = "insert into todo (uuid, owner, name, status, created_time, due_time,
+                       completed_time, priority, project, fields, external_id)
+                   values ($1, $2, $3, $4, coalesce($5::text::timestamptz, now()),
+                       $6::text::timestamptz, $7::text::timestamptz, $8, $9, $10, $11)
+                   on conflict (owner, external_id) do update
+                       set name = excluded.name, status = excluded.status,
+                           due_time = excluded.due_time, completed_time = excluded.completed_time,
+                           priority = excluded.priority, project = excluded.project,
+                           fields = todo.fields || excluded.fields
+                       where (todo.name, todo.status, todo.due_time, todo.completed_time,
+                               todo.priority, todo.project, todo.fields)
+                           is distinct from (excluded.name, excluded.status, excluded.due_time,
+                               excluded.completed_time, excluded.priority, excluded.project,
+                               todo.fields || excluded.fields)
+                   returning xmax = 0";
+        let name = ctx.encrypt(
+            text("description").unwrap_or_default(),
+            Location::task("name", uuid),
+        );
+        let (entry, due, project) = (text("entry"), text("due"), text("project"));
+        let fields = Json::Object(fields);
+        let params: [&(dyn ToSql + Sync); 11] = [
+            &uuid,
+            &ctx.user,
+            &name,
+            &status,
+            &entry,
+            &due,
+            &completed,
+            &priority,
+            &project,
+            &fields,
+            &external_id,
+        ];
+        ctx.show_statement(sql, &params);
+        match transaction.query_opt(sql, &params)? {
+            Some(row) if row.get(0) => imported += 1,
+            // Annotations are only added with the task, as they can't be told apart later.
+            Some(_) => {
+                updated += 1;
+                continue;
+            }
+            None => {
+                unchanged += 1;
+                continue;
+            }
+        }
+        dropped_tags |= !keep_tags && !tags.is_empty();
+        let annotations = task
+            .get("annotations")
+            .and_then(|a| a.as_array())
+            .unwrap_or_default();
+        for annotation in annotations {
+            let text = |key: &str| annotation.get(key).and_then(|v| v.as_str());
+            ctx.execute(
+                &mut transaction,
+                "insert into annotation (todo_id, time, text)
+                     select id, coalesce($2::text::timestamptz, now()), $3
+                     from todo where uuid = $1 and owner = $4",
+                &[
+                    &uuid,
+                    &text("entry"),
+                    &ctx.encrypt(
+                        text("description").unwrap_or_default(),
+                        Location::new("annotation", "text", uuid),
+                    ),
+                    &ctx.user,
+                ],
+            )?;
+        }
+    }
+    ctx.finish(transaction)?;
+    println!(
+        "{}",
+        tr!(
+            "Imported {} tasks, updated {} and left {} unchanged",
+            imported,
+            updated,
+            unchanged
+        )
+    );
+    if dropped_tags {
+        log::warn!(
+            "{}",
+            tr!(
+                "Tags were not imported, define a '{}' string field in the config to keep them",
+                TAGS_FIELD
+            )
+        );
+    }
+    Ok(())
+}
+
+fn parse(text: &str) -> Result<Vec<Json>, String> {
+    match Json::parse(text) {
+        Ok(Json::Array(tasks)) => Ok(tasks),
+        Ok(task @ Json::Object(_)) => Ok(vec![task]),
+        Ok(_) => Err(tr!("expected an array of tasks")),
+        Err(e) => text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Json::parse(line.trim().trim_end_matches(',')))
+            .collect::<Result<Vec<Json>, String>>()
+            .map_err(|_| e),
+    }
+}
@@ -0,0 +1,161 @@
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::json::Json;
+use crate::{fields, parse_estimate, priority_name, time, Context, TaskOptions};
+use todo_rust::client::Client;
+
+#[derive(Debug)]
+pub enum Action {
+    Add { name: String, options: TaskOptions },
+    List,
+    Remove { name: String },
+}
+
+/// A stored set of task attributes, used by `add --from-template`. The due time is kept as
+/// given (such as `in 1w`), so it is relative to when the task is added.
+#[derive(Debug, Default)]
+pub struct Template {
+    pub name: String,
+    pub due: Option<String>,
+    pub note: Option<String>,
+    pub project: Option<String>,
+    pub priority: Option<i16>,
+    pub context: Option<String>,
+    pub estimate: Option<i32>,
+    pub fields: Vec<(String, Json)>,
+}
+
+/// Loads the user's template called `name`.
+pub fn load(client: &mut Client, ctx: &Context, name: &str) -> Result<Template, Error> {
+    let row = client.query_opt(
+        "select name, due, description, project, priority, context, estimate, fields
+             from template
+             where owner = $1 and name = $2",
+        &[&ctx.user, &name],
+    )?;
+    let row = row.ok_or_else(|| Error::NotFound(tr!("No template named '{}'", name)))?;
+    Ok(from_row(ctx, &row))
+}
+
+fn from_row(ctx: &Context, row: &postgres::Row) -> Template {
+    Template {
+        name: row.get(0),
+        due: row.get(1),
+        note: row
+            .get::<_, Option<&str>>(2)
+            .map(|d| ctx.decrypt(d, Location::new("template", "description", row.get(0)))),
+        project: row.get(3),
+        priority: row.get(4),
+        context: row.get(5),
+        estimate: row.get(6),
+        fields: match row.get(7) {
+            Json::Object(members) => members,
+            _ => Vec::new(),
+        },
+    }
+}
+
+pub fn run(client: &mut Client, ctx: &Context, action: Action) -> Result<(), Error> {
+    match action {
+        Action::Add { name, options } => {
+            let fields = fields::parse_all(&ctx.fields, &options.fields).map_err(Error::Usage)?;
+            // Check the due time now rather than when the template is used.
+            if let Some(due) = &options.due {
+                time::parse_time(client, due).map_err(Error::Usage)?;
+            }
+            let estimate = options
+                .estimate
+                .as_deref()
+                .map(parse_estimate)
+                .transpose()
+                .map_err(Error::Usage)?;
+            let mut transaction = client.transaction()?;
+            ctx.execute(
+                &mut transaction,
+                "insert into template
+                     (owner, name, due, description, project, priority, context, estimate,
+                         fields)
+                 values ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 on conflict (owner, name) do update
+                     set due = excluded.due, description = excluded.description,
+                         project = excluded.project, priority = excluded.priority,
+                         context = excluded.context, estimate = excluded.estimate,
+                         fields = excluded.fields",
+                &[
+                    &ctx.user,
+                    &name,
+                    &options.due,
+                    &options
+                        .note
+                        .map(|n| ctx.encrypt(&n, Location::new("template", "description", &name))),
+                    &options.project,
+                    &options.priority,
+                    &options.context,
+                    &estimate,
+                    &Json::Object(fields),
+                ],
+            )?;
+            ctx.finish(transaction)?;
+            println!("{}", tr!("Saved template '{}'", name));
+        }
+        Action::List => {
+            let rows = client.query(
+                "select name, due, description, project, priority, context, estimate, fields
+                     from template
+                     where owner = $1
+                     order by name",
+                &[&ctx.user],
+            )?;
+            if rows.is_empty() {
+                println!("{}", tr!("No templates"));
+            }
+            for row in rows {
+                let template = from_row(ctx, &row);
+                let details = describe(ctx, &template);
+                if details.is_empty() {
+                    println!("{}", template.name);
+                } else {
+                    println!("{} ({})", template.name, details.join(", "));
+                }
+            }
+        }
+        Action::Remove { name } => {
+            let mut transaction = client.transaction()?;
+            let removed = ctx.execute(
+                &mut transaction,
+                "delete from template where owner = $1 and name = $2",
+                &[&ctx.user, &name],
+            )?;
+            ctx.finish(transaction)?;
+            if removed == 0 {
+                return Err(Error::NotFound(tr!("No template named '{}'", name)));
+            }
+            println!("{}", tr!("Removed template '{}'", name));
+        }
+    }
+    Ok(())
+}
+
+fn describe(ctx: &Context, template: &Template) -> Vec<String> {
+    let mut details = Vec::new();
+    if let Some(due) = &template.due {
+        details.push(tr!("due {}", due));
+    }
+    if let Some(priority) = template.priority {
+        details.push(tr!("{} priority", priority_name(priority)));
+    }
+    if let Some(project) = &template.project {
+        details.push(tr!("project {}", project));
+    }
+    if let Some(context) = &template.context {
+        details.push(format!("@{}", context));
+    }
+    if let Some(estimate) = template.estimate {
+        details.push(tr!("estimate {}", time::format_duration(estimate as f64)));
+    }
+    details.extend(fields::describe(
+        &ctx.fields,
+        &Json::Object(template.fields.clone()),
+    ));
+    details
+}
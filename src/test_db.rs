@@ -0,0 +1,28 @@
+// A database for tests, from the connection string in `TODO_TEST_DATABASE`. Tests that need one
+// are skipped without it. Each test works in a schema of its own, so they can run in parallel.
+
+use postgres::NoTls;
+
+use crate::config::Config;
+use crate::{parse_options, Context};
+use todo_rust::client::Client;
+
+/// Connects to the test database with an empty schema `name` as the only one on the search path,
+/// or returns `None` if there is no test database.
+pub fn connect(name: &str) -> Option<Client> {
+    let connection = std::env::var("TODO_TEST_DATABASE").ok()?;
+    let mut client = Client::connect(&connection, NoTls).expect("connecting to the test database");
+    client
+        .batch_execute(&format!(
+            "drop schema if exists {0} cascade; create schema {0}; set search_path to {0}",
+            name
+        ))
+        .expect("creating the test schema");
+    Some(client)
+}
+
+/// The settings of a command run by user `alice` without a config file.
+pub fn context() -> Context {
+    let mut args = vec!["--user".to_string(), "alice".to_string()];
+    parse_options(&mut args, &Config::default()).unwrap()
+}
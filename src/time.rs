@@ -0,0 +1,224 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::ical;
+use crate::tz::Zone;
+use todo_rust::client::{Client, GenericClient};
+
+/// Formats a number of seconds as a short human readable duration, using at most the two largest
+/// units, e.g. `3d 4h`, `5h 12m` or `40s`.
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    let units = [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)];
+    let mut parts: Vec<String> = Vec::new();
+    let mut rest = total;
+    for (suffix, size) in units.iter() {
+        let n = rest / size;
+        rest %= size;
+        if n > 0 || !parts.is_empty() {
+            parts.push(format!("{}{}", n, suffix));
+        }
+        if parts.len() == 2 {
+            break;
+        }
+    }
+    match parts.as_slice() {
+        [] => "0s".to_string(),
+        [first, second] if second.starts_with('0') => first.clone(),
+        _ => parts.join(" "),
+    }
+}
+
+/// Formats a point in time relative to `now`, in the largest unit, e.g. `2d ago` or `in 3h`.
+pub fn format_relative(t: SystemTime, now: SystemTime) -> String {
+    let (seconds, future) = match t.duration_since(now) {
+        Ok(d) => (d.as_secs_f64(), true),
+        Err(e) => (e.duration().as_secs_f64(), false),
+    };
+    if seconds < 60.0 {
+        return tr!("just now");
+    }
+    let duration = format_duration(seconds);
+    let largest = duration.split(' ').next().unwrap_or(&duration);
+    if future {
+        tr!("in {}", largest)
+    } else {
+        tr!("{} ago", largest)
+    }
+}
+
+/// Formats a point in time in the given zone (or UTC) as `2021-08-01 14:00 CEST`.
+pub fn format_absolute(t: SystemTime, zone: Option<&Zone>) -> String {
+    let abbreviation = zone.map_or("UTC", |z| z.offset_at(t).1);
+    let (year, month, day, hour, minute, _) = to_utc(to_local(t, zone));
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} {}",
+        year, month, day, hour, minute, abbreviation
+    )
+}
+
+// Shifts a point in time by the offset of `zone`, so `to_utc` gives the local calendar parts.
+fn to_local(t: SystemTime, zone: Option<&Zone>) -> SystemTime {
+    let offset = zone.map_or(0, |z| z.offset_at(t).0);
+    if offset >= 0 {
+        t + Duration::from_secs(offset as u64)
+    } else {
+        t - Duration::from_secs(-offset as u64)
+    }
+}
+
+/// Returns the day `t` falls on in `zone` (or UTC), as a number of days since the epoch.
+pub fn local_day(t: SystemTime, zone: Option<&Zone>) -> i64 {
+    let (year, month, day, _, _, _) = to_utc(to_local(t, zone));
+    let midnight = from_utc(year, month, day, 0, 0, 0);
+    match midnight.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64 / 86400,
+        Err(e) => -(e.duration().as_secs() as i64) / 86400,
+    }
+}
+
+/// Formats a point in time as an ISO 8601 UTC timestamp, such as `2021-08-01T12:00:00Z`.
+pub fn format_iso(t: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = to_utc(t);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Parses a duration such as `45s`, `30m`, `2h`, `3d`, `1w` or a combination like `1h30m`,
+/// returning the number of seconds.
+pub fn parse_duration(input: &str) -> Option<u64> {
+    let mut total = 0;
+    let mut digits = String::new();
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let size = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 7 * 86400,
+            _ => return None,
+        };
+        let n: u64 = digits.parse().ok()?;
+        total += n * size;
+        digits.clear();
+    }
+    if !digits.is_empty() || total == 0 {
+        return None;
+    }
+    Some(total)
+}
+
+/// Parses a point in time. Relative times (`2h`, `in 3d`, `+1w`) are taken from now; anything
+/// else is handed to the database, so every format Postgres accepts for a `timestamptz` (such as
+/// `2021-08-01 14:00`, `2021-08-01 14:00 Europe/Amsterdam` or `tomorrow`) works. Times without a
+/// zone are in the session's zone, see `set_session_zone`.
+pub fn parse_time(client: &mut impl GenericClient, input: &str) -> Result<SystemTime, String> {
+    let relative = input
+        .strip_prefix("in ")
+        .or_else(|| input.strip_prefix('+'))
+        .unwrap_or(input);
+    if let Some(seconds) = parse_duration(relative) {
+        return Ok(SystemTime::now() + Duration::from_secs(seconds));
+    }
+    client
+        .query_one("select $1::text::timestamptz", &[&input])
+        .map(|row| row.get(0))
+        .map_err(|_| tr!("Invalid time: {}", input))
+}
+
+/// Parses a time without the database, for when there is none: a relative time (`2h`, `in 3d`,
+/// `+1w`), a date (`2021-08-01`) or a UTC date and time (`2021-08-01T14:00:00Z`).
+pub fn parse_offline(input: &str) -> Option<SystemTime> {
+    let relative = input
+        .strip_prefix("in ")
+        .or_else(|| input.strip_prefix('+'))
+        .unwrap_or(input);
+    match parse_duration(relative) {
+        Some(seconds) => Some(SystemTime::now() + Duration::from_secs(seconds)),
+        None => ical::parse_time(&input.replace(['-', ':'], "")),
+    }
+}
+
+/// Makes the database interpret times without a zone in `zone` (or UTC).
+pub fn set_session_zone(client: &mut Client, zone: Option<&Zone>) -> Result<(), postgres::Error> {
+    let name = match zone {
+        Some(zone) => match &zone.name {
+            Some(name) => name.as_str(),
+            None => return Ok(()),
+        },
+        None => "UTC",
+    };
+    client.execute("select set_config('timezone', $1, false)", &[&name])?;
+    Ok(())
+}
+
+/// Splits a point in time into UTC calendar parts: `(year, month, day, hour, minute, second)`.
+pub fn to_utc(t: SystemTime) -> (i64, u32, u32, u32, u32, u32) {
+    let seconds = match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let days = seconds.div_euclid(86400);
+    let rest = seconds.rem_euclid(86400) as u32;
+    // Converts days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day, rest / 3600, rest / 60 % 60, rest % 60)
+}
+
+/// The inverse of `to_utc`.
+pub fn from_utc(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> SystemTime {
+    // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    let seconds = days * 86400 + (hour * 3600 + minute * 60 + second) as i64;
+    if seconds >= 0 {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(-seconds as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_offline_takes_dates_and_relative_times() {
+        assert_eq!(
+            parse_offline("2021-08-01T14:00:00Z"),
+            Some(from_utc(2021, 8, 1, 14, 0, 0))
+        );
+        assert_eq!(
+            parse_offline("2021-08-01"),
+            Some(from_utc(2021, 8, 1, 0, 0, 0))
+        );
+        assert!(parse_offline("in 2h").is_some_and(|t| t > SystemTime::now()));
+        assert_eq!(parse_offline("tomorrow"), None);
+    }
+}
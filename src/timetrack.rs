@@ -0,0 +1,128 @@
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::status::{self, Status};
+use crate::time::format_duration;
+use crate::Context;
+use todo_rust::client::{Client, Transaction};
+
+/// Starts tracking time on task `id`, stopping whatever the user was tracking before. The task
+/// is marked as in progress.
+pub fn start(client: &mut Client, ctx: &Context, id: i32) -> Result<(), Error> {
+    let mut transaction = client.transaction()?;
+    let todo = transaction.query_opt(
+        "select name, uuid from todo where id = $1 and owner = $2 and status = any($3)",
+        &[&id, &ctx.user, &status::OPEN],
+    )?;
+    let name = match todo {
+        Some(row) => ctx.decrypt(row.get(0), Location::task("name", row.get(1))),
+        None => return Err(Error::NotFound(tr!("No open task with id {}", id))),
+    };
+    stop_active(&mut transaction, ctx)?;
+    ctx.execute(
+        &mut transaction,
+        "insert into time_entry (todo_id) values ($1)",
+        &[&id],
+    )?;
+    ctx.execute(
+        &mut transaction,
+        "update todo set status = $1 where id = $2",
+        &[&Status::InProgress, &id],
+    )?;
+    ctx.finish(transaction)?;
+    println!("{}", tr!("Started {}: {}", id, name));
+    Ok(())
+}
+
+/// Stops tracking the active task, if any.
+pub fn stop(client: &mut Client, ctx: &Context) -> Result<(), Error> {
+    let mut transaction = client.transaction()?;
+    match stop_active(&mut transaction, ctx)? {
+        Some((id, name, seconds)) => {
+            println!(
+                "{}",
+                tr!("Stopped {}: {} ({})", id, name, format_duration(seconds))
+            )
+        }
+        None => println!("{}", tr!("No task is being tracked")),
+    }
+    ctx.finish(transaction)?;
+    Ok(())
+}
+
+fn stop_active(
+    transaction: &mut Transaction,
+    ctx: &Context,
+) -> Result<Option<(i32, String, f64)>, postgres::Error> {
+    let sql = "update time_entry e
+               set end_time = now()
+               from todo t
+               where t.id = e.todo_id and t.owner = $1 and e.end_time is null
+               returning t.id, t.name, extract(epoch from e.end_time - e.start_time)::float8,
+                   t.uuid";
+    ctx.show_statement(sql, &[&ctx.user]);
+    Ok(transaction.query_opt(sql, &[&ctx.user])?.map(|row| {
+        let name = ctx.decrypt(row.get(1), Location::task("name", row.get(3)));
+        (row.get(0), name, row.get(2))
+    }))
+}
+
+/// Returns the id of the task being tracked and for how many seconds it has been tracked.
+pub fn active(client: &mut Client, ctx: &Context) -> Result<Option<(i32, f64)>, postgres::Error> {
+    Ok(client
+        .query_opt(
+            "select e.todo_id, extract(epoch from now() - e.start_time)::float8
+             from time_entry e
+             join todo t on t.id = e.todo_id
+             where e.end_time is null and t.owner = $1",
+            &[&ctx.user],
+        )?
+        .map(|row| (row.get(0), row.get(1))))
+}
+
+/// Prints the time tracked in the current `period` (`day`, `week` or `month`), per project and
+/// task. Intervals that started before the period are only counted from the start of the period.
+pub fn timesheet(client: &mut Client, ctx: &Context, period: &str) -> Result<(), Error> {
+    let rows = client.query(
+        "select to_char(date_trunc($1, now()), 'YYYY-MM-DD'), t.project, t.id, t.name, t.uuid,
+                 extract(epoch from sum(
+                     coalesce(e.end_time, now()) - greatest(e.start_time, date_trunc($1, now()))
+                 ))::float8
+             from time_entry e
+             join todo t on t.id = e.todo_id
+             where coalesce(e.end_time, now()) > date_trunc($1, now()) and t.owner = $2
+             group by t.project, t.id, t.name, t.uuid
+             order by t.project nulls last, t.id",
+        &[&period, &ctx.user],
+    )?;
+    if rows.is_empty() {
+        println!("{}", tr!("No time tracked this {}", period));
+        return Ok(());
+    }
+    let since: String = rows[0].get(0);
+    println!("{}", tr!("Tracked since {}", since));
+    let mut total = 0.0;
+    let mut project_total = 0.0;
+    let mut current_project: Option<Option<String>> = None;
+    for row in &rows {
+        let project: Option<String> = row.get(1);
+        let id: i32 = row.get(2);
+        let name = ctx.decrypt(row.get(3), Location::task("name", row.get(4)));
+        let seconds: f64 = row.get(5);
+        if current_project.as_ref() != Some(&project) {
+            if current_project.is_some() {
+                println!("  total  {}", format_duration(project_total));
+            }
+            println!();
+            println!("{}", project.as_deref().unwrap_or("(no project)"));
+            current_project = Some(project);
+            project_total = 0.0;
+        }
+        println!("  {}: {}  {}", id, name, format_duration(seconds));
+        project_total += seconds;
+        total += seconds;
+    }
+    println!("  total  {}", format_duration(project_total));
+    println!();
+    println!("{}", tr!("Total: {}", format_duration(total)));
+    Ok(())
+}
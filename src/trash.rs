@@ -0,0 +1,294 @@
+// The trash: `remove` moves tasks here instead of deleting them, so `restore` can bring them back
+// with their id, time entries, annotations, attachments and dependencies. The task and the rows belonging to it
+// are kept as JSON, so they stay out of every query of the `todo` table. `trash empty` and
+// `remove --force` delete tasks for good.
+
+use std::time::SystemTime;
+
+use crate::alias;
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::output;
+use crate::task_ref::TaskRef;
+use crate::{ask, join_ids, time, webhook, Context};
+use todo_rust::client::{Client, Transaction};
+
+#[derive(Debug)]
+pub enum Action {
+    List,
+    Empty,
+}
+
+/// Moves the user's task `id` to the trash, returning how many tasks were moved.
+pub fn remove(t: &mut Transaction, ctx: &Context, id: i32) -> Result<u64, postgres::Error> {
+    ctx.execute(
+        t,
+        "insert into trash
+                 (todo_id, owner, task, time_entries, annotations, attachments, dependencies)
+             select t.id, t.owner, to_jsonb(t),
+                 (select coalesce(jsonb_agg(e), '[]') from time_entry e where e.todo_id = t.id),
+                 (select coalesce(jsonb_agg(a), '[]') from annotation a where a.todo_id = t.id),
+                 (select coalesce(jsonb_agg(a), '[]') from attachment a where a.todo_id = t.id),
+                 (select coalesce(jsonb_agg(d), '[]') from dependency d
+                     where d.todo_id = t.id or d.depends_on = t.id)
+             from todo t
+             where t.id = $1 and t.owner = $2",
+        &[&id, &ctx.user],
+    )?;
+    ctx.execute(
+        t,
+        "delete from todo where id = $1 and owner = $2",
+        &[&id, &ctx.user],
+    )
+}
+
+/// Restores the given tasks from the trash.
+pub fn restore(client: &mut Client, ctx: &Context, ids: &[TaskRef]) -> Result<(), Error> {
+    let ids = ids
+        .iter()
+        .map(|id| resolve(client, ctx, id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut transaction = client.transaction()?;
+    let mut missing = Vec::new();
+    for &id in &ids {
+        let restored = restore_rows(
+            &mut transaction,
+            ctx,
+            id,
+            "todo",
+            "jsonb_build_array(t.task)",
+        )?;
+        if restored == 0 {
+            missing.push(id);
+            continue;
+        }
+        for (table, column) in [
+            ("time_entry", "time_entries"),
+            ("annotation", "annotations"),
+            ("attachment", "attachments"),
+        ] {
+            restore_rows(&mut transaction, ctx, id, table, &format!("t.{}", column))?;
+        }
+        // Both sides of the dependencies are kept, so links between tasks restored one after the
+        // other come back with the second. Links to tasks deleted since are dropped.
+        ctx.execute(
+            &mut transaction,
+            "insert into dependency (todo_id, depends_on)
+                 select r.todo_id, r.depends_on
+                 from trash t, jsonb_populate_recordset(null::dependency, t.dependencies) r
+                 where t.todo_id = $1 and t.owner = $2
+                     and exists (select from todo where id = r.todo_id)
+                     and exists (select from todo where id = r.depends_on)
+                 on conflict do nothing",
+            &[&id, &ctx.user],
+        )?;
+        ctx.execute(
+            &mut transaction,
+            "delete from trash where todo_id = $1 and owner = $2",
+            &[&id, &ctx.user],
+        )?;
+        println!("{}: {}", id, tr!("restored"));
+    }
+    if !missing.is_empty() {
+        return Err(Error::NotFound(tr!(
+            "No tasks in the trash with ids {}",
+            join_ids(&missing)
+        )));
+    }
+    ctx.finish(transaction)?;
+    if !ctx.dry_run {
+        let tasks = webhook::tasks(client, ctx, &ids)?;
+        webhook::send(ctx, webhook::Event::Created, &tasks);
+    }
+    Ok(())
+}
+
+// Inserts the rows of `table` kept in the trash entry `t` of task `id`, where `rows` is an
+// expression on `t` for them as a JSON array. Only the columns the rows have are inserted, so
+// columns added by migrations after the task was trashed get their defaults instead of null.
+fn restore_rows(
+    t: &mut Transaction,
+    ctx: &Context,
+    id: i32,
+    table: &str,
+    rows: &str,
+) -> Result<u64, postgres::Error> {
+    // The rows of a table were all trashed at once, so they have the same columns as the first.
+    let columns: Option<String> = t
+        .query_one(
+            format!(
+                "select string_agg(quote_ident(c.column_name), ', ' order by c.ordinal_position)
+                     from information_schema.columns c, trash t
+                     where c.table_schema = current_schema() and c.table_name = $1
+                         and t.todo_id = $2 and t.owner = $3 and ({}) -> 0 ? c.column_name",
+                rows
+            )
+            .as_str(),
+            &[&table, &id, &ctx.user],
+        )?
+        .get(0);
+    let columns = match columns {
+        Some(columns) => columns,
+        None => return Ok(0),
+    };
+    let values: Vec<String> = columns.split(", ").map(|c| format!("r.{}", c)).collect();
+    ctx.execute(
+        t,
+        &format!(
+            "insert into {} ({})
+                 select {} from trash t, jsonb_populate_recordset(null::{}, {}) r
+                 where t.todo_id = $1 and t.owner = $2",
+            table,
+            columns,
+            values.join(", "),
+            table,
+            rows
+        ),
+        &[&id, &ctx.user],
+    )
+}
+
+// The id of a task in the trash. Prefixes can't be told apart from the tasks that aren't in the
+// trash, so only whole ids, aliases and UUIDs are accepted.
+fn resolve(client: &mut Client, ctx: &Context, id: &TaskRef) -> Result<i32, Error> {
+    match id {
+        TaskRef::Id(id) => Ok(*id),
+        TaskRef::Alias(a) => {
+            alias::decode(a).ok_or_else(|| Error::Usage(tr!("Invalid task id: {}", a)))
+        }
+        TaskRef::Uuid(uuid) => client
+            .query_opt(
+                "select todo_id from trash where task ->> 'uuid' = $1 and owner = $2",
+                &[uuid, &ctx.user],
+            )?
+            .map(|row| row.get(0))
+            .ok_or_else(|| Error::NotFound(tr!("No task in the trash with UUID {}", uuid))),
+        TaskRef::Prefix(prefix) => Err(Error::Usage(tr!(
+            "Expected the id, alias or UUID of a task in the trash, got: {}",
+            prefix
+        ))),
+    }
+}
+
+pub fn run(client: &mut Client, ctx: &Context, action: Action) -> Result<(), Error> {
+    match action {
+        Action::List => {
+            let rows = client.query(
+                "select todo_id, task ->> 'name', time, task ->> 'uuid' from trash
+                 where owner = $1 order by time",
+                &[&ctx.user],
+            )?;
+            if rows.is_empty() && ctx.output.is_text() {
+                println!("{}", tr!("The trash is empty"));
+            }
+            for row in rows {
+                let id: i32 = row.get(0);
+                let name = ctx.decrypt(row.get(1), Location::task("name", row.get(3)));
+                let removed: SystemTime = row.get(2);
+                if ctx.output.is_text() {
+                    println!(
+                        "{} ({}): {} ({})",
+                        id,
+                        alias::encode(id),
+                        name,
+                        tr!("removed {}", ctx.format_time(removed))
+                    );
+                } else {
+                    output::record(
+                        ctx.output,
+                        &[
+                            &id.to_string(),
+                            &alias::encode(id),
+                            &name,
+                            &time::format_iso(removed),
+                        ],
+                    );
+                }
+            }
+        }
+        Action::Empty => {
+            let count: i64 = client
+                .query_one("select count(*) from trash where owner = $1", &[&ctx.user])?
+                .get(0);
+            if count == 0 {
+                println!("{}", tr!("The trash is empty"));
+                return Ok(());
+            }
+            if !ctx.assume_yes && !ctx.dry_run {
+                ask(&tr!("Delete {} tasks in the trash for good?", count), &[])?;
+            }
+            let mut transaction = client.transaction()?;
+            let deleted = ctx.execute(
+                &mut transaction,
+                "delete from trash where owner = $1",
+                &[&ctx.user],
+            )?;
+            ctx.finish(transaction)?;
+            println!("{}", tr!("Deleted {} tasks", deleted));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{migrations, test_db};
+
+    #[test]
+    fn restores_tasks_trashed_before_a_migration() {
+        let mut client = match test_db::connect("trash_migrated") {
+            Some(client) => client,
+            None => return,
+        };
+        let ctx = test_db::context();
+        // Before the inbox, a column that is not null.
+        migrations::up(&mut client, &ctx, Some(29)).unwrap();
+        // Trashed as `remove` did at that version.
+        client
+            .batch_execute(
+                "insert into todo (name, owner) values ('a', 'alice');
+                 insert into trash (todo_id, owner, task, time_entries, annotations, attachments)
+                     select id, owner, to_jsonb(t), '[]', '[]', '[]' from todo t;
+                 delete from todo",
+            )
+            .unwrap();
+        migrations::up(&mut client, &ctx, None).unwrap();
+        restore(&mut client, &ctx, &[TaskRef::Id(1)]).unwrap();
+        let row = client
+            .query_one("select name, inbox from todo where id = 1", &[])
+            .unwrap();
+        assert_eq!(row.get::<_, String>(0), "a");
+        assert!(!row.get::<_, bool>(1));
+    }
+
+    #[test]
+    fn restores_dependencies_on_remaining_tasks() {
+        let mut client = match test_db::connect("trash_dependencies") {
+            Some(client) => client,
+            None => return,
+        };
+        let ctx = test_db::context();
+        migrations::up(&mut client, &ctx, None).unwrap();
+        client
+            .batch_execute(
+                "insert into todo (name, owner) values ('a', 'alice'), ('b', 'alice'), ('c', 'alice');
+                 insert into dependency (todo_id, depends_on) values (2, 1), (1, 3)",
+            )
+            .unwrap();
+        let mut transaction = client.transaction().unwrap();
+        remove(&mut transaction, &ctx, 1).unwrap();
+        transaction.commit().unwrap();
+        client
+            .execute("delete from todo where id = 3", &[])
+            .unwrap();
+        restore(&mut client, &ctx, &[TaskRef::Id(1)]).unwrap();
+        let dependencies: Vec<(i32, i32)> = client
+            .query("select todo_id, depends_on from dependency", &[])
+            .unwrap()
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+        assert_eq!(dependencies, vec![(2, 1)]);
+    }
+}
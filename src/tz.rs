@@ -0,0 +1,170 @@
+use std::convert::TryInto;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_ZONEINFO: &str = "/usr/share/zoneinfo";
+const LOCALTIME: &str = "/etc/localtime";
+
+/// A time zone, read from the system's time zone database.
+pub struct Zone {
+    /// The IANA name, such as `Europe/Amsterdam`, if known.
+    pub name: Option<String>,
+    // Times (in seconds since the epoch) at which the offset changes, with the index of the new
+    // local time type.
+    transitions: Vec<(i64, usize)>,
+    // Local time types: offset from UTC in seconds and abbreviation.
+    types: Vec<(i32, String)>,
+}
+
+impl Zone {
+    /// Loads a zone by IANA name.
+    pub fn load(name: &str) -> Option<Zone> {
+        if name.contains("..") {
+            return None;
+        }
+        let mut zone = Zone::read(&zoneinfo().join(name))?;
+        zone.name = Some(name.to_string());
+        Some(zone)
+    }
+
+    /// Returns the local zone, from `TZ` or the system configuration.
+    pub fn local() -> Option<Zone> {
+        if let Ok(name) = env::var("TZ") {
+            return Zone::load(name.trim_start_matches(':'));
+        }
+        let mut zone = Zone::read(Path::new(LOCALTIME))?;
+        // /etc/localtime is usually a link into the zone database, which gives the name.
+        zone.name = fs::read_link(LOCALTIME).ok().and_then(|target| {
+            let target = target.to_string_lossy().into_owned();
+            let (_, name) = target.split_once("zoneinfo/")?;
+            Some(name.to_string())
+        });
+        Some(zone)
+    }
+
+    // Reads a TZif file, see RFC 8536. Times after the last transition use the last local time
+    // type; the rule in the footer isn't used.
+    fn read(path: &Path) -> Option<Zone> {
+        let data = fs::read(path).ok()?;
+        let (header, rest) = parse_header(&data)?;
+        if header.version >= b'2' {
+            // The version 1 data is followed by the same data with 64 bit times.
+            let (header, rest) = parse_header(rest.get(header.data_size(4)..)?)?;
+            return Zone::read_data(&header, rest, 8);
+        }
+        Zone::read_data(&header, rest, 4)
+    }
+
+    fn read_data(header: &Header, data: &[u8], time_size: usize) -> Option<Zone> {
+        let times = data.get(..header.timecnt * time_size)?;
+        let indices = data.get(times.len()..times.len() + header.timecnt)?;
+        let types_start = times.len() + indices.len();
+        let types = data.get(types_start..types_start + header.typecnt * 6)?;
+        let chars_start = types_start + types.len();
+        let chars = data.get(chars_start..chars_start + header.charcnt)?;
+
+        let mut transitions = Vec::new();
+        for (i, time) in times.chunks(time_size).enumerate() {
+            let time = if time_size == 8 {
+                i64::from_be_bytes(time.try_into().ok()?)
+            } else {
+                i32::from_be_bytes(time.try_into().ok()?) as i64
+            };
+            let index = indices[i] as usize;
+            if index >= header.typecnt {
+                return None;
+            }
+            transitions.push((time, index));
+        }
+        let types = types
+            .chunks(6)
+            .map(|t| {
+                let offset = i32::from_be_bytes([t[0], t[1], t[2], t[3]]);
+                let abbreviation: String = chars
+                    .get(t[5] as usize..)
+                    .unwrap_or(&[])
+                    .iter()
+                    .take_while(|&&c| c != 0)
+                    .map(|&c| c as char)
+                    .collect();
+                (offset, abbreviation)
+            })
+            .collect::<Vec<_>>();
+        if types.is_empty() {
+            return None;
+        }
+        Some(Zone {
+            name: None,
+            transitions,
+            types,
+        })
+    }
+
+    /// Returns the offset from UTC in seconds and the abbreviation (such as `CEST`) at time `t`.
+    pub fn offset_at(&self, t: SystemTime) -> (i32, &str) {
+        let seconds = match t.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        let index = match self
+            .transitions
+            .iter()
+            .rposition(|(time, _)| *time <= seconds)
+        {
+            Some(i) => self.transitions[i].1,
+            None => 0,
+        };
+        let (offset, abbreviation) = &self.types[index];
+        (*offset, abbreviation)
+    }
+}
+
+struct Header {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn data_size(&self, time_size: usize) -> usize {
+        self.timecnt * time_size
+            + self.timecnt
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+fn parse_header(data: &[u8]) -> Option<(Header, &[u8])> {
+    if data.get(..4)? != b"TZif" {
+        return None;
+    }
+    let count = |i: usize| -> Option<usize> {
+        let bytes = data.get(20 + i * 4..24 + i * 4)?;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?) as usize)
+    };
+    let header = Header {
+        version: *data.get(4)?,
+        isutcnt: count(0)?,
+        isstdcnt: count(1)?,
+        leapcnt: count(2)?,
+        timecnt: count(3)?,
+        typecnt: count(4)?,
+        charcnt: count(5)?,
+    };
+    Some((header, data.get(44..)?))
+}
+
+fn zoneinfo() -> PathBuf {
+    env::var("TZDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_ZONEINFO))
+}
@@ -0,0 +1,194 @@
+use std::time::SystemTime;
+
+use crate::config::Config;
+use crate::context;
+use crate::crypto::Location;
+use crate::error::Error;
+use crate::fields;
+use crate::json::Json;
+use crate::output;
+use crate::status::{self, Status};
+use crate::time::format_iso;
+use crate::{
+    alias, in_context, priority_key, priority_name, typed_query, Context, TodoRecord, TODO_TABLE,
+};
+use todo_rust::client::Client;
+
+const DAY: f64 = 24.0 * 60.0 * 60.0;
+
+// Due dates further away than this count as far away, and tasks older than this as old.
+const DUE_HORIZON_DAYS: f64 = 14.0;
+const OVERDUE_DAYS: f64 = 7.0;
+const AGE_DAYS: f64 = 365.0;
+
+/// The weight of each part of the score, from the `[urgency]` section of the config: `due`,
+/// `priority`, `in_progress` and `age`, and `fields.<name>` for custom fields, which count when
+/// set to anything but `false`. Every part is scaled to between 0 and 1 before weighing.
+#[derive(Debug, Clone)]
+pub struct Coefficients {
+    due: f64,
+    priority: f64,
+    in_progress: f64,
+    age: f64,
+    fields: Vec<(String, f64)>,
+}
+
+impl Default for Coefficients {
+    fn default() -> Coefficients {
+        Coefficients {
+            due: 12.0,
+            priority: 6.0,
+            in_progress: 4.0,
+            age: 2.0,
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl Coefficients {
+    pub fn load(
+        config: &Config,
+        definitions: &[fields::Definition],
+    ) -> Result<Coefficients, String> {
+        let mut coefficients = Coefficients::default();
+        for (key, value) in config.section("urgency") {
+            let weight = value
+                .parse::<f64>()
+                .ok()
+                .filter(|w| w.is_finite())
+                .ok_or_else(|| tr!("Invalid urgency.{} in the config file: {}", key, value))?;
+            match key {
+                "due" => coefficients.due = weight,
+                "priority" => coefficients.priority = weight,
+                "in_progress" => coefficients.in_progress = weight,
+                "age" => coefficients.age = weight,
+                _ => match key.strip_prefix("fields.") {
+                    Some(name) if definitions.iter().any(|d| d.name == name) => {
+                        coefficients.fields.push((name.to_string(), weight))
+                    }
+                    Some(name) => return Err(tr!("Unknown field in urgency.{}: {}", key, name)),
+                    None => return Err(tr!("Unknown setting in the config file: urgency.{}", key)),
+                },
+            }
+        }
+        Ok(coefficients)
+    }
+}
+
+/// How urgent a task is, higher being more urgent. The score adds up the closeness of the due
+/// date (counting fully from a week overdue), the priority, whether the task was started, its
+/// age (counting fully after a year) and the custom fields with a weight, each weighed by its
+/// coefficient.
+pub fn score(task: &TodoRecord, now: SystemTime, coefficients: &Coefficients) -> f64 {
+    let mut score = 0.0;
+    if let Some(due) = task.due_time {
+        let days = seconds_between(now, due) / DAY;
+        let closeness = if days <= -OVERDUE_DAYS {
+            1.0
+        } else if days >= DUE_HORIZON_DAYS {
+            0.2
+        } else {
+            0.2 + 0.8 * (DUE_HORIZON_DAYS - days) / (DUE_HORIZON_DAYS + OVERDUE_DAYS)
+        };
+        score += coefficients.due * closeness;
+    }
+    score += coefficients.priority
+        * match task.priority {
+            Some(1) => 1.0,
+            Some(2) => 0.65,
+            Some(_) => 0.3,
+            None => 0.0,
+        };
+    if task.status == Status::InProgress {
+        score += coefficients.in_progress;
+    }
+    let age = seconds_between(task.created_time, now) / DAY;
+    score += coefficients.age * (age / AGE_DAYS).clamp(0.0, 1.0);
+    for (name, weight) in &coefficients.fields {
+        match task.fields.get(name) {
+            None | Some(Json::Null) | Some(Json::Bool(false)) => {}
+            Some(_) => score += weight,
+        }
+    }
+    score
+}
+
+/// Formats a score for showing it.
+pub fn format(score: f64) -> String {
+    format!("{:.1}", score)
+}
+
+// Returns the seconds from `from` to `to`, negative if `to` is earlier.
+fn seconds_between(from: SystemTime, to: SystemTime) -> f64 {
+    match to.duration_since(from) {
+        Ok(d) => d.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    }
+}
+
+/// Shows the `n` most urgent open tasks in the active context.
+pub fn next(client: &mut Client, ctx: &Context, n: usize) -> Result<(), Error> {
+    let active = context::active(client, ctx)?;
+    let query = typed_query::from(TODO_TABLE)
+        .where_(|t| {
+            t.owner.clone().eq(typed_query::Constant {
+                value: ctx.user.clone(),
+            })
+        })
+        .where_(|t| {
+            t.status.clone().in_(
+                status::OPEN
+                    .iter()
+                    .map(|&value| typed_query::Constant { value })
+                    .collect(),
+            )
+        });
+    let tasks = in_context(query, &active).query(client)?;
+    let now = SystemTime::now();
+    let mut scored: Vec<(f64, TodoRecord)> = tasks
+        .into_iter()
+        .map(|t| (score(&t, now, &ctx.urgency), t))
+        .collect();
+    scored.sort_by(|(a, ta), (b, tb)| b.total_cmp(a).then(ta.id.cmp(&tb.id)));
+    scored.truncate(n);
+
+    if !ctx.output.is_text() {
+        for (score, task) in &scored {
+            output::record(
+                ctx.output,
+                &[
+                    &task.id.to_string(),
+                    &alias::encode(task.id),
+                    &ctx.decrypt(&task.name, Location::task("name", &task.uuid)),
+                    &format(*score),
+                    &task.due_time.map(format_iso).unwrap_or_default(),
+                    task.priority.map(priority_key).unwrap_or_default(),
+                ],
+            );
+        }
+        return Ok(());
+    }
+    if scored.is_empty() {
+        println!("{}", tr!("Nothing to do"));
+    }
+    for (score, task) in &scored {
+        let mut details = vec![tr!("urgency {}", format(*score))];
+        if task.status == Status::InProgress {
+            details.push(task.status.label());
+        }
+        if let Some(priority) = task.priority {
+            details.push(tr!("{} priority", priority_name(priority)));
+        }
+        if let Some(due) = task.due_time {
+            details.push(tr!("due {}", ctx.format_time(due)));
+        }
+        println!(
+            "{} ({}): {} ({})",
+            task.id,
+            alias::encode(task.id),
+            ctx.decrypt(&task.name, Location::task("name", &task.uuid)),
+            details.join(", ")
+        );
+    }
+    Ok(())
+}
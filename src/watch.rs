@@ -0,0 +1,95 @@
+use postgres::fallible_iterator::FallibleIterator;
+use std::io::{self, IsTerminal, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::reload;
+use crate::Context;
+use todo_rust::client::Client;
+
+const CHANNEL: &str = "todo_changed";
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Shows the output of `render` and shows it again whenever the user's tasks change, forever.
+/// Changes are signalled by the `todo_notify` trigger; without it (or when notifications can't be
+/// received, such as through a pooler) the output is refreshed every `interval` instead. With
+/// notifications it is refreshed at that interval too, to keep relative times current. Changes to
+/// the config are applied at the next refresh.
+pub fn run<F>(
+    client: &mut Client,
+    ctx: &Context,
+    interval: Duration,
+    mut render: F,
+) -> Result<(), Error>
+where
+    F: FnMut(&mut Client, &Context) -> Result<(), Error>,
+{
+    let listening = listen(client)?;
+    if !listening {
+        log::warn!(
+            "{}",
+            tr!(
+                "Change notifications are not available, refreshing every {}s",
+                interval.as_secs()
+            )
+        );
+    }
+    let clear = io::stdout().is_terminal();
+    let mut watcher = reload::Watcher::new();
+    let mut reloaded = None;
+    loop {
+        if let Some(settings) = watcher.check(reloaded.as_ref().unwrap_or(ctx)) {
+            reloaded = Some(settings);
+        }
+        let ctx = reloaded.as_ref().unwrap_or(ctx);
+        if clear {
+            print!("{}", CLEAR_SCREEN);
+        }
+        render(client, ctx)?;
+        io::stdout().flush().ok();
+        if listening {
+            wait_for_change(client, ctx, interval)?;
+        } else {
+            thread::sleep(interval);
+        }
+    }
+}
+
+// Starts listening for changes, returning whether that worked.
+fn listen(client: &mut Client) -> Result<bool, postgres::Error> {
+    let trigger = client.query_opt(
+        "select 1 from pg_trigger where tgname = 'todo_notify' and tgrelid = 'todo'::regclass",
+        &[],
+    )?;
+    if trigger.is_none() {
+        return Ok(false);
+    }
+    match client.batch_execute(&format!("listen {}", CHANNEL)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.is_closed() => Err(e),
+        Err(e) => {
+            log::info!("listen failed: {}", e);
+            Ok(false)
+        }
+    }
+}
+
+// Waits until a task of the user changes, or `interval` has passed.
+fn wait_for_change(
+    client: &mut Client,
+    ctx: &Context,
+    interval: Duration,
+) -> Result<(), postgres::Error> {
+    let mut notifications = client.notifications();
+    let mut iter = notifications.timeout_iter(interval);
+    while let Some(notification) = iter.next()? {
+        if notification.payload() == ctx.user {
+            break;
+        }
+    }
+    drop(iter);
+    // Notifications that arrived meanwhile are covered by the refresh.
+    notifications.iter().count()?;
+    Ok(())
+}
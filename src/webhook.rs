@@ -0,0 +1,154 @@
+use std::time::SystemTime;
+
+use crate::config::Config;
+use crate::crypto;
+use crate::http;
+use crate::json::Json;
+use crate::{task_json, time, typed_query, Context, TodoRecord, TODO_TABLE};
+use todo_rust::client::Client;
+
+// Failed deliveries are retried this often, after a second and then two.
+const RETRIES: u32 = 2;
+const SIGNATURE_HEADER: &str = "X-Todo-Signature";
+
+/// URLs to send task events to, from `urls` in the `[webhooks]` section of the config. When
+/// `secret` is set, every payload is signed with it: the signature header holds
+/// `sha256=<hex HMAC-SHA256 of the body>`.
+#[derive(Debug, Default)]
+pub struct Webhooks {
+    urls: Vec<String>,
+    secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Event {
+    Created,
+    Completed,
+    Modified,
+    Deleted,
+    Assigned,
+}
+
+impl Event {
+    fn name(self) -> &'static str {
+        match self {
+            Event::Created => "task.created",
+            Event::Completed => "task.completed",
+            Event::Modified => "task.modified",
+            Event::Deleted => "task.deleted",
+            Event::Assigned => "task.assigned",
+        }
+    }
+
+    /// The file name of the hook script run for the event.
+    pub fn hook_name(self) -> &'static str {
+        match self {
+            Event::Created => "on-add",
+            Event::Completed => "on-complete",
+            Event::Modified => "on-modify",
+            Event::Deleted => "on-remove",
+            Event::Assigned => "on-assign",
+        }
+    }
+}
+
+impl Webhooks {
+    pub fn load(config: &Config) -> Webhooks {
+        Webhooks {
+            urls: config.get_list("webhooks.urls"),
+            secret: config.get("webhooks.secret").map(|s| s.to_string()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+}
+
+/// Loads the user's tasks with the given ids, for sending them with `send` (for deletions,
+/// before they are deleted). Without webhooks, hooks or a journal there is nothing to load.
+pub fn tasks(
+    client: &mut Client,
+    ctx: &Context,
+    ids: &[i32],
+) -> Result<Vec<TodoRecord>, postgres::Error> {
+    if (ctx.webhooks.is_empty() && ctx.hooks.is_empty() && ctx.journal.is_empty()) || ctx.dry_run {
+        return Ok(Vec::new());
+    }
+    let mut tasks = Vec::new();
+    for &id in ids {
+        tasks.extend(
+            typed_query::from(TODO_TABLE)
+                .where_(|t| {
+                    t.owner.clone().eq(typed_query::Constant {
+                        value: ctx.user.clone(),
+                    })
+                })
+                .where_(|t| t.id.clone().eq(typed_query::Constant { value: id }))
+                .query(client)?,
+        );
+    }
+    Ok(tasks)
+}
+
+/// Sends `event` for each of `tasks` to every webhook, and runs its hook script. The webhooks are
+/// sent in the background, so a slow or unreachable URL doesn't hold up the command. Failures are
+/// reported as warnings, since the change itself has been made, if the command still runs by
+/// then.
+pub fn send(ctx: &Context, event: Event, tasks: &[TodoRecord]) {
+    if ctx.dry_run || tasks.is_empty() {
+        return;
+    }
+    let payloads: Vec<String> = tasks
+        .iter()
+        .map(|task| {
+            Json::object(vec![
+                ("event", Json::from(event.name())),
+                ("time", Json::from(time::format_iso(SystemTime::now()))),
+                ("user", Json::from(ctx.user.as_str())),
+                ("task", task_json(ctx, task)),
+            ])
+            .to_string()
+        })
+        .collect();
+    for url in &ctx.webhooks.urls {
+        if let Err(e) = deliver(&ctx.webhooks, url, &payloads) {
+            warn_failed(url, &e);
+        }
+    }
+    for payload in &payloads {
+        ctx.hooks.run(event, payload);
+    }
+}
+
+// Posts the payloads to `url` one by one, in the background.
+fn deliver(webhooks: &Webhooks, url: &str, payloads: &[String]) -> Result<(), String> {
+    let signatures: Vec<Option<String>> = payloads
+        .iter()
+        .map(|payload| {
+            webhooks
+                .secret
+                .as_ref()
+                .map(|secret| format!("sha256={}", crypto::sign(secret, payload)))
+        })
+        .collect();
+    let requests: Vec<(Vec<(&str, &str)>, &str)> = payloads
+        .iter()
+        .zip(&signatures)
+        .map(|(payload, signature)| {
+            let mut headers = vec![("Content-Type", "application/json")];
+            if let Some(signature) = signature {
+                headers.push((SIGNATURE_HEADER, signature.as_str()));
+            }
+            (headers, payload.as_str())
+        })
+        .collect();
+    let failed_url = url.to_string();
+    http::post_in_background(url, &requests, RETRIES, move |e| {
+        warn_failed(&failed_url, &e)
+    })
+}
+
+fn warn_failed(url: &str, error: &str) {
+    log::warn!("{}", tr!("Failed to send webhook to {}: {}", url, error));
+}